@@ -0,0 +1,226 @@
+//! Enrich newline-delimited JSON records with parsed locations, e.g.
+//! `{"id": 1, "location": "Toronto, ON"}` is written as
+//! `{"id": 1, "location": "Toronto, ON", "parsed": {"city": "Toronto", ...}}`.
+//!
+//! Records are read from stdin and written to stdout in the same order, so
+//! the binary can sit between a consumer and a producer of a message queue.
+//! Locations are parsed by a pool of parser clones. At most `--buffer` records
+//! are read but not written yet, so a slow consumer of stdout or a slow record
+//! stops reading of stdin instead of buffering the whole stream in memory.
+//! Records that can't be parsed are written with an `error` field instead.
+//!
+//! Reading a Kafka topic directly is out of scope, records of a topic are piped
+//! through the binary with the Kafka console consumer and producer instead.
+use clap::{Arg, Command};
+use geo_rs::Parser;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[path = "../json.rs"]
+mod json;
+
+/// Number of records read and the number of them written with an error.
+#[derive(Debug, Default, PartialEq)]
+struct Stats {
+    records: usize,
+    failed: usize,
+}
+
+fn main() {
+    env_logger::init();
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let matches = Command::new("geo-rs-stream")
+        .about("Enrich newline-delimited JSON records of stdin with parsed locations")
+        .arg(
+            Arg::new("workers")
+                .long("workers")
+                .help("Number of threads parsing locations [default: number of CPUs]")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("buffer")
+                .long("buffer")
+                .help("Number of records read but not written yet before reading is paused")
+                .default_value("1024")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .get_matches();
+    let workers = matches.get_one::<usize>("workers").copied();
+    let buffer = *matches
+        .get_one::<usize>("buffer")
+        .expect("buffer has a default");
+    let parser = match Parser::builder().try_build() {
+        Ok(parser) => parser,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let stdin = io::stdin();
+    let output = io::BufWriter::new(io::stdout());
+    match process(
+        &parser,
+        stdin.lock(),
+        output,
+        workers.unwrap_or(threads),
+        buffer,
+    ) {
+        Ok(stats) => eprintln!("{} records, {} failed", stats.records, stats.failed),
+        Err(e) => {
+            eprintln!("failed to process records: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Record of the output for a line of the input.
+fn enrich(parser: &Parser, line: &str) -> Result<Value, Value> {
+    let mut record: Value = match serde_json::from_str(line) {
+        Ok(record) => record,
+        Err(e) => return Err(json!({ "error": format!("invalid JSON: {}", e), "record": line })),
+    };
+    let location = match record.get("location").and_then(Value::as_str) {
+        Some(input) => json::to_json(&parser.parse_location(input)),
+        None => {
+            let error = "expected an object with a \"location\" string";
+            return Err(json!({ "error": error, "record": record }));
+        }
+    };
+    record["parsed"] = location;
+    Ok(record)
+}
+
+/// Enrich records of the input with `workers` clones of the parser and write
+/// them to the output in the order they were read, blank lines are skipped.
+///
+/// # Arguments
+///
+/// * `parser` - Parser to clone for every worker
+/// * `input` - Newline-delimited JSON records
+/// * `output` - Where to write the enriched records
+/// * `workers` - Number of threads parsing locations
+/// * `buffer` - Number of records read but not written yet, also the capacity
+///   of the channels between the reader, the workers and the writer
+fn process<R: BufRead, W: Write + Send>(
+    parser: &Parser,
+    input: R,
+    mut output: W,
+    workers: usize,
+    buffer: usize,
+) -> io::Result<Stats> {
+    let (line_sender, line_receiver) = sync_channel::<(usize, String)>(buffer);
+    let (record_sender, record_receiver) = sync_channel::<(usize, Result<Value, Value>)>(buffer);
+    let line_receiver = Arc::new(Mutex::new(line_receiver));
+    // A record takes a slot when it's read and frees it when it's written, so the
+    // records waiting for a slow earlier one to be written are bounded too
+    let buffer = buffer.max(1);
+    let (slot_sender, slot_receiver) = sync_channel::<()>(buffer);
+    for _ in 0..buffer {
+        slot_sender.send(()).expect("slots fit the channel");
+    }
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            let parser = parser.clone();
+            let lines = Arc::clone(&line_receiver);
+            let records = record_sender.clone();
+            scope.spawn(move || loop {
+                let next = lines.lock().expect("worker panicked").recv();
+                let (i, line) = match next {
+                    Ok(next) => next,
+                    Err(_) => break,
+                };
+                if records.send((i, enrich(&parser, &line))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(record_sender);
+        // Workers finish records out of order, they wait here for the earlier ones
+        let writer = scope.spawn(move || -> io::Result<Stats> {
+            let mut stats = Stats::default();
+            let mut pending = BTreeMap::new();
+            for (i, record) in record_receiver {
+                pending.insert(i, record);
+                while let Some(record) = pending.remove(&stats.records) {
+                    if record.is_err() {
+                        stats.failed += 1;
+                    }
+                    let record = record.unwrap_or_else(|error| error);
+                    writeln!(output, "{}", record)?;
+                    stats.records += 1;
+                    let _ = slot_sender.send(());
+                }
+            }
+            output.flush()?;
+            Ok(stats)
+        });
+        let mut read = Ok(());
+        let lines = input.lines().filter(|line| match line {
+            Ok(line) => !line.trim().is_empty(),
+            Err(_) => true,
+        });
+        for (i, line) in lines.enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    read = Err(e);
+                    break;
+                }
+            };
+            // The writer has failed when the slots or the workers are gone
+            if slot_receiver.recv().is_err() || line_sender.send((i, line)).is_err() {
+                break;
+            }
+        }
+        drop(line_sender);
+        let stats = writer.join().expect("writer panicked")?;
+        read.map(|_| stats)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process() {
+        let parser = Parser::new();
+        let mut input = String::new();
+        for i in 0..100 {
+            let location = ["Toronto, ON", "Lansing, MI", "Sausalito"][i % 3];
+            input.push_str(&json!({ "id": i, "location": location }).to_string());
+            input.push('\n');
+        }
+        input.push_str("\n{\"id\": 100}\nnot json\n");
+        let mut output = vec![];
+        let stats = process(&parser, input.as_bytes(), &mut output, 4, 2).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                records: 102,
+                failed: 2
+            }
+        );
+        let records: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 102);
+        for (i, record) in records[..100].iter().enumerate() {
+            assert_eq!(record["id"], i);
+        }
+        assert_eq!(records[0]["parsed"]["city"], "Toronto");
+        assert_eq!(records[1]["parsed"]["state"], "MI");
+        assert_eq!(records[2]["parsed"]["warnings"], json!(["W003", "W004"]));
+        assert_eq!(records[100]["record"]["id"], 100);
+        assert!(records[101]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid JSON"));
+    }
+}
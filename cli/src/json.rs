@@ -0,0 +1,19 @@
+use geo_rs::nodes::Location;
+use serde_json::{json, Value};
+
+/// JSON object of the location with the same fields as in the Python module,
+/// e.g. `{"city": "Toronto", "state": "ON", "state_name": "Ontario", ...}`.
+pub fn to_json(location: &Location) -> Value {
+    let warnings: Vec<&str> = location.warnings.iter().map(|w| w.code()).collect();
+    json!({
+        "city": location.city.as_ref().map(|c| &c.name),
+        "state": location.state.as_ref().map(|s| &s.code),
+        "state_name": location.state.as_ref().map(|s| &s.name),
+        "country": location.country.as_ref().map(|c| &c.code),
+        "country_name": location.country.as_ref().map(|c| &c.name),
+        "zipcode": location.zipcode.as_ref().map(|z| &z.zipcode),
+        "address": location.address.as_ref().map(|a| &a.address),
+        "granularity": location.granularity().map(|g| g.name()),
+        "warnings": warnings,
+    })
+}
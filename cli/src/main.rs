@@ -1,10 +1,270 @@
-use env_logger;
-use geo_rs;
+use clap::{Arg, ArgMatches, Command};
+use geo_rs::nodes::Location;
+use geo_rs::utils::DataSource;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+#[cfg(feature = "serve")]
+mod json;
+#[cfg(feature = "serve")]
+mod serve;
 
 fn main() {
     env_logger::init();
-    let parser = geo_rs::Parser::new();
-    let location = std::env::args().nth(1).expect("no location given");
-    let output = parser.parse_location(&location);
-    println!(">> {}", output);
+    let matches = command().get_matches();
+    let load_parser = || load_parser(matches.get_one::<String>("config"));
+    match matches.subcommand() {
+        Some(("info", _)) => print_info(&load_parser()),
+        Some(("data", data)) => match data.subcommand() {
+            Some(("validate", args)) => validate_data(PathBuf::from(value(args, "dir"))),
+            _ => unreachable!("subcommand is required"),
+        },
+        Some(("lookup", lookup)) => print_lookup(&load_parser(), lookup),
+        Some(("repl", _)) => repl(&load_parser()),
+        #[cfg(feature = "serve")]
+        Some(("serve", args)) => {
+            let host = value(args, "host");
+            let port = *args.get_one::<u16>("port").expect("port has a default");
+            if let Err(e) = serve::serve(load_parser(), host, port) {
+                eprintln!("failed to serve on {}:{}: {}", host, port, e);
+                std::process::exit(1);
+            }
+        }
+        _ => {
+            let output = load_parser().parse_location(value(&matches, "location"));
+            println!(">> {}", output);
+        }
+    }
+}
+
+fn command() -> Command {
+    let required =
+        |name: &'static str, help: &'static str| Arg::new(name).help(help).required(true);
+    let command = Command::new("geo-rs")
+        .about("Parse locations and look up places in the datasets")
+        .args_conflicts_with_subcommands(true)
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .global(true)
+                .help("TOML or JSON config of the parser, see geo_rs::config"),
+        )
+        .arg(required(
+            "location",
+            "Location to parse, e.g. \"Toronto, ON\"",
+        ))
+        .subcommand(Command::new("info").about("Print statistics of the loaded datasets"))
+        .subcommand(
+            Command::new("repl")
+                .about("Parse every line typed, keeping the datasets loaded between them"),
+        )
+        .subcommand(
+            Command::new("data")
+                .about("Work with data files")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("validate")
+                        .about("Check custom data files before they're deployed")
+                        .arg(required("dir", "Folder with the data files")),
+                ),
+        )
+        .subcommand(
+            Command::new("lookup")
+                .about("Look up places in the datasets")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("state")
+                        .about("States by code or name")
+                        .arg(required("query", "State code or name, e.g. ON")),
+                )
+                .subcommand(
+                    Command::new("city")
+                        .about("Cities with the name, the most populous first")
+                        .arg(required("query", "City name, e.g. Springfield")),
+                )
+                .subcommand(
+                    Command::new("zip")
+                        .about("State and country of a ZIP or postal code")
+                        .arg(required("query", "ZIP or postal code, e.g. 48911")),
+                ),
+        );
+    #[cfg(feature = "serve")]
+    let command = command.subcommand(
+        Command::new("serve")
+            .about("Serve POST /parse and POST /parse/batch over HTTP")
+            .arg(
+                Arg::new("host")
+                    .long("host")
+                    .help("Address to listen on, e.g. 0.0.0.0 to accept outside connections")
+                    .default_value("127.0.0.1"),
+            )
+            .arg(
+                Arg::new("port")
+                    .long("port")
+                    .help("Port to listen on")
+                    .default_value("8080")
+                    .value_parser(clap::value_parser!(u16)),
+            ),
+    );
+    command
+}
+
+/// Value of a required argument.
+fn value<'a>(matches: &'a ArgMatches, name: &str) -> &'a str {
+    matches
+        .get_one::<String>(name)
+        .expect("argument is required")
+}
+
+/// Build the parser, configured by the config file when there's one,
+/// exit when the config is invalid or the data files are incompatible.
+fn load_parser(config: Option<&String>) -> geo_rs::Parser {
+    let parser = match config {
+        Some(path) => geo_rs::Parser::from_config(path).map_err(|e| e.to_string()),
+        None => geo_rs::Parser::builder()
+            .try_build()
+            .map_err(|e| e.to_string()),
+    };
+    match parser {
+        Ok(parser) => parser,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print statistics of the loaded datasets, states without
+/// cities usually indicate a problem with the data files.
+fn print_info(parser: &geo_rs::Parser) {
+    println!("countries: {}", parser.country_count());
+    for (country, states) in parser.city_counts() {
+        let total: usize = states.values().sum();
+        println!("{}: {} states, {} cities", country, states.len(), total);
+        for (state, count) in states {
+            let warning = if count == 0 { " (no cities!)" } else { "" };
+            println!("  {}: {}{}", state, count, warning);
+        }
+    }
+}
+
+/// Print places found by `lookup` subcommands, exit with an error when there are none.
+fn print_lookup(parser: &geo_rs::Parser, lookup: &ArgMatches) {
+    let (kind, args) = lookup.subcommand().expect("subcommand is required");
+    let query = value(args, "query");
+    let locations: Vec<Location> = match kind {
+        "state" => parser.lookup_states(query),
+        "city" => parser.lookup_cities(query),
+        _ => parser.location_from_zipcode(query).into_iter().collect(),
+    };
+    if locations.is_empty() {
+        eprintln!("nothing found for {:?}", query);
+        std::process::exit(1);
+    }
+    for location in locations {
+        let mut details = vec![];
+        if let Some(state) = location.state.as_ref().filter(|_| kind == "state") {
+            details.push(state.name.clone());
+        }
+        if let Some(population) = parser.population_of(&location) {
+            details.push(format!("population {}", population));
+        }
+        if let Some((latitude, longitude)) = parser.coordinates(&location) {
+            details.push(format!("{:.4}, {:.4}", latitude, longitude));
+        }
+        match details.is_empty() {
+            true => println!("{}", location),
+            false => println!("{} ({})", location, details.join("; ")),
+        }
+    }
+}
+
+/// Parse lines of stdin until it's closed, printing every location along
+/// with its parts, so problematic inputs can be tried without reloading datasets.
+fn repl(parser: &geo_rs::Parser) {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush().expect("failed to flush stdout");
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        print_breakdown(&parser.parse_location(&line));
+    }
+    println!();
+}
+
+/// Print the location and each of its parts on a separate line.
+fn print_breakdown(location: &Location) {
+    println!(">> {}", location);
+    if let Some(city) = &location.city {
+        println!("  city: {}", city.name);
+    }
+    if let Some(state) = &location.state {
+        println!("  state: {} ({})", state.code, state.name);
+    }
+    if let Some(country) = &location.country {
+        println!("  country: {} ({})", country.code, country.name);
+    }
+    if let Some(zipcode) = &location.zipcode {
+        println!("  zipcode: {}", zipcode);
+    }
+    if let Some(address) = &location.address {
+        println!("  address: {}", address);
+        match (&address.unit_designator, &address.unit) {
+            (Some(designator), Some(unit)) => println!("  unit: {} {}", designator, unit),
+            (None, Some(unit)) => println!("  unit: #{}", unit),
+            _ => {}
+        }
+    }
+    if let Some(landmark) = &location.landmark {
+        println!("  landmark: {} ({})", landmark, landmark.kind.code());
+    }
+    if !location.warnings.is_empty() {
+        let warnings: Vec<String> = location.warnings.iter().map(|w| w.to_string()).collect();
+        println!("  warnings: {}", warnings.join(", "));
+    }
+    if !location.alternatives.is_empty() {
+        let alternatives: Vec<String> = location
+            .alternatives
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        println!("  alternatives: {}", alternatives.join("; "));
+    }
+}
+
+/// Check custom data files in the folder, print their issues and statistics,
+/// exit with an error when there are any issues.
+fn validate_data(dir: PathBuf) {
+    if !dir.is_dir() {
+        eprintln!("{} is not a folder", dir.display());
+        std::process::exit(1);
+    }
+    let report = geo_rs::datafiles::check(&DataSource::Dir(dir));
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+    println!("countries: {}", report.countries);
+    for (country, states) in &report.states {
+        let cities = report.cities.get(country).copied().unwrap_or_default();
+        let zipcodes = report
+            .zipcode_ranges
+            .get(country)
+            .copied()
+            .unwrap_or_default();
+        println!(
+            "{}: {} states, {} cities, {} zipcode ranges",
+            country, states, cities, zipcodes
+        );
+    }
+    if !report.is_valid() {
+        eprintln!("{} issues found", report.issues.len());
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,366 @@
+//! HTTP server that parses locations for services written in other languages:
+//!
+//! - `POST /parse` with `{"location": "Toronto, ON"}` returns a location
+//! - `POST /parse/batch` with `["Toronto, ON", "Lansing, MI"]` returns an array of them
+//!
+//! Locations are objects of the same fields as in the Python module, e.g.
+//! `{"city": "Toronto", "state": "ON", "state_name": "Ontario", ...}`.
+use crate::json::to_json;
+use geo_rs::watch::WatchedParser;
+use geo_rs::Parser;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Largest accepted request body, in bytes
+const MAX_BODY: usize = 1 << 20;
+/// Longest accepted request line or header line, in bytes
+const MAX_LINE: usize = 8 << 10;
+/// Connections handled at once, further ones wait until a worker is free
+const WORKERS: usize = 16;
+/// How long a client may take to send the whole request or to read the response
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serve requests on the host and port until the process is stopped. Connections
+/// are handled by a fixed number of workers with the current parser, a client
+/// that takes longer than `TIMEOUT` to send its request gets 408. The parser is
+/// reloaded when its data files change, see `geo_rs::watch`.
+pub fn serve(parser: Parser, host: &str, port: u16) -> io::Result<()> {
+    let watched = WatchedParser::new(parser).map_err(io::Error::other)?;
+    let listener = TcpListener::bind((host, port))?;
+    eprintln!("listening on {}", listener.local_addr()?);
+    let (sender, receiver) = mpsc::sync_channel::<(TcpStream, Arc<Parser>)>(WORKERS);
+    spawn_workers(receiver, WORKERS, |parser, stream| {
+        handle(parser, stream, TIMEOUT)
+    });
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        // Blocks while every worker is busy and the queue is full
+        if sender.send((stream, watched.load())).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Start workers that handle connections of the receiver until its sender is
+/// dropped. A request that panics gets 500 and its worker moves on to the next
+/// connection, so that panics don't reduce the number of workers.
+fn spawn_workers<F>(
+    receiver: mpsc::Receiver<(TcpStream, Arc<Parser>)>,
+    count: usize,
+    handle: F,
+) -> Vec<thread::JoinHandle<()>>
+where
+    F: Fn(&Parser, TcpStream) -> io::Result<()> + Send + Sync + 'static,
+{
+    let receiver = Arc::new(Mutex::new(receiver));
+    let handle = Arc::new(handle);
+    (0..count)
+        .map(|_| {
+            let receiver = receiver.clone();
+            let handle = handle.clone();
+            thread::spawn(move || loop {
+                let next = receiver.lock().expect("worker panicked").recv();
+                let (stream, parser) = match next {
+                    Ok(connection) => connection,
+                    Err(_) => break,
+                };
+                let fallback = stream.try_clone();
+                let result = match panic::catch_unwind(AssertUnwindSafe(|| handle(&parser, stream)))
+                {
+                    Ok(result) => result,
+                    Err(_) => fallback.and_then(|stream| {
+                        let body = json!({ "error": "internal server error" });
+                        write_response(stream, 500, &body)
+                    }),
+                };
+                if let Err(e) = result {
+                    eprintln!("failed to handle a request: {}", e);
+                }
+            })
+        })
+        .collect()
+}
+
+/// Reads from the stream until the deadline, however often the client sends data,
+/// so that a slow client can't hold a worker for longer than the timeout.
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Method, path and body of a request.
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// Read one request from the connection within the timeout and write the
+/// response, the connection is closed afterwards.
+fn handle(parser: &Parser, stream: TcpStream, timeout: Duration) -> io::Result<()> {
+    let mut reader = BufReader::new(Deadline {
+        stream: stream.try_clone()?,
+        deadline: Instant::now() + timeout,
+    });
+    let (status, body) = match read_request(&mut reader) {
+        Ok(request) => respond(parser, &request.method, &request.path, &request.body),
+        Err(response) => response,
+    };
+    write_response(stream, status, &body)
+}
+
+/// Read the request, or return the error response when it can't be read.
+fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, (u16, Value)> {
+    let request_line = match read_line(reader)? {
+        Some(line) => line,
+        None => return Err((400, json!({ "error": "request line is too long" }))),
+    };
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+    let mut content_length = 0;
+    loop {
+        let header = match read_line(reader)? {
+            Some(header) => header,
+            None => return Err((431, json!({ "error": "header is too long" }))),
+        };
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err((413, json!({ "error": "request body is too large" })));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(read_error)?;
+    Ok(Request { method, path, body })
+}
+
+/// Read a line of the request head, `None` when it's longer than `MAX_LINE`.
+/// The line is empty at the end of the stream.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<String>, (u16, Value)> {
+    let mut line = String::new();
+    reader
+        .take(MAX_LINE as u64)
+        .read_line(&mut line)
+        .map_err(read_error)?;
+    if line.len() >= MAX_LINE && !line.ends_with('\n') {
+        return Ok(None);
+    }
+    Ok(Some(line))
+}
+
+/// Response to a request that failed to be read, 408 when the client is too slow.
+fn read_error(e: io::Error) -> (u16, Value) {
+    match e.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+            (408, json!({ "error": "request took too long" }))
+        }
+        _ => (400, json!({ "error": format!("malformed request: {}", e) })),
+    }
+}
+
+/// Status and JSON body of the response to the request.
+fn respond(parser: &Parser, method: &str, path: &str, body: &[u8]) -> (u16, Value) {
+    let batch = match path {
+        "/parse" => false,
+        "/parse/batch" => true,
+        _ => return (404, json!({ "error": "not found" })),
+    };
+    if method != "POST" {
+        return (405, json!({ "error": "only POST is allowed" }));
+    }
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return (400, json!({ "error": format!("invalid JSON: {}", e) })),
+    };
+    if !batch {
+        return match request.get("location").and_then(Value::as_str) {
+            Some(input) => (200, to_json(&parser.parse_location(input))),
+            None => (400, json!({ "error": "expected {\"location\": \"...\"}" })),
+        };
+    }
+    let inputs: Option<Vec<&str>> = request
+        .as_array()
+        .and_then(|inputs| inputs.iter().map(Value::as_str).collect());
+    match inputs {
+        Some(inputs) => {
+            let locations = inputs
+                .iter()
+                .map(|input| to_json(&parser.parse_location(input)))
+                .collect();
+            (200, Value::Array(locations))
+        }
+        None => (400, json!({ "error": "expected an array of strings" })),
+    }
+}
+
+/// Reason phrase of the status, empty for statuses the server never sends.
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
+
+fn write_response(mut stream: TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond() {
+        let parser = Parser::new();
+        let (status, body) = respond(&parser, "POST", "/parse", br#"{"location": "Toronto, ON"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(body["city"], "Toronto");
+        assert_eq!(body["country_name"], "Canada");
+        let request = br#"["Lansing, MI", "Sausalito"]"#;
+        let (status, body) = respond(&parser, "POST", "/parse/batch", request);
+        assert_eq!(status, 200);
+        assert_eq!(body[0]["state"], "MI");
+        assert_eq!(body[1]["warnings"], json!(["W003", "W004"]));
+        assert_eq!(respond(&parser, "GET", "/parse", b"").0, 405);
+        assert_eq!(respond(&parser, "POST", "/geocode", b"").0, 404);
+        assert_eq!(respond(&parser, "POST", "/parse", b"Toronto").0, 400);
+        assert_eq!(respond(&parser, "POST", "/parse/batch", b"[1, 2]").0, 400);
+    }
+
+    #[test]
+    fn test_reason() {
+        assert_eq!(reason(404), "Not Found");
+        assert_eq!(reason(413), "Payload Too Large");
+        assert_eq!(reason(408), "Request Timeout");
+        assert_eq!(reason(500), "Internal Server Error");
+        assert_eq!(reason(418), "");
+    }
+
+    #[test]
+    fn test_read_request() {
+        let request = "POST /parse HTTP/1.1\r\nContent-Length: 4\r\n\r\nbody";
+        let request = read_request(&mut request.as_bytes()).ok().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/parse");
+        assert_eq!(request.body, b"body");
+        let long = format!(
+            "POST /parse HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+            "a".repeat(MAX_LINE)
+        );
+        assert_eq!(read_request(&mut long.as_bytes()).err().unwrap().0, 431);
+        let long = format!("POST /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(read_request(&mut long.as_bytes()).err().unwrap().0, 400);
+        let large = format!(
+            "POST /parse HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert_eq!(read_request(&mut large.as_bytes()).err().unwrap().0, 413);
+    }
+
+    #[test]
+    fn test_slow_client_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(b"POST /parse HTTP/1.1\r\n").unwrap();
+            // A byte at a time never lets a single read time out
+            let started = Instant::now();
+            while started.elapsed() < Duration::from_secs(5) && stream.write_all(b"X").is_ok() {
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let started = Instant::now();
+        let mut reader = BufReader::new(Deadline {
+            stream,
+            deadline: started + Duration::from_millis(300),
+        });
+        let status = read_request(&mut reader).err().unwrap().0;
+        assert_eq!(status, 408);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        drop(reader);
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn test_panicking_request_keeps_its_worker() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = requests.clone();
+        // A single worker, so the second request is handled by the one that panicked
+        let workers = spawn_workers(receiver, 1, move |_, stream| {
+            if counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                panic!("failed to parse");
+            }
+            write_response(stream, 200, &json!({}))
+        });
+        let parser = Arc::new(Parser::new());
+        for status in ["500 Internal Server Error", "200 OK"] {
+            let mut client = TcpStream::connect(address).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            sender.send((stream, parser.clone())).unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+            assert!(
+                response.starts_with(&format!("HTTP/1.1 {}", status)),
+                "{}",
+                response
+            );
+        }
+        drop(sender);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Parsing of GeoNames dumps and merging them into the datasets of `geo-rs`.
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// GeoNames admin1 codes of Canadian provinces, US dumps use state codes as is
+const CA_PROVINCES: [(&str, &str); 13] = [
+    ("01", "AB"),
+    ("02", "BC"),
+    ("03", "MB"),
+    ("04", "NB"),
+    ("05", "NL"),
+    ("07", "NS"),
+    ("08", "ON"),
+    ("09", "PE"),
+    ("10", "QC"),
+    ("11", "SK"),
+    ("12", "YT"),
+    ("13", "NT"),
+    ("14", "NU"),
+];
+
+/// A populated place from a GeoNames dump.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Place {
+    pub state: String,
+    pub name: String,
+    pub population: u64,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Abbreviations of the place, e.g. "NYC"
+    pub abbreviations: Vec<String>,
+}
+
+/// Parse a line of a GeoNames dump, only populated places (feature class P) are taken.
+///
+/// # Arguments
+///
+/// * `country` - Country code of the dump, e.g. "US"
+/// * `line` - Tab separated line of the dump
+pub fn parse_place(country: &str, line: &str) -> Option<Place> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() < 15 || columns[6] != "P" || columns[8] != country {
+        return None;
+    }
+    let state = match country {
+        "CA" => CA_PROVINCES
+            .iter()
+            .find(|(admin1, _)| *admin1 == columns[10])
+            .map(|(_, code)| code.to_string())?,
+        _ => columns[10].to_string(),
+    };
+    if state.is_empty() {
+        return None;
+    }
+    let abbreviations = columns[3]
+        .split(',')
+        .filter(|a| {
+            (2..=4).contains(&a.len()) && a.chars().all(|c| c.is_ascii_uppercase()) && a != &state
+        })
+        .map(String::from)
+        .collect();
+    Some(Place {
+        state,
+        name: columns[1].to_string(),
+        population: columns[14].parse().unwrap_or(0),
+        latitude: columns[4].parse().ok()?,
+        longitude: columns[5].parse().ok()?,
+        abbreviations,
+    })
+}
+
+/// Merge places into lines of `cities.txt`. Known cities keep their names and
+/// get population and coordinates of the most populated place with the same name,
+/// unknown cities are added when they are populated enough. Cities of every state
+/// are ordered from the longest name to the shortest one, like `formatter.py` does.
+///
+/// # Arguments
+///
+/// * `lines` - Lines of the current `cities.txt`
+/// * `places` - Places from a dump
+/// * `min_population` - Minimal population of a city that isn't known yet
+pub fn merge_cities(lines: &[String], places: &[Place], min_population: u64) -> Vec<String> {
+    let mut by_key: HashMap<(String, String), &Place> = HashMap::new();
+    for place in places {
+        let key = (place.state.clone(), place.name.to_lowercase());
+        match by_key.get(&key) {
+            Some(p) if p.population >= place.population => (),
+            _ => {
+                by_key.insert(key, place);
+            }
+        }
+    }
+    let mut states: Vec<String> = vec![];
+    let mut cities: HashMap<String, Vec<(String, Option<String>)>> = HashMap::new();
+    let mut known: Vec<(String, String)> = vec![];
+    for line in lines {
+        let parts: Vec<&str> = line.split(';').collect();
+        if parts.len() < 2 {
+            continue;
+        }
+        let (state, name) = (parts[0].to_string(), parts[1].to_string());
+        if !states.contains(&state) {
+            states.push(state.clone());
+        }
+        let key = (state.clone(), name.to_lowercase());
+        let columns = match by_key.get(&key) {
+            Some(p) => Some(place_columns(p)),
+            None => parts
+                .get(2..)
+                .map(|c| c.join(";"))
+                .filter(|c| !c.is_empty()),
+        };
+        cities.entry(state).or_default().push((name, columns));
+        known.push(key);
+    }
+    let mut added: Vec<&Place> = by_key
+        .iter()
+        .filter(|(key, p)| p.population >= min_population && !known.contains(key))
+        .map(|(_, p)| *p)
+        .collect();
+    added.sort_by(|a, b| a.name.cmp(&b.name));
+    for place in added {
+        if !states.contains(&place.state) {
+            states.push(place.state.clone());
+        }
+        cities
+            .entry(place.state.clone())
+            .or_default()
+            .push((place.name.clone(), Some(place_columns(place))));
+    }
+    let mut output = vec![];
+    for state in states {
+        let mut state_cities = cities.remove(&state).unwrap_or_default();
+        state_cities.sort_by_key(|(name, _)| Reverse(name.len()));
+        for (name, columns) in state_cities {
+            match columns {
+                Some(c) => output.push(format!("{};{};{}", state, name, c)),
+                None => output.push(format!("{};{}", state, name)),
+            }
+        }
+    }
+    output
+}
+
+/// Merge abbreviations of places into lines of `aliases.txt`, existing aliases are kept.
+///
+/// # Arguments
+///
+/// * `lines` - Lines of the current `aliases.txt`
+/// * `places` - Places from a dump
+/// * `min_population` - Minimal population of a city to take its abbreviations
+pub fn merge_aliases(lines: &[String], places: &[Place], min_population: u64) -> Vec<String> {
+    let mut output: Vec<String> = lines.iter().filter(|l| !l.is_empty()).cloned().collect();
+    let known: Vec<String> = output
+        .iter()
+        .filter_map(|l| l.split(';').nth(2))
+        .map(|a| a.to_lowercase())
+        .collect();
+    let mut places: Vec<&Place> = places
+        .iter()
+        .filter(|p| p.population >= min_population)
+        .collect();
+    // The most populated place takes the abbreviation, e.g. "LA" is Los Angeles
+    places.sort_by_key(|p| Reverse(p.population));
+    let mut taken: Vec<String> = vec![];
+    for place in places {
+        for abbreviation in &place.abbreviations {
+            let lowercase = abbreviation.to_lowercase();
+            if known.contains(&lowercase) || taken.contains(&lowercase) {
+                continue;
+            }
+            taken.push(lowercase);
+            output.push(format!("{};{};{}", place.state, place.name, abbreviation));
+        }
+    }
+    output
+}
+
+fn place_columns(place: &Place) -> String {
+    format!(
+        "{};{:.4};{:.4}",
+        place.population, place.latitude, place.longitude
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(state: &str, name: &str, population: u64) -> Place {
+        Place {
+            state: String::from(state),
+            name: String::from(name),
+            population,
+            latitude: 1.0,
+            longitude: -2.0,
+            abbreviations: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse_place() {
+        let line = "5128581\tNew York City\tNew York City\tNYC,New York,NY,Nueva York\t40.71427\t-74.00597\tP\tPPL\tUS\t\tNY\t\t\t\t8804190\t10\t57\tAmerica/New_York\t2024-01-01";
+        let place = parse_place("US", line).unwrap();
+        assert_eq!(place.state, "NY");
+        assert_eq!(place.name, "New York City");
+        assert_eq!(place.population, 8804190);
+        assert_eq!(place.abbreviations, vec![String::from("NYC")]);
+        let line = "6167865\tToronto\tToronto\t\t43.70011\t-79.4163\tP\tPPLA\tCA\t\t08\t\t\t\t2600000\t\t175\tAmerica/Toronto\t2024-01-01";
+        assert_eq!(parse_place("CA", line).unwrap().state, "ON");
+        let line = "5128638\tNew York\tNew York\t\t43.00035\t-75.4999\tA\tADM1\tUS\t\tNY\t\t\t\t19274244\t\t\t\t";
+        assert_eq!(parse_place("US", line), None);
+    }
+
+    #[test]
+    fn test_merge_cities() {
+        let lines = vec![
+            String::from("AL;Birmingham;200733"),
+            String::from("AL;Apo"),
+            String::from("AK;Nome"),
+        ];
+        let places = vec![
+            place("AL", "Birmingham", 210000),
+            place("AL", "Huntsville", 215000),
+            place("AL", "Tiny", 10),
+        ];
+        assert_eq!(
+            merge_cities(&lines, &places, 1000),
+            vec![
+                "AL;Birmingham;210000;1.0000;-2.0000",
+                "AL;Huntsville;215000;1.0000;-2.0000",
+                "AL;Apo",
+                "AK;Nome",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_aliases() {
+        let lines = vec![String::from("NY;New York;NYC")];
+        let mut new_york = place("NY", "New York City", 8804190);
+        new_york.abbreviations = vec![String::from("NYC")];
+        let mut los_angeles = place("CA", "Los Angeles", 3898747);
+        los_angeles.abbreviations = vec![String::from("LA")];
+        let mut lake_arrowhead = place("CA", "Lake Arrowhead", 12424);
+        lake_arrowhead.abbreviations = vec![String::from("LA")];
+        assert_eq!(
+            merge_aliases(&lines, &[lake_arrowhead, new_york, los_angeles], 1000),
+            vec!["NY;New York;NYC", "CA;Los Angeles;LA"]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+#[macro_use]
+extern crate log;
+
+mod geonames;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const DUMPS_URL: &str = "https://download.geonames.org/export/dump";
+
+struct Args {
+    data_dir: PathBuf,
+    dumps_dir: Option<PathBuf>,
+    countries: Vec<String>,
+    min_population: u64,
+}
+
+fn main() {
+    env_logger::init();
+    if std::env::args().nth(1).as_deref() == Some("snapshot") {
+        if let Err(e) = write_snapshot() {
+            error!("Failed to write the snapshot: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let args = parse_args();
+    for country in &args.countries {
+        if let Err(e) = update_country(&args, country) {
+            error!("Failed to update {}: {}", country, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        data_dir: PathBuf::from("lib/src/data"),
+        dumps_dir: None,
+        countries: vec![String::from("US"), String::from("CA")],
+        min_population: 1000,
+    };
+    let mut raw = std::env::args().skip(1);
+    while let Some(flag) = raw.next() {
+        let value = raw
+            .next()
+            .unwrap_or_else(|| panic!("no value given for {}", flag));
+        match flag.as_str() {
+            "--data-dir" => args.data_dir = PathBuf::from(value),
+            "--dumps-dir" => args.dumps_dir = Some(PathBuf::from(value)),
+            "--countries" => args.countries = value.split(',').map(String::from).collect(),
+            "--min-population" => {
+                args.min_population = value.parse().expect("population is not a number")
+            }
+            _ => panic!("unknown option {}", flag),
+        }
+    }
+    args
+}
+
+/// Merge GeoNames dump of the country into its `cities.txt` and `aliases.txt`.
+fn update_country(args: &Args, country: &str) -> Result<(), String> {
+    let dump = read_dump(args, country)?;
+    let places: Vec<geonames::Place> = dump
+        .lines()
+        .filter_map(|line| geonames::parse_place(country, line))
+        .collect();
+    info!("{}: {} populated places in the dump", country, places.len());
+    let country_dir = args.data_dir.join(country);
+    let cities_path = country_dir.join("cities.txt");
+    let lines = read_lines(&cities_path)?;
+    let cities = geonames::merge_cities(&lines, &places, args.min_population);
+    info!(
+        "{}: {} cities, {} before",
+        country,
+        cities.len(),
+        lines.len()
+    );
+    write_lines(&cities_path, &cities)?;
+    let aliases_path = country_dir.join("aliases.txt");
+    let lines = read_lines(&aliases_path)?;
+    let aliases = geonames::merge_aliases(&lines, &places, args.min_population);
+    info!(
+        "{}: {} aliases, {} before",
+        country,
+        aliases.len(),
+        lines.len()
+    );
+    write_lines(&aliases_path, &aliases)
+}
+
+/// Parse datasets of the library and write them to `datasets.bin`
+/// next to them, see `geo_rs::snapshot`.
+fn write_snapshot() -> Result<(), String> {
+    let path = geo_rs::utils::data_dir().join(geo_rs::snapshot::SNAPSHOT_FILE);
+    geo_rs::snapshot::write_snapshot(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    info!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// Return content of `{country}.txt` from the `{country}.zip` dump,
+/// the dump is downloaded unless `--dumps-dir` is given.
+fn read_dump(args: &Args, country: &str) -> Result<String, String> {
+    let archive = match &args.dumps_dir {
+        Some(dir) => dir.join(format!("{}.zip", country)),
+        None => {
+            let path = std::env::temp_dir().join(format!("geonames-{}.zip", country));
+            let url = format!("{}/{}.zip", DUMPS_URL, country);
+            info!("Downloading {}", url);
+            run(Command::new("curl")
+                .args(["-sSfL", "-o"])
+                .arg(&path)
+                .arg(&url))?;
+            path
+        }
+    };
+    let output = run(Command::new("unzip")
+        .arg("-p")
+        .arg(&archive)
+        .arg(format!("{}.txt", country)))?;
+    String::from_utf8(output).map_err(|e| e.to_string())
+}
+
+fn run(command: &mut Command) -> Result<Vec<u8>, String> {
+    let output = command.output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+    Ok(output.stdout)
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content.lines().map(String::from).collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+fn write_lines(path: &Path, lines: &[String]) -> Result<(), String> {
+    let mut content = lines.join("\n");
+    content.push('\n');
+    fs::write(path, content).map_err(|e| format!("{}: {}", path.display(), e))
+}
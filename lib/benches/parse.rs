@@ -0,0 +1,118 @@
+//! Benchmarks of `Parser::parse_location` and of the stages it's made of
+//! across the mock corpus and a generated one, run with `cargo bench -p geo-rs`.
+//! Pass a name to run a single group, e.g. `cargo bench -p geo-rs -- fill_state`.
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use geo_rs::nodes;
+use geo_rs::nodes::Location;
+use geo_rs::Parser;
+use std::hint::black_box;
+
+#[path = "../src/mocks.rs"]
+#[allow(dead_code)]
+mod mocks;
+
+fn corpus() -> Vec<&'static str> {
+    let mut inputs: Vec<&'static str> = mocks::get_mocks().into_keys().collect();
+    inputs.sort();
+    inputs
+}
+
+fn bench_group(c: &mut Criterion, name: &str, fill: impl Fn(&Parser, &str)) {
+    let parser = Parser::new();
+    let inputs = corpus();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(inputs.len() as u64));
+    group.bench_function("mocks", |b| {
+        b.iter(|| {
+            for input in &inputs {
+                fill(&parser, black_box(input));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn parse_location(c: &mut Criterion) {
+    bench_group(c, "parse_location", |parser, input| {
+        black_box(parser.parse_location(input));
+    });
+}
+
+/// Same as `parse_location` but over messy strings of `geo_rs::testing::generate`
+fn parse_generated(c: &mut Criterion) {
+    let parser = Parser::new();
+    let inputs: Vec<String> = geo_rs::testing::generate(10_000, 0)
+        .into_iter()
+        .map(|s| s.input)
+        .collect();
+    let mut group = c.benchmark_group("parse_location");
+    group.throughput(Throughput::Elements(inputs.len() as u64));
+    group.bench_function("generated", |b| {
+        b.iter(|| {
+            for input in &inputs {
+                black_box(parser.parse_location(black_box(input)));
+            }
+        })
+    });
+    group.finish();
+}
+
+/// Inputs that used to be much slower to parse than the mock corpus on average,
+/// e.g. a city that isn't in the dataset with a word that looks like a state
+const SLOW_INPUTS: [&str; 1] = ["Colleretto Giacosa"];
+
+/// Same as `parse_location` but over `SLOW_INPUTS`, one at a time
+fn parse_slow(c: &mut Criterion) {
+    let parser = Parser::new();
+    let mut group = c.benchmark_group("parse_location");
+    for input in SLOW_INPUTS {
+        group.bench_function(input, |b| {
+            b.iter(|| black_box(parser.parse_location(black_box(input))))
+        });
+    }
+    group.finish();
+}
+
+fn fill_city(c: &mut Criterion) {
+    bench_group(c, "fill_city", |parser, input| {
+        let mut location = Location::default();
+        parser.fill_city(&mut location, input);
+        black_box(location);
+    });
+}
+
+fn fill_state(c: &mut Criterion) {
+    bench_group(c, "fill_state", |parser, input| {
+        let mut location = Location::default();
+        parser.fill_state(&mut location, input);
+        black_box(location);
+    });
+}
+
+fn fill_country(c: &mut Criterion) {
+    bench_group(c, "fill_country", |parser, input| {
+        let mut location = Location::default();
+        parser.fill_country(&mut location, input);
+        black_box(location);
+    });
+}
+
+fn fill_zipcode(c: &mut Criterion) {
+    bench_group(c, "fill_zipcode", |parser, input| {
+        let mut location = Location::default();
+        parser.fill_zipcode(&mut location, input);
+        black_box(location);
+    });
+}
+
+criterion_group!(
+    benches,
+    parse_location,
+    parse_generated,
+    parse_slow,
+    fill_city,
+    fill_state,
+    fill_country,
+    fill_zipcode
+);
+criterion_main!(benches);
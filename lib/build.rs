@@ -0,0 +1,59 @@
+//! Generate the table of data files that `embedded-data` feature embeds into
+//! the binary, see `utils::DataSource::Embedded`. The table is empty without it,
+//! files of datasets whose `data-*` feature is disabled are left out.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Data files and folders that are only embedded along with their feature,
+/// so that leaving a dataset out also keeps it out of the binary
+const OPTIONAL_DATA: &[(&str, &str)] = &[
+    ("US", "CARGO_FEATURE_DATA_US"),
+    ("CA", "CARGO_FEATURE_DATA_CA"),
+    ("world/cities.txt", "CARGO_FEATURE_DATA_WORLD"),
+    ("world/major_cities.txt", "CARGO_FEATURE_DATA_WORLD_MAJOR"),
+];
+
+/// Check whether the file or folder, relative to `src/data`, belongs to a disabled dataset.
+fn is_disabled(name: &Path) -> bool {
+    OPTIONAL_DATA
+        .iter()
+        .any(|(path, feature)| name == Path::new(path) && env::var_os(feature).is_none())
+}
+
+fn main() {
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("src/data");
+    println!("cargo:rerun-if-changed={}", root.display());
+    println!("cargo:rerun-if-changed=build.rs");
+    let mut files: Vec<PathBuf> = vec![];
+    if env::var_os("CARGO_FEATURE_EMBEDDED_DATA").is_some() {
+        let mut dirs = vec![root.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                if is_disabled(path.strip_prefix(&root).unwrap()) {
+                    continue;
+                }
+                if path.is_dir() {
+                    println!("cargo:rerun-if-changed={}", path.display());
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|e| e == "txt") {
+                    files.push(path);
+                }
+            }
+        }
+    }
+    files.sort();
+    let mut table = String::from("&[\n");
+    for path in files {
+        let name = path.strip_prefix(&root).unwrap().to_string_lossy();
+        table.push_str(&format!(
+            "    ({:?}, include_bytes!({:?})),\n",
+            name.replace('\\', "/"),
+            path.to_string_lossy()
+        ));
+    }
+    table.push(']');
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("embedded_data.rs");
+    fs::write(out, table).unwrap();
+}
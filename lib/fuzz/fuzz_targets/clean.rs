@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let mut s = input.to_string();
+    geo_rs::utils::clean(&mut s);
+});
@@ -0,0 +1,14 @@
+#![no_main]
+use geo_rs::nodes::Location;
+use geo_rs::Parser;
+use lazy_static::lazy_static;
+use libfuzzer_sys::fuzz_target;
+
+lazy_static! {
+    static ref PARSER: Parser = Parser::new();
+}
+
+fuzz_target!(|input: &str| {
+    let mut location = Location::default();
+    PARSER.fill_zipcode(&mut location, input);
+});
@@ -0,0 +1,12 @@
+#![no_main]
+use geo_rs::Parser;
+use lazy_static::lazy_static;
+use libfuzzer_sys::fuzz_target;
+
+lazy_static! {
+    static ref PARSER: Parser = Parser::new();
+}
+
+fuzz_target!(|input: &str| {
+    PARSER.parse_location(input);
+});
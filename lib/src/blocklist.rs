@@ -0,0 +1,67 @@
+use crate::Parser;
+use itertools::Itertools;
+use unidecode::unidecode;
+
+/// Lowercase ASCII words of the string joined with a single space,
+/// so that "N/A", "n / a" and "N.A." are the same entry of the blocklist.
+pub(crate) fn blocklist_key(s: &str) -> String {
+    unidecode(s)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .join(" ")
+}
+
+impl Parser {
+    /// Whether the whole input is on the blocklist, see `ParserBuilder::with_blocklist`.
+    /// Letter case, punctuation and spacing don't matter.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Raw location string
+    pub fn is_blocked(&self, input: &str) -> bool {
+        !self.blocklist.is_empty() && self.blocklist.contains(&blocklist_key(input))
+    }
+}
+
+#[cfg(all(test, feature = "data-ca"))]
+mod tests {
+    use super::*;
+    use crate::nodes::{Location, Warning};
+
+    #[test]
+    fn test_parse_blocked_inputs() {
+        let parser = Parser::builder()
+            .with_blocklist(["Remote", "N/A", "TBD", "Multiple Locations"])
+            .build();
+        for input in [
+            "Remote",
+            " remote ",
+            "n/a",
+            "N / A",
+            "TBD.",
+            "MULTIPLE LOCATIONS",
+        ] {
+            let location = parser.parse_location(input);
+            assert_eq!(
+                location,
+                Location::builder()
+                    .with_raw(input)
+                    .with_warning(Warning::Blocked)
+                    .build(),
+                "input: {}",
+                input
+            );
+        }
+        for (input, output) in [
+            ("Remote, Toronto, ON", "Toronto, ON, CA"),
+            ("Tbd Toronto, ON", "Toronto, ON, CA"),
+            ("", ""),
+        ] {
+            let location = parser.parse_location(input);
+            assert_eq!(location.to_string(), output, "input: {}", input);
+            assert!(!location.warnings.contains(&Warning::Blocked));
+        }
+        assert!(!Parser::new().is_blocked("Remote"));
+    }
+}
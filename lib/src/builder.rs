@@ -0,0 +1,523 @@
+use crate::blocklist::blocklist_key;
+use crate::metrics::MetricsSink;
+use crate::nodes::{city, country, landmark, state, LandmarkKind, Location};
+use crate::schema::{self, SchemaError};
+use crate::site::site_key;
+use crate::snapshot::Datasets;
+use crate::special::SpecialCase;
+use crate::stages::{self, Stage};
+use crate::utils::DataSource;
+use crate::{noise, utils, NormalizeStep, Parser, ParserOptions};
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Builder for a `Parser` with custom options and dictionaries.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let parser = geo_rs::Parser::builder()
+///     .with_abbreviations(vec![("Hwy", "Highway")])
+///     .with_city_alias("The Big Apple", "US", "NY", "New York")
+///     .build();
+/// let location = parser.parse_location("The Big Apple");
+/// assert_eq!(location.to_string(), String::from("New York, NY, US"));
+/// ```
+#[derive(Debug, Default)]
+pub struct ParserBuilder {
+    options: ParserOptions,
+    abbreviations: Vec<(String, String)>,
+    noise_patterns: Vec<String>,
+    removed_noise_patterns: Vec<String>,
+    normalize_steps: Option<Vec<NormalizeStep>>,
+    city_aliases: Vec<(String, String, String, String)>,
+    country_aliases: Vec<(String, String)>,
+    special_cases: Vec<(String, String, String, String)>,
+    landmark_patterns: Vec<(String, LandmarkKind)>,
+    site_codes: Vec<(String, Location)>,
+    blocklist: Vec<String>,
+    stages: Option<Vec<Box<dyn Stage>>>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    data_dir: Option<PathBuf>,
+}
+
+impl ParserBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set options of the parser.
+    pub fn with_options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Add abbreviations that are expanded before parsing in addition
+    /// to the ones from `src/data/abbreviations.txt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `abbreviations` - Pairs of abbreviation and its expansion, e.g. ("Hwy", "Highway")
+    pub fn with_abbreviations<I, A, E>(mut self, abbreviations: I) -> Self
+    where
+        I: IntoIterator<Item = (A, E)>,
+        A: Into<String>,
+        E: Into<String>,
+    {
+        self.abbreviations
+            .extend(abbreviations.into_iter().map(|(a, e)| (a.into(), e.into())));
+        self
+    }
+
+    /// Add patterns of noise that is removed before parsing, e.g. store numbers,
+    /// in addition to the ones from `src/data/noise_patterns.txt`.
+    /// Invalid regular expressions are ignored with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - Regular expressions matching noise, e.g. r"\s*-\s*Kiosk \d+$"
+    pub fn with_noise_patterns<I, P>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<String>,
+    {
+        self.noise_patterns
+            .extend(patterns.into_iter().map(|p| p.into()));
+        self
+    }
+
+    /// Remove a pattern from `src/data/noise_patterns.txt`, e.g. to keep consonant-only
+    /// codes like "DFW" that are stripped by default. Unknown patterns are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Regular expression exactly as it's written in the file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let input = "DFW, Dallas, TX";
+    /// let parser = geo_rs::Parser::new();
+    /// assert_eq!(parser.normalize(input), String::from("Dallas, TX"));
+    /// let parser = geo_rs::Parser::builder()
+    ///     .without_noise_pattern(r"\b[QWRTPSDFGHKLZXCVBNM]{3,5}\b\s*")
+    ///     .build();
+    /// assert_eq!(parser.normalize(input), String::from("Dfw, Dallas, TX"));
+    /// ```
+    pub fn without_noise_pattern(mut self, pattern: &str) -> Self {
+        self.removed_noise_patterns.push(pattern.to_string());
+        self
+    }
+
+    /// Run these steps of cleaning before parsing in this order instead of all of
+    /// them, see `NormalizeStep`. Parsing relies on punctuation and whitespace
+    /// being normalized, turning them off is only useful for inputs that already are.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - Steps to run, the ones that aren't listed are turned off
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::NormalizeStep;
+    /// let input = "600778 Toronto, ON";
+    /// let parser = geo_rs::Parser::new();
+    /// assert_eq!(parser.normalize(input), String::from("Toronto, ON"));
+    /// let parser = geo_rs::Parser::builder()
+    ///     .with_normalize_steps(vec![
+    ///         NormalizeStep::Abbreviations,
+    ///         NormalizeStep::Brackets,
+    ///         NormalizeStep::Punctuation,
+    ///         NormalizeStep::Whitespace,
+    ///         NormalizeStep::Dedup,
+    ///     ])
+    ///     .build();
+    /// assert_eq!(parser.normalize(input), String::from("600778 Toronto, ON"));
+    /// ```
+    pub fn with_normalize_steps<I>(mut self, steps: I) -> Self
+    where
+        I: IntoIterator<Item = NormalizeStep>,
+    {
+        self.normalize_steps = Some(steps.into_iter().unique().collect());
+        self
+    }
+
+    /// Add an alternate name of a city in addition to the ones
+    /// from `src/data/{country}/aliases.txt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `alias` - Alternate name of the city, e.g. "Philly"
+    /// * `country` - Country code of the city, e.g. "US"
+    /// * `state` - State code of the city, e.g. "PA"
+    /// * `city` - Name of the city, e.g. "Philadelphia"
+    pub fn with_city_alias(mut self, alias: &str, country: &str, state: &str, city: &str) -> Self {
+        self.city_aliases.push((
+            alias.to_string(),
+            country.to_string(),
+            state.to_string(),
+            city.to_string(),
+        ));
+        self
+    }
+
+    /// Add an alternate name of a country in addition to the ones
+    /// from `src/data/country_aliases.txt`. Custom aliases take priority.
+    ///
+    /// # Arguments
+    ///
+    /// * `alias` - Alternate name of the country, e.g. "Holland"
+    /// * `country` - Country code, e.g. "NL"
+    pub fn with_country_alias(mut self, alias: &str, country: &str) -> Self {
+        self.country_aliases
+            .push((alias.to_string(), country.to_string()));
+        self
+    }
+
+    /// Add a special case in addition to the ones from `src/data/special_cases.txt`,
+    /// an input matching the pattern is the given city no matter what else it says.
+    /// Custom special cases take priority, invalid patterns and unknown states
+    /// are ignored with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Regular expression matched against the ASCII input, e.g. r"(?i)\bthe big apple\b"
+    /// * `city` - Name of the city, e.g. "New York"
+    /// * `state` - State code of the city, e.g. "NY"
+    /// * `country` - Country code of the city, e.g. "US"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::builder()
+    ///     .with_special_case(r"(?i)\bnational mall\b", "Washington", "DC", "US")
+    ///     .build();
+    /// let location = parser.parse_location("Kiosk 4, National Mall");
+    /// assert_eq!(location.to_string(), String::from("Washington, DC, US"));
+    /// ```
+    pub fn with_special_case(
+        mut self,
+        pattern: &str,
+        city: &str,
+        state: &str,
+        country: &str,
+    ) -> Self {
+        self.special_cases.push((
+            pattern.to_string(),
+            city.to_string(),
+            state.to_string(),
+            country.to_string(),
+        ));
+        self
+    }
+
+    /// Add a pattern of landmarks in addition to the ones from `src/data/landmarks.txt`,
+    /// a segment of the input matching it is stored in `Location::landmark` instead
+    /// of being taken for a city. Custom patterns take priority, invalid ones
+    /// are ignored with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Regular expression matched against segments of the ASCII input, e.g. r"(?i)\bstadium\b"
+    /// * `kind` - Kind of the matching landmarks
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::LandmarkKind;
+    /// let parser = geo_rs::Parser::builder()
+    ///     .with_landmark_pattern(r"(?i)\bhangar\b", LandmarkKind::Building)
+    ///     .build();
+    /// let location = parser.parse_location("Hangar 7, Seattle, WA");
+    /// assert_eq!(location.to_string(), String::from("Seattle, WA, US"));
+    /// assert_eq!(location.landmark.unwrap().name, String::from("Hangar 7"));
+    /// ```
+    pub fn with_landmark_pattern(mut self, pattern: &str, kind: LandmarkKind) -> Self {
+        self.landmark_patterns.push((pattern.to_string(), kind));
+        self
+    }
+
+    /// Resolve internal site codes of a company, e.g. "DCCL" or "410 -", to the locations
+    /// of the sites before anything else, instead of treating them as noise. An input
+    /// that is a code, or starts with one, is the site's location no matter what else
+    /// it says, see `Parser::fill_site_code`.
+    ///
+    /// # Arguments
+    ///
+    /// * `codes` - Locations of the sites by their codes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::Location;
+    /// use std::collections::HashMap;
+    /// let site = Location::builder()
+    ///     .with_city("Mississauga")
+    ///     .with_state("ON", "Ontario")
+    ///     .with_country("CA", "Canada")
+    ///     .build();
+    /// let parser = geo_rs::Parser::builder()
+    ///     .with_site_codes(HashMap::from([(String::from("DCCL"), site)]))
+    ///     .build();
+    /// let location = parser.parse_location("DCCL");
+    /// assert_eq!(location.to_string(), String::from("Mississauga, ON, CA"));
+    /// ```
+    pub fn with_site_codes(mut self, codes: HashMap<String, Location>) -> Self {
+        self.site_codes.extend(codes);
+        self
+    }
+
+    /// Never parse these inputs, e.g. "Remote" or "N/A", so that garbage doesn't match
+    /// a city by accident. An input that is one of them as a whole, whatever its letter
+    /// case, punctuation and spacing, is parsed into an empty location with
+    /// `Warning::Blocked`.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Inputs to block, e.g. ["Remote", "N/A", "TBD"]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::Warning;
+    /// let parser = geo_rs::Parser::builder()
+    ///     .with_blocklist(["Remote", "N/A", "TBD", "Multiple Locations"])
+    ///     .build();
+    /// let location = parser.parse_location("multiple locations");
+    /// assert_eq!(location.to_string(), String::new());
+    /// assert_eq!(location.warnings, vec![Warning::Blocked]);
+    /// ```
+    pub fn with_blocklist<I, S>(mut self, inputs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.blocklist
+            .extend(inputs.into_iter().map(|i| i.as_ref().to_string()));
+        self
+    }
+
+    /// Replace the pipeline with the given stages, e.g. to reorder or remove
+    /// some of `stages::default_stages()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `stages` - Stages in the order they should run
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let stages = geo_rs::stages::default_stages()
+    ///     .into_iter()
+    ///     .filter(|s| s.name() != "zipcode")
+    ///     .collect();
+    /// let parser = geo_rs::Parser::builder().with_stages(stages).build();
+    /// let location = parser.parse_location("Lansing, MI, US, 48911");
+    /// assert_eq!(location.zipcode, None);
+    /// ```
+    pub fn with_stages(mut self, stages: Vec<Box<dyn Stage>>) -> Self {
+        self.stages = Some(stages);
+        self
+    }
+
+    /// Add a custom stage to the end of the pipeline, it doesn't run
+    /// when an earlier stage finds the location complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - Custom stage
+    pub fn with_stage<S: Stage + 'static>(mut self, stage: S) -> Self {
+        self.stages
+            .get_or_insert_with(stages::default_stages)
+            .push(Box::new(stage));
+        self
+    }
+
+    /// Remove a stage with the given name from the pipeline.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the stage, e.g. "zipcode"
+    pub fn without_stage(mut self, name: &str) -> Self {
+        self.stages
+            .get_or_insert_with(stages::default_stages)
+            .retain(|s| s.name() != name);
+        self
+    }
+
+    /// Report counters of every parsed location to the sink, e.g. how often
+    /// a city or a state is found and how often cities are ambiguous,
+    /// see `metrics::Metric`.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Receiver of the counters, e.g. `Arc<metrics::Counters>` to read them later
+    pub fn with_metrics<M: MetricsSink + 'static>(mut self, sink: M) -> Self {
+        self.metrics = Some(Arc::new(sink));
+        self
+    }
+
+    /// Read cities, states and countries from a folder laid out like `src/data`
+    /// instead of the shared datasets, e.g. to serve a tenant with its own data.
+    /// Other data files, like abbreviations and world cities, are still read
+    /// from `utils::data_source`.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Folder with `countries.txt` and a folder of every country
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/data");
+    /// let parser = geo_rs::Parser::builder().with_data_dir(&dir).build();
+    /// let location = parser.parse_location("Toronto, ON");
+    /// assert_eq!(location.to_string(), String::from("Toronto, ON, CA"));
+    /// ```
+    pub fn with_data_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.data_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Same as `build` but fail when the data files, including the folder of
+    /// `with_data_dir`, are written for another version of geo-rs, see `schema::validate`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::builder().try_build().unwrap();
+    /// ```
+    pub fn try_build(self) -> Result<Parser, SchemaError> {
+        schema::validate(utils::data_source())?;
+        if let Some(dir) = &self.data_dir {
+            schema::validate(&DataSource::Dir(dir.clone()))?;
+        }
+        Ok(self.build())
+    }
+
+    pub fn build(mut self) -> Parser {
+        let mut parser = Parser::uncompiled(self.options.clone(), Datasets::shared());
+        self.customize(&mut parser);
+        if let Some(stages) = self.stages.take() {
+            parser.stages = Arc::new(stages);
+        }
+        parser.metrics = self.metrics.take();
+        parser.compile();
+        parser.check_city_counts();
+        parser.recipe = Arc::new(self);
+        parser
+    }
+
+    /// Folder of `with_data_dir`, if any.
+    pub(crate) fn data_dir(&self) -> Option<PathBuf> {
+        self.data_dir.clone()
+    }
+
+    /// Apply the data folder and the custom dictionaries to a parser,
+    /// so that `Parser::reload` can apply them again to fresh datasets.
+    pub(crate) fn customize(&self, parser: &mut Parser) {
+        if let Some(dir) = &self.data_dir {
+            let source = DataSource::Dir(dir.clone());
+            parser.cities = Arc::new(city::read_cities_from(&source).0);
+            parser.states = Arc::new(state::read_states_from(&source).0);
+            parser.countries = Arc::new(country::read_countries_from(&source).0);
+        }
+        let normalizer = &mut parser.normalizer;
+        normalizer.abbreviations.extend(self.abbreviations.clone());
+        let removed = &self.removed_noise_patterns;
+        for pattern in removed {
+            if !normalizer
+                .noise_patterns
+                .iter()
+                .any(|p| p.as_str() == pattern)
+            {
+                warn!("Ignoring unknown noise pattern {:?}", pattern);
+            }
+        }
+        normalizer
+            .noise_patterns
+            .retain(|p| !removed.iter().any(|r| r == p.as_str()));
+        normalizer
+            .noise_patterns
+            .extend(noise::compile_noise_patterns(&self.noise_patterns));
+        if let Some(steps) = &self.normalize_steps {
+            normalizer.steps = steps.clone();
+        }
+        for (alias, country, state, city) in self.city_aliases.iter().cloned() {
+            match Arc::make_mut(&mut parser.cities).get_mut(&country) {
+                Some(country_cities) => {
+                    country_cities
+                        .aliases
+                        .insert(utils::city_key(&alias), (state, city));
+                }
+                None => warn!(
+                    "Ignoring alias {:?} of a city in unknown country {}",
+                    alias, country
+                ),
+            }
+        }
+        for (alias, country) in self.country_aliases.iter().cloned().rev() {
+            if !parser.countries.code_to_name.contains_key(&country) {
+                warn!(
+                    "Ignoring alias {:?} of an unknown country {}",
+                    alias, country
+                );
+                continue;
+            }
+            Arc::make_mut(&mut parser.countries)
+                .aliases
+                .insert(0, (alias, country));
+        }
+        for (pattern, city, state, country) in self.special_cases.iter().rev() {
+            let known = parser
+                .country_from_code(country)
+                .and_then(|c| parser.state_from_code(&Some(c), state));
+            if known.is_none() {
+                warn!(
+                    "Ignoring special case {:?} in unknown state {} of {}",
+                    pattern, state, country
+                );
+                continue;
+            }
+            match SpecialCase::new(pattern, city, state, country) {
+                Ok(case) => parser.special_cases.insert(0, case),
+                Err(e) => warn!("Ignoring invalid special case {:?}: {}", pattern, e),
+            }
+        }
+        for (code, location) in &self.site_codes {
+            let key = site_key(code);
+            if key.is_empty() {
+                warn!("Ignoring empty site code");
+                continue;
+            }
+            parser.site_codes.retain(|(k, _)| k != &key);
+            parser.site_codes.push((key, location.clone()));
+        }
+        parser.site_codes.sort_by_key(|(key, _)| Reverse(key.len()));
+        parser.blocklist.extend(
+            self.blocklist
+                .iter()
+                .map(|i| blocklist_key(i))
+                .filter(|k| !k.is_empty()),
+        );
+        for (pattern, kind) in self.landmark_patterns.iter().rev() {
+            if let Some(compiled) = landmark::compile_landmark_pattern(pattern, *kind) {
+                parser.landmark_patterns.insert(0, compiled);
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use crate::nodes::{Location, Warning};
+use crate::Parser;
+
+/// Location returned by a `ParserChain` along with the name of the parser that produced it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainedLocation {
+    pub location: Location,
+    /// Name of the parser in the chain that produced the location
+    pub parser: String,
+}
+
+/// Chain of parsers that are tried in order until one of them gives a full location,
+/// e.g. a tenant-specific parser first and the default parser as a fallback.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let tenant = geo_rs::Parser::builder()
+///     .with_city_alias("HQ", "US", "CA", "Cupertino")
+///     .build();
+/// let chain = geo_rs::ParserChain::new()
+///     .with_parser("tenant", tenant)
+///     .with_parser("default", geo_rs::Parser::new());
+/// let output = chain.parse_location("HQ").unwrap();
+/// assert_eq!(output.parser, String::from("tenant"));
+/// assert_eq!(output.location.to_string(), String::from("Cupertino, CA, US"));
+/// ```
+#[derive(Debug, Default)]
+pub struct ParserChain {
+    parsers: Vec<(String, Parser)>,
+}
+
+impl ParserChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a parser to the end of the chain.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the parser reported in `ChainedLocation::parser`
+    /// * `parser` - Parser to be tried after the ones already in the chain
+    pub fn with_parser(mut self, name: &str, parser: Parser) -> Self {
+        self.parsers.push((name.to_string(), parser));
+        self
+    }
+
+    /// Parse location string with every parser in the chain and return the first full
+    /// location, see `Location::is_full`. When no parser gives a full location,
+    /// the most complete one is returned, earlier parsers win ties.
+    /// Return `None` only when the chain is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string that's gonna be parsed
+    pub fn parse_location(&self, input: &str) -> Option<ChainedLocation> {
+        let mut best: Option<(usize, ChainedLocation)> = None;
+        for (name, parser) in &self.parsers {
+            let location = parser.parse_location(input);
+            if location.is_full() {
+                return Some(ChainedLocation {
+                    location,
+                    parser: name.clone(),
+                });
+            }
+            let completeness = completeness(&location);
+            if best
+                .as_ref()
+                .map(|(c, _)| completeness > *c)
+                .unwrap_or(true)
+            {
+                best = Some((
+                    completeness,
+                    ChainedLocation {
+                        location,
+                        parser: name.clone(),
+                    },
+                ));
+            }
+        }
+        best.map(|(_, output)| output)
+    }
+}
+
+/// Number of known components of the location.
+fn completeness(location: &Location) -> usize {
+    let city = location.city.is_some() && !location.warnings.contains(&Warning::UnknownCity);
+    [city, location.state.is_some(), location.country.is_some()]
+        .iter()
+        .filter(|known| **known)
+        .count()
+}
+
+#[cfg(all(test, feature = "data-us", feature = "data-ca"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parser_chain() {
+        let chain = ParserChain::new();
+        assert_eq!(chain.parse_location("Toronto, ON"), None);
+        let tenant = Parser::builder()
+            .with_city_alias("HQ", "US", "CA", "Cupertino")
+            .build();
+        let chain = ParserChain::new()
+            .with_parser("tenant", tenant)
+            .with_parser("default", Parser::new());
+        let output = chain.parse_location("Toronto, ON").unwrap();
+        assert_eq!(output.parser, String::from("tenant"));
+        assert_eq!(output.location.to_string(), String::from("Toronto, ON, CA"));
+        let chain = ParserChain::new()
+            .with_parser(
+                "canada",
+                Parser::with_options(crate::ParserOptions::new().default_countries(vec!["CA"])),
+            )
+            .with_parser("default", Parser::new());
+        let output = chain.parse_location("Sausalito").unwrap();
+        assert_eq!(output.parser, String::from("default"));
+        assert_eq!(
+            output.location.to_string(),
+            String::from("Sausalito, CA, US")
+        );
+        let output = chain.parse_location("Colleretto Giacosa").unwrap();
+        assert_eq!(output.parser, String::from("canada"));
+    }
+}
@@ -0,0 +1,289 @@
+//! Parser options loaded from a TOML or JSON config file, so that services
+//! configure the parser without recompiling, enable with `config` feature.
+//!
+//! Every key is optional, missing ones keep their defaults, unknown ones are rejected:
+//!
+//! ```toml
+//! # Countries to search in when the input has none, see `ParserOptions::default_countries`
+//! default_countries = ["CA", "US"]
+//! # What "CA" and other codes of both a country and a state mean, see `ParserOptions::country_bias`
+//! country_bias = ["US", "CA"]
+//! infer_unique_city = true
+//! strict = false
+//! # "title", "as_data" or "upper", see `OutputCase`
+//! output_case = "as_data"
+//! # Fuzzy matching of misspelled cities by how they sound, see `ParserOptions::phonetic_cities`
+//! phonetic_cities = true
+//! # Street addresses in the output, see `ParserOptions::addresses`
+//! addresses = true
+//! # Stages to run in order, all of `stages::default_stages` by default
+//! stages = ["normalize", "country", "zipcode", "state", "city"]
+//! # Stages to leave out of the pipeline
+//! disabled_stages = ["landmark"]
+//! # Folder with custom cities, states and countries, see `ParserBuilder::with_data_dir`,
+//! # relative to the config file
+//! data_dir = "data"
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs::config::ParserConfig;
+//! let config: ParserConfig = r#"{"country_bias": ["US", "CA"], "disabled_stages": ["zipcode"]}"#
+//!     .parse()
+//!     .unwrap();
+//! let parser = config.builder().unwrap().build();
+//! let location = parser.parse_location("Springfield, CA 95001");
+//! assert_eq!(location.to_string(), String::from("Springfield, CA, US"));
+//! ```
+use crate::schema::SchemaError;
+use crate::stages::{self, Stage};
+use crate::{OutputCase, Parser, ParserBuilder, ParserOptions};
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Contents of a config file, see the module documentation for the keys.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParserConfig {
+    pub default_countries: Option<Vec<String>>,
+    pub country_bias: Option<Vec<String>>,
+    pub infer_unique_city: Option<bool>,
+    pub strict: Option<bool>,
+    pub output_case: Option<OutputCase>,
+    pub phonetic_cities: Option<bool>,
+    pub addresses: Option<bool>,
+    /// Names of stages to run in order, see `Stage::name`
+    pub stages: Option<Vec<String>>,
+    /// Names of stages to leave out of the pipeline
+    pub disabled_stages: Vec<String>,
+    /// Folder with custom cities, states and countries
+    pub data_dir: Option<PathBuf>,
+}
+
+/// Reasons a config is rejected.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file can't be read
+    Io(io::Error),
+    /// The config isn't valid TOML or JSON or has unknown keys or values
+    Syntax(String),
+    /// The config names a stage that isn't a part of the default pipeline
+    UnknownStage(String),
+    /// Files of the data folder are written for another version of geo-rs
+    Schema(SchemaError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "can't read the config: {}", e),
+            ConfigError::Syntax(e) => write!(f, "invalid config: {}", e),
+            ConfigError::UnknownStage(name) => write!(f, "unknown stage {:?} in the config", name),
+            ConfigError::Schema(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<SchemaError> for ConfigError {
+    fn from(e: SchemaError) -> Self {
+        ConfigError::Schema(e)
+    }
+}
+
+/// Parse a config, it's JSON when it starts with "{" and TOML otherwise.
+impl FromStr for ParserConfig {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_start().starts_with('{') {
+            true => serde_json::from_str(s).map_err(|e| ConfigError::Syntax(e.to_string())),
+            false => toml::from_str(s).map_err(|e| ConfigError::Syntax(e.to_string())),
+        }
+    }
+}
+
+impl ParserConfig {
+    /// Read a config file, a relative `data_dir` is resolved against the folder of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of a TOML or JSON config file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let mut config: ParserConfig = fs::read_to_string(path)?.parse()?;
+        if let (Some(dir), Some(parent)) = (&config.data_dir, path.parent()) {
+            config.data_dir = Some(parent.join(dir));
+        }
+        Ok(config)
+    }
+
+    /// Options of the config on top of the default ones.
+    pub fn options(&self) -> ParserOptions {
+        let mut options = ParserOptions::new();
+        if let Some(codes) = &self.default_countries {
+            options = options.default_countries(codes.clone());
+        }
+        if let Some(codes) = &self.country_bias {
+            options = options.country_bias(codes);
+        }
+        if let Some(value) = self.infer_unique_city {
+            options = options.infer_unique_city(value);
+        }
+        if let Some(value) = self.strict {
+            options = options.strict(value);
+        }
+        if let Some(value) = self.output_case {
+            options = options.output_case(value);
+        }
+        if let Some(value) = self.phonetic_cities {
+            options = options.phonetic_cities(value);
+        }
+        if let Some(value) = self.addresses {
+            options = options.addresses(value);
+        }
+        options
+    }
+
+    /// Builder of a parser configured accordingly, custom dictionaries may be added to it.
+    /// Fails when the config names an unknown stage.
+    pub fn builder(&self) -> Result<ParserBuilder, ConfigError> {
+        let mut builder = Parser::builder().with_options(self.options());
+        let known: Vec<&str> = stages::default_stages().iter().map(|s| s.name()).collect();
+        let mut names = self.stages.iter().flatten().chain(&self.disabled_stages);
+        if let Some(name) = names.find(|n| !known.contains(&n.as_str())) {
+            return Err(ConfigError::UnknownStage(name.clone()));
+        }
+        if let Some(names) = &self.stages {
+            let mut defaults = stages::default_stages();
+            let mut pipeline: Vec<Box<dyn Stage>> = vec![];
+            for name in names {
+                if let Some(i) = defaults.iter().position(|s| s.name() == name) {
+                    pipeline.push(defaults.remove(i));
+                }
+            }
+            builder = builder.with_stages(pipeline);
+        }
+        for name in &self.disabled_stages {
+            builder = builder.without_stage(name);
+        }
+        if let Some(dir) = &self.data_dir {
+            builder = builder.with_data_dir(dir);
+        }
+        Ok(builder)
+    }
+}
+
+impl Parser {
+    /// Create a parser configured by a TOML or JSON config file, see `config`.
+    /// Fails when the file can't be read or parsed, names an unknown stage or
+    /// the data files are written for another version of geo-rs.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the config file, e.g. "parser.toml"
+    pub fn from_config<P: AsRef<Path>>(path: P) -> Result<Parser, ConfigError> {
+        Ok(ParserConfig::from_path(path)?.builder()?.try_build()?)
+    }
+
+    /// Same as `from_config` but take the contents of the config.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - TOML or JSON config
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::from_config_str("output_case = \"upper\"").unwrap();
+    /// let location = parser.parse_location("Bullhead City, AZ");
+    /// assert_eq!(location.to_string(), String::from("BULLHEAD CITY, AZ, US"));
+    /// assert!(geo_rs::Parser::from_config_str("stages = [\"guess\"]").is_err());
+    /// ```
+    pub fn from_config_str(config: &str) -> Result<Parser, ConfigError> {
+        Ok(config.parse::<ParserConfig>()?.builder()?.try_build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let toml = r#"
+            default_countries = ["CA", "US"]
+            infer_unique_city = true
+            output_case = "as_data"
+            disabled_stages = ["landmark"]
+        "#;
+        let json = r#"{
+            "default_countries": ["CA", "US"],
+            "infer_unique_city": true,
+            "output_case": "as_data",
+            "disabled_stages": ["landmark"]
+        }"#;
+        let expected = ParserConfig {
+            default_countries: Some(vec![String::from("CA"), String::from("US")]),
+            infer_unique_city: Some(true),
+            output_case: Some(OutputCase::AsData),
+            disabled_stages: vec![String::from("landmark")],
+            ..ParserConfig::default()
+        };
+        assert_eq!(toml.parse::<ParserConfig>().unwrap(), expected);
+        assert_eq!(json.parse::<ParserConfig>().unwrap(), expected);
+        assert_eq!("".parse::<ParserConfig>().unwrap(), ParserConfig::default());
+        for invalid in [
+            "strict = \"yes\"",
+            "fuzzy = true",
+            "{\"strict\": 1}",
+            "output_case = \"lower\"",
+        ] {
+            assert!(matches!(
+                invalid.parse::<ParserConfig>(),
+                Err(ConfigError::Syntax(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parser_from_config() {
+        let parser = Parser::from_config_str("default_countries = [\"CA\", \"US\"]").unwrap();
+        assert_eq!(
+            parser.parse_location("Hamilton").country.unwrap().code,
+            "CA"
+        );
+        let parser = Parser::from_config_str("stages = [\"normalize\", \"country\"]").unwrap();
+        let location = parser.parse_location("Toronto, ON, Canada");
+        assert_eq!(location.city, None);
+        assert_eq!(location.country.unwrap().code, "CA");
+        assert!(matches!(
+            Parser::from_config_str("disabled_stages = [\"zip\"]"),
+            Err(ConfigError::UnknownStage(name)) if name == "zip"
+        ));
+        assert!(matches!(
+            Parser::from_config("no/such/config.toml"),
+            Err(ConfigError::Io(_))
+        ));
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/schema/current");
+        let path =
+            std::env::temp_dir().join(format!("geo-rs-config-test-{}.toml", std::process::id()));
+        fs::write(&path, format!("data_dir = {:?}", dir)).unwrap();
+        let config = ParserConfig::from_path(&path).unwrap();
+        assert_eq!(config.data_dir, Some(dir));
+    }
+}
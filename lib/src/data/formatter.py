@@ -4,6 +4,7 @@ are ordered from longest one to shortest, e.g:
     - AB:city_name_xxx
     - AB:city_name_yy
     - AB:city_name_z
+Optional columns that follow the city name (e.g. population) are preserved.
 """
 
 with open("./US/cities.txt") as f:
@@ -12,12 +13,12 @@ with open("./US/cities.txt") as f:
 
 states = {}
 for line in content:
-    code, name = line.split(";")
+    code, name = line.split(";", 1)
     states.setdefault(code, [])
     states[code].append(name)
 
 with open("./tmp.txt", "w") as w:
     for k, v in states.items():
-        v = sorted(v, key=lambda x: len(x), reverse=True)
+        v = sorted(v, key=lambda x: len(x.split(";")[0]), reverse=True)
         for city in v:
             w.write(f"{k};{city}\n")
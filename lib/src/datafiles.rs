@@ -0,0 +1,254 @@
+//! Checks of custom data files, so they can be fixed before they're deployed.
+//! The parser skips malformed lines with a warning, `check` lists them along
+//! with the records that are well-formed but inconsistent.
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs;
+//! let report = geo_rs::datafiles::check(geo_rs::utils::data_source());
+//! assert!(report.countries > 200);
+//! for issue in &report.issues {
+//!     println!("{}", issue);
+//! }
+//! ```
+use crate::schema::{self, SchemaError};
+use crate::utils::{DataSource, Records};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Problem with data files found by `check`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataIssue {
+    /// A file every data folder must have doesn't exist
+    Missing { file: String },
+    /// The schema header of a file isn't compatible, see `schema::validate`
+    Schema(SchemaError),
+    /// A line can't be read, e.g. isn't valid UTF-8, the rest of the file is skipped
+    Unreadable {
+        file: String,
+        line: usize,
+        error: String,
+    },
+    /// A line is blank or has missing fields
+    Malformed {
+        file: String,
+        line: usize,
+        text: String,
+    },
+    /// A country name or a state code is listed more than once, so only one
+    /// of its lines is used
+    Duplicate {
+        file: String,
+        line: usize,
+        key: String,
+    },
+    /// A record refers to a state that isn't in `states.txt` of its country
+    UnknownState {
+        file: String,
+        line: usize,
+        state: String,
+    },
+}
+
+impl fmt::Display for DataIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataIssue::Missing { file } => write!(f, "{}: file is missing", file),
+            DataIssue::Schema(e) => write!(f, "{}", e),
+            DataIssue::Unreadable { file, line, error } => {
+                write!(f, "{}:{}: unreadable line: {}", file, line, error)
+            }
+            DataIssue::Malformed { file, line, text } => {
+                write!(f, "{}:{}: malformed line {:?}", file, line, text)
+            }
+            DataIssue::Duplicate { file, line, key } => {
+                write!(f, "{}:{}: duplicate {}", file, line, key)
+            }
+            DataIssue::UnknownState { file, line, state } => {
+                write!(f, "{}:{}: unknown state {}", file, line, state)
+            }
+        }
+    }
+}
+
+/// Outcome of `check`: issues of the data files and statistics of the datasets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DataReport {
+    pub issues: Vec<DataIssue>,
+    /// Number of countries in `countries.txt`
+    pub countries: usize,
+    /// Number of states by country code
+    pub states: BTreeMap<String, usize>,
+    /// Number of cities by country code
+    pub cities: BTreeMap<String, usize>,
+    /// Number of zipcode ranges by country code
+    pub zipcode_ranges: BTreeMap<String, usize>,
+}
+
+impl DataReport {
+    /// Whether the data files have no issues.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Call `f` with the line number and fields of every well-formed line
+    /// of the file, and report the malformed ones and the first unreadable one.
+    fn read<F>(&mut self, source: &DataSource, file: &str, fields: usize, mut f: F)
+    where
+        F: FnMut(&mut Self, usize, Vec<Cow<str>>),
+    {
+        let mut records = Records::new(file, fields);
+        for (i, text) in source.read_optional_results(file).enumerate() {
+            let text = match text {
+                Ok(text) => text,
+                Err(e) => {
+                    self.issues.push(DataIssue::Unreadable {
+                        file: file.to_string(),
+                        line: i + 1,
+                        error: e.to_string(),
+                    });
+                    break;
+                }
+            };
+            let skipped = records.skipped;
+            match records.split(&text) {
+                Some(parts) => f(self, i + 1, parts),
+                None if records.skipped > skipped => self.issues.push(DataIssue::Malformed {
+                    file: file.to_string(),
+                    line: i + 1,
+                    text: text.clone(),
+                }),
+                // Schema header
+                None => (),
+            }
+        }
+    }
+}
+
+/// Check data files of the source: countries, and states, cities and zipcodes
+/// of every country that has `{country}/states.txt`.
+///
+/// # Arguments
+///
+/// * `source` - Where to read data files from, e.g. a folder with custom files
+pub fn check(source: &DataSource) -> DataReport {
+    let mut report = DataReport::default();
+    if let Err(e) = schema::validate(source) {
+        report.issues.push(DataIssue::Schema(e));
+    }
+    if !source.file_exists("countries.txt") {
+        let file = String::from("countries.txt");
+        report.issues.push(DataIssue::Missing { file });
+    }
+    // Countries may have several names, e.g. "Czechia" and "Czech Republic"
+    let mut names: HashSet<String> = HashSet::new();
+    let mut codes: HashSet<String> = HashSet::new();
+    report.read(source, "countries.txt", 2, |report, line, parts| {
+        codes.insert(parts[1].to_string());
+        let key = parts[0].to_string();
+        if !names.insert(key.clone()) {
+            let file = String::from("countries.txt");
+            report.issues.push(DataIssue::Duplicate { file, line, key });
+        }
+    });
+    report.countries = codes.len();
+    for country in source.countries() {
+        let file = format!("{}/states.txt", country);
+        let mut states: HashSet<String> = HashSet::new();
+        report.read(source, &file, 2, |report, line, parts| {
+            let key = parts[0].to_string();
+            if !states.insert(key.clone()) {
+                let file = file.clone();
+                report.issues.push(DataIssue::Duplicate { file, line, key });
+            }
+        });
+        report.states.insert(country.clone(), states.len());
+        // Cities and zipcode ranges refer to states, by the column of the state code
+        for (name, fields, column) in [("cities", 2, 0), ("zipcodes", 3, 2)] {
+            let file = format!("{}/{}.txt", country, name);
+            let mut count = 0;
+            report.read(source, &file, fields, |report, line, parts| {
+                count += 1;
+                let state = parts[column].to_string();
+                if !states.contains(&state) {
+                    let file = file.clone();
+                    report
+                        .issues
+                        .push(DataIssue::UnknownState { file, line, state });
+                }
+            });
+            match name {
+                "cities" => report.cities.insert(country.clone(), count),
+                _ => report.zipcode_ranges.insert(country.clone(), count),
+            };
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_check() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+        let report = check(&DataSource::Dir(fixtures.join("csv")));
+        assert_eq!(report.issues, vec![]);
+        assert_eq!(report.countries, 3);
+        let report = check(&DataSource::Dir(fixtures.join("malformed")));
+        let issues: Vec<String> = report.issues.iter().map(|i| i.to_string()).collect();
+        assert!(issues.contains(&String::from("countries.txt:2: malformed line \"\"")));
+        assert!(issues.contains(&String::from("countries.txt:3: malformed line \"Mexico\"")));
+        if cfg!(feature = "data-us") {
+            assert!(issues.contains(&String::from("US/states.txt:3: malformed line \"TX\"")));
+            assert_eq!(report.states.get("US"), Some(&2));
+            assert_eq!(report.cities.get("US"), Some(&2));
+        }
+        let report = check(&DataSource::Dir(fixtures.join("inconsistent")));
+        let issues: Vec<String> = report.issues.iter().map(|i| i.to_string()).collect();
+        assert!(issues.contains(&String::from("countries.txt:2: duplicate Canada")));
+        if cfg!(feature = "data-us") {
+            assert_eq!(
+                issues[1..],
+                [
+                    "US/states.txt:3: duplicate NY",
+                    "US/cities.txt:2: unknown state ZZ",
+                    "US/zipcodes.txt:1: unknown state XX",
+                ]
+            );
+            assert_eq!(report.zipcode_ranges.get("US"), Some(&2));
+        }
+    }
+
+    #[test]
+    fn test_check_bundled_data() {
+        let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/data");
+        let report = check(&DataSource::Dir(data));
+        let issues: Vec<String> = report.issues.iter().map(|i| i.to_string()).collect();
+        assert_eq!(issues, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_check_unreadable() {
+        let dir = std::env::temp_dir().join(format!("geo-rs-unreadable-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("countries.txt"),
+            b"Canada;CA\nM\xe9xico;MX\nPeru;PE\n",
+        )
+        .unwrap();
+        let report = check(&DataSource::Dir(dir.clone()));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(report.countries, 1);
+        assert_eq!(
+            report.issues.last().map(|i| i.to_string()),
+            Some(String::from(
+                "countries.txt:2: unreadable line: stream did not contain valid UTF-8"
+            ))
+        );
+    }
+}
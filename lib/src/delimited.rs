@@ -0,0 +1,201 @@
+use crate::nodes::{Country, Location, State};
+use crate::utils;
+use crate::Parser;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref RE_DASH: Regex = Regex::new(r"\s*-\s*").unwrap();
+}
+
+impl Parser {
+    /// Parse hierarchical dash separated locations, where every segment is
+    /// more specific than the previous one: "Country-State-City" or "State-City",
+    /// optionally followed by a zipcode and anything else, e.g. a store code.
+    /// Segments are mapped by their position, so the city may contain dashes too.
+    /// Return `None` unless every leading segment is what its position says,
+    /// the city has to be known in the state.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string without commas, e.g. "US-DE-Wilmington"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_delimited("US-DE-Wilmington").unwrap();
+    /// assert_eq!(location.to_string(), String::from("Wilmington, DE, US"));
+    /// let location = parser.parse_delimited("VA-Christiansburg-24073").unwrap();
+    /// assert_eq!(location.to_string(), String::from("Christiansburg, VA, US, 24073"));
+    /// let location = parser.parse_delimited("QC-Saint-Lin-Laurentides").unwrap();
+    /// assert_eq!(location.to_string(), String::from("Saint-Lin-Laurentides, QC, CA"));
+    /// assert_eq!(parser.parse_delimited("Saint-Lin-Laurentides, QC"), None);
+    /// ```
+    pub fn parse_delimited(&self, input: &str) -> Option<Location> {
+        if input.contains(',') || !input.contains('-') {
+            return None;
+        }
+        let segments: Vec<&str> = RE_DASH
+            .split(input.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if segments.len() < 2 {
+            return None;
+        }
+        let (state, country, rest) = match self.country_of_segment(segments[0]) {
+            Some(country) => match self.state_of_segment(segments[1], &[country]) {
+                Some((state, country)) => (state, country, &segments[2..]),
+                None => self.state_first(&segments)?,
+            },
+            None => self.state_first(&segments)?,
+        };
+        // City runs until the zipcode or the first segment with digits, e.g. a store number
+        let city_len = rest
+            .iter()
+            .position(|s| s.chars().any(|c| c.is_ascii_digit()))
+            .unwrap_or(rest.len());
+        let mut location = Location {
+            state: Some(state),
+            country: Some(country),
+            ..Location::default()
+        };
+        // The city has to take whole segments, e.g. not just "Richmond" of "Richmond-Hill"
+        let (city, city_len) = (1..=city_len).rev().find_map(|len| {
+            let text = rest[..len].join("-").to_lowercase();
+            let mut probe = location.clone();
+            self.fill_city(&mut probe, &text);
+            probe
+                .city
+                .filter(|c| self.state_contains_city(location.state.as_ref().unwrap(), c))
+                .filter(|c| utils::words(&c.name.to_lowercase()).eq(utils::words(&text)))
+                .map(|c| (c, len))
+        })?;
+        location.city = Some(city);
+        location.zipcode = rest
+            .get(city_len)
+            .and_then(|s| self.location_from_zipcode(s))
+            .filter(|z| z.state == location.state)
+            .and_then(|z| z.zipcode);
+        // Segments aren't hierarchical if a state or a country follows the city,
+        // e.g. "CA - Richmond - British Columbia"
+        if rest[city_len..].iter().any(|s| {
+            self.country_of_segment(s).is_some()
+                || self.state_of_segment(s, &self.state_countries).is_some()
+        }) {
+            return None;
+        }
+        trace_event!(
+            "matched delimited format",
+            segments = segments,
+            location = location.to_string()
+        );
+        Some(location)
+    }
+
+    /// Read segments as "State-City", the state is searched in countries
+    /// with states in the order of `Parser::state_countries`.
+    fn state_first<'a, 'b>(
+        &self,
+        segments: &'a [&'b str],
+    ) -> Option<(State, Country, &'a [&'b str])> {
+        let (state, country) = self.state_of_segment(segments[0], &self.state_countries)?;
+        Some((state, country, &segments[1..]))
+    }
+
+    /// Resolve a segment that is a country code or a country name.
+    pub(crate) fn country_of_segment(&self, segment: &str) -> Option<Country> {
+        if let Some(country) = self.country_from_code(segment) {
+            return Some(country);
+        }
+        let (name, code) = self
+            .countries
+            .name_to_code
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(segment))?;
+        Some(Country {
+            code: code.clone(),
+            name: name.clone(),
+        })
+    }
+
+    /// Resolve a segment that is a state code or a state name of one of the countries,
+    /// short words don't count, e.g. "District of Columbia" is "District Columbia".
+    pub(crate) fn state_of_segment(
+        &self,
+        segment: &str,
+        countries: &[Country],
+    ) -> Option<(State, Country)> {
+        let segment_lowercase = segment.to_lowercase();
+        let words: Vec<&str> = utils::words(&segment_lowercase).collect();
+        let is_name = |name: &str| {
+            let name = name.to_lowercase();
+            utils::words(&name).all(|w| words.contains(&w))
+                && words
+                    .iter()
+                    .all(|w| w.len() <= 2 || utils::words(&name).any(|n| n == *w))
+        };
+        for country in countries {
+            let states = match self.states.get(&country.code) {
+                Some(s) => s,
+                None => continue,
+            };
+            let found = states
+                .code_to_name
+                .iter()
+                .find(|(code, name)| code.eq_ignore_ascii_case(segment) || is_name(name));
+            if let Some((code, name)) = found {
+                let state = State {
+                    code: code.clone(),
+                    name: name.clone(),
+                };
+                return Some((state, country.clone()));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(all(test, feature = "data-us", feature = "data-ca"))]
+mod tests {
+    use crate::Parser;
+
+    #[test]
+    fn test_parse_delimited() {
+        let parser = Parser::new();
+        let inputs = [
+            ("US-DE-Wilmington", Some("Wilmington, DE, US")),
+            ("MI-Commerce Township", Some("Commerce Township, MI, US")),
+            (
+                "VA-Christiansburg-24073",
+                Some("Christiansburg, VA, US, 24073"),
+            ),
+            ("CA-ON-Oakville-", Some("Oakville, ON, CA")),
+            ("CA-Cupertino", Some("Cupertino, CA, US")),
+            ("CAN - ON - Toronto", Some("Toronto, ON, CA")),
+            (
+                "United States-District of Columbia-washington-20340",
+                Some("Washington, DC, US, 20340"),
+            ),
+            ("United States-Alaska-Shemya", Some("Shemya, AK, US")),
+            ("NC-Winston-Salem", Some("Winston Salem, NC, US")),
+            ("US-Fort Worth TX", None),
+            ("CA - Richmond - British Columbia", None),
+            ("TX-Austin-78701-US", None),
+            ("TX-Austin-9999", Some("Austin, TX, US")),
+            ("MI-Atlantis", None),
+            ("Saint-Lin-Laurentides QC", None),
+            ("Lansing, MI-US", None),
+            ("Toronto", None),
+        ];
+        for (input, output) in inputs {
+            assert_eq!(
+                parser.parse_delimited(input).map(|l| l.to_string()),
+                output.map(String::from),
+                "input: {}",
+                input
+            );
+        }
+    }
+}
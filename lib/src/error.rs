@@ -0,0 +1,37 @@
+use std::error::Error;
+use std::fmt;
+
+/// Reasons `Parser::try_parse_location` rejects a location in strict mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// More than one location matches the input equally well
+    Ambiguous,
+    /// Nothing in the input matches a known location
+    NoMatch,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Ambiguous => write!(f, "location is ambiguous"),
+            ParseError::NoMatch => write!(f, "no location matches the input"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Reason `Parser::reload` failed, e.g. a data file is missing or can't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReloadError {
+    /// What went wrong while reading the data files
+    pub message: String,
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to reload data files: {}", self.message)
+    }
+}
+
+impl Error for ReloadError {}
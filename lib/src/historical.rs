@@ -0,0 +1,188 @@
+use crate::nodes::{Country, Location, Warning};
+use crate::utils::{self, read_optional_lines, Records};
+use crate::Parser;
+use std::cmp::Reverse;
+use unidecode::unidecode;
+
+/// Former name of a city or a country, e.g. "Bombay" of Mumbai or "Burma" of Myanmar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoricalName {
+    /// Former name, e.g. "Bombay"
+    pub name: String,
+    /// Code of the country the place belongs to now, e.g. "IN"
+    pub country: String,
+    /// Current name of the city, `None` when the name is a former name of the country
+    pub city: Option<String>,
+}
+
+/// Read historical names from `src/data/aliases_historical.txt`, one per line as
+/// "name;country;city" or "name;country" for former names of countries. A name
+/// that is listed for several countries is ambiguous, e.g. "Czechoslovakia".
+/// Longer names go first, custom data folders may leave the file out.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let names = geo_rs::historical::read_historical_names();
+/// let bombay = names.iter().find(|n| n.name == "Bombay").unwrap();
+/// assert_eq!(bombay.city, Some(String::from("Mumbai")));
+/// ```
+pub fn read_historical_names() -> Vec<HistoricalName> {
+    let mut records = Records::new("aliases_historical.txt", 2);
+    let mut names: Vec<HistoricalName> = read_optional_lines("aliases_historical.txt")
+        .filter_map(|line| {
+            let parts = records.split(&line)?;
+            Some(HistoricalName {
+                name: unidecode(&parts[0]),
+                country: parts[1].to_string(),
+                city: parts
+                    .get(2)
+                    .filter(|c| !c.trim().is_empty())
+                    .map(|c| c.to_string()),
+            })
+        })
+        .collect();
+    names.sort_by_key(|n| Reverse(n.name.len()));
+    names
+}
+
+impl Parser {
+    /// Replace former names of a country and a city that the input mentions with
+    /// the current ones and add `Warning::HistoricalName`, e.g. "Bombay, India" becomes
+    /// "Mumbai, India". A country is replaced with its alpha-3 code and a city gets
+    /// the code of its country when the input names no country. Names of cities of
+    /// the input's state are left alone, e.g. "Bombay, NY". When a name is ambiguous
+    /// the first listed country wins and the others are parsed into alternatives.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that stores final values
+    /// * `input` - Cleaned location string
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = geo_rs::nodes::Location::default();
+    /// let mut input = String::from("Saigon");
+    /// parser.replace_historical_name(&mut location, &mut input);
+    /// assert_eq!(input, String::from("Ho Chi Minh City, VNM"));
+    /// ```
+    pub fn replace_historical_name(&self, location: &mut Location, input: &mut String) {
+        // Countries go first, so that the city knows whether the country is mentioned
+        self.replace_historical_names(location, input, false);
+        self.replace_historical_names(location, input, true);
+    }
+
+    /// Replace the longest former name of a city or of a country in the input,
+    /// see `Parser::replace_historical_name`.
+    fn replace_historical_names(&self, location: &mut Location, input: &mut String, cities: bool) {
+        let as_lowercase = input.to_lowercase();
+        let historical = match self.historical_names.iter().find(|h| {
+            h.city.is_some() == cities
+                && !utils::find_token(&as_lowercase, &h.name.to_lowercase()).is_empty()
+                && !(self.is_known_name(&h.name) && self.mentions_state(input))
+        }) {
+            Some(h) => h,
+            None => return,
+        };
+        trace_event!("matched historical name", name = historical.name);
+        let mentions_country = cities && self.mentions_country(input);
+        let replace = |h: &HistoricalName| -> Option<String> {
+            let country = self.country_from_code(&h.country)?;
+            let code = country.alpha3().unwrap_or(&country.code).to_string();
+            let replacement = match &h.city {
+                Some(city) if mentions_country => city.clone(),
+                Some(city) => format!("{}, {}", city, code),
+                None => code,
+            };
+            let mut replaced = input.clone();
+            let name = h.name.to_lowercase();
+            for (start, end) in utils::find_token(&as_lowercase, &name).into_iter().rev() {
+                replaced.replace_range(start..end, &replacement);
+            }
+            Some(replaced)
+        };
+        let mut replaced = self
+            .historical_names
+            .iter()
+            .filter(|h| h.name == historical.name)
+            .filter_map(replace)
+            .collect::<Vec<String>>()
+            .into_iter();
+        if let Some(first) = replaced.next() {
+            *input = first;
+            location.warnings.push(Warning::HistoricalName);
+        }
+        for other in replaced {
+            let mut alternative = self.parse_location(&other);
+            alternative.warnings.push(Warning::HistoricalName);
+            alternative.warnings.sort();
+            alternative.warnings.dedup();
+            location.alternatives.push(alternative);
+        }
+    }
+
+    /// Check whether the input mentions a former name of the country, see
+    /// `Parser::replace_historical_name`.
+    pub(crate) fn mentions_historical_name(&self, input: &str, country: &Country) -> bool {
+        let as_lowercase = input.to_lowercase();
+        self.historical_names.iter().any(|h| {
+            h.country == country.code
+                && h.city.is_none()
+                && !utils::find_token(&as_lowercase, &h.name.to_lowercase()).is_empty()
+        })
+    }
+
+    /// Check whether the input mentions a country.
+    fn mentions_country(&self, input: &str) -> bool {
+        let mut probe = Location::default();
+        self.fill_country(&mut probe, input);
+        probe.country.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-world"))]
+    fn test_parse_historical_names() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("Bombay, India", "Mumbai, IN"),
+            ("Saigon, Vietnam", "Ho Chi Minh City, VN"),
+            ("Leningrad, USSR", "Saint Petersburg, RU"),
+            ("Rangoon, Burma", "Yangon, MM"),
+        ] {
+            let location = parser.parse_location(input);
+            assert_eq!(location.to_string(), output, "input: {}", input);
+            assert!(location.warnings.contains(&Warning::HistoricalName));
+            assert!(!location.warnings.contains(&Warning::InferredCountry));
+        }
+        let location = parser.parse_location("Bombay");
+        assert_eq!(location.to_string(), "Mumbai, IN");
+        assert!(location.warnings.contains(&Warning::HistoricalName));
+        let location = parser.parse_location("Bombay, NY");
+        assert_eq!(location.to_string(), "Bombay, NY, US");
+        assert!(!location.warnings.contains(&Warning::HistoricalName));
+        let location = parser.parse_location("Mumbai, India");
+        assert!(location.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ambiguous_historical_name() {
+        let parser = Parser::new();
+        let location = parser.parse_location("Prague, Czechoslovakia");
+        assert_eq!(location.country.unwrap().code, "CZ");
+        assert_eq!(location.alternatives.len(), 1);
+        let alternative = &location.alternatives[0];
+        assert_eq!(alternative.country.as_ref().unwrap().code, "SK");
+        assert!(alternative.warnings.contains(&Warning::HistoricalName));
+        assert!(location.warnings.contains(&Warning::HistoricalName));
+        assert!(location.warnings.contains(&Warning::AmbiguousCity));
+    }
+}
@@ -2,29 +2,323 @@
 #[macro_use]
 extern crate log;
 extern crate unidecode;
+#[macro_use]
+mod trace;
+mod blocklist;
+mod builder;
+mod chain;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod datafiles;
+mod delimited;
+mod error;
+pub mod historical;
+pub mod mapped;
+pub mod metrics;
 mod mocks;
+mod multi;
 pub mod nodes;
+pub mod noise;
+mod normalizer;
+mod options;
+mod preload;
+pub mod prelude;
+mod redact;
+mod repeats;
+pub mod schema;
+mod site;
+mod slug;
+pub mod snapshot;
+pub mod special;
+pub mod stages;
+pub mod testing;
 pub mod utils;
+mod validation;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub use builder::ParserBuilder;
+pub use chain::{ChainedLocation, ParserChain};
+pub use error::{ParseError, ReloadError};
+use lazy_static::lazy_static;
+use metrics::{Metric, MetricsSink};
 use nodes::{
-    read_cities, read_countries, read_states, City, CountriesMap, Country, CountryCities,
-    CountryStates, Location,
+    classify_codes, CityTrie, CodeKind, CountriesMap, Country, CountryCities, CountryStates,
+    Location, ScoredLocation, Warning, WorldCities, WorldCity,
 };
+pub use normalizer::{NormalizeStep, Normalizer};
+pub use options::{OutputCase, ParserOptions};
+pub use preload::Preload;
+use stages::{Flow, Stage};
+use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
 use titlecase::titlecase;
+pub use trace::TraceStep;
 use unidecode::unidecode;
+pub use validation::{LocationInput, ValidationIssue, ValidationReport};
+
+lazy_static! {
+    /// Parser with default options behind `FromStr` and `TryFrom` implementations
+    pub(crate) static ref DEFAULT_PARSER: Parser = Parser::new();
+}
 
-#[derive(Debug)]
+/// Location parser. Cloning is cheap, datasets, indexes and stages
+/// are shared between clones, so a parser can be cloned per worker.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let parser = geo_rs::Parser::new();
+/// let workers: Vec<_> = (0..2)
+///     .map(|_| {
+///         let parser = parser.clone();
+///         std::thread::spawn(move || parser.parse_location("Toronto, ON").to_string())
+///     })
+///     .collect();
+/// for worker in workers {
+///     assert_eq!(worker.join().unwrap(), String::from("Toronto, ON, CA"));
+/// }
+/// ```
+#[derive(Debug, Clone)]
 pub struct Parser {
-    cities: CountryCities,
-    states: CountryStates,
-    countries: CountriesMap,
+    // Datasets shared with other parsers, see `snapshot::Datasets::shared`
+    cities: Arc<CountryCities>,
+    world_cities: Arc<WorldCities>,
+    major_world_cities: Arc<Vec<WorldCity>>,
+    states: Arc<CountryStates>,
+    countries: Arc<CountriesMap>,
+    options: ParserOptions,
+    /// Cleaning of inputs before parsing, see `ParserBuilder::with_normalize_steps`
+    normalizer: Normalizer,
+    /// Inputs that name city, state and country at once, see `Parser::fill_special_case_city`
+    special_cases: Vec<special::SpecialCase>,
+    /// Patterns of campuses, buildings and other landmarks, see `Parser::fill_landmark`
+    landmark_patterns: Vec<(regex::Regex, nodes::LandmarkKind)>,
+    /// Internal site codes of companies by `site::site_key`, longest first,
+    /// see `ParserBuilder::with_site_codes`
+    site_codes: Vec<(String, Location)>,
+    /// Inputs that never match a location by `blocklist::blocklist_key`,
+    /// see `ParserBuilder::with_blocklist`
+    blocklist: HashSet<String>,
+    /// Former names of cities and countries, see `Parser::replace_historical_name`
+    historical_names: Vec<historical::HistoricalName>,
+    // Artifacts that depend on options and dictionaries, see `Parser::compile`
+    default_countries: Vec<Country>,
+    country_alias_tokens: Vec<(Vec<String>, String)>,
+    /// Countries that have states, see `Parser::state_countries`
+    state_countries: Vec<Country>,
+    /// What every known two-letter code stands for
+    code_kinds: HashMap<String, CodeKind>,
+    /// Prefix tree over city names, see `Parser::suggest_cities`
+    city_trie: Arc<CityTrie>,
+    /// Cities by country code and phonetic key, empty unless `ParserOptions::phonetic_cities`
+    phonetic_cities: Arc<nodes::PhoneticIndex>,
+    /// Cities by country code and the first word of their names, see `nodes::city::word_index`
+    city_words: Arc<nodes::WordIndex>,
+    /// Names of cities, states and countries, see `Parser::collapse_repeats`
+    known_names: Arc<HashSet<String>>,
+    stages: Arc<Vec<Box<dyn Stage>>>,
+    /// Receiver of counters of parsed locations, see `ParserBuilder::with_metrics`
+    metrics: Option<Arc<dyn MetricsSink>>,
+    /// Data folder and custom dictionaries the parser was built with, see `Parser::reload`
+    recipe: Arc<ParserBuilder>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Parser {
     pub fn new() -> Self {
+        Self::with_options(ParserOptions::default())
+    }
+
+    /// Create a parser that behaves according to the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Parser options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let options = geo_rs::ParserOptions::new().infer_unique_city(true);
+    /// let parser = geo_rs::Parser::with_options(options);
+    /// ```
+    pub fn with_options(options: ParserOptions) -> Self {
+        let mut parser = Self::uncompiled(options, snapshot::Datasets::shared());
+        parser.compile();
+        parser.check_city_counts();
+        parser
+    }
+
+    /// Parser made of the datasets that still has to be compiled, see `Parser::compile`,
+    /// so that builders customize it first and compile it only once.
+    fn uncompiled(options: ParserOptions, datasets: snapshot::Datasets) -> Self {
         Self {
-            cities: read_cities(),
-            states: read_states(),
-            countries: read_countries(),
+            cities: datasets.cities,
+            world_cities: datasets.world_cities,
+            major_world_cities: datasets.major_world_cities,
+            states: datasets.states,
+            countries: datasets.countries,
+            options,
+            normalizer: Normalizer::new(),
+            special_cases: special::read_special_cases(),
+            landmark_patterns: nodes::landmark::read_landmark_patterns(),
+            site_codes: vec![],
+            blocklist: HashSet::new(),
+            historical_names: historical::read_historical_names(),
+            default_countries: vec![],
+            country_alias_tokens: vec![],
+            state_countries: vec![],
+            code_kinds: HashMap::new(),
+            city_trie: Arc::default(),
+            phonetic_cities: Arc::default(),
+            city_words: Arc::default(),
+            known_names: Arc::default(),
+            stages: Arc::new(stages::default_stages()),
+            metrics: None,
+            recipe: Arc::default(),
+        }
+    }
+
+    /// Precompute everything that depends on options and dictionaries,
+    /// so that the cost of a single parse doesn't depend on them.
+    /// Has to be called whenever options or dictionaries change.
+    fn compile(&mut self) {
+        self.default_countries = self
+            .options
+            .default_countries
+            .iter()
+            .filter_map(|code| match self.countries.code_to_name.get(code) {
+                Some(name) => Some(Country {
+                    code: code.clone(),
+                    name: name.clone(),
+                }),
+                None => {
+                    warn!("Ignoring unknown default country {}", code);
+                    None
+                }
+            })
+            .collect();
+        self.country_alias_tokens = self
+            .countries
+            .aliases
+            .iter()
+            .map(|(alias, code)| (nodes::country::alias_tokens(alias), code.clone()))
+            .collect();
+        self.state_countries = nodes::state::rank_state_countries(
+            &self.default_countries,
+            &self.states,
+            &self.countries,
+        );
+        self.code_kinds = classify_codes(&self.states, &self.countries);
+        self.city_trie = Arc::new(CityTrie::new(&self.cities));
+        self.phonetic_cities = Arc::new(match self.options.phonetic_cities {
+            true => nodes::city::phonetic_index(&self.cities),
+            false => HashMap::new(),
+        });
+        self.city_words = Arc::new(nodes::city::word_index(&self.cities));
+        self.known_names = Arc::new(repeats::known_names(
+            &self.cities,
+            &self.states,
+            &self.countries,
+        ));
+    }
+
+    /// Create a parser from freshly read data files with the options, stages,
+    /// metrics and custom dictionaries of this one, e.g. after the data files
+    /// were updated on disk. Only the datasets of this parser are read again,
+    /// other parsers, including the ones created later, keep the datasets shared
+    /// by the process. See `watch::WatchedParser` to swap parsers atomically.
+    /// Fails when the data files can't be read, e.g. one of them is missing or
+    /// isn't valid UTF-8, so that the caller can keep using this parser.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::builder()
+    ///     .with_city_alias("The Big Apple", "US", "NY", "New York")
+    ///     .build();
+    /// let parser = parser.reload().unwrap();
+    /// let location = parser.parse_location("The Big Apple");
+    /// assert_eq!(location.to_string(), String::from("New York, NY, US"));
+    /// ```
+    pub fn reload(&self) -> Result<Parser, ReloadError> {
+        // Data readers panic on files they can't read
+        panic::catch_unwind(AssertUnwindSafe(|| self.reload_data())).map_err(|payload| {
+            let message = match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(payload) => match payload.downcast::<&str>() {
+                    Ok(message) => message.to_string(),
+                    Err(_) => String::from("unknown error"),
+                },
+            };
+            ReloadError { message }
+        })
+    }
+
+    fn reload_data(&self) -> Parser {
+        let datasets = match self.recipe.data_dir() {
+            // Cities, states and countries are read from the data folder by `customize`
+            Some(_) => snapshot::Datasets {
+                cities: self.cities.clone(),
+                world_cities: Arc::new(nodes::read_world_cities()),
+                major_world_cities: Arc::new(nodes::read_major_world_cities()),
+                states: self.states.clone(),
+                countries: self.countries.clone(),
+            },
+            None => snapshot::Datasets::load(),
+        };
+        let mut parser = Parser::uncompiled(self.options.clone(), datasets);
+        self.recipe.customize(&mut parser);
+        parser.stages = self.stages.clone();
+        parser.metrics = self.metrics.clone();
+        parser.recipe = self.recipe.clone();
+        parser.compile();
+        parser.check_city_counts();
+        parser
+    }
+
+    /// Return a builder to create a parser with custom options and dictionaries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::builder()
+    ///     .with_options(geo_rs::ParserOptions::new().infer_unique_city(true))
+    ///     .build();
+    /// ```
+    pub fn builder() -> ParserBuilder {
+        ParserBuilder::new()
+    }
+
+    /// Return the given country or, when it's unknown, countries to search
+    /// in ordered by priority, see `ParserOptions::default_countries`.
+    ///
+    /// # Arguments
+    ///
+    /// * `country` - An optional `Country`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let countries = parser.candidate_countries(&None);
+    /// assert_eq!(countries[0].code, "US".to_string());
+    /// assert_eq!(countries[1].code, "CA".to_string());
+    /// ```
+    pub fn candidate_countries(&self, country: &Option<Country>) -> Vec<Country> {
+        match country {
+            Some(c) => vec![c.clone()],
+            None => self.default_countries.clone(),
         }
     }
 
@@ -45,67 +339,288 @@ impl Parser {
     /// assert_eq!(location.country.unwrap().code, String::from("CA"));
     /// ```
     pub fn parse_location(&self, input: &str) -> Location {
+        stage!("parse", input);
+        let mut output = self.parse(input);
+        self.fill_warnings(&mut output, input);
+        if let Some(metrics) = &self.metrics {
+            Metric::of(&output)
+                .into_iter()
+                .for_each(|m| metrics.increment(m));
+        }
+        output
+    }
+
+    /// Parse location string like `parse_location` does, but in strict mode
+    /// reject low-confidence results, so they can be reviewed manually.
+    /// Without strict mode the location is always returned.
+    ///
+    /// Returns `ParseError::NoMatch` when no known city, state or country is
+    /// found and `ParseError::Ambiguous` when more than one city matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string that's gonna be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let options = geo_rs::ParserOptions::new().strict(true);
+    /// let parser = geo_rs::Parser::with_options(options);
+    /// let location = parser.try_parse_location("Toronto, ON, CA").unwrap();
+    /// assert_eq!(location.to_string(), String::from("Toronto, ON, CA"));
+    /// let result = parser.try_parse_location("Nothing to see here");
+    /// assert_eq!(result, Err(geo_rs::ParseError::NoMatch));
+    /// ```
+    pub fn try_parse_location(&self, input: &str) -> Result<Location, ParseError> {
+        let location = self.parse_location(input);
+        if !self.options.strict {
+            return Ok(location);
+        }
+        let recognized = location.state.is_some()
+            || location.country.is_some()
+            || (location.city.is_some() && !location.warnings.contains(&Warning::UnknownCity));
+        if !recognized {
+            return Err(ParseError::NoMatch);
+        }
+        if location.warnings.contains(&Warning::AmbiguousCity) {
+            return Err(ParseError::Ambiguous);
+        }
+        Ok(location)
+    }
+
+    /// Parse location string like `parse_location` does and also return
+    /// every step the parser went through, e.g. matched patterns,
+    /// considered candidates and the reasons they were discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string that's gonna be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let (location, steps) = parser.parse_location_explained("Toronto, ON, CA");
+    /// assert_eq!(location.to_string(), String::from("Toronto, ON, CA"));
+    /// let step = steps.iter().find(|s| s.message == "matched state").unwrap();
+    /// assert_eq!(step.stage, "parse");
+    /// assert!(step.fields[0].1.contains("Ontario"));
+    /// ```
+    pub fn parse_location_explained(&self, input: &str) -> (Location, Vec<TraceStep>) {
+        trace::record(|| self.parse_location(input))
+    }
+
+    /// Cleaning this parser runs before parsing, see `ParserBuilder::with_normalize_steps`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut s = String::from("Mt. Vernon, NY (Store 12)");
+    /// parser.normalizer().normalize(&mut s);
+    /// assert_eq!(s, String::from("Mount Vernon, NY"));
+    /// ```
+    pub fn normalizer(&self) -> &Normalizer {
+        &self.normalizer
+    }
+
+    /// Normalize location string without parsing it, so that different spellings
+    /// of the same location can be grouped together. Diacritics are removed,
+    /// abbreviations are expanded, parts of the input are title-cased
+    /// and state or country codes are upper-cased.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string that's gonna be normalized
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let normalized = parser.normalize("BUFFALO,New York,United States");
+    /// assert_eq!(normalized, String::from("Buffalo, New York, United States"));
+    /// let normalized = parser.normalize("Ft. Lauderdale , fl");
+    /// assert_eq!(normalized, String::from("Fort Lauderdale, FL"));
+    /// ```
+    pub fn normalize(&self, input: &str) -> String {
+        let mut normalized = unidecode(input);
+        self.clean(&mut normalized);
+        if self.normalizer.has_step(NormalizeStep::Dedup) {
+            self.collapse_repeats(&mut normalized);
+        }
+        normalized
+            .split(", ")
+            .map(|part| {
+                let upper = part.to_uppercase();
+                let is_code = !self.code_kind(&upper).is_empty()
+                    || self.countries.alpha3_to_code.contains_key(&upper);
+                if part.len() <= 3 && is_code {
+                    upper
+                } else {
+                    titlecase(&part.to_lowercase())
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn parse(&self, input: &str) -> Location {
         let mut output = Location {
             city: None,
             state: None,
             country: None,
             zipcode: None,
             address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: input.to_string(),
         };
-        let mut input_copy = unidecode(&input.to_string());
-        utils::clean(&mut input_copy);
-        let mut remainder = input_copy.clone();
-        debug!("input value: {}", remainder);
-        self.fill_country(&mut output, &remainder);
-        if let Some(c) = &output.country {
-            self.remove_country(c, &mut remainder);
-        }
-        self.fill_zipcode(&mut output, &remainder);
-        if let Some(z) = &output.zipcode {
-            self.remove_zipcode(z, &mut remainder);
-            if let Some(c) = &output.country {
-                self.remove_country(c, &mut remainder);
+        let mut remainder = unidecode(input);
+        for stage in self.stages.iter() {
+            if stage.run(self, &mut output, &mut remainder) == Flow::Stop {
+                break;
             }
         }
-        self.fill_special_case_city(&mut output, &remainder);
-        if let (Some(_), Some(_), Some(_)) = (&output.city, &output.state, &output.country) {
-            return output;
-        }
-        self.fill_state(&mut output, &remainder);
-        if let (Some(s), Some(c)) = (&output.state, &output.country) {
-            self.remove_state(s, c, &mut remainder);
-            self.remove_country(c, &mut remainder);
-        }
-        self.fill_city(&mut output, &remainder);
-        if let Some(c) = output.city {
-            output.city = Some(c.clone());
-            self.remove_city(&mut remainder, &c);
-        }
-        if output.city.is_none() && remainder.chars().count() > 0 {
-            output.city = Some(City {
-                name: titlecase(
-                    remainder
-                        .split(",")
-                        .next()
-                        .unwrap_or("")
-                        .to_string()
-                        .chars()
-                        .filter(|c| !c.is_digit(10))
-                        .collect::<String>()
-                        .as_str(),
-                ),
-            })
+        for alternative in &mut output.alternatives {
+            alternative.raw = input.to_string();
         }
-        utils::decode(&mut output);
-        debug!("output value: {}, remainder: {}", output, remainder);
+        self.apply_output_case(&mut output);
+        trace_event!("parsed", output = output.to_string(), remainder = remainder);
         output
     }
+
+    /// Add warnings about parts of the location that were guessed
+    /// rather than found in the input and order them by code.
+    fn fill_warnings(&self, location: &mut Location, input: &str) {
+        if !location.alternatives.is_empty() {
+            location.warnings.push(Warning::AmbiguousCity);
+        }
+        // Dotted codes are mentions too, e.g. "B.C." or "U.S."
+        let mut input = self.collapse_dotted_codes(&unidecode(input));
+        self.translate_state_names(&mut input);
+        if let Some(s) = &location.state {
+            if !utils::mentions(&input, &s.code, &s.name) {
+                location.warnings.push(Warning::InferredState);
+            }
+        }
+        if let Some(c) = &location.country {
+            if !utils::mentions(&input, &c.code, &c.name)
+                && !self.mentions_country_alias(&input, c)
+                && !self.mentions_historical_name(&input, c)
+            {
+                location.warnings.push(Warning::InferredCountry);
+            }
+        }
+        if !location.collapsed.is_empty() {
+            location.warnings.push(Warning::Duplicates);
+        }
+        location.warnings.sort();
+        location.warnings.dedup();
+    }
+
+    /// Parse location string and return every plausible interpretation of it
+    /// ranked by score, the most likely one first. Interpretations are weighted
+    /// by population of their cities when it's known, scores sum up to 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string that's gonna be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let candidates = parser.parse_location_candidates("Portland");
+    /// assert_eq!(candidates[0].location.to_string(), String::from("Portland, OR, US"));
+    /// assert_eq!(candidates[1].location.to_string(), String::from("Portland, ME, US"));
+    /// assert!(candidates[0].score > candidates[1].score);
+    /// ```
+    pub fn parse_location_candidates(&self, input: &str) -> Vec<ScoredLocation> {
+        let mut primary = self.parse_location(input);
+        let mut interpretations = std::mem::take(&mut primary.alternatives);
+        // Unless input mentions the state, the same city from any other
+        // state is a plausible interpretation too, e.g. "Greenville"
+        let collapsed = self.collapse_dotted_codes(input);
+        let state_mentioned = match &primary.state {
+            Some(s) => utils::mentions(&collapsed, &s.code, &s.name) || primary.zipcode.is_some(),
+            None => false,
+        };
+        let country_mentioned = match &primary.country {
+            Some(c) => utils::mentions(&collapsed, &c.code, &c.name),
+            None => false,
+        };
+        if let (Some(city), false) = (&primary.city, state_mentioned) {
+            let countries = match country_mentioned {
+                true => self.candidate_countries(&primary.country),
+                false => self.candidate_countries(&None),
+            };
+            for c in countries {
+                if let Some(country_cities) = self.cities.get(&c.code) {
+                    let city_name = city.name.to_lowercase();
+                    for state_code in country_cities
+                        .states_by_city
+                        .get(&city_name)
+                        .unwrap_or(&vec![])
+                    {
+                        let interpretation = Location {
+                            city: Some(city.clone()),
+                            state: self.state_from_code(&Some(c.clone()), state_code),
+                            country: Some(c.clone()),
+                            zipcode: primary.zipcode.clone(),
+                            address: primary.address.clone(),
+                            landmark: primary.landmark.clone(),
+                            alternatives: vec![],
+                            warnings: vec![],
+                            collapsed: vec![],
+                            raw: primary.raw.clone(),
+                        };
+                        if interpretation.to_string() != primary.to_string()
+                            && !interpretations.contains(&interpretation)
+                        {
+                            interpretations.push(interpretation);
+                        }
+                    }
+                }
+            }
+        }
+        interpretations.insert(0, primary);
+        let mut weights: Vec<f64> = interpretations
+            .iter()
+            .map(|l| self.population_of(l).unwrap_or(0) as f64 + 1.0)
+            .collect();
+        // Parser's own choice always stays on top
+        weights[0] = weights.iter().cloned().fold(0.0, f64::max);
+        let total: f64 = weights.iter().sum();
+        let mut candidates: Vec<ScoredLocation> = interpretations
+            .into_iter()
+            .zip(weights)
+            .map(|(location, weight)| ScoredLocation {
+                location,
+                score: weight / total,
+            })
+            .collect();
+        candidates.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        candidates
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
     use crate::mocks;
+    #[cfg(all(feature = "data-us", any(feature = "data-ca", feature = "data-world")))]
     use std::collections::HashMap;
 
     #[test]
@@ -114,6 +629,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
     fn test_format_location() {
         let mut locations: HashMap<&str, &str> = HashMap::new();
         // locations.insert("Moscow, Russia", "Moscow, RU");
@@ -127,7 +643,7 @@ mod tests {
         );
         locations.insert("Wilkes-Barre, Pennsylvania (PA)", "Wilkes Barre, PA, US");
         locations.insert("Sausalito, US", "Sausalito, CA, US");
-        // locations.insert("Lee's Summit, Missouri", "MO, US");
+        locations.insert("Lee's Summit, Missouri", "Lee's Summit, MO, US");
         locations.insert(
             "United States-California-San Diego-US CA San Diego - W. Brdway",
             "San Diego, CA, US",
@@ -138,7 +654,7 @@ mod tests {
         locations.insert("Lansing, MI, US, 48911", "Lansing, MI, US, 48911");
         locations.insert("Colleretto Giacosa", "Colleretto Giacosa");
         locations.insert("Mercer Island, WA", "Mercer Island, WA, US");
-        locations.insert("Lees Summit, Missouri", "Lees Summit, MO, US");
+        locations.insert("Lees Summit, Missouri", "Lee's Summit, MO, US");
         locations.insert(
             "BULLHEAD CITY FORT MOHAVE, Arizona, 86426",
             "Bullhead City, AZ, US, 86426",
@@ -188,10 +704,8 @@ mod tests {
             "United States-District of Columbia-washington-20340-DCCL",
             "Washington, DC, US, 20340",
         );
-        // locations.insert(
-        //     "01713-Mall At Greece Ridge Center",
-        //     "Mall At Greece Ridge Center, 01713",
-        // );
+        // The mall is a landmark, not a city, see `Location::landmark`
+        locations.insert("01713-Mall At Greece Ridge Center", "01713");
         locations.insert(
             "New Westminster, British Columbia, Canada",
             "New Westminster, BC, CA",
@@ -204,12 +718,13 @@ mod tests {
         locations.insert("Barcelona, Barcelona, ES, 08029", "Barcelona, ES, 08029");
         let parser = super::Parser::new();
         for (k, v) in locations {
-            let output = parser.parse_location(&k);
+            let output = parser.parse_location(k);
             assert_eq!(output.to_string(), v.to_string(), "Input: {}", k);
         }
     }
 
     #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
     fn test_parse_location() {
         let parser = Parser::new();
         for (input, (_, _, _, _, _, output)) in mocks::get_mocks() {
@@ -218,23 +733,492 @@ mod tests {
         }
     }
 
-    /// cargo test benchmark_parse_location -- --nocapture --ignored
-    /// 9.5ms -> 3.77ms -> ~1ms -> ~1.8ms -> 0.8ms
     #[test]
-    #[ignore]
-    fn benchmark_parse_location() {
-        let n = 250;
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_parse_location_with_unique_city() {
+        let options = ParserOptions::new().infer_unique_city(true);
+        let parser = Parser::with_options(options);
+        let mut locations: HashMap<&str, &str> = HashMap::new();
+        locations.insert("Sausalito", "Sausalito, CA, US");
+        locations.insert("Kelowna", "Kelowna, BC, CA");
+        locations.insert("Toronto, ON, CA", "Toronto, ON, CA");
+        locations.insert("Lansing, MI, US, 48911", "Lansing, MI, US, 48911");
+        for (k, v) in locations {
+            let output = parser.parse_location(k);
+            assert_eq!(output.to_string(), v.to_string(), "Input: {}", k);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_parse_location_candidates() {
         let parser = Parser::new();
-        let before = std::time::Instant::now();
-        for _ in 0..n {
-            for input in mocks::get_mocks().keys() {
-                parser.parse_location(input);
-            }
+        let candidates = parser.parse_location_candidates("Toronto, ON, CA");
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].location.to_string(), "Toronto, ON, CA");
+        assert_eq!(candidates[0].score, 1.0);
+        let candidates = parser.parse_location_candidates("Greenville");
+        assert!(candidates.len() > 3);
+        assert!(candidates
+            .iter()
+            .any(|c| c.location.to_string() == "Greenville, SC, US"));
+        let total: f64 = candidates.iter().map(|c| c.score).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_try_parse_location() {
+        let parser = Parser::new();
+        let location = parser.try_parse_location("Nothing to see here").unwrap();
+        assert!(location.warnings.contains(&Warning::UnknownCity));
+        let parser = Parser::with_options(ParserOptions::new().strict(true));
+        let location = parser.try_parse_location("Greenville, SC").unwrap();
+        assert_eq!(location.to_string(), "Greenville, SC, US");
+        assert_eq!(
+            parser.try_parse_location("ON, CA").unwrap().to_string(),
+            "ON, CA"
+        );
+        assert_eq!(
+            parser.try_parse_location("Portland"),
+            Err(ParseError::Ambiguous)
+        );
+        // Same-named cities in other countries and three or more candidates are ambiguous too
+        for input in ["Hamilton", "Windsor", "Greenville", "Greenville, US"] {
+            assert_eq!(
+                parser.try_parse_location(input),
+                Err(ParseError::Ambiguous),
+                "input: {}",
+                input
+            );
+        }
+        assert_eq!(
+            parser.try_parse_location("Nothing to see here"),
+            Err(ParseError::NoMatch)
+        );
+        assert_eq!(parser.try_parse_location(""), Err(ParseError::NoMatch));
+    }
+
+    #[test]
+    #[cfg(feature = "data-ca")]
+    fn test_normalize() {
+        let parser = Parser::new();
+        assert_eq!(
+            parser.normalize("BULLHEAD CITY, AZ"),
+            parser.normalize("Bullhead City, az")
+        );
+        assert_eq!(parser.normalize("Montréal, Québec"), "Montreal, Quebec");
+        assert_eq!(parser.normalize("Kelowna, bc, can"), "Kelowna, BC, CAN");
+        assert_eq!(parser.normalize(""), "");
+    }
+
+    #[test]
+    fn test_parse_location_explained() {
+        let parser = Parser::new();
+        let (location, steps) = parser.parse_location_explained("Greenville, 29601");
+        assert_eq!(location, parser.parse_location("Greenville, 29601"));
+        let stages: Vec<&str> = steps
+            .iter()
+            .filter(|s| s.message == "entered stage")
+            .map(|s| s.stage)
+            .collect();
+        assert_eq!(stages[0], "parse");
+        assert!(stages.contains(&"zipcode"));
+        assert!(stages.contains(&"city"));
+        let matched = steps
+            .iter()
+            .find(|s| s.message == "matched zipcode")
+            .unwrap();
+        assert_eq!(matched.fields[0].0, "zipcode");
+        assert!(matched.fields[0].1.contains("29601"));
+        // Nothing is left behind for the next parse
+        let (_, steps) = parser.parse_location_explained("Toronto");
+        assert!(!steps
+            .iter()
+            .any(|s| s.fields.iter().any(|f| f.1.contains("29601"))));
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_parse_location_with_custom_abbreviations() {
+        let parser = Parser::builder()
+            .with_abbreviations(vec![("Frt", "Fort")])
+            .build();
+        let location = parser.parse_location("Frt Lauderdale, FL");
+        assert_eq!(location.to_string(), "Fort Lauderdale, FL, US");
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_parse_location_with_single_letter_directions() {
+        let parser = Parser::new();
+        let location = parser.parse_location("E Providence, RI");
+        assert_eq!(location.to_string(), "East Providence, RI, US");
+        // An apartment letter isn't a direction of the city that follows it
+        let location = parser.parse_location("Apt E Providence, RI");
+        assert_eq!(location.city.unwrap().name, "Providence");
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-world"))]
+    fn test_parse_world_city() {
+        let mut locations: HashMap<&str, &str> = HashMap::new();
+        locations.insert("Berlin, DE", "Berlin, DE");
+        locations.insert("Paris, France", "Paris, FR");
+        locations.insert("Mexico City, MX", "Mexico City, MX");
+        locations.insert("Dover, DE", "Dover, DE, US");
+        locations.insert("Athens, GA", "Athens, GA, US");
+        let parser = Parser::new();
+        for (k, v) in locations {
+            let output = parser.parse_location(k);
+            assert_eq!(output.to_string(), v.to_string(), "Input: {}", k);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_parse_location_warnings() {
+        let parser = Parser::new();
+        let location = parser.parse_location("Toronto, ON, CA");
+        assert!(location.warnings.is_empty());
+        let location = parser.parse_location("Jacksonville, Florida, USA");
+        assert!(location.warnings.is_empty());
+        let location = parser.parse_location("Mercer Island, WA");
+        assert_eq!(location.warnings, vec![Warning::InferredCountry]);
+        let location = parser.parse_location("Vancouver, B.C.");
+        assert_eq!(location.to_string(), String::from("Vancouver, BC, CA"));
+        assert_eq!(location.warnings, vec![Warning::InferredCountry]);
+        let location = parser.parse_location("Brooklyn, N.Y., U.S.");
+        assert_eq!(location.to_string(), String::from("Brooklyn, NY, US"));
+        assert!(location.warnings.is_empty());
+        let location = parser.parse_location("Portland");
+        assert_eq!(
+            location.warnings,
+            vec![
+                Warning::AmbiguousCity,
+                Warning::InferredState,
+                Warning::InferredCountry
+            ]
+        );
+        let location = parser.parse_location("Colleretto Giacosa");
+        assert_eq!(location.warnings, vec![Warning::UnknownCity]);
+        let location = parser.parse_location("Cambridge, MA 2139");
+        assert_eq!(
+            location.to_string(),
+            String::from("Cambridge, MA, US, 02139")
+        );
+        assert_eq!(
+            location.warnings,
+            vec![Warning::InferredCountry, Warning::RestoredZipcode]
+        );
+        let location = parser.parse_location("Canton, MA, Canton, MA, US");
+        assert_eq!(location.to_string(), String::from("Canton, MA, US"));
+        assert_eq!(location.warnings, vec![Warning::Duplicates]);
+        assert_eq!(
+            location.collapsed,
+            vec![String::from("Canton"), String::from("MA")]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_parse_location_with_phonetic_cities() {
+        let parser = Parser::new();
+        let location = parser.parse_location("Pittsburg, PA");
+        assert_eq!(
+            location.warnings,
+            vec![Warning::UnknownCity, Warning::InferredCountry]
+        );
+        let parser = Parser::with_options(ParserOptions::new().phonetic_cities(true));
+        let location = parser.parse_location("Pittsburg, PA");
+        assert_eq!(location.to_string(), String::from("Pittsburgh, PA, US"));
+        assert_eq!(
+            location.warnings,
+            vec![Warning::InferredCountry, Warning::PhoneticCity]
+        );
+        let location = parser.parse_location("Cincinatti, OH 45202");
+        assert_eq!(
+            location.to_string(),
+            String::from("Cincinnati, OH, US, 45202")
+        );
+        // Exact matches win over phonetic ones
+        let location = parser.parse_location("Pittsburg, KS");
+        assert_eq!(location.to_string(), String::from("Pittsburg, KS, US"));
+        assert_eq!(location.warnings, vec![Warning::InferredCountry]);
+        let location = parser.parse_location("Cincinatti");
+        assert_eq!(location.city.unwrap().name, String::from("Cincinnati"));
+        assert_eq!(location.alternatives.len(), 1);
+        assert!(location.warnings.contains(&Warning::AmbiguousCity));
+        assert!(location.warnings.contains(&Warning::PhoneticCity));
+    }
+
+    /// Write a new file and rename it over the old one, mapped data
+    /// files must not be changed in place, see `mapped`
+    #[cfg(feature = "data-us")]
+    fn replace_file(path: &std::path::Path, contents: &[u8]) {
+        let new = path.with_extension("new");
+        std::fs::write(&new, contents).unwrap();
+        std::fs::rename(new, path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_reload() {
+        let fixture =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/schema/current");
+        let dir = std::env::temp_dir().join(format!("geo-rs-reload-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("US")).unwrap();
+        for file in [
+            "countries.txt",
+            "country_aliases.txt",
+            "US/states.txt",
+            "US/cities.txt",
+        ] {
+            std::fs::copy(fixture.join(file), dir.join(file)).unwrap();
         }
-        println!(
-            "Elapsed time: {:.2?}, {:.2?} each",
-            before.elapsed(),
-            before.elapsed() / (n * mocks::get_mocks().len() as u32)
+        let parser = Parser::builder()
+            .with_data_dir(&dir)
+            .with_city_alias("Nickel City", "US", "NY", "Buffalo")
+            .without_stage("duplicates")
+            .build();
+        let location = parser.parse_location("Albany, NY");
+        assert!(location.warnings.contains(&Warning::UnknownCity));
+        let cities = std::fs::read_to_string(dir.join("US/cities.txt")).unwrap();
+        replace_file(
+            &dir.join("US/cities.txt"),
+            (cities + "NY;Albany;99224\n").as_bytes(),
         );
+        let shared = snapshot::Datasets::shared();
+        let reloaded = parser.reload().unwrap();
+        assert!(Arc::ptr_eq(
+            &shared.cities,
+            &snapshot::Datasets::shared().cities
+        ));
+        let location = reloaded.parse_location("Albany, NY");
+        assert!(!location.warnings.contains(&Warning::UnknownCity));
+        let location = reloaded.parse_location("Nickel City");
+        assert_eq!(location.to_string(), "Buffalo, NY, US");
+        assert_eq!(reloaded.stages.len(), parser.stages.len());
+        replace_file(&dir.join("US/cities.txt"), b"NY;Alb\xe9ny;99224\n");
+        let error = reloaded.reload().err().unwrap();
+        assert!(error.message.contains("US/cities.txt"), "{}", error);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_location_keeps_raw_input() {
+        let parser = Parser::new();
+        let input = "CA-ON-Oakville-3235 Dundas St W (Store# 04278)";
+        let location = parser.parse_location(input);
+        assert_eq!(location.raw, input);
+        assert_eq!(parser.parse_location(&location.raw), location);
+        let location = parser.parse_location("Portland");
+        assert!(location.alternatives.iter().all(|a| a.raw == "Portland"));
+        let candidates = parser.parse_location_candidates("Greenville");
+        assert!(candidates.iter().all(|c| c.location.raw == "Greenville"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_parse_location_in_other_languages() {
+        let mut locations = HashMap::new();
+        locations.insert("Vancouver, Colombie-Britannique", "Vancouver, BC, CA");
+        locations.insert("Halifax (Nouvelle-Écosse)", "Halifax, NS, CA");
+        locations.insert(
+            "Charlottetown, Île-du-Prince-Édouard, Canada",
+            "Charlottetown, PE, CA",
+        );
+        locations.insert("Miami, Floride, États-Unis", "Miami, FL, US");
+        locations.insert(
+            "Charlotte, Carolina del Norte, Estados Unidos",
+            "Charlotte, NC, US",
+        );
+        locations.insert("Santa Fe, Nuevo México", "Santa Fe, NM, US");
+        let parser = Parser::new();
+        for (k, v) in locations {
+            let output = parser.parse_location(k);
+            assert_eq!(output.to_string(), v.to_string(), "Input: {}", k);
+            let warnings: Vec<Warning> = output
+                .warnings
+                .into_iter()
+                .filter(|w| w != &Warning::InferredCountry)
+                .collect();
+            assert!(warnings.is_empty(), "Input: {}", k);
+        }
+        let location = parser.parse_location("Miami, Floride, États-Unis");
+        assert!(location.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_candidate_countries() {
+        let options = ParserOptions::new().default_countries(vec!["XX", "CA"]);
+        let parser = Parser::with_options(options);
+        assert_eq!(
+            parser.candidate_countries(&None),
+            vec![nodes::CANADA.clone()]
+        );
+        assert_eq!(
+            parser.candidate_countries(&Some(nodes::UNITED_STATES.clone())),
+            vec![nodes::UNITED_STATES.clone()]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "data-ca")]
+    fn test_parse_location_with_diacritics() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("Montréal, Québec, Canada", "Montreal, QC, CA"),
+            ("Saint-Jérôme, QC", "Saint-Jerome, QC, CA"),
+            ("Saint-Jerome, QC", "Saint-Jerome, QC, CA"),
+            ("Trois-Rivières, Québec", "Trois-Rivieres, QC, CA"),
+            ("Trois Rivieres, QC", "Trois-Rivieres, QC, CA"),
+            ("Montreal Nord, QC", "Montreal-Nord, QC, CA"),
+            ("Québec, QC", "Quebec, QC, CA"),
+            (
+                "Notre-Dame-de-l'Île-Perrot, QC",
+                "Notre-Dame-De-L'Ile-Perrot, QC, CA",
+            ),
+        ] {
+            assert_eq!(parser.parse_location(input).to_string(), output);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_parse_location_with_apostrophes() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("Lee's Summit, MO", "Lee's Summit, MO, US"),
+            ("Lees Summit, MO", "Lee's Summit, MO, US"),
+            ("O'Fallon, IL", "O'Fallon, IL, US"),
+            ("O Fallon, MO", "O'Fallon, MO, US"),
+            ("Coeur D Alene, Idaho", "Coeur d'Alene, ID, US"),
+            ("Port O’Connor, TX", "Port O'Connor, TX, US"),
+            ("Hudsons Hope, BC", "Hudson's Hope, BC, CA"),
+            ("Val-d'Or, QC", "Val-D'Or, QC, CA"),
+            ("Abidjan, Côte d'Ivoire", "Abidjan, CI"),
+        ] {
+            assert_eq!(parser.parse_location(input).to_string(), output);
+        }
+        let parser = Parser::with_options(ParserOptions::new().output_case(OutputCase::Upper));
+        let location = parser.parse_location("o fallon, mo");
+        assert_eq!(location.to_string(), "O'FALLON, MO, US");
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_parse_location_with_country_alias() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("Austin, TX, U.S.A.", "Austin, TX, US"),
+            ("Boise, Idaho, U.S.", "Boise, ID, US"),
+            ("Holland, MI", "Holland, MI, US"),
+        ] {
+            assert_eq!(parser.parse_location(input).to_string(), output);
+        }
+        let location = parser.parse_location("Munich, DEU");
+        assert_eq!(location.to_string(), String::from("Munich, DE"));
+        let location = parser.parse_location("Ottawa, ON, CAN");
+        assert_eq!(location.to_string(), String::from("Ottawa, ON, CA"));
+        let parser = Parser::builder().with_country_alias("Nippon", "JP").build();
+        let location = parser.parse_location("Tokyo, Nippon");
+        assert_eq!(location.country.unwrap().code, String::from("JP"));
+    }
+
+    #[test]
+    fn test_parse_country_only() {
+        let parser = Parser::new();
+        for (input, code) in [
+            ("Deutschland", "DE"),
+            ("United Kingdom", "GB"),
+            ("Brasil", "BR"),
+            ("España", "ES"),
+            ("Österreich", "AT"),
+        ] {
+            let location = parser.parse_location(input);
+            assert_eq!(
+                location.country.as_ref().unwrap().code,
+                code,
+                "input: {}",
+                input
+            );
+            assert_eq!(location.granularity(), Some(nodes::Granularity::Country));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-world-major")]
+    fn test_parse_major_world_city() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("Tokyo", "Tokyo, JP"),
+            ("Sydney NSW", "Sydney, AU"),
+            ("Mexico City", "Mexico City, MX"),
+            ("Lagos, Nigeria", "Lagos, NG"),
+            ("London, ON", "London, ON, CA"),
+            ("Portland, OR", "Portland, OR, US"),
+        ] {
+            assert_eq!(parser.parse_location(input).to_string(), output);
+        }
+        let location = parser.parse_location("Tokyo");
+        assert_eq!(parser.coordinates(&location), Some((35.69, 139.69)));
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_parse_location_with_city_alias() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("NYC", "New York, NY, US"),
+            ("New York City, NY", "New York, NY, US"),
+            ("Philly, PA, USA", "Philadelphia, PA, US"),
+            ("Ville de Québec", "Quebec, QC, CA"),
+            ("St. Louis, MO", "Saint Louis, MO, US"),
+            ("Las Vegas, NM", "Las Vegas, NM, US"),
+        ] {
+            assert_eq!(parser.parse_location(input).to_string(), output);
+        }
+        let parser = Parser::builder()
+            .with_city_alias("Beantown", "US", "MA", "Boston")
+            .build();
+        let location = parser.parse_location("Beantown");
+        assert_eq!(location.to_string(), String::from("Boston, MA, US"));
+    }
+
+    #[test]
+    fn test_clone_shares_data() {
+        let parser = Parser::builder()
+            .with_options(ParserOptions::new().phonetic_cities(true))
+            .with_city_alias("Beantown", "US", "MA", "Boston")
+            .build();
+        let clone = parser.clone();
+        assert!(Arc::ptr_eq(&parser.cities, &clone.cities));
+        assert!(Arc::ptr_eq(&parser.city_trie, &clone.city_trie));
+        assert!(Arc::ptr_eq(&parser.phonetic_cities, &clone.phonetic_cities));
+        assert!(Arc::ptr_eq(&parser.stages, &clone.stages));
+        for input in ["Beantown", "Pittsburg, PA", "Toronto, ON, CA"] {
+            assert_eq!(parser.parse_location(input), clone.parse_location(input));
+        }
+    }
+
+    /// Characters that change byte length when their case changes, e.g. "İ",
+    /// must not shift spans found in one string and removed from another
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_non_ascii_input() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("İİ Toronto, ON", "Toronto, ON, CA"),
+            ("Toronto (İ 12), ON", "Toronto, ON, CA"),
+            ("Mt. Vernon (ﬁ (ẞ)), NY", "Mount Vernon, NY, US"),
+            ("Lansing, MI 4891İ", "Lansing, MI, US"),
+        ] {
+            assert_eq!(parser.parse_location(input).to_string(), output);
+        }
+        for input in ["(漢 9", "ǅ's Montréal’s", "Σ)(", "’", "İ"] {
+            parser.parse_location(input);
+        }
     }
 }
@@ -0,0 +1,291 @@
+//! Data files mapped into memory with `mmap` feature, so that datasets borrow city
+//! names and zipcode ranges from them instead of copying them, see `DataStr`.
+//!
+//! A mapping is shared by the datasets read from the same version of a file and
+//! unmapped once the last of them is dropped, e.g. after `Parser::reload`. Mapped
+//! files must be replaced, e.g. renamed over, rather than changed in place.
+use crate::utils::DataSource;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+#[cfg(feature = "mmap")]
+lazy_static::lazy_static! {
+    /// Files mapped by `map` by their path, size and modification time, so that
+    /// datasets read from the same version of a file share its mapping
+    static ref MAPPED: std::sync::Mutex<std::collections::HashMap<MappedKey, std::sync::Weak<MappedFile>>> =
+        std::sync::Mutex::new(std::collections::HashMap::new());
+}
+
+/// Path, size and modification time of a mapped file
+#[cfg(feature = "mmap")]
+type MappedKey = (std::path::PathBuf, u64, Option<std::time::SystemTime>);
+
+/// Data file mapped into memory, checked to be valid UTF-8 when it's mapped.
+pub(crate) struct MappedFile {
+    #[cfg(feature = "mmap")]
+    mmap: memmap2::Mmap,
+}
+
+impl MappedFile {
+    fn text(&self) -> &str {
+        #[cfg(feature = "mmap")]
+        {
+            // Safety: the contents were checked to be valid UTF-8 by `map`
+            unsafe { std::str::from_utf8_unchecked(&self.mmap) }
+        }
+        #[cfg(not(feature = "mmap"))]
+        {
+            ""
+        }
+    }
+}
+
+/// String of a dataset, either copied from its data file or borrowed from the
+/// mapping of the file, which it keeps alive. Compared, hashed and displayed
+/// as a plain string.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs::mapped::DataStr;
+/// let name = DataStr::from("Eielson AFB");
+/// assert_eq!(name, *"Eielson AFB");
+/// assert_eq!(name.to_lowercase(), "eielson afb");
+/// assert!(!name.is_mapped());
+/// ```
+#[derive(Clone)]
+pub struct DataStr(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Owned(String),
+    /// Byte range of the string in the mapped file
+    Mapped(Arc<MappedFile>, usize, usize),
+}
+
+impl DataStr {
+    /// Whether the string is borrowed from a mapped data file.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.0, Repr::Mapped(..))
+    }
+}
+
+impl Deref for DataStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match &self.0 {
+            Repr::Owned(s) => s,
+            Repr::Mapped(file, start, end) => &file.text()[*start..*end],
+        }
+    }
+}
+
+impl AsRef<str> for DataStr {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl From<String> for DataStr {
+    fn from(s: String) -> Self {
+        DataStr(Repr::Owned(s))
+    }
+}
+
+impl From<&str> for DataStr {
+    fn from(s: &str) -> Self {
+        DataStr(Repr::Owned(s.to_string()))
+    }
+}
+
+impl fmt::Display for DataStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+impl fmt::Debug for DataStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl PartialEq for DataStr {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for DataStr {}
+
+impl PartialEq<str> for DataStr {
+    fn eq(&self, other: &str) -> bool {
+        &**self == other
+    }
+}
+
+impl Hash for DataStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+#[cfg(feature = "binary-data")]
+impl serde::Serialize for DataStr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+#[cfg(feature = "binary-data")]
+impl<'de> serde::Deserialize<'de> for DataStr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(DataStr::from)
+    }
+}
+
+/// Line of a data file read by `read_lines`.
+pub(crate) enum Line {
+    Owned(String),
+    /// Byte range of the line in the mapped file
+    Mapped(Arc<MappedFile>, usize, usize),
+}
+
+impl Line {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            Line::Owned(s) => s,
+            Line::Mapped(file, start, end) => &file.text()[*start..*end],
+        }
+    }
+
+    /// Field of the line, see `utils::Records::split`, borrowed from the mapped
+    /// file when it's a slice of the line, copied otherwise, e.g. when it was unquoted.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - Field split from `Line::as_str`
+    pub(crate) fn field(&self, field: &str) -> DataStr {
+        if let Line::Mapped(file, line_start, line_end) = self {
+            let text = file.text();
+            let start = (field.as_ptr() as usize).checked_sub(text.as_ptr() as usize);
+            if let Some(start) = start.filter(|start| *start >= *line_start) {
+                let end = start + field.len();
+                if end <= *line_end {
+                    return DataStr(Repr::Mapped(file.clone(), start, end));
+                }
+            }
+        }
+        DataStr::from(field)
+    }
+}
+
+/// Same as `DataSource::read_optional_lines` but the lines of a folder's file
+/// are borrowed from its mapping with `mmap` feature.
+///
+/// # Arguments
+///
+/// * `source` - Where to read the file from
+/// * `filename` - Path of the file relative to `src/data`, e.g. "US/cities.txt"
+pub(crate) fn read_lines(source: &DataSource, filename: &str) -> Box<dyn Iterator<Item = Line>> {
+    let file = match map(source, filename) {
+        Some(file) => file,
+        None => return Box::new(source.read_optional_lines(filename).map(Line::Owned)),
+    };
+    let mut start = 0;
+    Box::new(std::iter::from_fn(move || {
+        let text = file.text();
+        if start >= text.len() {
+            return None;
+        }
+        let next = text[start..].find('\n').map_or(text.len(), |i| start + i);
+        let end = match text[start..next].ends_with('\r') {
+            true => next - 1,
+            false => next,
+        };
+        let line = Line::Mapped(file.clone(), start, end);
+        start = next + 1;
+        Some(line)
+    }))
+}
+
+/// Map the file of a folder into memory, or share the mapping of the same version
+/// of the file. `None` without `mmap` feature, for embedded files and when the
+/// file doesn't exist, panics when it isn't valid UTF-8.
+#[cfg(feature = "mmap")]
+fn map(source: &DataSource, filename: &str) -> Option<Arc<MappedFile>> {
+    let path = match source {
+        DataSource::Dir(dir) => dir.join(filename),
+        DataSource::Embedded => return None,
+    };
+    let file = std::fs::File::open(&path).ok()?;
+    let metadata = file.metadata().ok()?;
+    let key = (path, metadata.len(), metadata.modified().ok());
+    let mut mapped = MAPPED.lock().unwrap_or_else(|e| e.into_inner());
+    mapped.retain(|_, file| file.strong_count() > 0);
+    if let Some(file) = mapped.get(&key).and_then(|file| file.upgrade()) {
+        return Some(file);
+    }
+    // Safety: mapped files aren't changed in place, see the module docs
+    let mmap = match unsafe { memmap2::Mmap::map(&file) } {
+        Ok(mmap) => mmap,
+        Err(e) => {
+            warn!("Failed to map {:?}, reading it instead: {}", key.0, e);
+            return None;
+        }
+    };
+    if let Err(e) = std::str::from_utf8(&mmap) {
+        panic!("failed to read data file {}: {}", filename, e);
+    }
+    let file = Arc::new(MappedFile { mmap });
+    mapped.insert(key, Arc::downgrade(&file));
+    Some(file)
+}
+
+#[cfg(not(feature = "mmap"))]
+fn map(_source: &DataSource, _filename: &str) -> Option<Arc<MappedFile>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_read_lines() {
+        let dir = std::env::temp_dir().join(format!("geo-rs-mapped-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cities.txt"), "NY;New York\r\nNY;\"Buffalo\"\n").unwrap();
+        let source = DataSource::Dir(dir.clone());
+        let lines: Vec<Line> = read_lines(&source, "cities.txt").collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].as_str(), "NY;New York");
+        let name = lines[0].field(&lines[0].as_str()[3..]);
+        assert_eq!(name, *"New York");
+        assert_eq!(name.is_mapped(), cfg!(feature = "mmap"));
+        // Unquoted fields aren't slices of the line
+        assert!(!lines[1].field("Buffalo").is_mapped());
+        assert_eq!(read_lines(&source, "states.txt").count(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_mapping_is_shared_and_dropped() {
+        let dir = std::env::temp_dir().join(format!("geo-rs-unmapped-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cities.txt"), "NY;New York\n").unwrap();
+        let source = DataSource::Dir(dir.clone());
+        let first = map(&source, "cities.txt").unwrap();
+        let second = map(&source, "cities.txt").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        let weak = Arc::downgrade(&first);
+        drop((first, second));
+        assert_eq!(weak.strong_count(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
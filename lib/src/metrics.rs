@@ -0,0 +1,143 @@
+//! Counters of what the parser finds, so that extraction coverage can be
+//! monitored in production. A `MetricsSink` set with `ParserBuilder::with_metrics`
+//! is notified about every parsed location.
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs;
+//! use geo_rs::metrics::{Counters, Metric};
+//! use geo_rs::nodes::Warning;
+//! use std::sync::Arc;
+//! let counters = Arc::new(Counters::default());
+//! let parser = geo_rs::Parser::builder()
+//!     .with_metrics(counters.clone())
+//!     .build();
+//! parser.parse_location("Toronto, ON, CA");
+//! parser.parse_location("Springfield");
+//! assert_eq!(counters.get(Metric::Parse), 2);
+//! assert_eq!(counters.get(Metric::City), 2);
+//! assert_eq!(counters.get(Metric::Warning(Warning::AmbiguousCity)), 1);
+//! ```
+use crate::nodes::{Location, Warning};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Something counted for a parsed location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Metric {
+    /// Input was parsed, counted once for every location
+    Parse,
+    /// City was found
+    City,
+    /// State was found
+    State,
+    /// Country was found
+    Country,
+    /// Zipcode was found
+    Zipcode,
+    /// Street address was found
+    Address,
+    /// Location got the warning, e.g. `AmbiguousCity` when more than one city matches
+    Warning(Warning),
+}
+
+impl Metric {
+    /// Metrics of a parsed location, `Parse` goes first.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Parsed location
+    pub fn of(location: &Location) -> Vec<Metric> {
+        let found = [
+            (Metric::City, location.city.is_some()),
+            (Metric::State, location.state.is_some()),
+            (Metric::Country, location.country.is_some()),
+            (Metric::Zipcode, location.zipcode.is_some()),
+            (Metric::Address, location.address.is_some()),
+        ];
+        let mut metrics = vec![Metric::Parse];
+        metrics.extend(found.iter().filter(|(_, f)| *f).map(|(m, _)| *m));
+        metrics.extend(location.warnings.iter().map(|w| Metric::Warning(*w)));
+        metrics
+    }
+}
+
+/// Receiver of parse metrics, e.g. an adapter to a monitoring system.
+/// It's called from every thread that parses, so it has to be cheap.
+pub trait MetricsSink: fmt::Debug + Send + Sync {
+    /// Count the metric once.
+    fn increment(&self, metric: Metric);
+}
+
+impl<T: MetricsSink + ?Sized> MetricsSink for Arc<T> {
+    fn increment(&self, metric: Metric) {
+        (**self).increment(metric)
+    }
+}
+
+/// In-memory `MetricsSink` that keeps a count of every metric.
+#[derive(Debug, Default)]
+pub struct Counters {
+    counts: Mutex<HashMap<Metric, u64>>,
+}
+
+impl Counters {
+    /// How many times the metric was counted.
+    pub fn get(&self, metric: Metric) -> u64 {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(&metric)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Counts of every metric counted at least once, ordered by metric.
+    pub fn snapshot(&self) -> Vec<(Metric, u64)> {
+        let mut counts: Vec<(Metric, u64)> = self
+            .counts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(m, c)| (*m, *c))
+            .collect();
+        counts.sort();
+        counts
+    }
+
+    /// Forget everything counted so far.
+    pub fn reset(&self) {
+        self.counts.lock().unwrap().clear();
+    }
+}
+
+impl MetricsSink for Counters {
+    fn increment(&self, metric: Metric) {
+        *self.counts.lock().unwrap().entry(metric).or_insert(0) += 1;
+    }
+}
+
+#[cfg(all(test, feature = "data-ca"))]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn test_counters() {
+        let counters = Arc::new(Counters::default());
+        let parser = Parser::builder().with_metrics(counters.clone()).build();
+        parser.parse_location("Lansing, MI, US, 48911");
+        parser.parse_location("Springfield");
+        parser.parse_location("Nothing to see here");
+        assert!(parser.try_parse_location("Toronto, ON").is_ok());
+        assert_eq!(counters.get(Metric::Parse), 4);
+        assert_eq!(counters.get(Metric::Zipcode), 1);
+        assert_eq!(counters.get(Metric::Address), 0);
+        assert_eq!(counters.get(Metric::Warning(Warning::AmbiguousCity)), 1);
+        assert_eq!(counters.snapshot()[0], (Metric::Parse, 4));
+        counters.reset();
+        assert_eq!(counters.snapshot(), vec![]);
+    }
+}
@@ -210,7 +210,9 @@ pub fn get_mocks() -> HashMap<Input, Output> {
     locations.insert(
         "Lansing, US",
         (
-            None,
+            Some(City {
+                name: String::from("Lansing"),
+            }),
             None,
             Some(Country {
                 code: String::from("US"),
@@ -218,7 +220,7 @@ pub fn get_mocks() -> HashMap<Input, Output> {
             }),
             None,
             None,
-            "Lansing, US",
+            "Lansing, MI, US",
         ),
     );
     locations.insert(
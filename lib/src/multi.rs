@@ -0,0 +1,153 @@
+use crate::nodes::{Location, Warning};
+use crate::Parser;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// Separators of locations in one field, e.g. " or " in "Toronto or Vancouver".
+    /// Conjunctions are lowercase only, so that "OR" stays Oregon.
+    static ref RE_SEPARATORS: Regex = Regex::new(r"\s+(?:or|and|&)\s+|\s*[/;|]\s*").unwrap();
+}
+
+impl Parser {
+    /// Parse a field that lists several locations, e.g. "Toronto, ON or Vancouver, BC"
+    /// or "NYC / Boston / Remote", into every location it names. The field is split on
+    /// "or", "and", "&", slashes and semicolons and every segment is parsed on its own.
+    /// Segments naming nothing known, like "Remote", are dropped, names containing
+    /// a separator, like "Trinidad and Tobago", are kept whole, the same location
+    /// named twice is returned once. When no segment names a location the whole
+    /// field is parsed as one.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string that may name several locations
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let locations: Vec<String> = parser
+    ///     .parse_multi("Toronto, ON or Vancouver, BC")
+    ///     .iter()
+    ///     .map(|l| l.to_string())
+    ///     .collect();
+    /// assert_eq!(locations, vec!["Toronto, ON, CA", "Vancouver, BC, CA"]);
+    /// ```
+    pub fn parse_multi(&self, input: &str) -> Vec<Location> {
+        stage!("parse_multi", input);
+        let mut locations: Vec<Location> = vec![];
+        for segment in self.split_locations(input) {
+            let location = self.parse_location(&segment);
+            let recognized = location.state.is_some()
+                || location.country.is_some()
+                || (location.city.is_some() && !location.warnings.contains(&Warning::UnknownCity));
+            trace_event!("parsed segment", segment = segment, recognized = recognized);
+            if recognized
+                && !locations
+                    .iter()
+                    .any(|l| l.canonical_key() == location.canonical_key())
+            {
+                locations.push(location);
+            }
+        }
+        if locations.is_empty() {
+            return vec![self.parse_location(input)];
+        }
+        locations
+    }
+
+    /// Split the input on separators of locations, see `Parser::parse_multi`, rejoining
+    /// segments that make up a known name together, e.g. "Bosnia and Herzegovina".
+    fn split_locations(&self, input: &str) -> Vec<String> {
+        let mut pieces: Vec<&str> = vec![];
+        let mut last = 0;
+        for m in RE_SEPARATORS.find_iter(input) {
+            pieces.push(&input[last..m.start()]);
+            pieces.push(m.as_str());
+            last = m.end();
+        }
+        pieces.push(&input[last..]);
+        // Pieces alternate between segments and separators
+        let mut segments: Vec<String> = vec![];
+        let mut i = 0;
+        while i < pieces.len() {
+            let mut end = i;
+            for j in (i + 2..pieces.len()).step_by(2).rev() {
+                // Only the parts around the separators, e.g. "Trinidad and Tobago"
+                // of "Port of Spain, Trinidad and Tobago"
+                let head = pieces[i].rsplit(',').next().unwrap_or_default();
+                let tail = pieces[j].split(',').next().unwrap_or_default();
+                let name = [head, &pieces[i + 1..j].concat(), tail].concat();
+                if self.is_known_name(&name) {
+                    end = j;
+                    break;
+                }
+            }
+            let segment = pieces[i..=end].concat();
+            if !segment.trim().is_empty() {
+                segments.push(segment.trim().to_string());
+            }
+            i = end + 2;
+        }
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_locations() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("NYC / Boston / Remote", vec!["NYC", "Boston", "Remote"]),
+            (
+                "Portland, OR or Seattle, WA",
+                vec!["Portland, OR", "Seattle, WA"],
+            ),
+            (
+                "Toronto; Ottawa & Montreal",
+                vec!["Toronto", "Ottawa", "Montreal"],
+            ),
+            (
+                "Port of Spain, Trinidad and Tobago",
+                vec!["Port of Spain, Trinidad and Tobago"],
+            ),
+            ("Toronto, ON", vec!["Toronto, ON"]),
+        ] {
+            assert_eq!(parser.split_locations(input), output, "input: {}", input);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_parse_multi() {
+        let parser = Parser::new();
+        for (input, output) in [
+            (
+                "NYC / Boston / Remote",
+                vec!["New York, NY, US", "Boston, MA, US"],
+            ),
+            (
+                "Portland, OR or Seattle, WA",
+                vec!["Portland, OR, US", "Seattle, WA, US"],
+            ),
+            ("Toronto, ON and Toronto, Ontario", vec!["Toronto, ON, CA"]),
+            ("Toronto, ON / Remote", vec!["Toronto, ON, CA"]),
+            ("Remote", vec!["Remote"]),
+            ("Toronto, ON", vec!["Toronto, ON, CA"]),
+        ] {
+            let locations: Vec<String> = parser
+                .parse_multi(input)
+                .iter()
+                .map(|l| l.to_string())
+                .collect();
+            assert_eq!(locations, output, "input: {}", input);
+        }
+        // A single location named in the field is the one parsed from its segment
+        let locations = parser.parse_multi("Toronto, ON / Remote");
+        assert_eq!(locations[0].raw, "Toronto, ON");
+    }
+}
@@ -1,15 +1,235 @@
-use crate::utils;
+use super::Location;
 use crate::Parser;
+use lazy_static::lazy_static;
+use regex::Regex;
 use std::fmt;
 
-#[derive(Debug, Clone, Hash, Eq)]
+lazy_static! {
+    static ref STREET_ADDRESS: Regex = Regex::new(
+        r"(?i)^(?:(\d+[a-z]?(?:-\d+)?)\s+)?(.+?)(?:,?\s+(?:apt|apartment|ste|suite|unit|#)\.?\s*#?\s*([a-z0-9-]+))?$"
+    )
+    .unwrap();
+    /// Words that end a street name, e.g. "BLVD" in "3485 SW Ceder Hills BLVD"
+    static ref STREET_SUFFIXES: Vec<&'static str> = vec![
+        "ave", "avenue", "blvd", "boulevard", "ct", "court", "dr", "drive", "hwy", "highway",
+        "ln", "lane", "pkwy", "parkway", "rd", "road", "st", "street",
+    ];
+    /// Words that go before a unit number, e.g. "Ste" in "BLVD Ste 170"
+    static ref UNIT_DESIGNATORS: Vec<&'static str> =
+        vec!["apt", "apartment", "ste", "suite", "unit"];
+    /// Secondary unit at the end of an address, e.g. "Apt. 4B", "Suite 170" or "#301"
+    static ref UNIT: Regex = Regex::new(
+        r"(?i)(?:^|[\s,])(?:(apt|apartment|ste|suite|unit)\.?\s*#?\s*|#\s*)([a-z0-9][a-z0-9-]*)$"
+    )
+    .unwrap();
+    static ref DIRECTIONS: Vec<&'static str> = vec![
+        "n", "s", "e", "w", "ne", "nw", "se", "sw", "north", "south", "east", "west",
+    ];
+    /// Post office box, e.g. "PO Box 123", "P.O. Box 123" or "Post Office Box 123"
+    static ref PO_BOX: Regex =
+        Regex::new(r"(?i)\b(?:p\.?\s?o\.?|post\s+office)\s*box\s*#?\s*\d+\b").unwrap();
+    /// Military mail goes to a unit and a box of a post office of the military, e.g.
+    /// "PSC 1234, Box 12345" or "CMR 450 Box 5000", "Unit" needs a box to tell it
+    /// from a unit of a street address
+    static ref MILITARY: Regex = Regex::new(
+        r"(?i)(?:^|[,;]\s*)((?:psc|cmr)\s*#?\s*\d+(?:\s*,?\s*box\s*#?\s*\d+)?|unit\s*#?\s*\d+\s*,?\s*box\s*#?\s*\d+)\b"
+    )
+    .unwrap();
+}
+
+/// Split raw input into segments by punctuation and dashes, a dash
+/// after a digit is a part of a house number, e.g. "12-14 Main St".
+fn segments(s: &str) -> Vec<&str> {
+    let mut segments = vec![];
+    let mut start = 0;
+    let mut previous = ' ';
+    for (i, c) in s.char_indices() {
+        let is_separator = match c {
+            ',' | ';' | '(' | ')' | '/' => true,
+            '-' => !previous.is_ascii_digit(),
+            _ => false,
+        };
+        if is_separator {
+            segments.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+        previous = c;
+    }
+    segments.push(&s[start..]);
+    segments
+}
+
+/// Find a street address at the start of a segment, it has to start with a house number
+/// and have a street suffix, words after the suffix and a unit aren't a part of it,
+/// e.g. "15 McKenna Rd" in "15 McKenna Rd Arden".
+fn find_street_address(segment: &str) -> Option<&str> {
+    let words: Vec<(usize, &str)> = segment
+        .split_whitespace()
+        .map(|w| (w.as_ptr() as usize - segment.as_ptr() as usize, w))
+        .collect();
+    let starts_with_number = words
+        .first()
+        .is_some_and(|(_, w)| w.starts_with(|c: char| c.is_ascii_digit()));
+    if words.len() < 3 || !starts_with_number {
+        return None;
+    }
+    let normalized: Vec<String> = words
+        .iter()
+        .map(|(_, w)| w.trim_end_matches('.').to_lowercase())
+        .collect();
+    let suffix = (2..words.len()).find(|&i| STREET_SUFFIXES.contains(&normalized[i].as_str()))?;
+    let mut end = suffix + 1;
+    if end < words.len() && DIRECTIONS.contains(&normalized[end].as_str()) {
+        end += 1;
+    }
+    if end + 1 < words.len() && UNIT_DESIGNATORS.contains(&normalized[end].as_str()) {
+        end += 2;
+    } else if end < words.len() && normalized[end].starts_with('#') {
+        // "#301" or "# 301"
+        end += if normalized[end] == "#" { 2 } else { 1 };
+        end = end.min(words.len());
+    }
+    let (last_start, last) = words[end - 1];
+    Some(&segment[words[0].0..last_start + last.len()])
+}
+
+/// Identifier of the secondary unit at the end of the address and its designator
+/// spelled the same way for every address, e.g. ("Suite", "170") for "Ste. 170",
+/// there's no designator when the unit is just a number, e.g. "#301".
+fn find_unit(address: &str) -> Option<(Option<&'static str>, String)> {
+    let captures = UNIT.captures(address.trim())?;
+    let number = captures[2].to_uppercase();
+    let designator = match captures.get(1).map(|m| m.as_str().to_lowercase()) {
+        Some(d) if d == "apt" || d == "apartment" => Some("Apt"),
+        Some(d) if d == "ste" || d == "suite" => Some("Suite"),
+        Some(_) => Some("Unit"),
+        None => None,
+    };
+    Some((designator, number))
+}
+
+/// Whether the whole segment is a secondary unit, e.g. "Suite 170" in
+/// "100 Queen St W, Suite 170".
+fn is_unit(segment: &str) -> bool {
+    UNIT.find(segment.trim()).is_some_and(|m| m.start() == 0)
+}
+
+/// Find a post office box or a box of military mail in the input.
+fn find_mailbox(input: &str) -> Option<(&str, AddressKind)> {
+    if let Some(m) = PO_BOX.find(input) {
+        return Some((m.as_str(), AddressKind::PoBox));
+    }
+    let m = MILITARY.captures(input)?.get(1)?;
+    Some((m.as_str(), AddressKind::Military))
+}
+
+/// What an address is, so that mail can be sorted without parsing it again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AddressKind {
+    /// House number and street, e.g. "3485 SW Ceder Hills BLVD Ste 170"
+    Street,
+    /// Post office box, e.g. "PO Box 123"
+    PoBox,
+    /// Box of military mail, e.g. "PSC 1234, Box 12345" or "Unit 2050 Box 4190"
+    Military,
+    /// Anything else that's left of the input, e.g. a name of a building
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Address {
     pub address: String,
+    pub kind: AddressKind,
+    /// Identifier of an apartment, suite or unit, e.g. "4B" of "Apt. 4b" or "301" of "#301"
+    pub unit: Option<String>,
+    /// "Apt", "Suite" or "Unit" before the unit identifier, `None` when it's just a number
+    pub unit_designator: Option<String>,
+}
+
+/// Address split into house number, street and unit,
+/// e.g. "3485 SW Ceder Hills BLVD Ste 170"
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct StreetAddress {
+    pub street_number: Option<String>,
+    pub street_name: Option<String>,
+    /// Same as `Address::unit`
+    pub unit: Option<String>,
+    /// Same as `Address::unit_designator`
+    pub unit_designator: Option<String>,
 }
 
-impl PartialEq for Address {
-    fn eq(&self, other: &Address) -> bool {
-        self.address == other.address
+impl Address {
+    /// Address of the kind its text looks like.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address, e.g. "PO Box 123"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::{Address, AddressKind};
+    /// assert_eq!(Address::new("P.O. Box 4410").kind, AddressKind::PoBox);
+    /// assert_eq!(Address::new("PSC 1234, Box 12345").kind, AddressKind::Military);
+    /// assert_eq!(Address::new("15 McKenna Rd").kind, AddressKind::Street);
+    /// assert_eq!(Address::new("Kent Atholville 44").kind, AddressKind::Other);
+    /// let address = Address::new("12 Main St Apt. 4b");
+    /// assert_eq!(address.unit, Some(String::from("4B")));
+    /// assert_eq!(address.unit_designator, Some(String::from("Apt")));
+    /// ```
+    pub fn new(address: &str) -> Self {
+        let kind = match find_mailbox(address) {
+            Some((_, kind)) => kind,
+            None if find_street_address(address.trim()).is_some() => AddressKind::Street,
+            None => AddressKind::Other,
+        };
+        // Units of military mail are a part of the box, e.g. "Unit 2050 Box 4190"
+        let unit = match kind {
+            AddressKind::Street | AddressKind::Other => find_unit(address),
+            AddressKind::PoBox | AddressKind::Military => None,
+        };
+        Self::with_unit(address, kind, unit)
+    }
+
+    fn with_unit(address: &str, kind: AddressKind, unit: Option<(Option<&str>, String)>) -> Self {
+        let (unit_designator, unit) = match unit {
+            Some((designator, unit)) => (designator.map(String::from), Some(unit)),
+            None => (None, None),
+        };
+        Self {
+            address: address.to_string(),
+            kind,
+            unit,
+            unit_designator,
+        }
+    }
+
+    /// Split address into house number, street and unit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let address = geo_rs::nodes::Address::new("3485 SW Ceder Hills BLVD Ste 170");
+    /// let street = address.street();
+    /// assert_eq!(street.street_number, Some(String::from("3485")));
+    /// assert_eq!(street.street_name, Some(String::from("SW Ceder Hills BLVD")));
+    /// assert_eq!(street.unit, Some(String::from("170")));
+    /// assert_eq!(street.unit_designator, Some(String::from("Suite")));
+    /// ```
+    pub fn street(&self) -> StreetAddress {
+        let address = self.address.trim();
+        match STREET_ADDRESS.captures(address) {
+            Some(captures) => StreetAddress {
+                street_number: captures.get(1).map(|m| m.as_str().to_string()),
+                street_name: captures.get(2).map(|m| m.as_str().to_string()),
+                unit: self.unit.clone(),
+                unit_designator: self.unit_designator.clone(),
+            },
+            None => StreetAddress::default(),
+        }
     }
 }
 
@@ -22,41 +242,305 @@ impl fmt::Display for Address {
 impl Parser {
     pub fn remove_address(&self, s: &mut String, address: &Address) {
         *s = s.replace(&address.address, "");
-        utils::clean(s);
+        self.clean(s);
+    }
+
+    /// Fence off a street address, so that its words aren't mistaken for a city,
+    /// a unit in the next segment is a part of it, e.g. "100 Queen St W, Suite 170".
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that's gonna be filled with an address
+    /// * `input` - Location string to search in
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = geo_rs::nodes::Location {
+    ///     city: None,
+    ///     state: None,
+    ///     country: None,
+    ///     zipcode: None,
+    ///     address: None,
+    ///     landmark: None,
+    ///     alternatives: vec![],
+    ///     warnings: vec![],
+    ///     collapsed: vec![],
+    ///     raw: String::new(),
+    /// };
+    /// parser.fill_street_address(&mut location, "OR, Beaverton, 3485 SW Ceder Hills BLVD Ste 170");
+    /// let address = location.address.unwrap();
+    /// assert_eq!(address.address, String::from("3485 SW Ceder Hills BLVD Ste 170"));
+    /// assert_eq!(address.unit, Some(String::from("170")));
+    /// assert_eq!(address.unit_designator, Some(String::from("Suite")));
+    /// ```
+    pub fn fill_street_address(&self, location: &mut Location, input: &str) {
+        if location.address.is_some() {
+            return;
+        }
+        let segments = segments(input);
+        let offset = |s: &str| s.as_ptr() as usize - input.as_ptr() as usize;
+        let address = segments.iter().enumerate().find_map(|(i, segment)| {
+            let address = find_street_address(segment)?;
+            match segments.get(i + 1).filter(|s| is_unit(s)) {
+                Some(unit) => Some(&input[offset(address)..offset(unit) + unit.trim_end().len()]),
+                None => Some(address),
+            }
+        });
+        trace_event!("matched street address", address = address);
+        location.address = address
+            .map(|address| Address::with_unit(address, AddressKind::Street, find_unit(address)));
+    }
+
+    /// Fence off a post office box or a box of military mail, so that "Box"
+    /// or "PSC" aren't mistaken for a city, see `AddressKind`.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that's gonna be filled with an address
+    /// * `input` - Location string to search in
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::{AddressKind, Location};
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = Location::default();
+    /// parser.fill_mailbox_address(&mut location, "P.O. Box 4410, Toronto, ON");
+    /// let address = location.address.unwrap();
+    /// assert_eq!(address.address, String::from("P.O. Box 4410"));
+    /// assert_eq!(address.kind, AddressKind::PoBox);
+    /// ```
+    pub fn fill_mailbox_address(&self, location: &mut Location, input: &str) {
+        if location.address.is_some() {
+            return;
+        }
+        let mailbox = find_mailbox(input);
+        trace_event!("matched mailbox", address = mailbox.map(|(m, _)| m));
+        location.address = mailbox.map(|(address, kind)| Address::with_unit(address, kind, None));
     }
 
     pub fn fill_address(&self, s: &str) -> Option<Address> {
-        Some(Address {
-            address: s.to_string(),
-        })
+        Some(Address::new(s))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ParserOptions;
     use std::collections::HashMap;
 
     #[test]
     fn test_fmt_address() {
-        let address = Address {
-            address: String::from("test address  "),
-        };
+        let address = Address::new("test address  ");
         assert_eq!(format!("{}", address), String::from("test address"))
     }
 
+    #[test]
+    fn test_street_address() {
+        let mut addresses: HashMap<&str, [Option<&str>; 3]> = HashMap::new();
+        addresses.insert("15 McKenna Rd", [Some("15"), Some("McKenna Rd"), None]);
+        addresses.insert(
+            "9985 PRITCHARD RD, Suite 4B",
+            [Some("9985"), Some("PRITCHARD RD"), Some("4B")],
+        );
+        addresses.insert(
+            "12 Main St Apt. 3",
+            [Some("12"), Some("Main St"), Some("3")],
+        );
+        addresses.insert("Route 50", [None, Some("Route 50"), None]);
+        addresses.insert("", [None, None, None]);
+        for (input, [number, name, unit]) in addresses {
+            let street = Address::new(input).street();
+            assert_eq!(street.street_number.as_deref(), number, "input: {}", input);
+            assert_eq!(street.street_name.as_deref(), name, "input: {}", input);
+            assert_eq!(street.unit.as_deref(), unit, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_street_unit_agrees_with_address() {
+        for input in [
+            "3485 SW Ceder Hills BLVD Ste 170",
+            "12 Main St, Apartment #4b",
+            "55 Water St # 301",
+            "100 Queen St W, Unit 12",
+            "15 McKenna Rd",
+        ] {
+            let address = Address::new(input);
+            let street = address.street();
+            assert_eq!(street.unit, address.unit, "input: {}", input);
+            assert_eq!(
+                street.unit_designator, address.unit_designator,
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_street_address() {
+        let mut addresses: HashMap<&str, Option<&str>> = HashMap::new();
+        addresses.insert(
+            "3485 SW Ceder Hills BLVD Ste 170",
+            Some("3485 SW Ceder Hills BLVD Ste 170"),
+        );
+        addresses.insert("15 McKenna Rd Arden", Some("15 McKenna Rd"));
+        addresses.insert("3235 Dundas St W", Some("3235 Dundas St W"));
+        addresses.insert("9985 PRITCHARD RD", Some("9985 PRITCHARD RD"));
+        addresses.insert("55 Water St #301", Some("55 Water St #301"));
+        addresses.insert("55 Water St # 301 Lansing", Some("55 Water St # 301"));
+        addresses.insert("Saint Louis", None);
+        addresses.insert("410 Wichita", None);
+        addresses.insert("Kent Atholville 44", None);
+        for (input, output) in addresses {
+            assert_eq!(find_street_address(input), output, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_find_unit() {
+        for (input, output) in [
+            ("12 Main St Apt 4B", Some((Some("Apt"), "4B"))),
+            ("12 Main St, Apartment #4b", Some((Some("Apt"), "4B"))),
+            (
+                "3485 SW Ceder Hills BLVD Ste. 170",
+                Some((Some("Suite"), "170")),
+            ),
+            ("100 Queen St W, Unit 12", Some((Some("Unit"), "12"))),
+            ("55 Water St #301", Some((None, "301"))),
+            ("55 Water St # 301", Some((None, "301"))),
+            ("Suite 170", Some((Some("Suite"), "170"))),
+            ("15 McKenna Rd", None),
+            ("Unitarian Church", None),
+        ] {
+            let unit = find_unit(input);
+            let unit = unit.as_ref().map(|(d, n)| (*d, n.as_str()));
+            assert_eq!(unit, output, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_units() {
+        let parser = Parser::with_options(ParserOptions::new().addresses(true));
+        for (input, address, unit) in [
+            (
+                "OR, Beaverton, 3485 SW Ceder Hills BLVD Ste 170",
+                "3485 SW Ceder Hills BLVD Ste 170",
+                (Some("Suite"), "170"),
+            ),
+            (
+                "100 Queen St W, Suite 170, Toronto, ON",
+                "100 Queen St W, Suite 170",
+                (Some("Suite"), "170"),
+            ),
+            (
+                "55 Water St #301, Lansing, MI",
+                "55 Water St #301",
+                (None, "301"),
+            ),
+        ] {
+            let location = parser.parse_location(input);
+            let parsed = location.address.expect(input);
+            assert_eq!(parsed.address, address, "input: {}", input);
+            assert_eq!(parsed.unit.as_deref(), Some(unit.1), "input: {}", input);
+            assert_eq!(
+                parsed.unit_designator.as_deref(),
+                unit.0,
+                "input: {}",
+                input
+            );
+            assert!(location.city.is_some(), "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_find_mailbox() {
+        for (input, output) in [
+            (
+                "PO Box 123, Lansing, MI",
+                Some(("PO Box 123", AddressKind::PoBox)),
+            ),
+            (
+                "Lansing, MI, P.O. BOX 12",
+                Some(("P.O. BOX 12", AddressKind::PoBox)),
+            ),
+            (
+                "Post Office Box #7",
+                Some(("Post Office Box #7", AddressKind::PoBox)),
+            ),
+            (
+                "PSC 1234, Box 12345, APO AE 09204",
+                Some(("PSC 1234, Box 12345", AddressKind::Military)),
+            ),
+            (
+                "CMR 450 Box 5000, APO AE 09180",
+                Some(("CMR 450 Box 5000", AddressKind::Military)),
+            ),
+            (
+                "Unit 2050 Box 4190, APO AP 96278",
+                Some(("Unit 2050 Box 4190", AddressKind::Military)),
+            ),
+            ("100 Queen St W, Unit 5, Toronto", None),
+            ("Boxborough, MA", None),
+            ("Topsc 12", None),
+        ] {
+            assert_eq!(find_mailbox(input), output, "input: {}", input);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_parse_mailbox_addresses() {
+        let parser = Parser::new();
+        let location = parser.parse_location("PO Box 123, Lansing, MI 48911");
+        assert_eq!(location.to_string(), "Lansing, MI, US, 48911");
+        assert_eq!(location.address, None);
+        let parser = Parser::with_options(ParserOptions::new().addresses(true));
+        let location = parser.parse_location("PO Box 123, Lansing, MI 48911");
+        assert_eq!(location.to_string(), "Lansing, MI, US, 48911, PO Box 123");
+        assert_eq!(location.address.unwrap().kind, AddressKind::PoBox);
+        let location = parser.parse_location("PSC 1234, Box 12345, APO AE 09204");
+        let address = location.address.unwrap();
+        assert_eq!(address.address, "PSC 1234, Box 12345");
+        assert_eq!(address.kind, AddressKind::Military);
+        let location = parser.parse_location("100 Queen St W Unit 5, Toronto, ON");
+        assert_eq!(location.address.unwrap().kind, AddressKind::Street);
+    }
+
+    #[test]
+    fn test_segments() {
+        assert_eq!(
+            segments("CA-ON-Oakville-3235 Dundas St W (Store# 04278)"),
+            vec![
+                "CA",
+                "ON",
+                "Oakville",
+                "3235 Dundas St W ",
+                "Store# 04278",
+                ""
+            ]
+        );
+        assert_eq!(
+            segments("12-14 Main St, Boston"),
+            vec!["12-14 Main St", " Boston"]
+        );
+    }
+
     #[test]
     fn test_fill_address() {
         let mut addresses: HashMap<&str, Option<Address>> = HashMap::new();
         addresses.insert(
             "Kent Atholville 44",
-            Some(Address {
-                address: String::from("Kent Atholville 44"),
-            }),
+            Some(Address::new("Kent Atholville 44")),
         );
         let parser = Parser::new();
         for (input, address) in addresses {
-            let output = parser.fill_address(&input);
+            let output = parser.fill_address(input);
             assert_eq!(output, address);
         }
     }
@@ -67,9 +551,7 @@ mod tests {
         addresses.insert(
             "Atholville, New Brunswick, Canada, Kent Atholville 44",
             (
-                Address {
-                    address: String::from("Kent Atholville 44"),
-                },
+                Address::new("Kent Atholville 44"),
                 "Atholville, New Brunswick, Canada",
             ),
         );
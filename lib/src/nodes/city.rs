@@ -1,19 +1,32 @@
-use crate::nodes::country::UNITED_STATES;
-use crate::nodes::State;
-use crate::utils;
-use crate::{Location, Parser};
-use std::collections::HashMap;
+use crate::mapped::{self, DataStr};
+use crate::nodes::{Country, State, Warning};
+use crate::utils::{self, DataSource, Records};
+use crate::{Location, OutputCase, Parser};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use titlecase::titlecase;
+use unidecode::unidecode;
 
-#[derive(Debug, Clone, Hash, Eq)]
+/// US states of Armed Forces mail, that only have military post offices.
+const MILITARY_STATES: [&str; 2] = ["AE", "AP"];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct City {
     pub name: String,
 }
 
-impl PartialEq for City {
-    fn eq(&self, other: &City) -> bool {
-        self.name == other.name
+impl City {
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// assert_eq!(geo_rs::nodes::City::new("Toronto").name, String::from("Toronto"));
+    /// ```
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
     }
 }
 
@@ -25,41 +38,834 @@ impl fmt::Display for City {
 
 impl Parser {
     pub fn remove_city(&self, s: &mut String, city: &City) {
-        *s = s.replace(&city.name, "");
-        utils::clean(s);
+        if s.contains(&city.name) {
+            *s = s.replace(&city.name, "");
+        } else if let Some(&(start, end)) =
+            utils::find_words(s, &utils::city_key(&city.name)).first()
+        {
+            // Input spells the city in another case or without apostrophes,
+            // e.g. "TORONTO" or "Coeur d Alene", it's not a repeated mention
+            s.replace_range(start..end, "");
+        }
+        self.clean(s);
     }
 
-    pub fn fill_special_case_city(&self, location: &mut Location, s: &str) {
-        if s.to_lowercase().contains("washington") && s.to_lowercase().contains("dc") {
-            location.country = Some(UNITED_STATES.clone());
-            location.state = Some(State {
-                code: String::from("DC"),
-                name: String::from("District Of Columbia"),
-            });
-            location.city = Some(City {
-                name: String::from("Washington"),
-            })
+    /// Return number of cities loaded for the given country and state.
+    ///
+    /// # Arguments
+    ///
+    /// * `country` - Country code, e.g. "US"
+    /// * `state` - State code, e.g. "CA"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// assert!(parser.city_count("US", "CA") > 0);
+    /// assert_eq!(parser.city_count("US", "XX"), 0);
+    /// ```
+    pub fn city_count(&self, country: &str, state: &str) -> usize {
+        self.cities
+            .get(country)
+            .and_then(|c| c.cities_by_state.get(state))
+            .map(|cities| cities.len())
+            .unwrap_or(0)
+    }
+
+    /// Return number of cities loaded for every state of every country,
+    /// states that don't have any cities are included with zero count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let counts = parser.city_counts();
+    /// assert_eq!(counts["US"]["CA"], parser.city_count("US", "CA"));
+    /// ```
+    pub fn city_counts(&self) -> BTreeMap<String, BTreeMap<String, usize>> {
+        let mut counts: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+        for (country, states) in self.states.iter() {
+            let country_counts = counts.entry(country.clone()).or_default();
+            for state in states.code_to_name.keys() {
+                country_counts.insert(state.clone(), self.city_count(country, state));
+            }
         }
-        if s.to_lowercase().contains("district of columbia") {
-            location.country = Some(UNITED_STATES.clone());
-            location.state = Some(State {
-                code: String::from("DC"),
-                name: String::from("District Of Columbia"),
-            });
+        for (country, country_cities) in self.cities.iter() {
+            let country_counts = counts.entry(country.clone()).or_default();
+            for (state, cities) in &country_cities.cities_by_state {
+                country_counts.insert(state.clone(), cities.len());
+            }
+        }
+        counts
+    }
+
+    /// Warn about states that have no cities loaded, that usually
+    /// means a problem with the dataset, e.g. mismatching state codes.
+    pub(crate) fn check_city_counts(&self) {
+        for (country, state) in self.states_without_cities() {
+            warn!("no cities loaded for state {} of {}", state, country);
+        }
+    }
+
+    /// Country and state codes of states that have no cities loaded, except for
+    /// Armed Forces states, their military post offices, e.g. "APO", aren't cities.
+    fn states_without_cities(&self) -> Vec<(String, String)> {
+        let mut states = vec![];
+        for (country, counts) in self.city_counts() {
+            for (state, count) in counts {
+                if count == 0 && !(country == "US" && MILITARY_STATES.contains(&&*state)) {
+                    states.push((country.clone(), state));
+                }
+            }
+        }
+        states
+    }
+
+    /// Return population of the location's city when it's known.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location with city, state and country filled
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Portland, OR");
+    /// assert!(parser.population_of(&location).unwrap() > 500000);
+    /// ```
+    pub fn population_of(&self, location: &Location) -> Option<u64> {
+        match (&location.city, &location.state, &location.country) {
+            (Some(city), Some(state), Some(country)) => self
+                .cities
+                .get(&country.code)?
+                .population
+                .get(&(state.code.clone(), city.name.to_lowercase()))
+                .cloned(),
+            _ => None,
+        }
+    }
+
+    /// Look for a comma separated part of the input that is a name of a city
+    /// existing in exactly one state across all loaded countries, and fill
+    /// city, state and country of the location from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that stores final values
+    /// * `input` - Location string to be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = geo_rs::nodes::Location {
+    ///     city: None,
+    ///     state: None,
+    ///     country: None,
+    ///     zipcode: None,
+    ///     address: None,
+    ///     landmark: None,
+    ///     alternatives: vec![],
+    ///     warnings: vec![],
+    ///     collapsed: vec![],
+    ///     raw: String::new(),
+    /// };
+    /// parser.fill_unique_city(&mut location, "Sausalito");
+    /// assert_eq!(location.state.unwrap().code, String::from("CA"));
+    /// assert_eq!(location.country.unwrap().code, String::from("US"));
+    /// ```
+    pub fn fill_unique_city(&self, location: &mut Location, input: &str) {
+        let input = &unidecode(input);
+        if location.city.is_some() || location.state.is_some() {
+            return;
+        }
+        let countries = self.candidate_countries(&location.country);
+        for part in input.to_lowercase().split(", ") {
+            let mut matches: Vec<(&Country, &String)> = vec![];
+            for c in &countries {
+                if let Some(country_cities) = self.cities.get(&c.code) {
+                    if let Some(states) = country_cities.states_by_city.get(part) {
+                        matches.extend(states.iter().map(|s| (c, s)));
+                    }
+                }
+            }
+            if matches.len() != 1 {
+                continue;
+            }
+            let (country, state_code) = matches[0];
             location.city = Some(City {
-                name: String::from("Washington"),
-            })
+                name: titlecase(part),
+            });
+            location.state = self.state_from_code(&Some(country.clone()), state_code);
+            location.country = Some(country.clone());
+            trace_event!(
+                "inferred location from a unique city",
+                location = location.to_string()
+            );
+            return;
         }
-        if s.to_lowercase().contains("d.c.") || s.to_lowercase().contains(" d, c") {
-            location.country = Some(UNITED_STATES.clone());
-            location.state = Some(State {
-                code: String::from("DC"),
-                name: String::from("District Of Columbia"),
+    }
+
+    /// Parse location string and try to find a city that sounds like its first part,
+    /// a fallback for misspelled cities when nothing matches their spelling,
+    /// see `ParserOptions::phonetic_cities`. The most populous city wins,
+    /// others are kept as alternatives. Adds `Warning::PhoneticCity` on success.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that stores final values
+    /// * `input` - Location string to be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let options = geo_rs::ParserOptions::new().phonetic_cities(true);
+    /// let parser = geo_rs::Parser::with_options(options);
+    /// let mut location = geo_rs::nodes::Location::default();
+    /// location.state = parser.state_from_code(&None, "OH");
+    /// parser.fill_phonetic_city(&mut location, "Cincinatti");
+    /// assert_eq!(location.to_string(), String::from("Cincinnati, OH, US"));
+    /// ```
+    pub fn fill_phonetic_city(&self, location: &mut Location, input: &str) {
+        if location.city.is_some() {
+            return;
+        }
+        let part = input.split(',').next().unwrap_or("");
+        let key = utils::phonetic_key(part);
+        if key.is_empty() {
+            return;
+        }
+        let mut candidates: Vec<(u64, Country, String, String)> = vec![];
+        for c in self.candidate_countries(&location.country) {
+            let found = self.phonetic_cities.get(&c.code).and_then(|p| p.get(&key));
+            for (state_code, city) in found.into_iter().flatten() {
+                if location
+                    .state
+                    .as_ref()
+                    .is_some_and(|s| &s.code != state_code)
+                {
+                    continue;
+                }
+                let population = self
+                    .cities
+                    .get(&c.code)
+                    .and_then(|cities| cities.population.get(&(state_code.clone(), city.clone())))
+                    .copied()
+                    .unwrap_or(0);
+                candidates.push((population, c.clone(), state_code.clone(), city.clone()));
+            }
+        }
+        candidates.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.2.cmp(&b.2)));
+        trace_event!("phonetic city candidates", candidates = candidates);
+        let mut locations = candidates
+            .into_iter()
+            .map(|(_, country, state_code, city)| Location {
+                city: Some(City::new(&titlecase(&city))),
+                state: self.state_from_code(&Some(country.clone()), &state_code),
+                country: Some(country),
+                zipcode: location.zipcode.clone(),
+                ..Location::default()
             });
-            location.city = Some(City {
-                name: String::from("Washington"),
+        let first = match locations.next() {
+            Some(l) => l,
+            None => return,
+        };
+        location.alternatives = locations.collect();
+        location.city = first.city;
+        location.state = first.state;
+        location.country = first.country;
+        location.warnings.push(Warning::PhoneticCity);
+    }
+
+    /// Recognize inputs that consist of just a city and a country, e.g. "Berlin, DE"
+    /// or "Paris, France", using the list of major international cities.
+    /// Pairs that are also a US or CA city and state, e.g. "Dover, DE", are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that stores final values
+    /// * `input` - Location string to be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = geo_rs::nodes::Location {
+    ///     city: None,
+    ///     state: None,
+    ///     country: None,
+    ///     zipcode: None,
+    ///     address: None,
+    ///     landmark: None,
+    ///     alternatives: vec![],
+    ///     warnings: vec![],
+    ///     collapsed: vec![],
+    ///     raw: String::new(),
+    /// };
+    /// parser.fill_world_city(&mut location, "Berlin, DE");
+    /// assert_eq!(location.city.unwrap().name, String::from("Berlin"));
+    /// assert_eq!(location.country.unwrap().name, String::from("Germany"));
+    /// ```
+    pub fn fill_world_city(&self, location: &mut Location, input: &str) {
+        let input = &unidecode(input);
+        let parts: Vec<&str> = input.split(", ").collect();
+        if parts.len() != 2 {
+            return;
+        }
+        let (city_name, country_part) = (parts[0].to_lowercase(), parts[1]);
+        // Country codes are expected in upper case, e.g. "DE" or "DEU"
+        let country_by_code = match country_part == country_part.to_uppercase() {
+            true => self.country_from_code(country_part),
+            false => None,
+        };
+        let country = match country_by_code {
+            Some(c) => c,
+            None => match self
+                .countries
+                .name_to_code
+                .iter()
+                .find(|(name, _)| name.to_lowercase() == country_part.to_lowercase())
+            {
+                Some((name, code)) => Country {
+                    code: code.clone(),
+                    name: name.clone(),
+                },
+                None => return,
+            },
+        };
+        let city = match self
+            .world_cities
+            .get(&country.code)
+            .and_then(|cities| cities.iter().find(|c| c.to_lowercase() == city_name))
+        {
+            Some(c) => c,
+            None => return,
+        };
+        for c in self.candidate_countries(&None) {
+            if let Some(state_cities) = self
+                .cities
+                .get(&c.code)
+                .and_then(|country_cities| country_cities.cities_by_state.get(country_part))
+            {
+                if state_cities.contains(&city_name) {
+                    return;
+                }
+            }
+        }
+        location.city = Some(City { name: city.clone() });
+        location.country = Some(country);
+    }
+
+    /// Search the input for alternate names of cities, e.g. "NYC" or "Philly",
+    /// and fill city, state and country of the aliased city.
+    /// Return `true` when an alias was found.
+    ///
+    /// Aliases that contradict the state or country already found
+    /// in the location or mentioned in the input are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that stores final values
+    /// * `input` - Location string to be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = geo_rs::nodes::Location {
+    ///     city: None,
+    ///     state: None,
+    ///     country: None,
+    ///     zipcode: None,
+    ///     address: None,
+    ///     landmark: None,
+    ///     alternatives: vec![],
+    ///     warnings: vec![],
+    ///     collapsed: vec![],
+    ///     raw: String::new(),
+    /// };
+    /// assert!(parser.fill_city_alias(&mut location, "Philly"));
+    /// assert_eq!(location.to_string(), String::from("Philadelphia, PA, US"));
+    /// ```
+    pub fn fill_city_alias(&self, location: &mut Location, input: &str) -> bool {
+        let input = &unidecode(input);
+        let as_lowercase = input.to_lowercase();
+        let mut matches: Vec<(&String, &String, &(String, String))> = vec![];
+        for (country_code, country_cities) in self.cities.iter() {
+            for (alias, target) in &country_cities.aliases {
+                if !utils::find_token(&as_lowercase, alias).is_empty() {
+                    matches.push((alias, country_code, target));
+                }
+            }
+        }
+        // Prefer longer aliases, e.g. "New York City" over "NYC"
+        matches.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.1.cmp(b.1)));
+        for (alias, country_code, (state_code, city)) in matches {
+            // Look for a state in what's left after removing the alias
+            let mut remainder = input.clone();
+            for (start, end) in utils::find_token(&as_lowercase, alias).into_iter().rev() {
+                remainder.replace_range(start..end, "");
+            }
+            self.clean(&mut remainder);
+            let mut mentioned = Location {
+                city: None,
+                state: location.state.clone(),
+                country: location.country.clone(),
+                zipcode: None,
+                address: None,
+                landmark: None,
+                alternatives: vec![],
+                warnings: vec![],
+                collapsed: vec![],
+                raw: String::new(),
+            };
+            if mentioned.state.is_none() {
+                self.fill_state(&mut mentioned, &remainder);
+            }
+            if let Some(c) = &mentioned.country {
+                if &c.code != country_code {
+                    continue;
+                }
+            }
+            if let Some(s) = &mentioned.state {
+                if &s.code != state_code {
+                    continue;
+                }
+            }
+            let country = self
+                .countries
+                .code_to_name
+                .get(country_code)
+                .map(|name| Country {
+                    code: country_code.clone(),
+                    name: name.clone(),
+                });
+            location.state = self.state_from_code(&country, state_code);
+            location.country = country;
+            location.city = Some(City { name: city.clone() });
+            return true;
+        }
+        false
+    }
+
+    /// Search the beginning of the input for a major international city,
+    /// e.g. "Tokyo", "Sydney NSW" or "Mexico City", and fill city and country.
+    /// Requires `data-world-major` feature, does nothing otherwise.
+    ///
+    /// Cities followed by a US or CA state, e.g. "London, ON", are ignored
+    /// and so are cities in a country other than the one mentioned in the input.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that stores final values
+    /// * `input` - Location string to be parsed
+    pub fn fill_major_world_city(&self, location: &mut Location, input: &str) {
+        if self.major_world_cities.is_empty() || location.city.is_some() {
+            return;
+        }
+        let input = &unidecode(input);
+        let as_lowercase = input.to_lowercase();
+        let first_part = as_lowercase.split(", ").next().unwrap_or("");
+        let first_tokens = utils::split(first_part);
+        let mut mentioned = Location {
+            city: None,
+            state: None,
+            country: location.country.clone(),
+            zipcode: None,
+            address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
+        };
+        self.fill_country(&mut mentioned, input);
+        let mut best: Option<(&WorldCity, usize)> = None;
+        for city in self.major_world_cities.iter() {
+            if let Some(c) = &mentioned.country {
+                if c.code != city.country {
+                    continue;
+                }
+            }
+            let name = city.name.to_lowercase();
+            let city_tokens = utils::split(&name);
+            // Cities are ordered by population, only a longer name beats a bigger city
+            if first_tokens.starts_with(&city_tokens)
+                && best.map(|(_, n)| city_tokens.len() > n).unwrap_or(true)
+            {
+                best = Some((city, city_tokens.len()));
+            }
+        }
+        let city = match best {
+            Some((city, _)) => city,
+            None => return,
+        };
+        let mut rest = input.clone();
+        if let Some(&(start, end)) =
+            utils::find_token(&as_lowercase, &city.name.to_lowercase()).first()
+        {
+            rest.replace_range(start..end, "");
+        }
+        if self.mentions_state(&rest) {
+            return;
+        }
+        location.country = self.country_from_code(&city.country);
+        location.city = Some(City {
+            name: city.name.clone(),
+        });
+    }
+
+    /// Change letter case of the city name according to `ParserOptions::output_case`.
+    /// Names with apostrophes are matched without them, so their punctuation is
+    /// restored from the dataset, e.g. "Lees Summit" becomes "Lee's Summit".
+    pub(crate) fn apply_output_case(&self, location: &mut Location) {
+        let data_name = match (&location.city, &location.state, &location.country) {
+            (Some(city), Some(s), Some(c)) => {
+                let key = (s.code.clone(), utils::city_key(&city.name));
+                self.cities
+                    .get(&c.code)
+                    .and_then(|c| c.names.get(&key))
+                    .map(|n| n.to_string())
+            }
+            _ => None,
+        };
+        let city = match location.city.as_mut() {
+            Some(c) => c,
+            None => return,
+        };
+        let punctuated = data_name
+            .as_ref()
+            .filter(|name| name.contains('\''))
+            .cloned();
+        match self.options.output_case {
+            OutputCase::Title => {
+                if let Some(name) = punctuated {
+                    city.name = name;
+                }
+            }
+            OutputCase::Upper => {
+                city.name = punctuated
+                    .unwrap_or_else(|| city.name.clone())
+                    .to_uppercase()
+            }
+            OutputCase::AsData => {
+                if let Some(name) = data_name {
+                    city.name = name;
+                }
+            }
+        }
+    }
+
+    /// Return coordinates (latitude, longitude) of the location's city
+    /// when they are known: larger US and CA cities, and major international
+    /// cities with `data-world-major` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Parsed location
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Seattle, WA");
+    /// assert_eq!(parser.coordinates(&location), Some((47.61, -122.33)));
+    /// ```
+    pub fn coordinates(&self, location: &Location) -> Option<(f64, f64)> {
+        let (city, country) = match (&location.city, &location.country) {
+            (Some(city), Some(country)) => (city, country),
+            _ => return None,
+        };
+        if let Some(state) = &location.state {
+            let key = (state.code.clone(), utils::city_key(&city.name));
+            let found = self
+                .cities
+                .get(&country.code)
+                .and_then(|c| c.coordinates.get(&key));
+            if let Some(coordinates) = found {
+                return Some(*coordinates);
+            }
+        }
+        self.major_world_cities
+            .iter()
+            .find(|c| c.country == country.code && c.name == city.name)
+            .map(|c| (c.latitude, c.longitude))
+    }
+
+    /// Check whether cities of two locations belong to the same metropolitan
+    /// area, e.g. "Mississauga, ON" and "Toronto, ON". Locations of the same city
+    /// are always in the same metro, see `src/data/{country}/metros.txt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - Parsed location
+    /// * `b` - Parsed location
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let toronto = parser.parse_location("Toronto, ON");
+    /// assert!(parser.same_metro(&toronto, &parser.parse_location("Mississauga, ON")));
+    /// assert!(!parser.same_metro(&toronto, &parser.parse_location("Ottawa, ON")));
+    /// ```
+    pub fn same_metro(&self, a: &Location, b: &Location) -> bool {
+        match (self.metro(a), self.metro(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Metropolitan area of the location's city, falls back to the city itself.
+    fn metro(&self, location: &Location) -> Option<(String, String)> {
+        let (city, state, country) = match (&location.city, &location.state, &location.country) {
+            (Some(city), Some(state), Some(country)) => (city, state, country),
+            _ => return None,
+        };
+        let key = (state.code.clone(), utils::city_key(&city.name));
+        let metro = self
+            .cities
+            .get(&country.code)
+            .and_then(|c| c.metros.get(&key))
+            .cloned()
+            .unwrap_or_else(|| format!("{};{}", key.0, key.1));
+        Some((country.code.clone(), metro))
+    }
+
+    /// Return cities whose names start with the given prefix, the most populous first,
+    /// e.g. to back a type-ahead location picker. Prefix is matched case-insensitively
+    /// and without diacritics.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Beginning of a city name
+    /// * `country` - Optional code of the country to search in, e.g. "CA"
+    /// * `limit` - Maximum number of cities to return
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let cities = parser.suggest_cities("toro", Some("CA"), 1);
+    /// let (city, state, country) = &cities[0];
+    /// assert_eq!(city.name, String::from("Toronto"));
+    /// assert_eq!(state.code, String::from("ON"));
+    /// assert_eq!(country.code, String::from("CA"));
+    /// ```
+    pub fn suggest_cities(
+        &self,
+        prefix: &str,
+        country: Option<&str>,
+        limit: usize,
+    ) -> Vec<(City, State, Country)> {
+        // Trailing space is kept, "new " is the beginning of "New York" but not of "Newark"
+        let mut key = utils::city_key(prefix);
+        if !key.is_empty() && prefix.ends_with(char::is_whitespace) {
+            key.push(' ');
+        }
+        let prefix = key;
+        if prefix.is_empty() || limit == 0 {
+            return vec![];
+        }
+        let country = country.map(|c| c.trim().to_uppercase());
+        let mut found: Vec<(u64, String, String, String)> = self
+            .city_trie
+            .find_prefix(&prefix)
+            .into_iter()
+            .filter(|(_, c, _)| country.as_ref().is_none_or(|country| country == c))
+            .map(|(name, country_code, state_code)| {
+                let key = (state_code, name);
+                let population = self
+                    .cities
+                    .get(&country_code)
+                    .and_then(|c| c.population.get(&key))
+                    .copied()
+                    .unwrap_or(0);
+                (population, country_code, key.0, key.1)
             })
+            .collect();
+        // Sort is stable, so cities of the same population stay in alphabetical order
+        found.sort_by_key(|(population, _, _, _)| Reverse(*population));
+        let mut suggestions = vec![];
+        for (_, country_code, state_code, key) in found {
+            let country = match self.country_from_code(&country_code) {
+                Some(c) => c,
+                None => continue,
+            };
+            let state = match self.state_from_code(&Some(country.clone()), &state_code) {
+                Some(s) => s,
+                None => continue,
+            };
+            let name = self
+                .cities
+                .get(&country.code)
+                .and_then(|c| c.names.get(&(state_code, key.clone())))
+                .map_or(key.as_str(), |n| n.as_ref());
+            let mut location = Location {
+                city: Some(City::new(&titlecase(name))),
+                state: Some(state),
+                country: Some(country),
+                ..Location::default()
+            };
+            self.apply_output_case(&mut location);
+            if let (Some(city), Some(state), Some(country)) =
+                (location.city, location.state, location.country)
+            {
+                suggestions.push((city, state, country));
+            }
+            if suggestions.len() == limit {
+                break;
+            }
+        }
+        suggestions
+    }
+
+    /// Find the closest known city to the given point, searching US and CA
+    /// cities with known coordinates and major international cities.
+    ///
+    /// # Arguments
+    ///
+    /// * `latitude` - Latitude of the point in degrees
+    /// * `longitude` - Longitude of the point in degrees
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.nearest_city(43.7, -79.4).unwrap();
+    /// assert_eq!(location.to_string(), String::from("Toronto, ON, CA"));
+    /// ```
+    pub fn nearest_city(&self, latitude: f64, longitude: f64) -> Option<Location> {
+        let point = (latitude, longitude);
+        let mut nearest: Option<(f64, Location)> = None;
+        let mut consider = |coordinates: (f64, f64), location: Location| {
+            let distance = utils::haversine(point, coordinates);
+            if nearest.as_ref().is_none_or(|(d, _)| distance < *d) {
+                nearest = Some((distance, location));
+            }
+        };
+        for (country_code, cities) in self.cities.iter() {
+            let country = match self.country_from_code(country_code) {
+                Some(c) => c,
+                None => continue,
+            };
+            for ((state_code, key), coordinates) in &cities.coordinates {
+                let state = match self.state_from_code(&Some(country.clone()), state_code) {
+                    Some(s) => s,
+                    None => continue,
+                };
+                let name = cities.names.get(&(state_code.clone(), key.clone()));
+                let name = match name {
+                    Some(n) => n,
+                    None => continue,
+                };
+                let location = Location {
+                    city: Some(City::new(&titlecase(name))),
+                    state: Some(state),
+                    country: Some(country.clone()),
+                    ..Location::default()
+                };
+                consider(*coordinates, location);
+            }
         }
+        for city in self.major_world_cities.iter() {
+            if let Some(country) = self.country_from_code(&city.country) {
+                let location = Location {
+                    city: Some(City::new(&city.name)),
+                    country: Some(country),
+                    ..Location::default()
+                };
+                consider((city.latitude, city.longitude), location);
+            }
+        }
+        nearest.map(|(_, mut location)| {
+            self.apply_output_case(&mut location);
+            location
+        })
+    }
+
+    /// Distance in kilometers between cities of two locations, `None` when
+    /// coordinates of either city are unknown, see `Parser::coordinates`.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - Location to measure the distance from
+    /// * `b` - Location to measure the distance to
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let toronto = parser.parse_location("Toronto, ON");
+    /// let ottawa = parser.parse_location("Ottawa, ON");
+    /// assert_eq!(parser.distance(&toronto, &ottawa).map(|d| d.round()), Some(352.0));
+    /// assert_eq!(parser.distance(&toronto, &parser.parse_location("ON, CA")), None);
+    /// ```
+    pub fn distance(&self, a: &Location, b: &Location) -> Option<f64> {
+        let from = self.coordinates(a)?;
+        let to = self.coordinates(b)?;
+        Some(utils::haversine(from, to))
+    }
+
+    /// Find every known city with the given name, in every state of every
+    /// country, the most populous first. Names are compared like in parsing,
+    /// case-insensitively and without diacritics and apostrophes.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the city, e.g. "Springfield"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let locations = parser.lookup_cities("springfield");
+    /// assert!(locations.len() > 10);
+    /// assert_eq!(locations[0].to_string(), String::from("Springfield, MO, US"));
+    /// assert!(parser.lookup_cities("Atlantis").is_empty());
+    /// ```
+    pub fn lookup_cities(&self, name: &str) -> Vec<Location> {
+        let key = utils::city_key(name);
+        let mut found: Vec<(u64, Location)> = vec![];
+        for country in &self.state_countries {
+            let cities = match self.cities.get(&country.code) {
+                Some(c) => c,
+                None => continue,
+            };
+            for state_code in cities.states_by_city.get(&key).into_iter().flatten() {
+                let state = match self.state_from_code(&Some(country.clone()), state_code) {
+                    Some(s) => s,
+                    None => continue,
+                };
+                let city_key = (state_code.clone(), key.clone());
+                let city_name = cities.names.get(&city_key).map_or(name, |n| n.as_ref());
+                let mut location = Location {
+                    city: Some(City::new(&titlecase(city_name))),
+                    state: Some(state),
+                    country: Some(country.clone()),
+                    ..Location::default()
+                };
+                self.apply_output_case(&mut location);
+                let population = cities.population.get(&city_key).copied().unwrap_or(0);
+                found.push((population, location));
+            }
+        }
+        found.sort_by_key(|(population, _)| Reverse(*population));
+        found.into_iter().map(|(_, location)| location).collect()
     }
 
     /// Parse location string and try to extract city out of it.
@@ -80,12 +886,23 @@ impl Parser {
     ///     country: Some(geo_rs::nodes::Country { code: String::from("CA"), name: String::from("Canada") }),
     ///     zipcode: None,
     ///     address: None,
+    ///     landmark: None,
+    ///     alternatives: vec![],
+    ///     warnings: vec![],
+    ///     collapsed: vec![],
+    ///     raw: String::new(),
     /// };
     /// parser.fill_city(&mut location, "Toronto, ON, CA");
     /// let city = location.city.unwrap();
     /// assert_eq!(city.name, String::from("Toronto"));
     /// ```
     pub fn fill_city(&self, location: &mut Location, input: &str) {
+        stage!("city", input);
+        let input = &unidecode(input);
+        if location.city.is_none() && self.fill_city_alias(location, input) {
+            utils::decode(location);
+            return;
+        }
         if location.state.is_some() & location.country.is_none() {
             self.fill_country_from_state(location);
         }
@@ -95,7 +912,14 @@ impl Parser {
             .next()
             .unwrap_or("")
             .to_string();
-        for c in utils::get_countries(&location.country) {
+        let input_lowercase = input.to_lowercase();
+        let input_parts = utils::split(&input_lowercase);
+        let input_uppercase = input.to_uppercase();
+        let input_parts_uppercase = utils::split(&input_uppercase);
+        // Ranked candidates of every country with their population and
+        // whether the input names their state
+        let mut matches: Vec<(bool, u64, Country, (String, String))> = vec![];
+        for c in self.candidate_countries(&location.country) {
             let [state_codes, state_names] = match &location.state {
                 Some(s) => [vec![&s.code], vec![&s.name]],
                 None => match self.states.get(&c.code) {
@@ -109,186 +933,584 @@ impl Parser {
             if let Some(country_cities) = &self.cities.get(&c.code) {
                 let mut candidates: Vec<(String, String)> = vec![];
                 // Search for a full match (when input consists of just a city)
-                for s in &state_codes {
-                    if let Some(state_cities) = country_cities.cities_by_state.get(*s) {
-                        if state_cities.contains(&input_first_word.to_string()) {
+                if let Some(city_states) = country_cities.states_by_city.get(&input_first_word) {
+                    for s in &state_codes {
+                        if city_states.contains(s) {
                             candidates.push((s.to_string(), input_first_word.clone()))
                         }
                     }
                 }
-                if candidates.len() == 0 {
-                    // Search for a partly match (when input consists of a city and some other stuff)
+                // Search for a city that ends right before the state code, e.g. "Kansas City MO"
+                if candidates.is_empty() {
+                    let parts = utils::split(input);
+                    if let Some((city, state, _)) =
+                        self.state_after_city(&parts, std::slice::from_ref(&c))
+                    {
+                        if state_codes.contains(&&state.code) {
+                            candidates.push((state.code, city));
+                        }
+                    }
+                }
+                if candidates.is_empty() {
+                    // Search for a partly match (when input consists of a city and some other stuff),
+                    // only cities whose first word is in the input can match
+                    let mut positions: HashMap<&str, Vec<usize>> = HashMap::new();
+                    if let Some(words) = self.city_words.get(&c.code) {
+                        for part in &input_parts {
+                            for (state, i) in words.get(*part).into_iter().flatten() {
+                                positions.entry(state).or_default().push(*i);
+                            }
+                        }
+                    }
                     for s in state_codes {
-                        if let Some(state_cities) = country_cities.cities_by_state.get(s) {
-                            for city in state_cities {
-                                let input_lowercase = input.to_lowercase();
-                                let parts_city: Vec<&str> = utils::split(city);
-                                let parts_input: Vec<&str> = utils::split(&input_lowercase);
-                                if parts_city
-                                    .iter()
-                                    .all(|p| parts_input.to_owned().contains(&p))
-                                {
+                        let state_cities = country_cities.cities_by_state.get(s);
+                        if let (Some(state_cities), Some(positions)) =
+                            (state_cities, positions.get_mut(s.as_str()))
+                        {
+                            // Keep the order of the dataset, so that ties break the same way
+                            positions.sort_unstable();
+                            positions.dedup();
+                            for city in positions.iter().map(|i| &state_cities[*i]) {
+                                if utils::words(city).all(|p| input_parts.contains(&p)) {
                                     candidates.push((s.to_string(), city.to_string()))
                                 }
                             }
                         }
                     }
+                    // Keep hyphenated names intact, e.g. "Montreal Nord" is
+                    // Montreal-Nord rather than Montreal followed by "Nord"
+                    let input_words = input_parts.join(" ");
+                    let parts: Vec<Vec<&str>> =
+                        candidates.iter().map(|(_, c)| utils::split(c)).collect();
+                    let keep: Vec<bool> = parts
+                        .iter()
+                        .map(|own| {
+                            !parts.iter().any(|other| {
+                                other.len() > own.len()
+                                    && own.iter().all(|p| other.contains(p))
+                                    && input_words.contains(&other.join(" "))
+                            })
+                        })
+                        .collect();
+                    candidates = candidates
+                        .into_iter()
+                        .zip(keep)
+                        .filter_map(|(candidate, keep)| keep.then_some(candidate))
+                        .collect();
                 }
                 let mut ranged_candidates: Vec<(String, String)> = vec![];
-                if candidates.len() >= 1 && candidates.len() < 3 {
+                let mut state_matched = false;
+                let state_names: Vec<String> =
+                    state_names.iter().map(|v| v.to_lowercase()).collect();
+                if !candidates.is_empty() {
                     if candidates.len() > 1 {
-                        debug!(
-                            "Found multiple city candidates for an input {:?}: {:?}",
-                            input, candidates
-                        );
+                        trace_event!("multiple city candidates", candidates = candidates);
                     }
                     for candidate in &candidates {
                         let candidate_city = &candidate.1;
                         let candidate_state = &candidate.0;
-                        if country_cities.cities_by_state.get(&candidate.0).is_some() {
-                            let city_full_match = input_first_word == candidate_city.to_lowercase();
-                            let city_part_match = input
-                                .to_lowercase()
-                                .contains(&candidate_city.to_lowercase());
-                            let state_match = utils::split(input.to_uppercase().as_str())
-                                .contains(&candidate_state.as_str());
+                        if country_cities.cities_by_state.contains_key(&candidate.0) {
+                            let city_full_match = &input_first_word == candidate_city;
+                            let city_part_match = input_lowercase.contains(candidate_city);
+                            let state_match =
+                                input_parts_uppercase.contains(&candidate_state.as_str());
                             let input_starts_with_city =
-                                &input_first_word.starts_with(&candidate_city.to_lowercase());
+                                input_first_word.starts_with(candidate_city);
                             // Ignore when city is also state, e.g. Quebec or New York
-                            if state_names
-                                .iter()
-                                .map(|v| v.to_lowercase())
-                                .collect::<Vec<String>>()
-                                .contains(&&candidate_city)
+                            if state_names.contains(candidate_city)
                                 && !city_full_match
                                 && !input_starts_with_city
                             {
-                                debug!(
-                                    "Candidate city is also a state {:?}: {:?}",
-                                    input_first_word, candidates
+                                trace_event!(
+                                    "rejected candidate, city is also a state",
+                                    candidate = candidate
                                 );
                                 continue;
                             }
                             if city_full_match && state_match {
                                 ranged_candidates = vec![candidate.clone()];
+                                state_matched = true;
                                 break;
                             }
                             if city_part_match && state_match {
                                 ranged_candidates.insert(0, candidate.clone());
+                                state_matched = true;
                                 break;
                             }
                             ranged_candidates.push(candidate.clone());
                         }
                     }
                 }
-                if ranged_candidates.len() > 0 {
-                    location.city = Some(City {
-                        name: String::from(titlecase(
-                            ranged_candidates.first().unwrap().1.as_str(),
-                        )),
+                // Without a state in the input prefer more populous cities,
+                // e.g. "Portland" is more likely to be Portland, OR than Portland, ME
+                if !state_matched {
+                    ranged_candidates.sort_by(|a, b| {
+                        let a_population = country_cities.population.get(a).unwrap_or(&0);
+                        let b_population = country_cities.population.get(b).unwrap_or(&0);
+                        b_population.cmp(a_population).then_with(|| a.0.cmp(&b.0))
                     });
-                    if location.country.is_none() {
-                        location.country = Some(c.clone());
-                    }
-                    if location.state.is_none() {
-                        let state = self.state_from_code(
-                            &Some(c),
-                            ranged_candidates.first().unwrap().0.as_str(),
-                        );
-                        location.state = state;
+                }
+                if !ranged_candidates.is_empty() {
+                    trace_event!("ranked city candidates", candidates = ranged_candidates);
+                    for (i, candidate) in ranged_candidates.into_iter().enumerate() {
+                        let population = *country_cities.population.get(&candidate).unwrap_or(&0);
+                        matches.push((state_matched && i == 0, population, c.clone(), candidate));
                     }
                 }
             }
         }
+        // A city of the state named in the input wins, otherwise the most populous
+        // city of any country does, e.g. "London" is London, ON rather than London, KY.
+        // Earlier countries break ties, the rest become alternatives,
+        // so that same-named cities are reported as ambiguous
+        matches.sort_by_key(|(state_matched, population, _, _)| {
+            (Reverse(*state_matched), Reverse(*population))
+        });
+        if let Some((_, _, country, (state_code, city))) = matches.first() {
+            location.city = Some(City {
+                name: titlecase(city),
+            });
+            location.alternatives = matches
+                .iter()
+                .skip(1)
+                .map(|(_, _, c, (state_code, city))| Location {
+                    city: Some(City {
+                        name: titlecase(city),
+                    }),
+                    state: self.state_from_code(&Some(c.clone()), state_code),
+                    country: Some(c.clone()),
+                    zipcode: location.zipcode.clone(),
+                    ..Location::default()
+                })
+                .collect();
+            if location.country.is_none() {
+                location.country = Some(country.clone());
+            }
+            if location.state.is_none() {
+                location.state = self.state_from_code(&Some(country.clone()), state_code);
+            }
+        }
         utils::decode(location);
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "binary-data", derive(serde::Serialize, serde::Deserialize))]
 pub struct CitiesMap {
     pub cities_by_state: HashMap<String, Vec<String>>,
     pub state_of_city: HashMap<String, String>,
+    pub states_by_city: HashMap<String, Vec<String>>,
+    /// Population keyed by state code and lowercase city name, when known
+    pub population: HashMap<(String, String), u64>,
+    /// Coordinates (latitude, longitude) keyed by state code
+    /// and lowercase city name, when known
+    pub coordinates: HashMap<(String, String), (f64, f64)>,
+    /// Original names of cities with diacritics keyed by state code
+    /// and lowercase ASCII name, e.g. ("QC", "montreal") -> "Montréal"
+    pub accented_names: HashMap<(String, String), String>,
+    /// Names of cities as they are spelled in the dataset keyed by state code
+    /// and lowercase ASCII name, e.g. ("AK", "eielson afb") -> "Eielson AFB",
+    /// borrowed from the mapped file with `mmap` feature
+    pub names: HashMap<(String, String), DataStr>,
+    /// Metropolitan areas keyed by state code and lowercase city name,
+    /// e.g. ("ON", "mississauga") -> "Toronto"
+    pub metros: HashMap<(String, String), String>,
+    /// Alternate names of cities keyed by lowercase alias,
+    /// e.g. "nyc" -> ("NY", "New York")
+    pub aliases: HashMap<String, (String, String)>,
 }
 
-impl Default for CitiesMap {
-    fn default() -> Self {
-        CitiesMap {
-            cities_by_state: HashMap::new(),
-            state_of_city: HashMap::new(),
+pub type CountryCities = HashMap<String, CitiesMap>;
+
+/// Major international cities by country code.
+pub type WorldCities = HashMap<String, Vec<String>>;
+
+/// State codes and lowercase names of cities by country code and phonetic key.
+pub type PhoneticIndex = HashMap<String, HashMap<String, Vec<(String, String)>>>;
+
+/// State codes and positions in `CitiesMap::cities_by_state` of cities by
+/// country code and the first word of their lowercase names.
+pub type WordIndex = HashMap<String, HashMap<String, Vec<(String, usize)>>>;
+
+/// Read a list of major international cities and group them by country code,
+/// the list is empty unless `data-world` or `data-world-major` feature is enabled.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let cities = geo_rs::nodes::read_world_cities();
+/// if cfg!(feature = "data-world") {
+///     assert!(cities.get("DE").unwrap().contains(&String::from("Berlin")));
+/// }
+/// ```
+pub fn read_world_cities() -> WorldCities {
+    let mut data: WorldCities = HashMap::new();
+    if cfg!(feature = "data-world") {
+        let filename = "world/cities.txt";
+        for s in utils::read_lines(filename).map(|line| utils::expect_line(filename, line)) {
+            let parts: Vec<&str> = s.split(";").collect();
+            data.entry(parts[0].to_string())
+                .or_default()
+                .push(parts[1].to_string());
+        }
+    }
+    for city in read_major_world_cities() {
+        let cities = data.entry(city.country).or_default();
+        if !cities.contains(&city.name) {
+            cities.push(city.name);
         }
     }
+    data
 }
 
-pub type CountryCities = HashMap<String, CitiesMap>;
+/// Major international city with its coordinates.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "binary-data", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorldCity {
+    pub name: String,
+    pub country: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Read major international cities ordered from the most populous one,
+/// the list is empty unless `data-world-major` feature is enabled.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let cities = geo_rs::nodes::read_major_world_cities();
+/// if cfg!(feature = "data-world-major") {
+///     assert_eq!(cities[0].name, String::from("Tokyo"));
+/// } else {
+///     assert!(cities.is_empty());
+/// }
+/// ```
+pub fn read_major_world_cities() -> Vec<WorldCity> {
+    let mut data: Vec<WorldCity> = vec![];
+    if !cfg!(feature = "data-world-major") {
+        return data;
+    }
+    let filename = "world/major_cities.txt";
+    for s in utils::read_lines(filename).map(|line| utils::expect_line(filename, line)) {
+        let parts: Vec<&str> = s.split(";").collect();
+        if let (Ok(latitude), Ok(longitude)) = (parts[2].parse::<f64>(), parts[3].parse::<f64>()) {
+            data.push(WorldCity {
+                name: parts[1].to_string(),
+                country: parts[0].to_string(),
+                latitude,
+                longitude,
+            });
+        }
+    }
+    data
+}
 
-/// Read US and CA states GEO data and create a map between
-/// state names and state abbreviations and vice-versa.
+/// Group cities of every country by phonetic keys of their names,
+/// see `utils::phonetic_key`.
+///
+/// # Arguments
+///
+/// * `cities` - Cities of all countries
 ///
 /// # Examples
 ///
 /// ```
 /// use geo_rs;
-/// let states = geo_rs::nodes::read_states();
+/// let cities = geo_rs::nodes::read_cities();
+/// let index = geo_rs::nodes::city::phonetic_index(&cities);
+/// let key = geo_rs::utils::phonetic_key("Pittsburg");
+/// assert!(index["US"][&key].contains(&(String::from("PA"), String::from("pittsburgh"))));
+/// ```
+pub fn phonetic_index(cities: &CountryCities) -> PhoneticIndex {
+    let mut index: PhoneticIndex = HashMap::new();
+    for (country, country_cities) in cities {
+        let country_index = index.entry(country.clone()).or_default();
+        for (state, names) in &country_cities.cities_by_state {
+            for name in names {
+                country_index
+                    .entry(utils::phonetic_key(name))
+                    .or_default()
+                    .push((state.clone(), name.clone()));
+            }
+        }
+    }
+    index
+}
+
+/// Group cities of every country by the first word of their names, so that
+/// only cities that can be a part of the input are compared to it.
+///
+/// # Arguments
+///
+/// * `cities` - Cities of all countries
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let cities = geo_rs::nodes::read_cities();
+/// let index = geo_rs::nodes::city::word_index(&cities);
+/// let names: Vec<&String> = index["US"]["salt"]
+///     .iter()
+///     .map(|(state, i)| &cities["US"].cities_by_state[state][*i])
+///     .collect();
+/// assert!(names.contains(&&String::from("salt lake city")));
+/// ```
+pub fn word_index(cities: &CountryCities) -> WordIndex {
+    let mut index: WordIndex = HashMap::new();
+    for (country, country_cities) in cities {
+        let country_index = index.entry(country.clone()).or_default();
+        for (state, names) in &country_cities.cities_by_state {
+            for (i, name) in names.iter().enumerate() {
+                if let Some(word) = utils::words(name).next() {
+                    country_index
+                        .entry(word.to_string())
+                        .or_default()
+                        .push((state.clone(), i));
+                }
+            }
+        }
+    }
+    index
+}
+
+/// Read cities of every country of `utils::data_countries` that has
+/// `src/data/{country}/cities.txt`, along with their aliases and metros.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let cities = geo_rs::nodes::read_cities();
+/// assert_eq!(cities.contains_key("US"), cfg!(feature = "data-us"));
 /// ```
 pub fn read_cities() -> HashMap<String, CitiesMap> {
+    read_cities_from(utils::data_source()).0
+}
+
+/// Same as `read_cities` but read the files of the given source and also
+/// return the number of malformed lines that were skipped, see `utils::Records`.
+///
+/// # Arguments
+///
+/// * `source` - Where to read `{country}/cities.txt` and the rest from
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let (cities, skipped) = geo_rs::nodes::city::read_cities_from(geo_rs::utils::data_source());
+/// assert_eq!(cities.contains_key("CA"), cfg!(feature = "data-ca"));
+/// assert_eq!(skipped, 0);
+/// ```
+pub fn read_cities_from(source: &DataSource) -> (HashMap<String, CitiesMap>, usize) {
     let mut data: HashMap<String, CitiesMap> = HashMap::new();
-    for country in ["US", "CA"].iter() {
+    let mut skipped = 0;
+    for country in source.countries() {
         let filename = format!("{}/{}.txt", &country, "cities");
+        if !source.file_exists(&filename) {
+            continue;
+        }
         let mut cities_by_state: HashMap<String, Vec<String>> = HashMap::new();
         let mut state_of_city: HashMap<String, String> = HashMap::new();
-        for line in utils::read_lines(&filename) {
-            if let Ok(s) = line {
-                let parts: Vec<&str> = s.split(";").collect();
-                if parts[1].len() <= 3 {
-                    continue;
+        let mut states_by_city: HashMap<String, Vec<String>> = HashMap::new();
+        let mut population: HashMap<(String, String), u64> = HashMap::new();
+        let mut coordinates: HashMap<(String, String), (f64, f64)> = HashMap::new();
+        let mut accented_names: HashMap<(String, String), String> = HashMap::new();
+        let mut names: HashMap<(String, String), DataStr> = HashMap::new();
+        let mut records = Records::new(&filename, 2);
+        for line in mapped::read_lines(source, &filename) {
+            let s = line.as_str();
+            let parts = match records.split(s) {
+                Some(parts) => parts,
+                None => continue,
+            };
+            if parts[1].len() <= 3 {
+                continue;
+            }
+            // Cities are matched by their ASCII names, so "Montréal" and
+            // "Montreal" in the input both match the same city
+            let ascii_name = unidecode(&parts[1]);
+            let key = utils::city_key(&ascii_name);
+            if ascii_name != parts[1] {
+                accented_names.insert((parts[0].to_string(), key.clone()), parts[1].to_string());
+                state_of_city.insert(ascii_name.clone(), parts[0].to_string());
+            }
+            let name = match ascii_name == parts[1] {
+                true => line.field(&parts[1]),
+                false => DataStr::from(ascii_name.clone()),
+            };
+            names.insert((parts[0].to_string(), key.clone()), name);
+            match cities_by_state.get_mut(&*parts[0]) {
+                Some(state_cities) => {
+                    state_cities.push(key.clone());
                 }
-                match cities_by_state.get_mut(parts[0]) {
-                    Some(state_cities) => {
-                        state_cities.push(parts[1].to_lowercase().to_string());
-                    }
-                    None => {
-                        cities_by_state.insert(
-                            parts[0].to_string(),
-                            vec![parts[1].to_lowercase().to_string()],
-                        );
-                    }
+                None => {
+                    cities_by_state.insert(parts[0].to_string(), vec![key.clone()]);
                 }
-                state_of_city.insert(parts[1].to_string(), parts[0].to_string());
+            }
+            state_of_city.insert(parts[1].to_string(), parts[0].to_string());
+            let city_states = states_by_city.entry(key.clone()).or_default();
+            if !city_states.contains(&parts[0].to_string()) {
+                city_states.push(parts[0].to_string());
+            }
+            if let (Some(Ok(lat)), Some(Ok(lon))) = (
+                parts.get(3).map(|p| p.parse::<f64>()),
+                parts.get(4).map(|p| p.parse::<f64>()),
+            ) {
+                coordinates.insert((parts[0].to_string(), key.clone()), (lat, lon));
+            }
+            if let Some(Ok(p)) = parts.get(2).map(|p| p.parse::<u64>()) {
+                population.insert((parts[0].to_string(), key), p);
+            }
+        }
+        skipped += records.skipped;
+        let mut aliases: HashMap<String, (String, String)> = HashMap::new();
+        let filename = format!("{}/{}.txt", &country, "aliases");
+        let mut records = Records::new(&filename, 3);
+        for s in source.read_optional_lines(&filename) {
+            if let Some(parts) = records.split(&s) {
+                aliases.insert(
+                    utils::city_key(&parts[2]),
+                    (parts[0].to_string(), parts[1].to_string()),
+                );
+            }
+        }
+        skipped += records.skipped;
+        let mut metros: HashMap<(String, String), String> = HashMap::new();
+        let filename = format!("{}/{}.txt", &country, "metros");
+        let mut records = Records::new(&filename, 3);
+        for s in source.read_optional_lines(&filename) {
+            if let Some(parts) = records.split(&s) {
+                metros.insert(
+                    (parts[0].to_string(), utils::city_key(&parts[1])),
+                    parts[2].to_string(),
+                );
             }
         }
+        skipped += records.skipped;
         data.insert(
             country.to_string(),
             CitiesMap {
                 cities_by_state,
                 state_of_city,
+                states_by_city,
+                population,
+                coordinates,
+                accented_names,
+                names,
+                metros,
+                aliases,
             },
         );
     }
-    data
+    (data, skipped)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
     use crate::mocks;
+    #[cfg(feature = "data-us")]
+    use crate::ParserOptions;
+    use std::path::Path;
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_output_case() {
+        for (case, city) in [
+            (OutputCase::Title, "Eielson Afb"),
+            (OutputCase::AsData, "Eielson AFB"),
+            (OutputCase::Upper, "EIELSON AFB"),
+        ] {
+            let parser = Parser::with_options(ParserOptions::new().output_case(case));
+            let location = parser.parse_location("EIELSON AFB, Alaska");
+            assert_eq!(location.city.unwrap().name, city, "case: {:?}", case);
+        }
+        let parser = Parser::with_options(ParserOptions::new().output_case(OutputCase::AsData));
+        let location = parser.parse_location("Colleretto Giacosa");
+        assert_eq!(location.city.unwrap().name, "Colleretto Giacosa");
+    }
 
     #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
     fn test_read_cities() {
         let cities = super::read_cities();
-        assert!(cities.get("US").is_some());
-        assert!(cities.get("CA").is_some());
+        assert!(cities.contains_key("US"));
+        assert!(cities.contains_key("CA"));
         let us_cities = cities.get("US").unwrap();
-        assert!(us_cities.state_of_city.get("New York").is_some());
+        assert!(us_cities.state_of_city.contains_key("New York"));
         let ca_cities = cities.get("CA").unwrap();
-        assert!(ca_cities.cities_by_state.get("ON").is_some());
-        assert!(ca_cities.state_of_city.get("Toronto").is_some());
+        assert!(ca_cities.cities_by_state.contains_key("ON"));
+        assert!(ca_cities.state_of_city.contains_key("Toronto"));
         let ca_state_cities = ca_cities.cities_by_state.get("ON").unwrap();
         assert!(ca_state_cities.contains(&"toronto".to_string()));
         let us_state_cities = us_cities.cities_by_state.get("NY").unwrap();
         assert!(us_state_cities.contains(&"new york".to_string()));
+        assert_eq!(
+            us_cities
+                .population
+                .get(&("OR".to_string(), "portland".to_string())),
+            Some(&652503)
+        );
+        assert_eq!(
+            us_cities.states_by_city.get("sausalito"),
+            Some(&vec!["CA".to_string()])
+        );
+        // Names are borrowed from the mapped file with `mmap` feature
+        let name = &us_cities.names[&("NY".to_string(), "new york".to_string())];
+        assert_eq!(name, "New York");
+        assert_eq!(name.is_mapped(), cfg!(feature = "mmap"));
+    }
+
+    #[test]
+    fn test_read_cities_skips_malformed_lines() {
+        let source =
+            DataSource::Dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/malformed"));
+        let (cities, skipped) = read_cities_from(&source);
+        if !cfg!(feature = "data-us") {
+            assert!(cities.is_empty());
+            return;
+        }
+        assert_eq!(skipped, 4);
+        let us_cities = &cities["US"];
+        assert_eq!(
+            us_cities.cities_by_state["NY"],
+            vec![String::from("buffalo")]
+        );
+        assert_eq!(
+            us_cities.cities_by_state["CA"],
+            vec![String::from("san diego")]
+        );
+        let alias = (String::from("NY"), String::from("New York"));
+        assert_eq!(us_cities.aliases.get("nyc"), Some(&alias));
     }
 
     #[test]
+    fn test_read_cities_from_csv() {
+        let source = DataSource::Dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/csv"));
+        let (cities, skipped) = read_cities_from(&source);
+        if !cfg!(feature = "data-us") {
+            assert!(cities.is_empty());
+            return;
+        }
+        assert_eq!(skipped, 0);
+        let us_cities = &cities["US"];
+        assert_eq!(
+            us_cities.state_of_city.get("Washington, D.C."),
+            Some(&String::from("DC"))
+        );
+        assert!(us_cities.state_of_city.contains_key("The \"Big\" Apple"));
+        let key = (String::from("NY"), String::from("buffalo"));
+        assert_eq!(us_cities.population.get(&key), Some(&278349));
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
     fn test_california_cities() {
         let parser = Parser::new();
         parser
@@ -301,6 +1523,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "data-us")]
     fn test_fill_special_case_city() {
         let mut cities: HashMap<&str, Option<City>> = HashMap::new();
         cities.insert(
@@ -322,14 +1545,284 @@ mod tests {
             country: None,
             zipcode: None,
             address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
         };
         for (input, city) in cities {
-            parser.fill_special_case_city(&mut location, &input);
+            parser.fill_special_case_city(&mut location, input);
             assert_eq!(location.city, city);
         }
     }
 
     #[test]
+    #[cfg(feature = "data-ca")]
+    fn test_city_counts() {
+        let parser = Parser::new();
+        let counts = parser.city_counts();
+        assert_eq!(counts["CA"]["ON"], parser.city_count("CA", "ON"));
+        assert!(counts["CA"]["ON"] > 1000);
+        assert!(counts["CA"]["NT"] > 0);
+        assert_eq!(parser.city_count("XX", "ON"), 0);
+    }
+
+    #[test]
+    fn test_bundled_states_have_cities() {
+        // A default parser starts without "no cities loaded" warnings
+        let parser = Parser::new();
+        assert_eq!(parser.states_without_cities(), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "data-ca")]
+    fn test_read_cities_with_diacritics() {
+        let cities = read_cities();
+        let quebec = cities.get("CA").unwrap();
+        for (ascii, accented) in [
+            ("montreal", "Montréal"),
+            ("quebec", "Québec"),
+            ("trois-rivieres", "Trois-Rivières"),
+        ] {
+            let key = (String::from("QC"), String::from(ascii));
+            assert_eq!(quebec.accented_names.get(&key).unwrap(), accented);
+            assert!(quebec.cities_by_state["QC"].contains(&key.1));
+            assert_eq!(quebec.state_of_city[accented], "QC");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-ca")]
+    fn test_fill_city_with_diacritics() {
+        let parser = Parser::new();
+        for input in [
+            "Montréal",
+            "Montreal",
+            "Saint-Jérôme",
+            "Trois-Rivières",
+            "Trois-Rivieres",
+            "Québec",
+        ] {
+            let mut location = Location {
+                city: None,
+                state: Some(State {
+                    code: String::from("QC"),
+                    name: String::from("Quebec"),
+                }),
+                country: None,
+                zipcode: None,
+                address: None,
+                landmark: None,
+                alternatives: vec![],
+                warnings: vec![],
+                collapsed: vec![],
+                raw: String::new(),
+            };
+            parser.fill_city(&mut location, input);
+            assert_eq!(
+                location.city.unwrap().name,
+                unidecode(input),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-ca")]
+    fn test_fill_unique_city() {
+        let parser = Parser::new();
+        let mut location = Location {
+            city: None,
+            state: None,
+            country: None,
+            zipcode: None,
+            address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
+        };
+        parser.fill_unique_city(&mut location, "Store 42, Kelowna");
+        assert_eq!(format!("{}", location), "Kelowna, BC, CA");
+        let mut location = Location {
+            city: None,
+            state: None,
+            country: None,
+            zipcode: None,
+            address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
+        };
+        parser.fill_unique_city(&mut location, "Springfield");
+        assert_eq!(location.city, None);
+        assert_eq!(location.state, None);
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_fill_city_prefers_populous_city() {
+        let parser = Parser::new();
+        let mut location = Location {
+            city: None,
+            state: None,
+            country: None,
+            zipcode: None,
+            address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
+        };
+        parser.fill_city(&mut location, "Portland");
+        assert_eq!(format!("{}", location), "Portland, OR, US");
+        let alternative = location.alternatives.first().unwrap();
+        assert_eq!(format!("{}", alternative), "Portland, ME, US");
+        let mut location = Location {
+            city: None,
+            state: None,
+            country: None,
+            zipcode: None,
+            address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
+        };
+        parser.fill_city(&mut location, "Portland, ME");
+        assert_eq!(format!("{}", location), "Portland, ME, US");
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_fill_city_with_many_same_named_cities() {
+        let parser = Parser::new();
+        let mut inputs = vec![
+            ("Greenville", "Greenville, NC, US"),
+            ("Greenville, US", "Greenville, NC, US"),
+            ("Hamilton", "Hamilton, ON, CA"),
+            ("Boston", "Boston, MA, US"),
+        ];
+        // Major world cities win over same-named US and CA cities
+        if !cfg!(feature = "data-world-major") {
+            inputs.push(("London", "London, ON, CA"));
+        }
+        for (input, output) in inputs {
+            let location = parser.parse_location(input);
+            assert_eq!(location.to_string(), output, "input: {}", input);
+            assert!(location.warnings.contains(&Warning::AmbiguousCity));
+            assert!(!location.warnings.contains(&Warning::UnknownCity));
+        }
+        // Less populous cities of other countries are kept as alternatives
+        let location = parser.parse_location("Hamilton");
+        assert!(location
+            .alternatives
+            .iter()
+            .any(|a| a.to_string() == "Hamilton, OH, US"));
+        // Country order only breaks ties and never beats the state of the input
+        let parser = Parser::with_options(ParserOptions::new().default_countries(vec!["US", "CA"]));
+        let location = parser.parse_location("Hamilton");
+        assert_eq!(location.to_string(), "Hamilton, ON, CA");
+        let location = parser.parse_location("Hamilton, OH");
+        assert_eq!(location.to_string(), "Hamilton, OH, US");
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_same_metro() {
+        let parser = Parser::new();
+        for (a, b, same) in [
+            ("Toronto, ON", "Mississauga, ON", true),
+            ("Vancouver, WA", "Portland, OR", true),
+            ("Vancouver, WA", "Vancouver, BC", false),
+            ("Kansas City, MO", "Kansas City, KS", true),
+            ("Springfield, IL", "Springfield, IL", true),
+            ("Springfield, IL", "Springfield, MA", false),
+            ("Toronto, ON", "ON, CA", false),
+        ] {
+            let (a, b) = (parser.parse_location(a), parser.parse_location(b));
+            assert_eq!(parser.same_metro(&a, &b), same);
+            assert_eq!(parser.same_metro(&b, &a), same);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_suggest_cities() {
+        let parser = Parser::new();
+        let format = |cities: Vec<(City, State, Country)>| -> Vec<String> {
+            cities
+                .iter()
+                .map(|(city, state, country)| format!("{}, {}, {}", city, state, country))
+                .collect()
+        };
+        assert_eq!(
+            format(parser.suggest_cities("Portl", None, 2)),
+            vec!["Portland, OR, US", "Portland, ME, US"]
+        );
+        assert_eq!(
+            format(parser.suggest_cities("montr", Some("ca"), 1)),
+            vec!["Montreal, QC, CA"]
+        );
+        assert_eq!(parser.suggest_cities("Montré", None, 5).len(), 5);
+        assert!(parser.suggest_cities("Toronto", Some("DE"), 5).is_empty());
+        assert!(parser.suggest_cities("", None, 5).is_empty());
+        assert!(parser.suggest_cities("xyzzy", None, 5).is_empty());
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_nearest_city() {
+        let parser = Parser::new();
+        for ((latitude, longitude), output) in [
+            ((47.6, -122.3), "Seattle, WA, US"),
+            ((45.6, -122.7), "Vancouver, WA, US"),
+            ((49.3, -123.1), "Vancouver, BC, CA"),
+            ((38.6, -90.2), "Saint Louis, MO, US"),
+        ] {
+            let location = parser.nearest_city(latitude, longitude).unwrap();
+            assert_eq!(location.to_string(), output);
+            assert!(parser.distance(&location, &location).unwrap() < 1e-6);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_distance_with_data_dir() {
+        // Coordinates come from the parser's own datasets, not the default ones
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/csv");
+        let parser = Parser::builder().with_data_dir(&dir).build();
+        let new_york = Parser::new().parse_location("New York, NY");
+        assert_eq!(parser.distance(&new_york, &new_york), None);
+        assert_eq!(Parser::new().distance(&new_york, &new_york), Some(0.0));
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_lookup_cities() {
+        let parser = Parser::new();
+        let names = |name: &str| -> Vec<String> {
+            let locations = parser.lookup_cities(name);
+            locations.iter().map(|l| l.to_string()).collect()
+        };
+        let montreals = names("MONTREAL");
+        assert_eq!(montreals[0], "Montreal, QC, CA");
+        assert!(montreals.contains(&String::from("Montreal, WI, US")));
+        assert_eq!(names("coeur d alene"), vec!["Coeur d'Alene, ID, US"]);
+        let portlands = names("Portland");
+        assert_eq!(portlands[..2], ["Portland, OR, US", "Portland, ME, US"]);
+        assert!(names("").is_empty());
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
     fn test_fill_city() {
         let parser = Parser::new();
         for (input, output) in mocks::get_mocks() {
@@ -339,16 +1832,21 @@ mod tests {
                 country: output.2,
                 zipcode: output.3,
                 address: None,
+                landmark: None,
+                alternatives: vec![],
+                warnings: vec![],
+                collapsed: vec![],
+                raw: String::new(),
             };
             let mut input_string = String::from(input);
             if let Some(z) = &location.zipcode {
-                parser.remove_zipcode(&z, &mut input_string);
+                parser.remove_zipcode(z, &mut input_string);
             }
             if let Some(c) = &location.country {
-                parser.remove_country(&c, &mut input_string);
+                parser.remove_country(c, &mut input_string);
             }
             if let (Some(s), Some(c)) = (&location.state, &location.country) {
-                parser.remove_state(&s, &c, &mut input_string);
+                parser.remove_state(s, c, &mut input_string);
             }
             parser.fill_city(&mut location, input_string.as_str());
             assert_eq!(location.city, output.0, "input: {}", input);
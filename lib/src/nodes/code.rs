@@ -0,0 +1,104 @@
+use super::{CountriesMap, CountryStates};
+use crate::Parser;
+use bitflags::bitflags;
+use std::collections::HashMap;
+
+bitflags! {
+    /// What a two-letter code may stand for, e.g. "PA" is both
+    /// Pennsylvania and Panama, "CA" is California, Canada, and nothing else.
+    pub struct CodeKind: u8 {
+        const US_STATE = 0b001;
+        const CA_PROVINCE = 0b010;
+        const COUNTRY_CODE = 0b100;
+    }
+}
+
+impl CodeKind {
+    /// Kind of state codes of the given country, empty for countries without states.
+    pub fn state_of(country_code: &str) -> CodeKind {
+        match country_code {
+            "US" => CodeKind::US_STATE,
+            "CA" => CodeKind::CA_PROVINCE,
+            _ => CodeKind::empty(),
+        }
+    }
+}
+
+/// Classify every known state and country code.
+///
+/// # Arguments
+///
+/// * `states` - States of all countries
+/// * `countries` - Countries map
+pub fn classify_codes(
+    states: &CountryStates,
+    countries: &CountriesMap,
+) -> HashMap<String, CodeKind> {
+    let mut kinds: HashMap<String, CodeKind> = HashMap::new();
+    for (country_code, country_states) in states {
+        let kind = CodeKind::state_of(country_code);
+        for code in country_states.code_to_name.keys() {
+            *kinds.entry(code.clone()).or_insert_with(CodeKind::empty) |= kind;
+        }
+    }
+    for code in countries.code_to_name.keys() {
+        *kinds.entry(code.clone()).or_insert_with(CodeKind::empty) |= CodeKind::COUNTRY_CODE;
+    }
+    kinds
+}
+
+impl Parser {
+    /// Return what the given uppercase code may stand for.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Two-letter code, e.g. "PA"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::CodeKind;
+    /// let parser = geo_rs::Parser::new();
+    /// assert_eq!(parser.code_kind("PA"), CodeKind::US_STATE | CodeKind::COUNTRY_CODE);
+    /// assert_eq!(parser.code_kind("ON"), CodeKind::CA_PROVINCE);
+    /// assert!(parser.code_kind("XX").is_empty());
+    /// ```
+    pub fn code_kind(&self, code: &str) -> CodeKind {
+        self.code_kinds
+            .get(code)
+            .copied()
+            .unwrap_or_else(CodeKind::empty)
+    }
+
+    /// Check whether the code is a state of the given country,
+    /// countries without a `CodeKind` are looked up in their states data.
+    pub(crate) fn is_state_of(&self, code: &str, country_code: &str) -> bool {
+        let kind = CodeKind::state_of(country_code);
+        match kind.is_empty() {
+            false => self.code_kind(code).contains(kind),
+            true => self
+                .states
+                .get(country_code)
+                .is_some_and(|s| s.code_to_name.contains_key(code)),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "data-us", feature = "data-ca"))]
+mod tests {
+    use super::*;
+    use crate::nodes::{read_countries, read_states};
+
+    #[test]
+    fn test_classify_codes() {
+        let kinds = classify_codes(&read_states(), &read_countries());
+        assert_eq!(kinds["CA"], CodeKind::US_STATE | CodeKind::COUNTRY_CODE);
+        assert_eq!(kinds["NL"], CodeKind::CA_PROVINCE | CodeKind::COUNTRY_CODE);
+        assert_eq!(kinds["NY"], CodeKind::US_STATE);
+        assert_eq!(kinds["QC"], CodeKind::CA_PROVINCE);
+        assert_eq!(kinds["DE"], CodeKind::US_STATE | CodeKind::COUNTRY_CODE);
+        assert_eq!(kinds["FR"], CodeKind::COUNTRY_CODE);
+        assert!(!kinds.contains_key("XX"));
+    }
+}
@@ -1,11 +1,14 @@
-use super::Location;
-use crate::utils;
-use crate::Parser;
+use super::{CodeKind, Location};
+use crate::utils::{self, DataSource, Records};
+use crate::{ParseError, Parser, DEFAULT_PARSER};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt;
+use unidecode::unidecode;
 
-#[derive(Debug, Clone, Hash, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Country {
     pub name: String,
     pub code: String,
@@ -20,11 +23,162 @@ lazy_static! {
         code: String::from("CA"),
         name: String::from("Canada"),
     };
+    /// Countries of the default data folder, source of the codes and regions of `Country`
+    static ref COUNTRIES: CountriesMap = read_countries();
 }
 
-impl PartialEq for Country {
-    fn eq(&self, other: &Country) -> bool {
-        self.name == other.name && self.code == other.code
+impl Country {
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let country = geo_rs::nodes::Country::new("CA", "Canada");
+    /// assert_eq!(country, *geo_rs::nodes::CANADA);
+    /// ```
+    pub fn new(code: &str, name: &str) -> Self {
+        Self {
+            code: code.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    /// Return ISO 3166-1 alpha-3 code of the country.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// assert_eq!(geo_rs::nodes::CANADA.alpha3(), Some("CAN"));
+    /// ```
+    pub fn alpha3(&self) -> Option<&'static str> {
+        COUNTRIES
+            .code_to_iso
+            .get(self.code.trim())
+            .map(|(a, _)| a.as_str())
+    }
+
+    /// Return ISO 3166-1 numeric code of the country, leading zeros are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// assert_eq!(geo_rs::nodes::UNITED_STATES.numeric(), Some("840"));
+    /// ```
+    pub fn numeric(&self) -> Option<&'static str> {
+        COUNTRIES
+            .code_to_iso
+            .get(self.code.trim())
+            .map(|(_, n)| n.as_str())
+    }
+
+    /// Return the continent of the country, one of "Africa", "Antarctica", "Asia",
+    /// "Europe", "North America", "Oceania" and "South America".
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::Country;
+    /// assert_eq!(geo_rs::nodes::CANADA.continent(), Some("North America"));
+    /// assert_eq!(Country::new("BR", "Brazil").continent(), Some("South America"));
+    /// ```
+    pub fn continent(&self) -> Option<&'static str> {
+        COUNTRIES
+            .code_to_region
+            .get(self.code.trim())
+            .map(|(c, _)| c.as_str())
+    }
+
+    /// Return the business region of the country, one of "NAMER" (United States,
+    /// Canada and their neighbours in the north), "LATAM" (Latin America and
+    /// the Caribbean), "EMEA" (Europe, the Middle East and Africa) and "APAC"
+    /// (the rest of Asia and Oceania). Antarctica belongs to none of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::Country;
+    /// assert_eq!(geo_rs::nodes::UNITED_STATES.region(), Some("NAMER"));
+    /// assert_eq!(Country::new("MX", "Mexico").region(), Some("LATAM"));
+    /// assert_eq!(Country::new("AE", "United Arab Emirates").region(), Some("EMEA"));
+    /// assert_eq!(Country::new("AQ", "Antarctica").region(), None);
+    /// ```
+    pub fn region(&self) -> Option<&'static str> {
+        COUNTRIES
+            .code_to_region
+            .get(self.code.trim())
+            .map(|(_, r)| r.as_str())
+            .filter(|r| !r.is_empty())
+    }
+
+    /// Return the international dialing code of the country, e.g. "+49".
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// assert_eq!(geo_rs::nodes::CANADA.dialing_code(), Some("+1"));
+    /// ```
+    pub fn dialing_code(&self) -> Option<&'static str> {
+        COUNTRIES
+            .code_to_dialing_code
+            .get(self.code.trim())
+            .map(|d| d.as_str())
+    }
+
+    /// Return ISO 4217 code of the currency of the country, e.g. "EUR".
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// assert_eq!(geo_rs::nodes::CANADA.currency(), Some("CAD"));
+    /// ```
+    pub fn currency(&self) -> Option<&'static str> {
+        COUNTRIES
+            .code_to_currency
+            .get(self.code.trim())
+            .map(|c| c.as_str())
+    }
+}
+
+/// Reference data of a country, see `Parser::country_by_code`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountryInfo {
+    pub country: Country,
+    /// ISO 3166-1 alpha-3 code, e.g. "DEU"
+    pub alpha3: Option<String>,
+    /// ISO 3166-1 numeric code with leading zeros, e.g. "276"
+    pub numeric: Option<String>,
+    /// Continent, see `Country::continent`
+    pub continent: Option<String>,
+    /// Business region, see `Country::region`
+    pub region: Option<String>,
+    /// International dialing code, e.g. "+49"
+    pub dialing_code: Option<String>,
+    /// ISO 4217 currency code, e.g. "EUR"
+    pub currency: Option<String>,
+}
+
+/// Resolve a country by its ISO 3166-1 alpha-2, alpha-3 or numeric code.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs::nodes::Country;
+/// use std::convert::TryFrom;
+/// assert_eq!(Country::try_from("DEU"), Ok(Country::new("DE", "Germany")));
+/// assert_eq!(Country::try_from("Germany"), Err(geo_rs::ParseError::NoMatch));
+/// ```
+impl TryFrom<&str> for Country {
+    type Error = ParseError;
+
+    fn try_from(code: &str) -> Result<Self, Self::Error> {
+        DEFAULT_PARSER
+            .country_from_code(code)
+            .ok_or(ParseError::NoMatch)
     }
 }
 
@@ -34,10 +188,60 @@ impl fmt::Display for Country {
     }
 }
 
-#[derive(Debug)]
+/// Countrys are ordered by code, then by name.
+impl Ord for Country {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.code
+            .cmp(&other.code)
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+impl PartialOrd for Country {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// What a code that's both a country code and a state code stands for
+enum CodeReading {
+    Country(Country),
+    State,
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary-data", derive(serde::Serialize, serde::Deserialize))]
 pub struct CountriesMap {
     pub code_to_name: HashMap<String, String>,
     pub name_to_code: HashMap<String, String>,
+    /// Alternate names of countries and their codes, e.g. ("Holland", "NL"),
+    /// longer aliases go first
+    pub aliases: Vec<(String, String)>,
+    /// ISO 3166-1 alpha-3 and numeric codes keyed by alpha-2 code,
+    /// e.g. "DE" -> ("DEU", "276")
+    pub code_to_iso: HashMap<String, (String, String)>,
+    pub alpha3_to_code: HashMap<String, String>,
+    pub numeric_to_code: HashMap<String, String>,
+    /// Continent and business region keyed by alpha-2 code, e.g. "DE" ->
+    /// ("Europe", "EMEA"), the region is empty for countries without one
+    pub code_to_region: HashMap<String, (String, String)>,
+    /// International dialing codes keyed by alpha-2 code, e.g. "DE" -> "+49"
+    pub code_to_dialing_code: HashMap<String, String>,
+    /// ISO 4217 currency codes keyed by alpha-2 code, e.g. "DE" -> "EUR"
+    pub code_to_currency: HashMap<String, String>,
+    /// Codes of member countries keyed by union, e.g. "EU" -> {"DE", "FR", ...},
+    /// see `Parser::is_member`
+    pub unions: HashMap<String, HashSet<String>>,
+}
+
+/// Split an alias or an input string into lowercase words ignoring dots,
+/// so that "U.S.A." and "USA" both become `["usa"]`.
+pub(crate) fn alias_tokens(s: &str) -> Vec<String> {
+    utils::split(&s.replace('.', "").to_lowercase())
+        .into_iter()
+        .map(String::from)
+        .collect()
 }
 
 impl Parser {
@@ -59,11 +263,18 @@ impl Parser {
     ///     country: None,
     ///     zipcode: None,
     ///     address: None,
+    ///     landmark: None,
+    ///     alternatives: vec![],
+    ///     warnings: vec![],
+    ///     collapsed: vec![],
+    ///     raw: String::new(),
     /// };
     /// parser.fill_country(&mut location, "Toronto, ON, CA");
     /// assert_eq!(location.country, Some(geo_rs::nodes::CANADA.clone()));
     /// ```
     pub fn fill_country(&self, location: &mut Location, input: &str) {
+        stage!("country", input);
+        let input = &unidecode(input);
         if input.chars().count() == 0 {
             return;
         }
@@ -72,100 +283,79 @@ impl Parser {
         }
         let as_lowercase = input.to_lowercase().to_string();
         let parts = utils::split(&as_lowercase);
-        for part in &parts {
-            if vec!["usa", "us"].contains(&part) {
-                location.country = Some(UNITED_STATES.clone());
-                return;
+        let input_tokens = alias_tokens(input);
+        for (tokens, code) in &self.country_alias_tokens {
+            if tokens.is_empty()
+                || !input_tokens
+                    .windows(tokens.len())
+                    .any(|w| w == tokens.as_slice())
+            {
+                continue;
+            }
+            // "Holland, MI" is a city in Michigan rather than the Netherlands
+            if !self.states.contains_key(code) && self.mentions_state(input) {
+                trace_event!("rejected country alias, input has a state", code = code);
+                continue;
             }
-            if vec!["canada"].contains(&part) {
-                location.country = Some(CANADA.clone());
+            if let Some(name) = self.countries.code_to_name.get(code) {
+                location.country = Some(Country {
+                    code: code.clone(),
+                    name: name.clone(),
+                });
                 return;
             }
         }
-        if as_lowercase.contains("united states") {
-            location.country = Some(UNITED_STATES.clone());
-            return;
-        }
-        if parts.contains(&"ca") {
-            let ca_states = self.states.get("CA").unwrap();
-            let codes: Vec<&String> = ca_states.code_to_name.keys().collect();
-            let names: Vec<&String> = ca_states.name_to_code.keys().collect();
-            if parts
-                .iter()
-                .find(|x| codes.contains(&&x.to_uppercase()))
-                .is_some()
-            {
-                location.country = Some(CANADA.clone());
-                return;
+        // Search alpha-3 codes, e.g. "DEU" or "CAN"
+        for part in utils::split(input) {
+            if part.len() != 3 || part != part.to_uppercase() {
+                continue;
             }
-            if parts
-                .iter()
-                .find(|x| names.contains(&&x.to_string()))
-                .is_some()
-            {
-                location.country = Some(CANADA.clone());
+            if let Some(code) = self.countries.alpha3_to_code.get(part) {
+                if !self.states.contains_key(code) && self.mentions_state(input) {
+                    continue;
+                }
+                location.country = self.country_from_code(code);
                 return;
             }
-            let ca_cities: Vec<&String> = self
-                .cities
-                .get("CA")
-                .unwrap()
-                .cities_by_state
-                .values()
-                .flatten()
-                .collect();
-            let us_cities = self.cities.get("US").unwrap();
-            let california_cities = us_cities.cities_by_state.get("CA").unwrap();
-            if california_cities
-                .iter()
-                .find(|x| {
-                    // Check whether input string has a California city in it
-                    if !as_lowercase.contains(&x.to_lowercase()) {
-                        return false;
-                    }
-                    // Make sure that California city is not also a Canadian city
-                    if ca_cities.contains(x) {
-                        return false;
-                    }
-                    return true;
-                })
-                .is_some()
-            {
-                return;
+        }
+        for part in utils::split(input) {
+            match self.read_ambiguous_code(part, &as_lowercase, &parts) {
+                CodeReading::Country(country) => {
+                    location.country = Some(country);
+                    return;
+                }
+                CodeReading::State => return,
+                CodeReading::Unknown => {}
             }
         }
         if input.contains("US") {
             location.country = Some(UNITED_STATES.clone());
         }
-        if input.contains("CA") {
-            location.country = Some(CANADA.clone());
-        }
         // Search fill country name in the input string, ignore country if code is also US or CA state,
         // For example, ignore country code PA (Panama) because it's also Pennsylvania
+        // Apostrophes don't count, cleaned input has spaces instead, e.g. "Cote d Ivoire"
+        let unquoted = as_lowercase.replace('\'', " ");
         for (country_name, country_code) in self.countries.name_to_code.iter() {
-            if as_lowercase.contains(&country_name.to_lowercase()) {
-                if let Some(us_states) = self.states.get("US") {
-                    if us_states
-                        .name_to_code
-                        .keys()
-                        .find(|name| name == &country_name)
-                        .is_some()
-                    {
-                        continue;
-                    }
-                }
-                if let Some(ca_states) = self.states.get("CA") {
-                    if ca_states
-                        .name_to_code
-                        .keys()
-                        .find(|name| name == &country_name)
-                        .is_some()
-                    {
-                        continue;
-                    }
+            // Country name has to end a part of the input, e.g. "Greece" in
+            // "Mall At Greece Ridge Center" is a part of a place name
+            let ends_part =
+                utils::find_token(&unquoted, &country_name.to_lowercase().replace('\'', " "))
+                    .iter()
+                    .any(|&(_, end)| {
+                        let mut rest = unquoted[end..].chars();
+                        !(rest.next() == Some(' ')
+                            && rest.next().is_some_and(|c| c.is_alphabetic()))
+                    });
+            if ends_part {
+                if ["US", "CA"].iter().any(|code| {
+                    self.states
+                        .get(*code)
+                        .is_some_and(|states| states.name_to_code.contains_key(country_name))
+                }) {
+                    continue;
                 }
                 location.country = Some(Country {
-                    name: String::from(country_name),
+                    name: self.countries.code_to_name[country_code].clone(),
                     code: String::from(country_code),
                 });
                 return;
@@ -173,18 +363,15 @@ impl Parser {
         }
         // Search country code in the input string, ignore country if code is also US or CA state,
         // For example, ignore country code PA (Panama) because it's also Pennsylvania
+        let input_parts = utils::split(input);
         for (country_name, country_code) in self.countries.name_to_code.iter() {
-            if let Some(us_states) = self.states.get("US") {
-                if us_states.code_to_name.contains_key(country_code) {
-                    continue;
-                }
-            }
-            if let Some(ca_states) = self.states.get("CA") {
-                if ca_states.code_to_name.contains_key(country_code) {
-                    continue;
-                }
+            if self
+                .code_kind(country_code)
+                .intersects(CodeKind::US_STATE | CodeKind::CA_PROVINCE)
+            {
+                continue;
             }
-            if utils::split(&input.to_string()).contains(&country_code.as_str()) {
+            if input_parts.contains(&country_code.as_str()) {
                 location.country = Some(Country {
                     code: country_code.clone(),
                     name: country_name.clone(),
@@ -194,6 +381,239 @@ impl Parser {
         }
     }
 
+    /// Decide what a code that's both a country code and a state code stands for,
+    /// e.g. "CA" for Canada and California. Other states of the country, e.g. "ON",
+    /// make it the country, cities of the state, e.g. "San Diego", make it the state.
+    /// Without such context an upper case code is resolved by `ParserOptions::country_bias`.
+    fn read_ambiguous_code(&self, part: &str, as_lowercase: &str, parts: &[&str]) -> CodeReading {
+        let code = part.to_uppercase();
+        if code.len() != 2 || !self.code_kind(&code).contains(CodeKind::COUNTRY_CODE) {
+            return CodeReading::Unknown;
+        }
+        let state_countries: Vec<&Country> = self
+            .state_countries
+            .iter()
+            .filter(|c| c.code != code && self.is_state_of(&code, &c.code))
+            .collect();
+        if state_countries.is_empty() {
+            return CodeReading::Unknown;
+        }
+        let country = match self.country_from_code(&code) {
+            Some(c) => c,
+            None => return CodeReading::Unknown,
+        };
+        if let Some(states) = self.states.get(&code) {
+            let has_state_code = parts
+                .iter()
+                .any(|x| *x != part.to_lowercase() && self.is_state_of(&x.to_uppercase(), &code));
+            let has_state_name = states
+                .name_to_code
+                .keys()
+                .any(|name| !utils::find_token(as_lowercase, &name.to_lowercase()).is_empty());
+            if has_state_code || has_state_name {
+                return CodeReading::Country(country);
+            }
+        }
+        let country_cities = self.cities.get(&code).map(|c| &c.states_by_city);
+        for state_country in &state_countries {
+            let state_cities = self
+                .cities
+                .get(&state_country.code)
+                .and_then(|c| c.cities_by_state.get(&code));
+            if state_cities.is_some_and(|cities| {
+                cities.iter().any(|x| {
+                    as_lowercase.contains(&x.to_lowercase())
+                        && !country_cities.is_some_and(|c| c.contains_key(x))
+                })
+            }) {
+                return CodeReading::State;
+            }
+        }
+        if part != code {
+            return CodeReading::Unknown;
+        }
+        let rank = |code: &str| self.options.country_bias.iter().position(|c| c == code);
+        let state_rank = state_countries.iter().filter_map(|c| rank(&c.code)).min();
+        match (rank(&code), state_rank) {
+            (Some(c), Some(s)) if c < s => CodeReading::Country(country),
+            (Some(_), None) => CodeReading::Country(country),
+            _ => CodeReading::Unknown,
+        }
+    }
+
+    /// Check whether the input mentions a US or CA state.
+    pub(crate) fn mentions_state(&self, input: &str) -> bool {
+        let mut probe = Location::default();
+        self.fill_state(&mut probe, input);
+        probe.state.is_some()
+    }
+
+    /// Return a country by its ISO 3166-1 alpha-2, alpha-3 or numeric code.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Country code, e.g. "DE", "DEU" or "276"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let country = parser.country_from_code("DEU").unwrap();
+    /// assert_eq!(country.code, String::from("DE"));
+    /// assert_eq!(country.name, String::from("Germany"));
+    /// assert_eq!(parser.country_from_code("276"), Some(country));
+    /// ```
+    pub fn country_from_code(&self, code: &str) -> Option<Country> {
+        let code = code.trim().to_uppercase();
+        let alpha2 = match code.len() {
+            2 => Some(&code),
+            3 => self
+                .countries
+                .alpha3_to_code
+                .get(&code)
+                .or_else(|| self.countries.numeric_to_code.get(&code)),
+            _ => None,
+        }?;
+        self.countries.code_to_name.get(alpha2).map(|name| Country {
+            code: alpha2.clone(),
+            name: name.clone(),
+        })
+    }
+
+    /// Return reference data of a country by its ISO 3166-1 alpha-2, alpha-3
+    /// or numeric code: ISO codes, continent, region, dialing code and currency.
+    /// Parts that `countries.txt` of the parser doesn't list are `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Country code, e.g. "DE", "DEU" or "276"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let info = parser.country_by_code("DEU").unwrap();
+    /// assert_eq!(info.country.name, String::from("Germany"));
+    /// assert_eq!(info.dialing_code, Some(String::from("+49")));
+    /// assert_eq!(info.currency, Some(String::from("EUR")));
+    /// assert_eq!(info.region, Some(String::from("EMEA")));
+    /// assert_eq!(parser.country_by_code("XX"), None);
+    /// ```
+    pub fn country_by_code(&self, code: &str) -> Option<CountryInfo> {
+        let country = self.country_from_code(code)?;
+        let countries = &self.countries;
+        let iso = countries.code_to_iso.get(&country.code);
+        let region = countries.code_to_region.get(&country.code);
+        Some(CountryInfo {
+            alpha3: iso.map(|(a, _)| a.clone()),
+            numeric: iso.map(|(_, n)| n.clone()),
+            continent: region.map(|(c, _)| c.clone()),
+            region: region.map(|(_, r)| r.clone()).filter(|r| !r.is_empty()),
+            dialing_code: countries.code_to_dialing_code.get(&country.code).cloned(),
+            currency: countries.code_to_currency.get(&country.code).cloned(),
+            country,
+        })
+    }
+
+    /// Check whether the country is a member of the union according to
+    /// `src/data/country_unions.txt`, e.g. "EU", "EEA" or "Schengen".
+    /// Only sovereign members are listed, not their overseas territories.
+    ///
+    /// # Arguments
+    ///
+    /// * `country` - Country, e.g. of a parsed location
+    /// * `union` - Name of the union as it's written in the file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::Country;
+    /// let parser = geo_rs::Parser::new();
+    /// let norway = Country::new("NO", "Norway");
+    /// assert!(parser.is_member(&norway, "Schengen"));
+    /// assert!(!parser.is_member(&norway, "EU"));
+    /// assert!(!parser.is_member(&norway, "Hanseatic League"));
+    /// ```
+    pub fn is_member(&self, country: &Country, union: &str) -> bool {
+        self.countries
+            .unions
+            .get(union)
+            .is_some_and(|members| members.contains(country.code.trim()))
+    }
+
+    /// Check whether the country is a member of the European Union, see `Parser::is_member`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Berlin, Germany");
+    /// assert!(parser.is_eu(&location.country.unwrap()));
+    /// let location = parser.parse_location("Toronto, ON");
+    /// assert!(!parser.is_eu(&location.country.unwrap()));
+    /// ```
+    pub fn is_eu(&self, country: &Country) -> bool {
+        self.is_member(country, "EU")
+    }
+
+    /// Check whether the country is a member of the European Economic Area,
+    /// i.e. of the EU or one of Iceland, Liechtenstein and Norway.
+    pub fn is_eea(&self, country: &Country) -> bool {
+        self.is_member(country, "EEA")
+    }
+
+    /// Check whether the country is a member of the Schengen Area.
+    pub fn is_schengen(&self, country: &Country) -> bool {
+        self.is_member(country, "Schengen")
+    }
+
+    /// Replace dotted country aliases like "U.S.A." with their undotted form,
+    /// otherwise `utils::clean` drops them as abbreviations.
+    pub(crate) fn collapse_country_aliases(&self, input: &mut String) {
+        for (alias, _) in &self.countries.aliases {
+            if !alias.contains('.') {
+                continue;
+            }
+            for (start, end) in utils::find_token(input, alias).into_iter().rev() {
+                input.replace_range(start..end, &alias.replace('.', ""));
+            }
+        }
+    }
+
+    /// Check whether the input mentions the country by one of its aliases,
+    /// e.g. "Estados Unidos". Uppercase aliases like "USA" are matched case-sensitively.
+    pub(crate) fn mentions_country_alias(&self, input: &str, country: &Country) -> bool {
+        let input_lowercase = input.to_lowercase();
+        self.countries.aliases.iter().any(|(alias, code)| {
+            if code != &country.code {
+                return false;
+            }
+            let alias = alias.replace('.', "");
+            let found = match alias == alias.to_uppercase() {
+                true => utils::find_token(input, &alias),
+                false => utils::find_token(&input_lowercase, &alias.to_lowercase()),
+            };
+            !found.is_empty()
+        })
+    }
+
+    /// Return number of loaded countries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// assert!(parser.country_count() > 200);
+    /// ```
+    pub fn country_count(&self) -> usize {
+        self.countries.code_to_name.len()
+    }
+
     /// Remove country from location string.
     ///
     /// # Arguments
@@ -215,29 +635,42 @@ impl Parser {
     /// assert_eq!(location, String::from("New York, NY"));
     /// ```
     pub fn remove_country(&self, country: &Country, input: &mut String) {
-        let case_insensitive_parts: Vec<String> = match country.code.as_str() {
-            "US" => vec![
-                String::from("united states of america"),
-                String::from("united states"),
-            ],
-            "CA" => vec![String::from("canada")],
-            _ => vec![country.name.to_lowercase()],
-        };
-        let case_sensitive_parts: Vec<String> = match country.code.as_str() {
-            "US" => vec![String::from("USA"), String::from("US")],
-            "CA" => vec![String::from("CA")],
-            _ => vec![country.code.clone()],
-        };
+        let mut case_insensitive_parts: Vec<String> =
+            vec![unidecode(&country.name).to_lowercase().replace('\'', " ")];
+        let mut case_sensitive_parts: Vec<String> = vec![country.code.clone()];
+        if let Some(alpha3) = country.alpha3() {
+            case_sensitive_parts.push(alpha3.to_string());
+        }
+        for (alias, code) in &self.countries.aliases {
+            if code != &country.code {
+                continue;
+            }
+            let alias = alias.replace('.', "");
+            // Uppercase aliases like "USA" are codes, anything else is a name
+            if alias == alias.to_uppercase() {
+                case_sensitive_parts.push(alias);
+            } else {
+                case_insensitive_parts.push(alias.to_lowercase());
+            }
+        }
+        // Remove longer parts first, e.g. "united states of america" before "united states"
+        for parts in [&mut case_insensitive_parts, &mut case_sensitive_parts] {
+            parts.sort_by_key(|p| std::cmp::Reverse(p.len()));
+            parts.dedup();
+        }
         for part in &case_insensitive_parts {
-            if let Some(start) = input.to_lowercase().find(part) {
-                input.replace_range(start..part.chars().count() + start, "");
+            let unquoted = input.to_lowercase().replace('\'', " ");
+            if let Some(&(start, end)) = utils::find_token(&unquoted, part).first() {
+                input.replace_range(start..end, "");
             }
         }
+        // Only remove standalone codes so that cities like "HOUSTON" or
+        // "CASPER" aren't mangled into "HOTON" and "SPER"
         for part in case_sensitive_parts {
-            *input = input.replace(&part, "");
+            utils::remove_token(input, &part);
         }
-        utils::clean(input);
-        debug!("after removing country: {}", input);
+        self.clean(input);
+        trace_event!("removed country", remainder = input);
     }
 }
 
@@ -251,25 +684,114 @@ impl Parser {
 /// let countries = geo_rs::nodes::read_countries();
 /// ```
 pub fn read_countries() -> CountriesMap {
+    read_countries_from(utils::data_source()).0
+}
+
+/// Same as `read_countries` but read the files of the given source and also
+/// return the number of malformed lines that were skipped, see `utils::Records`.
+///
+/// # Arguments
+///
+/// * `source` - Where to read `countries.txt` and `country_aliases.txt` from
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let (countries, skipped) = geo_rs::nodes::country::read_countries_from(geo_rs::utils::data_source());
+/// assert_eq!(countries.code_to_name.get("CA"), Some(&String::from("Canada")));
+/// assert_eq!(skipped, 0);
+/// ```
+pub fn read_countries_from(source: &DataSource) -> (CountriesMap, usize) {
     let mut name_to_code: HashMap<String, String> = HashMap::new();
     let mut code_to_name: HashMap<String, String> = HashMap::new();
-    for line in utils::read_lines("countries.txt") {
-        if let Ok(s) = line {
-            let parts: Vec<&str> = s.split(";").collect();
-            code_to_name.insert(parts[1].to_string(), parts[0].to_string());
-            name_to_code.insert(parts[0].to_string(), parts[1].to_string());
+    let mut code_to_iso: HashMap<String, (String, String)> = HashMap::new();
+    let mut alpha3_to_code: HashMap<String, String> = HashMap::new();
+    let mut numeric_to_code: HashMap<String, String> = HashMap::new();
+    let mut code_to_region: HashMap<String, (String, String)> = HashMap::new();
+    let mut code_to_dialing_code: HashMap<String, String> = HashMap::new();
+    let mut code_to_currency: HashMap<String, String> = HashMap::new();
+    let mut records = Records::new("countries.txt", 2);
+    for s in source
+        .read_lines("countries.txt")
+        .map(|line| utils::expect_line("countries.txt", line))
+    {
+        let parts = match records.split(&s) {
+            Some(parts) => parts,
+            None => continue,
+        };
+        code_to_name.insert(parts[1].to_string(), parts[0].to_string());
+        name_to_code.insert(parts[0].to_string(), parts[1].to_string());
+        if let (Some(alpha3), Some(numeric)) = (parts.get(2), parts.get(3)) {
+            code_to_iso.insert(
+                parts[1].to_string(),
+                (alpha3.to_string(), numeric.to_string()),
+            );
+            alpha3_to_code.insert(alpha3.to_string(), parts[1].to_string());
+            numeric_to_code.insert(numeric.to_string(), parts[1].to_string());
+        }
+        if let Some(continent) = parts.get(4).filter(|c| !c.trim().is_empty()) {
+            let region = parts
+                .get(5)
+                .map(|r| r.trim().to_string())
+                .unwrap_or_default();
+            code_to_region.insert(parts[1].to_string(), (continent.to_string(), region));
+        }
+        if let Some(dialing_code) = parts.get(6).filter(|d| !d.trim().is_empty()) {
+            code_to_dialing_code.insert(parts[1].to_string(), dialing_code.trim().to_string());
+        }
+        if let Some(currency) = parts.get(7).filter(|c| !c.trim().is_empty()) {
+            code_to_currency.insert(parts[1].to_string(), currency.trim().to_string());
+        }
+        // Input is matched after folding diacritics, keep an ASCII version of the name
+        let ascii_name = unidecode(&parts[0]);
+        if ascii_name != parts[0] {
+            name_to_code.insert(ascii_name, parts[1].to_string());
+        }
+    }
+    let mut skipped = records.skipped;
+    let mut aliases: Vec<(String, String)> = vec![];
+    let mut records = Records::new("country_aliases.txt", 2);
+    for s in source
+        .read_lines("country_aliases.txt")
+        .map(|line| utils::expect_line("country_aliases.txt", line))
+    {
+        if let Some(parts) = records.split(&s) {
+            // Aliases in other languages may have diacritics, e.g. "États-Unis"
+            aliases.push((unidecode(&parts[0]), parts[1].to_string()));
         }
     }
-    CountriesMap {
+    skipped += records.skipped;
+    let mut unions: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut records = Records::new("country_unions.txt", 2);
+    for s in source.read_optional_lines("country_unions.txt") {
+        if let Some(parts) = records.split(&s) {
+            unions
+                .entry(parts[0].to_string())
+                .or_default()
+                .insert(parts[1].to_string());
+        }
+    }
+    skipped += records.skipped;
+    let countries = CountriesMap {
         name_to_code,
         code_to_name,
-    }
+        aliases,
+        code_to_iso,
+        alpha3_to_code,
+        numeric_to_code,
+        code_to_region,
+        code_to_dialing_code,
+        code_to_currency,
+        unions,
+    };
+    (countries, skipped)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::mocks;
+    use std::path::Path;
 
     #[test]
     fn test_ca() {
@@ -277,6 +799,103 @@ mod tests {
         parser.countries.code_to_name.get("CA").unwrap();
     }
 
+    #[test]
+    fn test_continents_and_regions() {
+        let countries = read_countries();
+        let regions = ["NAMER", "LATAM", "EMEA", "APAC"];
+        for (code, name) in &countries.code_to_name {
+            let country = Country::new(code, name);
+            assert!(country.continent().is_some(), "{}", name);
+            match country.region() {
+                Some(region) => assert!(regions.contains(&region), "{}", name),
+                None => assert_eq!(country.continent(), Some("Antarctica")),
+            }
+        }
+        assert_eq!(Country::new("XX", "Nowhere").continent(), None);
+        assert_eq!(Country::new("IL", "Israel").region(), Some("EMEA"));
+        assert_eq!(Country::new("NZ", "New Zealand").region(), Some("APAC"));
+    }
+
+    #[test]
+    fn test_unions() {
+        let parser = Parser::new();
+        let eu: Vec<&String> = parser.countries.unions["EU"].iter().collect();
+        assert_eq!(eu.len(), 27);
+        for code in eu {
+            let country = parser.country_from_code(code).unwrap();
+            assert!(parser.is_eea(&country), "{}", code);
+        }
+        let switzerland = Country::new("CH", "Switzerland");
+        assert!(parser.is_schengen(&switzerland));
+        assert!(!parser.is_eea(&switzerland));
+        let ireland = Country::new("IE", "Ireland");
+        assert!(parser.is_eu(&ireland));
+        assert!(!parser.is_schengen(&ireland));
+        let source = DataSource::Dir(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/schema/current"),
+        );
+        let (countries, skipped) = read_countries_from(&source);
+        assert!(countries.unions.is_empty());
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn test_country_by_code() {
+        let parser = Parser::new();
+        for code in parser.countries.code_to_name.keys() {
+            let info = parser.country_by_code(code).unwrap();
+            assert!(info.dialing_code.unwrap().starts_with('+'), "{}", code);
+            if code != "AQ" {
+                assert_eq!(info.currency.unwrap().len(), 3, "{}", code);
+            }
+        }
+        let info = parser.country_by_code("124").unwrap();
+        assert_eq!(info.country, *CANADA);
+        assert_eq!(info.alpha3.as_deref(), Some("CAN"));
+        assert_eq!(info.dialing_code.as_deref(), CANADA.dialing_code());
+        assert_eq!(info.currency.as_deref(), Some("CAD"));
+        let source = DataSource::Dir(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/schema/current"),
+        );
+        let (countries, _) = read_countries_from(&source);
+        assert!(countries.code_to_currency.is_empty());
+    }
+
+    #[test]
+    fn test_read_countries_skips_malformed_lines() {
+        let source =
+            DataSource::Dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/malformed"));
+        let (countries, skipped) = read_countries_from(&source);
+        assert_eq!(skipped, 5);
+        assert_eq!(countries.code_to_name.len(), 2);
+        assert_eq!(
+            countries.name_to_code.get("Canada"),
+            Some(&String::from("CA"))
+        );
+        assert_eq!(
+            countries.aliases,
+            vec![(String::from("USA"), String::from("US"))]
+        );
+    }
+
+    #[test]
+    fn test_read_countries_from_csv() {
+        let source = DataSource::Dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/csv"));
+        let (countries, skipped) = read_countries_from(&source);
+        assert_eq!(skipped, 0);
+        let code = |name: &str| countries.name_to_code.get(name).cloned();
+        assert_eq!(code("Korea, Republic of"), Some(String::from("KR")));
+        assert_eq!(code("Cote d'Ivoire"), Some(String::from("CI")));
+        assert_eq!(
+            countries.code_to_iso.get("US"),
+            Some(&(String::from("USA"), String::from("840")))
+        );
+        assert_eq!(
+            countries.aliases[0],
+            (String::from("South Korea"), String::from("KR"))
+        );
+    }
+
     #[test]
     fn test_us() {
         let parser = Parser::new();
@@ -292,6 +911,24 @@ mod tests {
         assert_eq!(format!("{}", country), "US");
     }
 
+    #[test]
+    fn test_country_ordering() {
+        let mut countrys = vec![
+            Country::new("US", "United States"),
+            Country::new("CA", "Canada"),
+            Country::new("US", "United States"),
+        ];
+        countrys.sort();
+        countrys.dedup();
+        assert_eq!(
+            countrys,
+            vec![
+                Country::new("CA", "Canada"),
+                Country::new("US", "United States")
+            ]
+        );
+    }
+
     #[test]
     fn test_remove_country() {
         let parser = Parser::new();
@@ -320,29 +957,135 @@ mod tests {
         assert_eq!(location, String::from("Barcelona"));
     }
 
-    /// cargo test benchmark_fill_country -- --nocapture --ignored
     #[test]
-    #[ignore]
-    fn benchmark_fill_country() {
-        let n = 250;
+    fn test_remove_country_keeps_words_containing_code() {
+        let parser = Parser::new();
+        let country = UNITED_STATES.clone();
+        let mut location = String::from("HOUSTON, TX, US");
+        parser.remove_country(&country, &mut location);
+        assert_eq!(location, String::from("HOUSTON, TX"));
+        let mut location = String::from("AUGUSTA, GA, USA");
+        parser.remove_country(&country, &mut location);
+        assert_eq!(location, String::from("AUGUSTA, GA"));
+        let mut location = String::from("1 USA Parkway, Sparks, NV, USA");
+        parser.remove_country(&country, &mut location);
+        assert_eq!(location, String::from("1 USA Parkway, Sparks, NV"));
+        let mut location = String::from("1 USA PARKWAY, SPARKS, NV, USA");
+        parser.remove_country(&country, &mut location);
+        assert_eq!(location, String::from("1 USA PARKWAY, SPARKS, NV"));
+        let country = CANADA.clone();
+        let mut location = String::from("CASPER, CA");
+        parser.remove_country(&country, &mut location);
+        assert_eq!(location, String::from("CASPER"));
+        let mut location = String::from("Toronto, ON, Canadaville");
+        parser.remove_country(&country, &mut location);
+        assert_eq!(location, String::from("Toronto, ON, Canadaville"));
+    }
+
+    #[test]
+    fn test_remove_country_ignores_case_of_input() {
         let parser = Parser::new();
-        let before = std::time::Instant::now();
-        for _ in 0..n {
-            for country in mocks::get_mocks().keys() {
-                let mut location = Location {
-                    city: None,
-                    state: None,
-                    country: None,
-                    zipcode: None,
-                    address: None,
-                };
-                parser.fill_country(&mut location, &country);
+        let country = parser.country_from_code("FR").unwrap();
+        for input in ["PARIS FR", "Paris FR"] {
+            let mut location = String::from(input);
+            parser.remove_country(&country, &mut location);
+            assert_eq!(location.to_lowercase(), "paris");
+        }
+        for (upper, mixed) in [
+            ("PARIS FR", "Paris FR"),
+            (
+                "1 USA PARKWAY, SPARKS, NV, USA",
+                "1 USA Parkway, Sparks, NV, USA",
+            ),
+        ] {
+            assert_eq!(parser.parse_location(upper), parser.parse_location(mixed));
+        }
+    }
+
+    #[test]
+    fn test_fill_country_with_diacritics() {
+        let parser = Parser::new();
+        for input in ["Abidjan, Côte d'Ivoire", "Abidjan, Cote d'Ivoire"] {
+            let mut location = Location {
+                city: None,
+                state: None,
+                country: None,
+                zipcode: None,
+                address: None,
+                landmark: None,
+                alternatives: vec![],
+                warnings: vec![],
+                collapsed: vec![],
+                raw: String::new(),
+            };
+            parser.fill_country(&mut location, input);
+            let country = location.country.unwrap();
+            assert_eq!(country.code, "CI");
+            assert_eq!(country.name, "Côte d'Ivoire");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_fill_country_from_alias() {
+        let parser = Parser::new();
+        let mut inputs: HashMap<&str, Option<&str>> = HashMap::new();
+        inputs.insert("Austin, TX, U.S.A.", Some("US"));
+        inputs.insert("Austin, Texas, usa", Some("US"));
+        inputs.insert("London, Great Britain", Some("GB"));
+        inputs.insert("Manchester, England", Some("GB"));
+        inputs.insert("Amsterdam, Holland", Some("NL"));
+        inputs.insert("Seoul, South Korea", Some("KR"));
+        inputs.insert("Holland, MI", None);
+        inputs.insert("Munich, DEU", Some("DE"));
+        inputs.insert("Ottawa, ON, CAN", Some("CA"));
+        for (input, output) in inputs {
+            let mut location = Location {
+                city: None,
+                state: None,
+                country: None,
+                zipcode: None,
+                address: None,
+                landmark: None,
+                alternatives: vec![],
+                warnings: vec![],
+                collapsed: vec![],
+                raw: String::new(),
+            };
+            parser.fill_country(&mut location, input);
+            assert_eq!(
+                location.country.map(|c| c.code),
+                output.map(String::from),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_fill_country_with_country_bias() {
+        let default = Parser::new();
+        let us_first = Parser::with_options(crate::ParserOptions::new().country_bias(["US", "CA"]));
+        let cases = [
+            ("Springfield, CA", Some("CA"), None),
+            ("Springfield, ca", None, None),
+            ("Toronto, ON, CA", Some("CA"), Some("CA")),
+            ("CA, Ontario", Some("CA"), Some("CA")),
+            ("San Diego, CA", None, None),
+            ("Wilmington, DE", None, None),
+        ];
+        for (input, expected, expected_us_first) in cases {
+            for (parser, output) in [(&default, expected), (&us_first, expected_us_first)] {
+                let mut location = Location::default();
+                parser.fill_country(&mut location, input);
+                assert_eq!(
+                    location.country.map(|c| c.code),
+                    output.map(String::from),
+                    "input: {}",
+                    input
+                );
             }
         }
-        println!(
-            "Elapsed time: {:.2?}, {:.2?} each",
-            before.elapsed(),
-            before.elapsed() / (n * mocks::get_mocks().len() as u32)
-        );
     }
 }
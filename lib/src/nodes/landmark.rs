@@ -0,0 +1,243 @@
+use super::Location;
+use crate::utils::read_optional_lines;
+use crate::Parser;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt;
+
+lazy_static! {
+    /// Separators of segments a landmark is looked for in, e.g. "/" in
+    /// "Shemya/Eareckson Air Station" or "-" in "01713-Mall At Greece Ridge Center"
+    static ref SEPARATORS: Regex = Regex::new(r"[,;()/\-]").unwrap();
+}
+
+/// What a landmark is, see `Landmark`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LandmarkKind {
+    /// Military base or air station, e.g. "Eareckson Air Station"
+    Base,
+    /// Airport, e.g. "Pearson International Airport"
+    Airport,
+    /// Hospital or medical center, e.g. "Mount Sinai Hospital"
+    Hospital,
+    /// Campus of a university or a college, e.g. "Fanshawe College"
+    Campus,
+    /// Shopping mall or plaza, e.g. "Mall At Greece Ridge Center"
+    Mall,
+    /// Building or office complex, e.g. "Toronto Eaton Center"
+    Building,
+}
+
+impl LandmarkKind {
+    /// Name of the kind as it's written in `src/data/landmarks.txt`, e.g. "base"
+    pub fn code(&self) -> &'static str {
+        match self {
+            LandmarkKind::Base => "base",
+            LandmarkKind::Airport => "airport",
+            LandmarkKind::Hospital => "hospital",
+            LandmarkKind::Campus => "campus",
+            LandmarkKind::Mall => "mall",
+            LandmarkKind::Building => "building",
+        }
+    }
+
+    /// Kind by its name, see `LandmarkKind::code`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs::nodes::LandmarkKind;
+    /// assert_eq!(LandmarkKind::from_code("mall"), Some(LandmarkKind::Mall));
+    /// assert_eq!(LandmarkKind::from_code("castle"), None);
+    /// ```
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "base" => Some(LandmarkKind::Base),
+            "airport" => Some(LandmarkKind::Airport),
+            "hospital" => Some(LandmarkKind::Hospital),
+            "campus" => Some(LandmarkKind::Campus),
+            "mall" => Some(LandmarkKind::Mall),
+            "building" => Some(LandmarkKind::Building),
+            _ => None,
+        }
+    }
+}
+
+/// Campus, building or another landmark named in the input instead of
+/// or next to the city, e.g. "Eareckson Air Station" in
+/// "United States-Alaska-Shemya/Eareckson Air Station".
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Landmark {
+    /// Name of the landmark as it's written in the input
+    pub name: String,
+    pub kind: LandmarkKind,
+}
+
+impl Landmark {
+    pub fn new(name: &str, kind: LandmarkKind) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for Landmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name.trim())
+    }
+}
+
+/// Compile a landmark pattern, invalid ones are ignored with a warning.
+///
+/// # Arguments
+///
+/// * `pattern` - Regular expression matching a segment of the input, e.g. r"(?i)\bmall\b"
+/// * `kind` - Kind of the matching landmarks
+pub(crate) fn compile_landmark_pattern(
+    pattern: &str,
+    kind: LandmarkKind,
+) -> Option<(Regex, LandmarkKind)> {
+    match Regex::new(pattern) {
+        Ok(regex) => Some((regex, kind)),
+        Err(e) => {
+            warn!("Ignoring invalid landmark pattern {:?}: {}", pattern, e);
+            None
+        }
+    }
+}
+
+/// Read landmark patterns from `src/data/landmarks.txt`, one per line as "pattern;kind",
+/// see `LandmarkKind::code`. The pattern may contain semicolons, the kind is split off.
+/// Lines of unknown kinds and invalid patterns are ignored with a warning, custom
+/// data folders may leave the file out.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// use geo_rs::nodes::LandmarkKind;
+/// let patterns = geo_rs::nodes::landmark::read_landmark_patterns();
+/// assert!(patterns
+///     .iter()
+///     .any(|(p, kind)| p.is_match("Eareckson Air Station") && *kind == LandmarkKind::Base));
+/// ```
+pub fn read_landmark_patterns() -> Vec<(Regex, LandmarkKind)> {
+    read_optional_lines("landmarks.txt")
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| {
+            let (pattern, code) = line.rsplit_once(';')?;
+            match LandmarkKind::from_code(code) {
+                Some(kind) => compile_landmark_pattern(pattern, kind),
+                None => {
+                    warn!("Ignoring landmark pattern {:?} of unknown kind", line);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+impl Parser {
+    /// Find the first segment of the input that names a landmark, e.g. a campus,
+    /// a mall or an air station, see `src/data/landmarks.txt`. Segments that are
+    /// names of cities, states or countries, like "College Station", and segments
+    /// that special cases resolve, see `ParserBuilder::with_special_case`, aren't landmarks.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that's gonna be filled with a landmark
+    /// * `input` - Location string to search in
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::{LandmarkKind, Location};
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = Location::default();
+    /// parser.fill_landmark(&mut location, "United States-Alaska-Shemya/Eareckson Air Station");
+    /// let landmark = location.landmark.unwrap();
+    /// assert_eq!(landmark.name, String::from("Eareckson Air Station"));
+    /// assert_eq!(landmark.kind, LandmarkKind::Base);
+    /// ```
+    pub fn fill_landmark(&self, location: &mut Location, input: &str) {
+        if location.landmark.is_some() {
+            return;
+        }
+        let landmark = SEPARATORS
+            .split(input)
+            .map(|segment| segment.trim_matches(|c: char| c.is_whitespace() || c == '.'))
+            .filter(|segment| !self.is_known_name(segment))
+            .filter(|segment| {
+                !self
+                    .special_cases
+                    .iter()
+                    .any(|c| c.pattern.is_match(segment))
+            })
+            .find_map(|segment| {
+                let (_, kind) = self
+                    .landmark_patterns
+                    .iter()
+                    .find(|(pattern, _)| pattern.is_match(segment))?;
+                Some(Landmark::new(segment, *kind))
+            });
+        trace_event!("matched landmark", landmark = landmark);
+        location.landmark = landmark;
+    }
+}
+
+#[cfg(all(test, feature = "data-us"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_landmark() {
+        let parser = Parser::new();
+        for (input, output) in [
+            (
+                "01713-Mall At Greece Ridge Center",
+                Some(("Mall At Greece Ridge Center", LandmarkKind::Mall)),
+            ),
+            (
+                "Toronto (Toronto Eaton Center (ON)), ON, Canada",
+                Some(("Toronto Eaton Center", LandmarkKind::Building)),
+            ),
+            (
+                "Sunnybrook Hospital, Toronto",
+                Some(("Sunnybrook Hospital", LandmarkKind::Hospital)),
+            ),
+            ("College Station, TX", None),
+            ("Rockville Centre, NY", None),
+            ("Toronto, ON", None),
+        ] {
+            let mut location = Location::default();
+            parser.fill_landmark(&mut location, input);
+            let landmark = location
+                .landmark
+                .as_ref()
+                .map(|l| (l.name.as_str(), l.kind));
+            assert_eq!(landmark, output, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_landmarks() {
+        let parser = Parser::new();
+        let location = parser.parse_location("United States-Alaska-Shemya/Eareckson Air Station");
+        assert_eq!(location.to_string(), "Shemya, AK, US");
+        assert_eq!(location.landmark.unwrap().kind, LandmarkKind::Base);
+        let location = parser.parse_location("01713-Mall At Greece Ridge Center");
+        assert_eq!(location.city, None);
+        assert_eq!(location.landmark.unwrap().kind, LandmarkKind::Mall);
+        let parser = Parser::builder()
+            .with_special_case(r"(?i)\bgreece ridge\b", "Rochester", "NY", "US")
+            .build();
+        let location = parser.parse_location("01713-Mall At Greece Ridge Center");
+        assert_eq!(location.city.unwrap().name, "Rochester");
+        assert_eq!(location.landmark, None);
+        let parser = Parser::builder().without_stage("landmark").build();
+        let location = parser.parse_location("01713-Mall At Greece Ridge Center");
+        assert_eq!(location.landmark, None);
+    }
+}
@@ -1,66 +1,585 @@
-use super::{Address, City, Country, State, Zipcode};
+use super::{Address, City, Country, Landmark, LandmarkKind, State, Warning, Zipcode};
+use crate::utils;
+use crate::{ParseError, DEFAULT_PARSER};
+use bitflags::bitflags;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::str::FromStr;
+
+bitflags! {
+    /// Parts of a location to display, see `Location::display_parts`.
+    pub struct Parts: u8 {
+        const CITY = 0b00001;
+        const STATE = 0b00010;
+        const COUNTRY = 0b00100;
+        const ZIPCODE = 0b01000;
+        const ADDRESS = 0b10000;
+    }
+}
+
+/// Finest part a location names, from an address down to a country,
+/// see `Location::granularity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Granularity {
+    Address,
+    Zipcode,
+    City,
+    State,
+    Country,
+}
+
+impl Granularity {
+    /// Lowercase name of the granularity, e.g. "country" for `Country`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Granularity::Address => "address",
+            Granularity::Zipcode => "zipcode",
+            Granularity::City => "city",
+            Granularity::State => "state",
+            Granularity::Country => "country",
+        }
+    }
+}
+
+impl std::fmt::Display for Granularity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
 
 lazy_static! {
     static ref COMMAS: Regex = Regex::new(r"(, ){2,5}").unwrap();
+    static ref KEY_WORDS: Regex = Regex::new(r"[a-z0-9]+").unwrap();
 }
 
-#[derive(Debug, Clone, Hash, Eq)]
+#[derive(Debug, Clone, Default)]
 pub struct Location {
     pub city: Option<City>,
     pub state: Option<State>,
     pub country: Option<Country>,
     pub zipcode: Option<Zipcode>,
     pub address: Option<Address>,
+    /// Campus, building or another landmark named in the input, see `Parser::fill_landmark`
+    pub landmark: Option<Landmark>,
+    /// Less likely interpretations of the same input, most likely first
+    pub alternatives: Vec<Location>,
+    /// Doubtful decisions made while parsing, ordered by code
+    pub warnings: Vec<Warning>,
+    /// Repeated parts of the input that were collapsed into one while parsing,
+    /// e.g. "Canton" in "Canton, MA, Canton, MA"
+    pub collapsed: Vec<String>,
+    /// Original input the location was parsed from, so that it can be parsed
+    /// again, e.g. after upgrading the library. Empty unless parsed.
+    pub raw: String,
 }
 
-impl PartialEq for Location {
-    fn eq(&self, other: &Location) -> bool {
-        self.city == other.city
-            && self.state == other.state
-            && self.country == other.country
-            && self.zipcode == other.zipcode
-            && self.address == other.address
+/// One of possible interpretations of the input, see `Parser::parse_location_candidates`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredLocation {
+    pub location: Location,
+    /// Estimated probability of the interpretation, from 0 to 1
+    pub score: f64,
+}
+
+/// Builder for a `Location`, mostly useful in tests.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// use geo_rs::nodes::Location;
+/// let location = Location::builder()
+///     .with_city("Toronto")
+///     .with_state("ON", "Ontario")
+///     .with_country("CA", "Canada")
+///     .with_raw("Toronto, ON, CA")
+///     .build();
+/// let parser = geo_rs::Parser::new();
+/// assert_eq!(parser.parse_location("Toronto, ON, CA"), location);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LocationBuilder {
+    location: Location,
+}
+
+impl LocationBuilder {
+    pub fn with_city(mut self, name: &str) -> Self {
+        self.location.city = Some(City::new(name));
+        self
+    }
+
+    pub fn with_state(mut self, code: &str, name: &str) -> Self {
+        self.location.state = Some(State::new(code, name));
+        self
+    }
+
+    pub fn with_country(mut self, code: &str, name: &str) -> Self {
+        self.location.country = Some(Country::new(code, name));
+        self
+    }
+
+    pub fn with_zipcode(mut self, zipcode: &str) -> Self {
+        self.location.zipcode = Some(Zipcode::new(zipcode));
+        self
+    }
+
+    pub fn with_address(mut self, address: &str) -> Self {
+        self.location.address = Some(Address::new(address));
+        self
+    }
+
+    pub fn with_landmark(mut self, name: &str, kind: LandmarkKind) -> Self {
+        self.location.landmark = Some(Landmark::new(name, kind));
+        self
+    }
+
+    pub fn with_raw(mut self, raw: &str) -> Self {
+        self.location.raw = raw.to_string();
+        self
+    }
+
+    /// Add a warning, warnings are kept ordered by code.
+    pub fn with_warning(mut self, warning: Warning) -> Self {
+        self.location.warnings.push(warning);
+        self.location.warnings.sort();
+        self.location.warnings.dedup();
+        self
+    }
+
+    pub fn build(self) -> Location {
+        self.location
     }
 }
 
-impl std::fmt::Display for Location {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Location {
+    /// Return a builder of a location, see `LocationBuilder`.
+    pub fn builder() -> LocationBuilder {
+        LocationBuilder::default()
+    }
+
+    /// Check whether city, state and country are all known,
+    /// i.e. city isn't just taken from the input as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// assert!(parser.parse_location("Toronto, ON").is_full());
+    /// assert!(!parser.parse_location("Colleretto Giacosa").is_full());
+    /// ```
+    pub fn is_full(&self) -> bool {
+        self.city.is_some()
+            && self.state.is_some()
+            && self.country.is_some()
+            && !self.warnings.contains(&Warning::UnknownCity)
+    }
+
+    /// Finest part the location names, e.g. `Granularity::Country` for inputs that are
+    /// just a country like "Deutschland", `None` when nothing was recognized. A city
+    /// with an empty name, e.g. of input that is just a number, doesn't count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::Granularity;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Deutschland");
+    /// assert_eq!(location.granularity(), Some(Granularity::Country));
+    /// let location = parser.parse_location("Toronto, ON");
+    /// assert_eq!(location.granularity(), Some(Granularity::City));
+    /// ```
+    pub fn granularity(&self) -> Option<Granularity> {
+        if self.address.is_some() {
+            Some(Granularity::Address)
+        } else if self.zipcode.is_some() {
+            Some(Granularity::Zipcode)
+        } else if self.city.as_ref().is_some_and(|c| !c.name.is_empty()) {
+            Some(Granularity::City)
+        } else if self.state.is_some() {
+            Some(Granularity::State)
+        } else if self.country.is_some() {
+            Some(Granularity::Country)
+        } else {
+            None
+        }
+    }
+
+    /// Check whether every part of this location is also a part of the other one,
+    /// e.g. "Toronto, ON" is a subset of "Toronto, ON, CA, M4E3H8". City names
+    /// are compared case-insensitively, zipcodes without spaces.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Location that is supposed to be more specific
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let short = parser.parse_location("Toronto, ON");
+    /// let long = parser.parse_location("Toronto, ON, CA, M4E3H8");
+    /// assert!(short.is_subset_of(&long));
+    /// assert!(!long.is_subset_of(&short));
+    /// assert!(!parser.parse_location("Ottawa, ON").is_subset_of(&long));
+    /// ```
+    pub fn is_subset_of(&self, other: &Location) -> bool {
+        fn covered<T>(own: &Option<T>, other: &Option<T>, same: impl Fn(&T, &T) -> bool) -> bool {
+            match (own, other) {
+                (None, _) => true,
+                (Some(a), Some(b)) => same(a, b),
+                (Some(_), None) => false,
+            }
+        }
+        covered(&self.city, &other.city, |a, b| {
+            a.name.to_lowercase() == b.name.to_lowercase()
+        }) && covered(&self.state, &other.state, |a, b| a.code == b.code)
+            && covered(&self.country, &other.country, |a, b| a.code == b.code)
+            && covered(&self.zipcode, &other.zipcode, |a, b| {
+                a.to_string().to_uppercase() == b.to_string().to_uppercase()
+            })
+            && covered(&self.address, &other.address, |a, b| {
+                a.address.trim().to_lowercase() == b.address.trim().to_lowercase()
+            })
+    }
+
+    /// Fold two records of the same location into one, parts known to only one
+    /// of them are taken from it. When both know a part the more specific one wins,
+    /// e.g. ZIP+4 over ZIP code or a longer address over its beginning, otherwise
+    /// parts of this location win. Only warnings that both locations have are kept,
+    /// a doubt of one record is settled by the other one.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Another record of the same location
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let short = parser.parse_location("Toronto, ON");
+    /// let long = parser.parse_location("Toronto, CA, M4E3H8");
+    /// assert_eq!(short.merge(&long).to_string(), String::from("Toronto, ON, CA, M4E3H8"));
+    /// assert!(short.merge(&long).warnings.is_empty());
+    /// ```
+    pub fn merge(&self, other: &Location) -> Location {
+        fn longer<T: Clone>(
+            own: &Option<T>,
+            other: &Option<T>,
+            text: impl Fn(&T) -> String,
+        ) -> Option<T> {
+            match (own, other) {
+                (Some(a), Some(b)) => {
+                    let (a_text, b_text) = (text(a).to_lowercase(), text(b).to_lowercase());
+                    match b_text.len() > a_text.len() && b_text.starts_with(&a_text) {
+                        true => Some(b.clone()),
+                        false => Some(a.clone()),
+                    }
+                }
+                _ => own.clone().or_else(|| other.clone()),
+            }
+        }
+        let mut collapsed = self.collapsed.clone();
+        for c in &other.collapsed {
+            if !collapsed.contains(c) {
+                collapsed.push(c.clone());
+            }
+        }
+        Location {
+            city: self.city.clone().or_else(|| other.city.clone()),
+            state: self.state.clone().or_else(|| other.state.clone()),
+            country: self.country.clone().or_else(|| other.country.clone()),
+            zipcode: longer(&self.zipcode, &other.zipcode, |z| z.to_string()),
+            address: longer(&self.address, &other.address, |a| {
+                a.address.trim().to_string()
+            }),
+            landmark: self.landmark.clone().or_else(|| other.landmark.clone()),
+            alternatives: vec![],
+            warnings: self
+                .warnings
+                .iter()
+                .filter(|w| other.warnings.contains(w))
+                .cloned()
+                .collect(),
+            collapsed,
+            raw: match self.raw.is_empty() {
+                true => other.raw.clone(),
+                false => self.raw.clone(),
+            },
+        }
+    }
+
+    /// Key that identifies the location in databases, e.g. "us:mi:lansing:48911",
+    /// made of country code, state code, city and zipcode. Missing parts are
+    /// left empty, e.g. "ca:on:toronto:", address isn't a part of the key.
+    ///
+    /// The key is stable: it's lowercase ASCII and doesn't depend on letter case,
+    /// diacritics, punctuation or spacing of the parts, nor on `OutputCase`,
+    /// so "Montréal" and "MONTREAL", "Winston-Salem" and "Winston Salem" or
+    /// "Lee's Summit" and "Lees Summit" get the same key. The format won't change within a major version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Lansing, MI, US, 48911");
+    /// assert_eq!(location.canonical_key(), String::from("us:mi:lansing:48911"));
+    /// let location = parser.parse_location("Saint-Lin-Laurentides, QC J5M 0G3");
+    /// assert_eq!(location.canonical_key(), String::from("ca:qc:saint-lin-laurentides:j5m0g3"));
+    /// ```
+    pub fn canonical_key(&self) -> String {
+        let country = self.country.as_ref().map(|c| key_words(&c.code, ""));
+        let state = self.state.as_ref().map(|s| key_words(&s.code, ""));
+        let city = self.city.as_ref().map(|c| key_words(&c.name, "-"));
+        let zipcode = self.zipcode.as_ref().map(|z| key_words(&z.zipcode, ""));
+        [country, state, city, zipcode]
+            .iter()
+            .map(|part| part.as_deref().unwrap_or(""))
+            .collect::<Vec<&str>>()
+            .join(":")
+    }
+
+    /// Slug of the location for URLs, e.g. "toronto-on-ca", made of city, state code
+    /// and country code separated by dashes. Missing parts are skipped, zipcode
+    /// and address aren't a part of the slug.
+    ///
+    /// Like `canonical_key`, the slug is lowercase ASCII letters, digits and dashes
+    /// and doesn't depend on letter case, diacritics, punctuation or spacing,
+    /// words of the city are separated by dashes too, e.g. "winston-salem-nc-us".
+    /// The format won't change within a major version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Toronto, ON M4E 3H8");
+    /// assert_eq!(location.to_slug(), String::from("toronto-on-ca"));
+    /// let location = parser.parse_location("Montréal, QC");
+    /// assert_eq!(location.to_slug(), String::from("montreal-qc-ca"));
+    /// ```
+    pub fn to_slug(&self) -> String {
+        self.key_parts(false).join("-")
+    }
+
+    /// Compact identifier of the location for cache keys and file names, e.g.
+    /// "toronto_on_ca_m4e3h8", made of city, state code, country code and zipcode
+    /// separated by underscores. Missing parts are skipped, address isn't a part of it.
+    ///
+    /// Follows the rules of `to_slug`, but words of the city are separated by dashes,
+    /// so that parts stay apart, e.g. "winston-salem_nc_us", and the zipcode loses
+    /// spaces and dashes, e.g. "m4e3h8". The format won't change within a major version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Toronto, ON M4E 3H8");
+    /// assert_eq!(location.to_compact(), String::from("toronto_on_ca_m4e3h8"));
+    /// let location = parser.parse_location("Winston-Salem, NC");
+    /// assert_eq!(location.to_compact(), String::from("winston-salem_nc_us"));
+    /// ```
+    pub fn to_compact(&self) -> String {
+        self.key_parts(true).join("_")
+    }
+
+    /// Non-empty parts of `to_slug` and `to_compact` in their order.
+    fn key_parts(&self, with_zipcode: bool) -> Vec<String> {
+        let city = self.city.as_ref().map(|c| key_words(&c.name, "-"));
+        let state = self.state.as_ref().map(|s| key_words(&s.code, ""));
+        let country = self.country.as_ref().map(|c| key_words(&c.code, ""));
+        let zipcode = self
+            .zipcode
+            .as_ref()
+            .filter(|_| with_zipcode)
+            .map(|z| key_words(&z.zipcode, ""));
+        vec![city, state, country, zipcode]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .collect()
+    }
+
+    /// Display selected parts of the location the way `Display` does, e.g. "Toronto, ON"
+    /// for job boards that don't show the country and the zipcode. Missing parts are
+    /// skipped along with their commas.
+    ///
+    /// # Arguments
+    ///
+    /// * `parts` - Parts to display, see `Parts`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::Parts;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Toronto, ON M4E 3H8");
+    /// assert_eq!(location.display_parts(Parts::CITY | Parts::STATE), String::from("Toronto, ON"));
+    /// assert_eq!(location.display_parts(Parts::CITY | Parts::ZIPCODE), String::from("Toronto, M4E3H8"));
+    /// assert_eq!(location.display_parts(Parts::all()), location.to_string());
+    /// ```
+    pub fn display_parts(&self, parts: Parts) -> String {
         let city = self
             .city
-            .to_owned()
+            .as_ref()
+            .filter(|_| parts.contains(Parts::CITY))
             .map(|c| format!("{}", c))
-            .unwrap_or(String::from(""));
+            .unwrap_or_default();
         let state = self
             .state
-            .to_owned()
+            .as_ref()
+            .filter(|_| parts.contains(Parts::STATE))
             .map(|s| format!("{}", s))
-            .unwrap_or(String::from(""));
+            .unwrap_or_default();
         let country = self
             .country
-            .to_owned()
+            .as_ref()
+            .filter(|_| parts.contains(Parts::COUNTRY))
             .map(|c| format!("{}", c))
-            .unwrap_or(String::from(""));
+            .unwrap_or_default();
         let zipcode = self
             .zipcode
-            .to_owned()
+            .as_ref()
+            .filter(|_| parts.contains(Parts::ZIPCODE))
             .map(|z| format!("{}", z))
-            .unwrap_or(String::from(""));
+            .unwrap_or_default();
         let address = self
             .address
-            .to_owned()
+            .as_ref()
+            .filter(|_| parts.contains(Parts::ADDRESS))
             .map(|a| format!("{}", a))
-            .unwrap_or(String::from(""));
-        let mut location = format!("{}, {}, {}, {}, {}", city, state, country, zipcode, address);
-        location = COMMAS
+            .unwrap_or_default();
+        let location = format!("{}, {}, {}, {}, {}", city, state, country, zipcode, address);
+        COMMAS
             .replace_all(&location, ", ")
             .trim()
             .trim_end_matches(",")
             .trim_start_matches(", ")
             .trim()
-            .to_string();
-        write!(f, "{}", location)
+            .to_string()
+    }
+
+    /// Format location according to the template. Supported placeholders are
+    /// `{city}`, `{state}`, `{state_name}`, `{country}`, `{country_name}`, `{zipcode}`,
+    /// `{address}`, parts of the address `{street_number}`, `{street_name}`, `{unit}`
+    /// and `{landmark}`.
+    /// Placeholders of missing values are replaced with an empty string.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Output template, e.g. "{street_number} {street_name}\n{city}, {state}"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = parser.parse_location("Toronto, ON, CA");
+    /// location.address = Some(geo_rs::nodes::Address::new("100 Queen St W Unit 5"));
+    /// assert_eq!(
+    ///     location.format("{unit}-{street_number} {street_name}, {city} {state}"),
+    ///     String::from("5-100 Queen St W, Toronto ON")
+    /// );
+    /// ```
+    pub fn format(&self, template: &str) -> String {
+        let street = self
+            .address
+            .as_ref()
+            .map(|a| a.street())
+            .unwrap_or_default();
+        let values = [
+            ("{city}", self.city.as_ref().map(|c| c.to_string())),
+            ("{state}", self.state.as_ref().map(|s| s.to_string())),
+            ("{state_name}", self.state.as_ref().map(|s| s.name.clone())),
+            ("{country}", self.country.as_ref().map(|c| c.to_string())),
+            (
+                "{country_name}",
+                self.country.as_ref().map(|c| c.name.clone()),
+            ),
+            ("{zipcode}", self.zipcode.as_ref().map(|z| z.to_string())),
+            ("{address}", self.address.as_ref().map(|a| a.to_string())),
+            ("{street_number}", street.street_number),
+            ("{street_name}", street.street_name),
+            ("{unit}", street.unit),
+            ("{landmark}", self.landmark.as_ref().map(|l| l.to_string())),
+        ];
+        let mut output = template.to_string();
+        for (placeholder, value) in values {
+            output = output.replace(placeholder, &value.unwrap_or_default());
+        }
+        output
+    }
+}
+
+/// Lowercase ASCII letters and digits of the string, words joined with the separator,
+/// e.g. "winston-salem" of "Winston Salem". Apostrophes are dropped like in
+/// `utils::city_key`, so "Lee's Summit" and "Lees Summit" are both "lees-summit".
+fn key_words(s: &str, separator: &str) -> String {
+    KEY_WORDS
+        .find_iter(&utils::city_key(s))
+        .map(|m| m.as_str())
+        .collect::<Vec<&str>>()
+        .join(separator)
+}
+
+/// Parse location with a parser with default options, it's created on the first use.
+/// Use `Parser` directly to customize parsing.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs::nodes::Location;
+/// let location: Location = "Toronto, ON, CA".parse().unwrap();
+/// assert_eq!(location.to_string(), String::from("Toronto, ON, CA"));
+/// assert_eq!("".parse::<Location>(), Err(geo_rs::ParseError::NoMatch));
+/// ```
+impl FromStr for Location {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let location = DEFAULT_PARSER.parse_location(s);
+        match (&location.city, &location.state, &location.country) {
+            (None, None, None) => Err(ParseError::NoMatch),
+            _ => Ok(location),
+        }
+    }
+}
+
+/// Locations are equal when they name the same place, regardless of the input
+/// they were parsed from and of what was noticed while parsing it.
+impl PartialEq for Location {
+    fn eq(&self, other: &Location) -> bool {
+        self.city == other.city
+            && self.state == other.state
+            && self.country == other.country
+            && self.zipcode == other.zipcode
+            && self.address == other.address
+            && self.landmark == other.landmark
+    }
+}
+
+impl Eq for Location {}
+
+impl std::hash::Hash for Location {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.city.hash(state);
+        self.state.hash(state);
+        self.country.hash(state);
+        self.zipcode.hash(state);
+        self.address.hash(state);
+        self.landmark.hash(state);
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display_parts(Parts::all()))
     }
 }
 
@@ -70,6 +589,224 @@ mod tests {
     use crate::nodes::{CANADA, UNITED_STATES};
     use env_logger;
 
+    #[test]
+    fn test_location_builder() {
+        assert_eq!(Location::builder().build(), Location::default());
+        let location = Location::builder()
+            .with_city("Toronto")
+            .with_state("ON", "Ontario")
+            .with_country("CA", "Canada")
+            .with_zipcode("M4E 3J1")
+            .with_address("100 Queen St W")
+            .with_warning(Warning::InferredCountry)
+            .with_warning(Warning::InferredState)
+            .build();
+        assert_eq!(location.city, Some(City::new("Toronto")));
+        assert_eq!(location.state, Some(State::new("ON", "Ontario")));
+        assert_eq!(location.country, Some(CANADA.clone()));
+        assert_eq!(
+            location.warnings,
+            vec![Warning::InferredState, Warning::InferredCountry]
+        );
+        assert_eq!(
+            location.to_string(),
+            "Toronto, ON, CA, M4E3J1, 100 Queen St W"
+        );
+    }
+
+    #[test]
+    fn test_location_eq() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        let hash = |location: &Location| {
+            let mut hasher = DefaultHasher::new();
+            location.hash(&mut hasher);
+            hasher.finish()
+        };
+        let parser = crate::Parser::new();
+        let first = parser.parse_location("Boston, MA");
+        let second = parser.parse_location("boston ma");
+        assert_ne!(first.raw, second.raw);
+        assert_eq!(first, second);
+        assert_eq!(hash(&first), hash(&second));
+        let cambridge = parser.parse_location("Cambridge, MA");
+        assert_ne!(first, cambridge);
+    }
+
+    #[test]
+    fn test_is_subset_of() {
+        let toronto = Location::builder()
+            .with_city("Toronto")
+            .with_state("ON", "Ontario")
+            .build();
+        let full = Location::builder()
+            .with_city("TORONTO")
+            .with_state("ON", "Ontario")
+            .with_country("CA", "Canada")
+            .with_zipcode("M4E 3H8")
+            .build();
+        assert!(toronto.is_subset_of(&full));
+        assert!(toronto.is_subset_of(&toronto));
+        assert!(!full.is_subset_of(&toronto));
+        assert!(Location::default().is_subset_of(&toronto));
+        let zipcode = Location::builder().with_zipcode("m4e3h8").build();
+        assert!(zipcode.is_subset_of(&full));
+        let ottawa = Location::builder().with_city("Ottawa").build();
+        assert!(!ottawa.is_subset_of(&full));
+    }
+
+    #[test]
+    fn test_merge() {
+        let short = Location::builder()
+            .with_city("Canton")
+            .with_state("MA", "Massachusetts")
+            .with_zipcode("02021")
+            .with_warning(Warning::InferredCountry)
+            .with_warning(Warning::AmbiguousCity)
+            .build();
+        let long = Location::builder()
+            .with_city("Canton")
+            .with_country("US", "United States")
+            .with_zipcode("02021-1234")
+            .with_address("100 Main St")
+            .with_warning(Warning::AmbiguousCity)
+            .build();
+        let merged = short.merge(&long);
+        assert_eq!(
+            merged.to_string(),
+            "Canton, MA, US, 02021-1234, 100 Main St"
+        );
+        assert_eq!(merged.warnings, vec![Warning::AmbiguousCity]);
+        assert_eq!(long.merge(&short).to_string(), merged.to_string());
+        assert!(long.is_subset_of(&merged));
+        let other = Location::builder().with_city("Dedham").build();
+        assert_eq!(short.merge(&other).city, short.city);
+    }
+
+    #[test]
+    fn test_canonical_key() {
+        let parser = crate::Parser::new();
+        let upper = crate::Parser::with_options(
+            crate::ParserOptions::new().output_case(crate::OutputCase::Upper),
+        );
+        for (input, (_, _, _, _, _, output)) in crate::mocks::get_mocks() {
+            let location = parser.parse_location(input);
+            let key = location.canonical_key();
+            assert_eq!(
+                key,
+                upper.parse_location(input).canonical_key(),
+                "{}",
+                input
+            );
+            assert_eq!(key.matches(':').count(), 3, "{}", input);
+            // Formatted location is parsed into the same key
+            if location.to_string() == output {
+                assert_eq!(
+                    key,
+                    parser.parse_location(output).canonical_key(),
+                    "{}",
+                    input
+                );
+            }
+        }
+        let montreal = Location::builder()
+            .with_city("Montréal-Nord")
+            .with_state("QC", "Quebec")
+            .build();
+        let plain = Location::builder()
+            .with_city("MONTREAL NORD")
+            .with_state("QC", "Quebec")
+            .build();
+        assert_eq!(montreal.canonical_key(), plain.canonical_key());
+        assert_eq!(montreal.canonical_key(), ":qc:montreal-nord:");
+        // Apostrophes don't make another key, e.g. of names output before they were kept
+        for (with, without, key) in [
+            ("Lee's Summit", "Lees Summit", "lees-summit"),
+            ("Coeur d’Alene", "Coeur D Alene", "coeur-d-alene"),
+        ] {
+            let with = Location::builder().with_city(with).build();
+            let without = Location::builder().with_city(without).build();
+            assert_eq!(with.canonical_key(), format!("::{}:", key));
+            assert_eq!(with.canonical_key(), without.canonical_key());
+            assert_eq!(with.to_slug(), key);
+            assert_eq!(with.to_compact(), without.to_compact());
+        }
+    }
+
+    #[test]
+    fn test_slug_and_compact() {
+        let location = Location::builder()
+            .with_city("Montréal-Nord")
+            .with_state("QC", "Quebec")
+            .with_country("CA", "Canada")
+            .with_zipcode("H1G 2V5")
+            .build();
+        assert_eq!(location.to_slug(), "montreal-nord-qc-ca");
+        assert_eq!(location.to_compact(), "montreal-nord_qc_ca_h1g2v5");
+        let location = Location::builder()
+            .with_state("NY", "New York")
+            .with_zipcode("10001")
+            .build();
+        assert_eq!(location.to_slug(), "ny");
+        assert_eq!(location.to_compact(), "ny_10001");
+        assert_eq!(Location::default().to_slug(), "");
+        assert_eq!(Location::default().to_compact(), "");
+    }
+
+    #[test]
+    fn test_display_parts() {
+        let location = Location::builder()
+            .with_city("Toronto")
+            .with_state("ON", "Ontario")
+            .with_country("CA", "Canada")
+            .with_zipcode("M4E 3H8")
+            .with_address("100 Queen St W")
+            .build();
+        for (parts, output) in [
+            (Parts::CITY | Parts::STATE, "Toronto, ON"),
+            (Parts::STATE | Parts::COUNTRY, "ON, CA"),
+            (Parts::CITY | Parts::COUNTRY, "Toronto, CA"),
+            (Parts::ADDRESS | Parts::CITY, "Toronto, 100 Queen St W"),
+            (
+                Parts::all() - Parts::ZIPCODE,
+                "Toronto, ON, CA, 100 Queen St W",
+            ),
+            (Parts::empty(), ""),
+        ] {
+            assert_eq!(location.display_parts(parts), output, "{:?}", parts);
+        }
+        let location = Location::builder().with_state("ON", "Ontario").build();
+        assert_eq!(location.display_parts(Parts::CITY | Parts::STATE), "ON");
+    }
+
+    #[test]
+    fn test_granularity() {
+        let location = Location::builder()
+            .with_city("Toronto")
+            .with_state("ON", "Ontario")
+            .with_country("CA", "Canada")
+            .build();
+        assert_eq!(location.granularity(), Some(Granularity::City));
+        let location = Location {
+            zipcode: Some(Zipcode::new("M4E 3H8")),
+            ..location
+        };
+        assert_eq!(location.granularity(), Some(Granularity::Zipcode));
+        let location = Location::builder().with_state("ON", "Ontario").build();
+        assert_eq!(location.granularity(), Some(Granularity::State));
+        let location = Location::builder().with_country("CA", "Canada").build();
+        assert_eq!(location.granularity(), Some(Granularity::Country));
+        assert_eq!(Granularity::Country.to_string(), "country");
+        assert_eq!(Location::default().granularity(), None);
+        let location = Location {
+            city: Some(City::new("")),
+            ..Location::builder().with_state("ON", "Ontario").build()
+        };
+        assert_eq!(location.granularity(), Some(Granularity::State));
+        let location = DEFAULT_PARSER.parse_location("1713");
+        assert_eq!(location.granularity(), None);
+    }
+
     #[test]
     fn test_location_display() {
         env_logger::init();
@@ -84,6 +821,11 @@ mod tests {
             country: Some(CANADA.clone()),
             zipcode: None,
             address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
         };
         assert_eq!(format!("{}", location), "Toronto, ON, CA");
         let location = Location {
@@ -94,6 +836,11 @@ mod tests {
             country: None,
             zipcode: None,
             address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
         };
         assert_eq!(format!("{}", location), "Toronto");
         let location = Location {
@@ -104,6 +851,11 @@ mod tests {
             country: Some(UNITED_STATES.clone()),
             zipcode: None,
             address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
         };
         assert_eq!(format!("{}", location), "Sausalito, US");
         let location = Location {
@@ -116,6 +868,11 @@ mod tests {
                 zipcode: String::from("90E 717"),
             }),
             address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
         };
         assert_eq!(format!("{}", location), "Toronto, 90E717");
     }
@@ -1,13 +1,24 @@
 pub mod address;
 pub mod city;
+pub mod code;
 pub mod country;
+pub mod landmark;
 pub mod location;
 pub mod state;
+pub mod trie;
+pub mod warning;
 pub mod zipcode;
 
-pub use address::Address;
-pub use city::{read_cities, CitiesMap, City, CountryCities};
-pub use country::{read_countries, CountriesMap, Country, CANADA, UNITED_STATES};
-pub use location::Location;
+pub use address::{Address, AddressKind, StreetAddress};
+pub use city::{
+    read_cities, read_major_world_cities, read_world_cities, CitiesMap, City, CountryCities,
+    PhoneticIndex, WordIndex, WorldCities, WorldCity,
+};
+pub use code::{classify_codes, CodeKind};
+pub use country::{read_countries, CountriesMap, Country, CountryInfo, CANADA, UNITED_STATES};
+pub use landmark::{Landmark, LandmarkKind};
+pub use location::{Granularity, Location, LocationBuilder, Parts, ScoredLocation};
 pub use state::{read_states, CountryStates, State, StatesMap};
+pub use trie::CityTrie;
+pub use warning::Warning;
 pub use zipcode::Zipcode;
@@ -1,18 +1,54 @@
-use super::{Country, Location, CANADA, UNITED_STATES};
+use super::{City, CodeKind, CountriesMap, Country, Location};
+use crate::mapped::{self, DataStr};
 use crate::nodes::CitiesMap;
-use crate::{utils, Parser};
+use crate::utils::{self, DataSource, Records};
+use crate::{ParseError, Parser, DEFAULT_PARSER};
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt;
+use unidecode::unidecode;
 
-#[derive(Debug, Clone, Hash, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct State {
     pub name: String,
     pub code: String,
 }
 
-impl PartialEq for State {
-    fn eq(&self, other: &State) -> bool {
-        self.name == other.name && self.code == other.code
+impl State {
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let state = geo_rs::nodes::State::new("ON", "Ontario");
+    /// assert_eq!(state.code, String::from("ON"));
+    /// assert_eq!(state.name, String::from("Ontario"));
+    /// ```
+    pub fn new(code: &str, name: &str) -> Self {
+        Self {
+            code: code.to_string(),
+            name: name.to_string(),
+        }
+    }
+}
+
+/// Resolve a US state or a Canadian province by its code.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs::nodes::State;
+/// use std::convert::TryFrom;
+/// assert_eq!(State::try_from("on"), Ok(State::new("ON", "Ontario")));
+/// assert_eq!(State::try_from("XX"), Err(geo_rs::ParseError::NoMatch));
+/// ```
+impl TryFrom<&str> for State {
+    type Error = ParseError;
+
+    fn try_from(code: &str) -> Result<Self, Self::Error> {
+        DEFAULT_PARSER
+            .state_from_code(&None, &code.trim().to_uppercase())
+            .ok_or(ParseError::NoMatch)
     }
 }
 
@@ -22,6 +58,24 @@ impl fmt::Display for State {
     }
 }
 
+/// States are ordered by code, then by name.
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.code
+            .cmp(&other.code)
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Byte range of a mention of a state in the input
+type Span = (usize, usize);
+
 impl Parser {
     /// Parse location string and try to extract state out of it.
     ///
@@ -41,6 +95,11 @@ impl Parser {
     ///     country: None,
     ///     zipcode: None,
     ///     address: None,
+    ///     landmark: None,
+    ///     alternatives: vec![],
+    ///     warnings: vec![],
+    ///     collapsed: vec![],
+    ///     raw: String::new(),
     /// };
     /// parser.fill_state(&mut location, "Toronto, ON, CA");
     /// let state = location.state.unwrap();
@@ -48,120 +107,140 @@ impl Parser {
     /// assert_eq!(state.name, String::from("Ontario"));
     /// ```
     pub fn fill_state(&self, location: &mut Location, input: &str) {
+        self.detect_state(location, input);
+    }
+
+    /// Same as `fill_state` but also return the byte range of the input that
+    /// mentions the state, so that exactly that mention can be removed later.
+    /// The range is `None` when no state is found, when the input has characters
+    /// with diacritics, or when the state name is a part of a city name,
+    /// e.g. "Colorado" of "Colorado Springs".
+    pub(crate) fn detect_state(&self, location: &mut Location, input: &str) -> Option<Span> {
+        stage!("state", input);
+        let ascii = unidecode(input);
+        let same_bytes = ascii == input;
+        let input = &ascii;
         if input.chars().count() == 0 {
-            return;
+            return None;
         }
         if location.state.is_some() {
-            return;
+            return None;
         }
-        let as_lowercase = input.to_lowercase().to_string();
         let mut parts = utils::split(input);
         parts.dedup();
-        let mut parts_lowercase = utils::split(&as_lowercase);
-        parts_lowercase.dedup();
+        // State names are matched as whole words, so "Indianapolis" isn't Indiana
+        let has_name = |name: &str| !utils::find_words(input, name).is_empty();
+        // Spans are only meaningful when the input is the string they're applied to
+        let span = |span: Option<Span>| span.filter(|_| same_bytes);
         let countries = match &location.country {
             Some(c) => vec![c.clone()],
-            None => vec![UNITED_STATES.clone(), CANADA.clone()],
+            None => self.candidate_countries(&None),
         };
 
-        // Search by a full match of input and state name
+        if let Some((_, state, country)) = self.state_after_city(&parts, &countries) {
+            trace_event!("matched state code after city", state = state.code);
+            let code = parts.last().copied().unwrap_or_default();
+            location.state = Some(state);
+            if location.country.is_none() {
+                location.country = Some(country);
+            }
+            return span(utils::find_token(input, code).last().copied());
+        }
+
+        // Search by a full match of input and state name, the longest name wins,
+        // e.g. "West Virginia" over "Virginia"
+        let mut found: Option<(State, &Country)> = None;
         for c in &countries {
             let default = CitiesMap::default();
             let country_cities = self.cities.get(&c.code).unwrap_or(&default);
-            // also split by ":" and take the second part
-            let city_names = country_cities
-                .cities_by_state
-                .values()
-                .flatten()
-                .collect::<Vec<_>>();
             if let Some(states) = self.states.get(&c.code) {
                 for (code, name) in &states.code_to_name {
                     // check if state name isn't a city
-                    if city_names.contains(&&name.to_string().to_lowercase()) {
+                    if country_cities
+                        .states_by_city
+                        .contains_key(&name.to_lowercase())
+                    {
                         continue;
                     }
-                    if as_lowercase.contains(&name.to_lowercase()) {
-                        location.state = Some(State {
-                            code: code.clone(),
-                            name: name.clone(),
-                        });
-                        if location.country.is_none() {
-                            location.country = Some(c.clone());
-                        }
-                        return;
+                    let longer = found
+                        .as_ref()
+                        .is_none_or(|(s, _)| name.len() > s.name.len());
+                    if longer && has_name(name) {
+                        found = Some((State::new(code, name), c));
                     }
                 }
             }
         }
+        if let Some((state, country)) = found {
+            let mention = self.state_span(&state, country, input);
+            location.state = Some(state);
+            if location.country.is_none() {
+                location.country = Some(country.clone());
+            }
+            return span(mention);
+        }
         // Search by input containing state code or state name
-        let mut candidates: Vec<(State, Country)> = vec![];
+        let mut candidates: Vec<(State, Country, Option<Span>)> = vec![];
         for c in &countries {
             if let Some(states) = self.states.get(&c.code) {
                 for (code, name) in &states.code_to_name {
-                    for part in &parts {
-                        if code == &part.to_string() {
-                            let state = State {
-                                code: code.clone(),
-                                name: name.clone(),
-                            };
-                            candidates.push((state, c.clone()));
-                        }
+                    if parts.contains(&code.as_str()) {
+                        let mention = utils::find_token(input, code).last().copied();
+                        candidates.push((State::new(code, name), c.clone(), mention));
                     }
-                    if name.split_whitespace().all(|s| {
-                        return parts_lowercase.contains(&s.to_lowercase().as_str());
-                    }) {
-                        let state = State {
-                            code: code.clone(),
-                            name: name.clone(),
-                        };
-                        candidates.push((state, c.clone()));
+                    if has_name(name) {
+                        let state = State::new(code, name);
+                        let mention = self.state_span(&state, c, input);
+                        candidates.push((state, c.clone(), mention));
                     }
                 }
             };
         }
-        let mut candidates_deduped: Vec<(State, Country)> = vec![];
-        for (state, country) in &candidates {
-            if !candidates_deduped.contains(&(state.clone(), country.clone())) {
-                candidates_deduped.push((state.clone(), country.clone()));
+        // A name that's a part of another candidate's name isn't a candidate,
+        // e.g. "Virginia" of "West Virginia", unless it's written as a code
+        let is_covered = |state: &State| {
+            !parts.contains(&state.code.as_str())
+                && candidates.iter().any(|(other, _, _)| {
+                    other.name.len() > state.name.len()
+                        && !utils::find_words(&other.name, &state.name).is_empty()
+                })
+        };
+        let mut candidates_deduped: Vec<(State, Country, Option<Span>)> = vec![];
+        for (state, country, mention) in &candidates {
+            if is_covered(state) {
+                continue;
+            }
+            if !candidates_deduped
+                .iter()
+                .any(|(s, c, _)| s == state && c == country)
+            {
+                candidates_deduped.push((state.clone(), country.clone(), *mention));
             }
         }
-        let country_codes: Vec<String> = self.countries.code_to_name.keys().cloned().collect();
         // When analyzing locations such as `Sherwood Park, AB, CA`
         // we may end up having more than one state, in that case
         // use the one that doesn't look like a country
-        match candidates_deduped.len() {
-            0 => (),
-            1 => {
-                let s = candidates_deduped.first().unwrap().0.clone();
-                let c = candidates_deduped.first().unwrap().1.clone();
-                location.state = Some(s);
-                if location.country.is_none() {
-                    location.country = Some(c);
-                }
-                // if !country_codes.contains(&s.code) || location.country == Some(c.clone()) {
-                //     location.state = Some(s);
-                //     if location.country.is_none() {
-                //         location.country = Some(c);
-                //     }
-                // }
-            }
+        let chosen = match candidates_deduped.len() {
+            0 => None,
+            1 => candidates_deduped.first().cloned(),
             _ => {
-                let first_candidate_state = candidates_deduped.first().unwrap().0.clone();
-                let first_candidate_country = candidates_deduped.first().unwrap().1.clone();
-
-                let mut filtered_candidates: Vec<(State, Country)> = match &location.country {
-                    Some(_) => candidates_deduped.clone(),
-                    None => candidates_deduped
-                        .into_iter()
-                        .filter(|(x, _)| !country_codes.contains(&x.code))
-                        .collect(),
-                };
+                let first_candidate = candidates_deduped.first().cloned();
+                let mut filtered_candidates: Vec<(State, Country, Option<Span>)> =
+                    match &location.country {
+                        Some(_) => candidates_deduped,
+                        None => candidates_deduped
+                            .into_iter()
+                            .filter(|(x, _, _)| {
+                                !self.code_kind(&x.code).contains(CodeKind::COUNTRY_CODE)
+                            })
+                            .collect(),
+                    };
                 // [(State { name: "Washington", code: "WA" }, Country { name: "United States", code: "US" }), (State { name: "Pennsylvania", code: "PA" }, Country { name: "United States", code: "US" })]
                 // Iterate over candidates and choose more likely state: if one candidate has name in the input string and
                 // another candidate has code in the input string pick the second one because state is usually written as code
                 filtered_candidates.sort_by(|a, b| {
-                    let a_state_code_in_str = as_lowercase.contains(&a.0.code.to_lowercase());
-                    let b_state_code_in_str = as_lowercase.contains(&b.0.code.to_lowercase());
+                    let a_state_code_in_str = parts.contains(&a.0.code.as_str());
+                    let b_state_code_in_str = parts.contains(&b.0.code.as_str());
 
                     if a_state_code_in_str && !b_state_code_in_str {
                         return std::cmp::Ordering::Less;
@@ -170,8 +249,8 @@ impl Parser {
                         return std::cmp::Ordering::Greater;
                     }
 
-                    let a_state_name_in_str = as_lowercase.contains(&a.0.name.to_lowercase());
-                    let b_state_name_in_str = as_lowercase.contains(&b.0.name.to_lowercase());
+                    let a_state_name_in_str = has_name(&a.0.name);
+                    let b_state_name_in_str = has_name(&b.0.name);
 
                     if a_state_name_in_str && !b_state_name_in_str {
                         return std::cmp::Ordering::Greater;
@@ -180,32 +259,104 @@ impl Parser {
                         return std::cmp::Ordering::Less;
                     }
 
-                    std::cmp::Ordering::Equal
+                    // State usually follows the city, so the later mention wins,
+                    // e.g. "Ohio" of "Washington Court House, Ohio"
+                    let a_start = a.2.map(|(start, _)| start);
+                    let b_start = b.2.map(|(start, _)| start);
+                    b_start.cmp(&a_start)
                 });
-
-                if filtered_candidates.len() == 1 {
-                    location.state = Some(filtered_candidates.first().unwrap().0.clone());
-                    if location.country.is_none() {
-                        location.country = Some(filtered_candidates.first().unwrap().1.clone());
-                    }
-                }
-                if filtered_candidates.len() == 0 {
-                    // pick first candidate
-                    location.state = Some(first_candidate_state);
-                    if location.country.is_none() {
-                        location.country = Some(first_candidate_country);
-                    }
-                }
-                if filtered_candidates.len() > 1 {
-                    let first_candidate = filtered_candidates.first().unwrap();
-                    location.state = Some(first_candidate.0.clone());
-                    if location.country.is_none() {
-                        location.country = Some(first_candidate.1.clone());
-                    }
-                }
+                // Pick the first candidate when all of them look like countries
+                filtered_candidates.into_iter().next().or(first_candidate)
             }
+        };
+        let (state, country, mention) = chosen?;
+        location.state = Some(state);
+        if location.country.is_none() {
+            location.country = Some(country);
         }
         utils::decode(location);
+        span(mention)
+    }
+
+    /// Byte range of the last mention of the state name in the input that isn't
+    /// a part of a city of the state, e.g. "Colorado" of "Colorado Springs".
+    /// A city named after the state counts only when it starts the input,
+    /// so "New York" stays in "New York" but not in "Albany, New York".
+    fn state_name_span(&self, state: &State, country: &Country, input: &str) -> Option<Span> {
+        let name = state.name.to_lowercase();
+        let cities: Vec<&String> = self
+            .cities
+            .get(&country.code)
+            .and_then(|c| c.cities_by_state.get(&state.code))
+            .map(|cities| {
+                cities
+                    .iter()
+                    .filter(|city| {
+                        city.len() >= name.len() && !utils::find_words(city, &name).is_empty()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        utils::find_words(input, &name)
+            .into_iter()
+            .rev()
+            .find(|&(start, end)| {
+                !cities.iter().any(|city| {
+                    utils::find_words(input, city)
+                        .iter()
+                        .any(|&(s, e)| s <= start && end <= e && (e - s > end - start || s == 0))
+                })
+            })
+    }
+
+    /// Find the state of "<city> <state code>" inputs, e.g. "Kansas City MO",
+    /// where the code ends the input and the words right before it are a city
+    /// of that state. Return the lowercase city along with the state. The longest such city wins, so "Main St Kansas City MO"
+    /// works too, while "Kansas" alone would be taken for a state name.
+    ///
+    /// # Arguments
+    ///
+    /// * `parts` - Words of the input
+    /// * `countries` - Countries to search the state in
+    pub(crate) fn state_after_city(
+        &self,
+        parts: &[&str],
+        countries: &[Country],
+    ) -> Option<(String, State, Country)> {
+        let (code, city_parts) = parts.split_last()?;
+        if code.len() != 2 {
+            return None;
+        }
+        let code = code.to_uppercase();
+        for c in countries {
+            let name = match self
+                .states
+                .get(&c.code)
+                .and_then(|s| s.code_to_name.get(&code))
+            {
+                Some(name) => name,
+                None => continue,
+            };
+            let states_by_city = match self.cities.get(&c.code) {
+                Some(cities) => &cities.states_by_city,
+                None => continue,
+            };
+            let city = (0..city_parts.len())
+                .map(|start| city_parts[start..].join(" ").to_lowercase())
+                .find(|city| {
+                    states_by_city
+                        .get(city)
+                        .is_some_and(|states| states.contains(&code))
+                });
+            if let Some(city) = city {
+                let state = State {
+                    code: code.clone(),
+                    name: name.clone(),
+                };
+                return Some((city, state, c.clone()));
+            }
+        }
+        None
     }
 
     /// Remove state from location string.
@@ -233,58 +384,207 @@ impl Parser {
     /// assert_eq!(location, String::from("Los Angeles, US"));
     /// ```
     pub fn remove_state(&self, state: &State, country: &Country, input: &mut String) {
-        let input_raw = input.clone();
-        // first of all, remove state code from the input string
-        // make sure to not remove parts, e.g. for location
-        // Washington-20340-DCCL we want to keep DCCL untouched
-        // without removing DC out of it
-        *input = input
-            .split_whitespace()
-            .filter(|s| s != &state.code.as_str())
-            .collect::<Vec<_>>()
-            .join(" ");
-        if let Some(p) = input.to_lowercase().find(&state.name.to_lowercase()) {
-            // Easy cases with the same state and city "New York, NY, US"
-            if !utils::split(&input_raw).contains(&state.code.as_str()) {
-                // remove state name only if it's not a part of cities
-                // for example, when we parse "Colorado Springs, CO, US"
-                // we want to remove "CO" but not "Colorado" because it's a city
-                if let Some(country_cities) = self.cities.get(&country.code) {
-                    if let Some(state_cities) = country_cities.cities_by_state.get(&state.code) {
-                        if state_cities.iter().all(|s| {
-                            let parts = s.split_whitespace().collect::<Vec<_>>();
-                            state
-                                .name
-                                .to_lowercase()
-                                .split_whitespace()
-                                .all(|s| !parts.contains(&s))
-                        }) || !input.starts_with(&state.name)
-                        {
-                            input.replace_range(p..p + state.name.chars().count(), "");
-                        }
-                    }
-                }
-            }
+        if let Some(span) = self.state_span(state, country, input) {
+            input.replace_range(span.0..span.1, "");
         }
-        if utils::split(input).contains(&state.code.as_str()) {
-            if let Some(p) = input.find(&state.code) {
-                input.replace_range(p..p + state.code.chars().count(), "");
+        self.clean(input);
+        trace_event!("removed state", remainder = input);
+    }
+
+    /// Byte range of the mention of the state in the input. A standalone code goes first,
+    /// "ON" in "RiCHmONd HiLL-ON" is a part of the city, "DC" of "DCCL" isn't a code.
+    /// Otherwise the last mention of the name that isn't a part of a city name,
+    /// so nothing is removed from "New York Mills" or "Colorado Springs".
+    fn state_span(&self, state: &State, country: &Country, input: &str) -> Option<Span> {
+        if let Some(&span) = utils::find_token(input, &state.code).last() {
+            return Some(span);
+        }
+        self.state_name_span(state, country, input)
+    }
+
+    /// Replace names of states in other languages with state codes, so that
+    /// "Vancouver, Colombie-Britannique" becomes "Vancouver, BC",
+    /// see `src/data/{country}/state_names.txt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string without diacritics
+    pub(crate) fn translate_state_names(&self, input: &mut String) {
+        for states in self.states.values() {
+            for (name, code) in &states.localized_names {
+                let ranges = utils::find_token(&input.to_lowercase(), name);
+                for (start, end) in ranges.into_iter().rev() {
+                    input.replace_range(start..end, code);
+                }
             }
         }
-        utils::clean(input);
-        debug!("after removing state: {}", input);
     }
 
+    /// Infer country of the location from its state, codes shared by several
+    /// countries resolve to the first of them in `Parser::state_countries`.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location with a state
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = geo_rs::nodes::Location {
+    ///     state: parser.state_from_code(&None, "ON"),
+    ///     ..Default::default()
+    /// };
+    /// parser.fill_country_from_state(&mut location);
+    /// assert_eq!(location.country.unwrap().code, String::from("CA"));
+    /// ```
     pub fn fill_country_from_state(&self, location: &mut Location) {
         if let Some(s) = &location.state {
-            for country in utils::get_countries(&None) {
-                if let Some(country_states) = self.states.get(&country.code) {
-                    if country_states.code_to_name.get(&s.code).is_some() {
-                        location.country = Some(country.clone());
-                    }
-                }
+            if let Some(country) = self
+                .state_countries
+                .iter()
+                .find(|c| self.is_state_of(&s.code, &c.code))
+            {
+                location.country = Some(country.clone());
+            }
+        }
+    }
+
+    /// Return countries that have states data ordered by priority:
+    /// `ParserOptions::default_countries` first, then the rest alphabetically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let countries = parser.state_countries();
+    /// assert_eq!(countries[0].code, String::from("US"));
+    /// assert_eq!(countries[1].code, String::from("CA"));
+    /// ```
+    pub fn state_countries(&self) -> &[Country] {
+        &self.state_countries
+    }
+
+    /// Check whether the city belongs to the state according to the dataset,
+    /// city names are compared case-insensitively and without diacritics.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - State to search the city in
+    /// * `city` - City to look for
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::nodes::{City, State};
+    /// let parser = geo_rs::Parser::new();
+    /// let quebec = State::new("QC", "Quebec");
+    /// assert!(parser.state_contains_city(&quebec, &City::new("Montréal")));
+    /// assert!(!parser.state_contains_city(&quebec, &City::new("Toronto")));
+    /// ```
+    pub fn state_contains_city(&self, state: &State, city: &City) -> bool {
+        let name = utils::city_key(&city.name);
+        self.cities.values().any(|cities| {
+            cities
+                .cities_by_state
+                .get(&state.code)
+                .is_some_and(|c| c.contains(&name))
+        })
+    }
+
+    /// Find states of every country by their code or name, codes and names
+    /// are compared case-insensitively and names also without diacritics.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - State code or name, e.g. "ON" or "ontario"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let locations: Vec<String> = parser.lookup_states("ontario").iter().map(|l| l.to_string()).collect();
+    /// assert_eq!(locations, vec![String::from("ON, CA")]);
+    /// assert!(parser.lookup_states("Atlantis").is_empty());
+    /// ```
+    pub fn lookup_states(&self, query: &str) -> Vec<Location> {
+        let code = query.trim().to_uppercase();
+        let name = unidecode(query.trim()).to_lowercase();
+        let mut locations = vec![];
+        for country in &self.state_countries {
+            let states = match self.states.get(&country.code) {
+                Some(s) => s,
+                None => continue,
+            };
+            let mut found: Vec<State> = states
+                .code_to_name
+                .iter()
+                .filter(|(c, n)| **c == code || unidecode(n).to_lowercase() == name)
+                .map(|(c, n)| State::new(c, n))
+                .collect();
+            found.sort();
+            for state in found {
+                locations.push(Location {
+                    state: Some(state),
+                    country: Some(country.clone()),
+                    ..Location::default()
+                });
             }
         }
+        locations
+    }
+
+    /// Return a US state that matches the given FIPS code.
+    ///
+    /// # Arguments
+    ///
+    /// * `fips` - Two-digit FIPS code of the state, e.g. "06"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let state = parser.state_from_fips("06").unwrap();
+    /// assert_eq!(state.code, String::from("CA"));
+    /// assert_eq!(state.name, String::from("California"));
+    /// ```
+    pub fn state_from_fips(&self, fips: &str) -> Option<State> {
+        let states = self.states.get("US")?;
+        let code = states.fips_to_code.get(fips.trim())?;
+        states.code_to_name.get(code).map(|name| State {
+            code: code.clone(),
+            name: name.clone(),
+        })
+    }
+
+    /// Return two-digit FIPS code of the state, only US states
+    /// and territories have FIPS codes.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - State of a parsed location
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let state = geo_rs::nodes::State::new("TX", "Texas");
+    /// assert_eq!(parser.state_fips(&state), Some("48"));
+    /// let state = geo_rs::nodes::State::new("ON", "Ontario");
+    /// assert_eq!(parser.state_fips(&state), None);
+    /// ```
+    pub fn state_fips(&self, state: &State) -> Option<&str> {
+        let states = self.states.get("US")?;
+        states
+            .code_to_fips
+            .get(state.code.trim())
+            .map(|f| f.as_str())
     }
 
     /// Return a State struct that match the given state code.
@@ -313,7 +613,7 @@ impl Parser {
     pub fn state_from_code(&self, country: &Option<Country>, input: &str) -> Option<State> {
         let countries = match country {
             Some(c) => vec![c.clone()],
-            None => vec![UNITED_STATES.clone(), CANADA.clone()],
+            None => self.state_countries.clone(),
         };
         for c in &countries {
             if let Some(states) = self.states.get(&c.code) {
@@ -331,16 +631,27 @@ impl Parser {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary-data", derive(serde::Serialize, serde::Deserialize))]
 pub struct StatesMap {
     pub code_to_name: HashMap<String, String>,
     pub name_to_code: HashMap<String, String>,
+    /// FIPS codes of states, only known for US
+    pub code_to_fips: HashMap<String, String>,
+    pub fips_to_code: HashMap<String, String>,
+    /// Lowercase ASCII names of states in other languages and their codes,
+    /// longer names first, e.g. ("colombie-britannique", "BC")
+    pub localized_names: Vec<(String, String)>,
+    /// Ranges of first characters of postal codes and their states,
+    /// e.g. ("010", "027", "MA") or ("K", "P", "ON"), borrowed from
+    /// the mapped file with `mmap` feature
+    pub zipcode_prefixes: Vec<(DataStr, DataStr, DataStr)>,
 }
 
 pub type CountryStates = HashMap<String, StatesMap>;
 
-/// Read US and CA states GEO data and create a map between
-/// state names and state abbreviations and vice-versa.
+/// Read states GEO data of every country of `utils::data_countries`
+/// and create a map between state names and state abbreviations and vice-versa.
 ///
 /// # Examples
 ///
@@ -349,54 +660,201 @@ pub type CountryStates = HashMap<String, StatesMap>;
 /// let states = geo_rs::nodes::read_states();
 /// ```
 pub fn read_states() -> HashMap<String, StatesMap> {
+    read_states_from(utils::data_source()).0
+}
+
+/// Same as `read_states` but read the files of the given source and also
+/// return the number of malformed lines that were skipped, see `utils::Records`.
+///
+/// # Arguments
+///
+/// * `source` - Where to read `{country}/states.txt` and the rest from
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let (states, skipped) = geo_rs::nodes::state::read_states_from(geo_rs::utils::data_source());
+/// assert_eq!(states.contains_key("US"), cfg!(feature = "data-us"));
+/// assert_eq!(skipped, 0);
+/// ```
+pub fn read_states_from(source: &DataSource) -> (HashMap<String, StatesMap>, usize) {
     let mut data: HashMap<String, StatesMap> = HashMap::new();
-    for country in ["US", "CA"].iter() {
+    let mut skipped = 0;
+    for country in source.countries() {
         let filename = format!("{}/{}.txt", &country, "states");
         let mut name_to_code: HashMap<String, String> = HashMap::new();
         let mut code_to_name: HashMap<String, String> = HashMap::new();
-        for line in utils::read_lines(&filename) {
-            if let Ok(s) = line {
-                let parts: Vec<&str> = s.split(";").collect();
-                name_to_code.insert(parts[1].to_string(), parts[0].to_string());
-                code_to_name.insert(parts[0].to_string(), parts[1].to_string());
+        let mut code_to_fips: HashMap<String, String> = HashMap::new();
+        let mut fips_to_code: HashMap<String, String> = HashMap::new();
+        let mut records = Records::new(&filename, 2);
+        for s in source
+            .read_lines(&filename)
+            .map(|line| utils::expect_line(&filename, line))
+        {
+            let parts = match records.split(&s) {
+                Some(parts) => parts,
+                None => continue,
+            };
+            name_to_code.insert(parts[1].to_string(), parts[0].to_string());
+            code_to_name.insert(parts[0].to_string(), parts[1].to_string());
+            if let Some(fips) = parts.get(2) {
+                code_to_fips.insert(parts[0].to_string(), fips.to_string());
+                fips_to_code.insert(fips.to_string(), parts[0].to_string());
+            }
+            let ascii_name = unidecode(&parts[1]);
+            if ascii_name != parts[1] {
+                name_to_code.insert(ascii_name, parts[0].to_string());
             }
         }
+        skipped += records.skipped;
+        let filename = format!("{}/{}.txt", &country, "state_names");
+        let mut records = Records::new(&filename, 2);
+        let mut localized_names: Vec<(String, String)> = source
+            .read_optional_lines(&filename)
+            .filter_map(|s| {
+                let parts = records.split(&s)?;
+                Some((unidecode(&parts[1]).to_lowercase(), parts[0].to_string()))
+            })
+            .collect();
+        skipped += records.skipped;
+        localized_names.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+        localized_names.dedup();
+        let filename = format!("{}/{}.txt", &country, "zipcodes");
+        let mut records = Records::new(&filename, 3);
+        let zipcode_prefixes = mapped::read_lines(source, &filename)
+            .filter_map(|line| {
+                let parts = records.split(line.as_str())?;
+                Some((
+                    line.field(&parts[0]),
+                    line.field(&parts[1]),
+                    line.field(&parts[2]),
+                ))
+            })
+            .collect();
+        skipped += records.skipped;
         data.insert(
             country.to_string(),
             StatesMap {
                 name_to_code,
                 code_to_name,
+                code_to_fips,
+                fips_to_code,
+                localized_names,
+                zipcode_prefixes,
             },
         );
     }
-    data
+    (data, skipped)
+}
+
+/// Countries that have states, `default_countries` first and then the rest
+/// in alphabetical order, so that codes shared by several countries resolve
+/// to the one users expect most.
+pub(crate) fn rank_state_countries(
+    default_countries: &[Country],
+    states: &CountryStates,
+    countries: &CountriesMap,
+) -> Vec<Country> {
+    let mut ranked: Vec<Country> = default_countries
+        .iter()
+        .filter(|c| states.contains_key(&c.code))
+        .cloned()
+        .collect();
+    let mut rest: Vec<&String> = states
+        .keys()
+        .filter(|code| !ranked.iter().any(|c| &c.code == *code))
+        .collect();
+    rest.sort();
+    for code in rest {
+        match countries.code_to_name.get(code) {
+            Some(name) => ranked.push(Country {
+                code: code.clone(),
+                name: name.clone(),
+            }),
+            None => warn!("Ignoring states of unknown country {}", code),
+        }
+    }
+    ranked
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::mocks;
+    use crate::nodes::{CANADA, UNITED_STATES};
+    use std::path::Path;
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    use std::sync::Arc;
 
     #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
     fn test_read_states() {
         let states = super::read_states();
-        assert!(states.get("US").is_some());
-        assert!(states.get("CA").is_some());
+        assert!(states.contains_key("US"));
+        assert!(states.contains_key("CA"));
         let us_states = states.get("US").unwrap();
         let ca_states = states.get("CA").unwrap();
-        assert!(ca_states.code_to_name.get("ON").is_some());
-        assert!(ca_states.name_to_code.get("Ontario").is_some());
-        assert!(us_states.code_to_name.get("CA").is_some());
-        assert!(us_states.name_to_code.get("California").is_some());
+        assert!(ca_states.code_to_name.contains_key("ON"));
+        assert!(ca_states.name_to_code.contains_key("Ontario"));
+        assert!(us_states.code_to_name.contains_key("CA"));
+        assert!(us_states.name_to_code.contains_key("California"));
+        // Military codes of APO and FPO addresses have no FIPS codes
+        assert_eq!(
+            us_states.code_to_fips.len(),
+            us_states.code_to_name.len() - 2
+        );
+        assert!(!us_states.code_to_fips.contains_key("AE"));
+        assert_eq!(us_states.code_to_fips.get("NY"), Some(&String::from("36")));
+        assert!(ca_states.code_to_fips.is_empty());
+    }
+
+    #[test]
+    fn test_read_states_skips_malformed_lines() {
+        let source =
+            DataSource::Dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/malformed"));
+        let (states, skipped) = read_states_from(&source);
+        if !cfg!(feature = "data-us") {
+            assert!(states.is_empty());
+            return;
+        }
+        assert_eq!(skipped, 3);
+        let us_states = &states["US"];
+        assert_eq!(us_states.code_to_name.len(), 2);
+        assert_eq!(us_states.code_to_fips.get("CA"), Some(&String::from("06")));
+        assert!(us_states.localized_names.is_empty());
+    }
+
+    #[test]
+    fn test_read_states_from_tsv() {
+        let source = DataSource::Dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/csv"));
+        let (states, skipped) = read_states_from(&source);
+        if !cfg!(feature = "data-us") {
+            assert!(states.is_empty());
+            return;
+        }
+        assert_eq!(skipped, 0);
+        let us_states = &states["US"];
+        assert_eq!(
+            us_states.name_to_code.get("District of Columbia"),
+            Some(&String::from("DC"))
+        );
+        assert_eq!(us_states.code_to_fips.get("NY"), Some(&String::from("36")));
+        let parser = Parser::builder()
+            .with_data_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/csv"))
+            .build();
+        assert_eq!(parser.state_fips(&State::new("NY", "New York")), Some("36"));
+        assert_eq!(parser.state_fips(&State::new("TX", "Texas")), None);
     }
 
     #[test]
+    #[cfg(feature = "data-ca")]
     fn test_ca_states() {
         let parser = Parser::new();
         parser.states.get("CA").unwrap();
     }
 
     #[test]
+    #[cfg(feature = "data-us")]
     fn test_us_states() {
         let parser = Parser::new();
         parser.states.get("US").unwrap();
@@ -412,6 +870,25 @@ mod tests {
     }
 
     #[test]
+    fn test_state_ordering() {
+        let mut states = vec![
+            State::new("ON", "Ontario"),
+            State::new("BC", "British Columbia"),
+            State::new("ON", "Ontario"),
+        ];
+        states.sort();
+        states.dedup();
+        assert_eq!(
+            states,
+            vec![
+                State::new("BC", "British Columbia"),
+                State::new("ON", "Ontario")
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
     fn test_fill_state() {
         let parser = Parser::new();
         let input = String::from("Northwood, ND, 104 ND-15");
@@ -421,11 +898,92 @@ mod tests {
             country: None,
             zipcode: None,
             address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
         };
         parser.fill_state(&mut location, &input);
         assert_eq!(location.state.unwrap().code, String::from("ND"));
     }
 
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_fill_state_after_city() {
+        let parser = Parser::new();
+        let inputs = [
+            ("Kansas City MO", Some("MO"), "Kansas City"),
+            ("kansas city mo", Some("MO"), "Kansas City"),
+            ("Mississauga ON", Some("ON"), "Mississauga"),
+            ("Main St Kansas City MO", Some("MO"), "Kansas City"),
+            ("Austin TX", Some("TX"), "Austin"),
+        ];
+        for (input, state, city) in inputs {
+            let mut location = Location::default();
+            parser.fill_state(&mut location, input);
+            assert_eq!(
+                location.state.as_ref().map(|s| s.code.as_str()),
+                state,
+                "{}",
+                input
+            );
+            parser.fill_city(&mut location, input);
+            assert_eq!(location.city.unwrap().name, city, "{}", input);
+        }
+        let mut location = Location::default();
+        parser.fill_state(&mut location, "Kansas City");
+        assert_eq!(location.state.unwrap().code, String::from("KS"));
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_fill_state_matches_whole_words() {
+        let parser = Parser::new();
+        let inputs = [
+            ("Texasville", None),
+            ("Indianapolis", None),
+            ("Kansas City, MO", Some("MO")),
+            ("Arkansas City, KS", Some("KS")),
+            ("Charleston, West Virginia", Some("WV")),
+            ("West Virginia", Some("WV")),
+            ("Virginia Beach, VA", Some("VA")),
+            ("Iowa Falls", Some("IA")),
+        ];
+        for (input, state) in inputs {
+            let mut location = Location::default();
+            parser.fill_state(&mut location, input);
+            assert_eq!(
+                location.state.as_ref().map(|s| s.code.as_str()),
+                state,
+                "{}",
+                input
+            );
+        }
+        let location = parser.parse_location("Indianapolis");
+        assert_eq!(location.to_string(), "Indianapolis, IN, US");
+    }
+
+    #[test]
+    #[cfg(feature = "data-ca")]
+    fn test_fill_state_with_diacritics() {
+        let parser = Parser::new();
+        let mut location = Location {
+            city: None,
+            state: None,
+            country: None,
+            zipcode: None,
+            address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
+        };
+        parser.fill_state(&mut location, "Gatineau, Québec");
+        assert_eq!(location.state.unwrap().code, String::from("QC"));
+    }
+
     #[test]
     fn test_remove_state() {
         let parser = Parser::new();
@@ -443,6 +1001,9 @@ mod tests {
         let mut location = String::from("Toronto, ON, CA");
         parser.remove_state(&state, &CANADA.clone(), &mut location);
         assert_eq!(location, String::from("Toronto, CA"));
+        let mut location = String::from("RiCHmONd HiLL-ON-CaNAdA");
+        parser.remove_state(&state, &CANADA.clone(), &mut location);
+        assert_eq!(location, String::from("RiCHmONd HiLL-CaNAdA"));
         let state = State {
             code: String::from("CA"),
             name: String::from("California"),
@@ -474,9 +1035,41 @@ mod tests {
         let mut location = String::from("United States-District of Columbia-washington-20340-DCCL");
         parser.remove_state(&state, &UNITED_STATES.clone(), &mut location);
         assert_eq!(location, String::from("United States-washington-20340"));
+        let state = State::new("MN", "Minnesota");
+        let mut location = String::from("New York Mills, MN");
+        parser.remove_state(&state, &UNITED_STATES.clone(), &mut location);
+        assert_eq!(location, String::from("New York Mills"));
+        let state = State::new("OH", "Ohio");
+        let mut location = String::from("Washington Court House, Ohio");
+        parser.remove_state(&state, &UNITED_STATES.clone(), &mut location);
+        assert_eq!(location, String::from("Washington Court House"));
     }
 
     #[test]
+    #[cfg(feature = "data-us")]
+    fn test_parse_city_containing_state_name() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("New York Mills, MN", "New York Mills, MN, US"),
+            ("New York Mills, Minnesota", "New York Mills, MN, US"),
+            (
+                "Washington Court House, OH",
+                "Washington Court House, OH, US",
+            ),
+            (
+                "Washington Court House, Ohio",
+                "Washington Court House, OH, US",
+            ),
+            ("Nevada City, Nevada", "Nevada City, NV, US"),
+            ("Colorado Springs, Colorado", "Colorado Springs, CO, US"),
+            ("New York, New York", "New York, NY, US"),
+        ] {
+            assert_eq!(parser.parse_location(input).to_string(), output);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
     fn test_state_from_code() {
         let parser = Parser::new();
         let state_code = "CA";
@@ -492,6 +1085,19 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "data-us")]
+    fn test_state_contains_city() {
+        let parser = Parser::new();
+        let oregon = State::new("OR", "Oregon");
+        assert!(parser.state_contains_city(&oregon, &City::new("Portland")));
+        assert!(parser.state_contains_city(&oregon, &City::new("PORTLAND")));
+        assert!(!parser.state_contains_city(&oregon, &City::new("Seattle")));
+        let unknown = State::new("XX", "Unknown");
+        assert!(!parser.state_contains_city(&unknown, &City::new("Portland")));
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
     fn test_fill_country_from_state() {
         let parser = Parser::new();
         let mut location = Location {
@@ -503,6 +1109,11 @@ mod tests {
             country: None,
             zipcode: None,
             address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
         };
         parser.fill_country_from_state(&mut location);
         assert_eq!(location.country.unwrap(), UNITED_STATES.clone());
@@ -515,34 +1126,56 @@ mod tests {
             country: None,
             zipcode: None,
             address: None,
+            landmark: None,
+            alternatives: vec![],
+            warnings: vec![],
+            collapsed: vec![],
+            raw: String::new(),
         };
         parser.fill_country_from_state(&mut location);
         assert_eq!(location.country.unwrap(), CANADA.clone());
     }
 
-    /// cargo test benchmark_fill_state -- --nocapture --ignored
     #[test]
-    #[ignore]
-    fn benchmark_fill_state() {
-        let n = 250;
-        let parser = Parser::new();
-        let before = std::time::Instant::now();
-        for _ in 0..n {
-            for input in mocks::get_mocks().keys() {
-                let mut location = Location {
-                    city: None,
-                    state: None,
-                    country: None,
-                    zipcode: None,
-                    address: None,
-                };
-                parser.fill_state(&mut location, &input);
-            }
-        }
-        println!(
-            "Elapsed time: {:.2?}, {:.2?} each",
-            before.elapsed(),
-            before.elapsed() / (n * mocks::get_mocks().len() as u32)
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_fill_country_from_state_of_other_countries() {
+        let mut parser = Parser::new();
+        let code_to_name: HashMap<String, String> = [("NSW", "New South Wales"), ("ON", "Ontario")]
+            .iter()
+            .map(|(code, name)| (code.to_string(), name.to_string()))
+            .collect();
+        Arc::make_mut(&mut parser.states).insert(
+            String::from("AU"),
+            StatesMap {
+                name_to_code: code_to_name
+                    .iter()
+                    .map(|(k, v)| (v.clone(), k.clone()))
+                    .collect(),
+                code_to_name,
+                code_to_fips: HashMap::new(),
+                fips_to_code: HashMap::new(),
+                localized_names: vec![],
+                zipcode_prefixes: vec![],
+            },
         );
+        parser.compile();
+        let codes: Vec<&str> = parser
+            .state_countries()
+            .iter()
+            .map(|c| c.code.as_str())
+            .collect();
+        assert_eq!(codes, vec!["US", "CA", "AU"]);
+        let mut location = Location {
+            state: parser.state_from_code(&None, "NSW"),
+            ..Default::default()
+        };
+        parser.fill_country_from_state(&mut location);
+        assert_eq!(location.country.unwrap().code, String::from("AU"));
+        let mut location = Location {
+            state: parser.state_from_code(&None, "ON"),
+            ..Default::default()
+        };
+        parser.fill_country_from_state(&mut location);
+        assert_eq!(location.country.unwrap(), CANADA.clone());
     }
 }
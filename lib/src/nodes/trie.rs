@@ -0,0 +1,124 @@
+use super::CountryCities;
+use std::collections::BTreeMap;
+
+/// Prefix tree over lowercase ASCII city names of all countries,
+/// see `Parser::suggest_cities`.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let cities = geo_rs::nodes::read_cities();
+/// let trie = geo_rs::nodes::CityTrie::new(&cities);
+/// let found = trie.find_prefix("sausal");
+/// assert_eq!(found, vec![(
+///     String::from("sausalito"),
+///     String::from("US"),
+///     String::from("CA"),
+/// )]);
+/// ```
+#[derive(Debug, Default)]
+pub struct CityTrie {
+    root: TrieNode,
+}
+
+#[derive(Debug, Default)]
+struct TrieNode {
+    children: BTreeMap<char, TrieNode>,
+    /// Country and state codes of cities whose names end at this node
+    cities: Vec<(String, String)>,
+}
+
+impl CityTrie {
+    /// Build a prefix tree from cities of all countries.
+    ///
+    /// # Arguments
+    ///
+    /// * `cities` - Cities of all countries
+    pub fn new(cities: &CountryCities) -> Self {
+        let mut trie = Self::default();
+        for (country, country_cities) in cities {
+            for (state, names) in &country_cities.cities_by_state {
+                for name in names {
+                    trie.insert(name, country, state);
+                }
+            }
+        }
+        trie
+    }
+
+    /// Add a city to the tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Lowercase ASCII name of the city
+    /// * `country` - Country code of the city
+    /// * `state` - State code of the city
+    pub fn insert(&mut self, name: &str, country: &str, state: &str) {
+        let mut node = &mut self.root;
+        for c in name.chars() {
+            node = node.children.entry(c).or_default();
+        }
+        let city = (country.to_string(), state.to_string());
+        if !node.cities.contains(&city) {
+            node.cities.push(city);
+        }
+    }
+
+    /// Return name, country code and state code of every city whose name
+    /// starts with the given lowercase prefix, in alphabetical order of names.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Lowercase ASCII prefix of city names
+    pub fn find_prefix(&self, prefix: &str) -> Vec<(String, String, String)> {
+        let mut node = &self.root;
+        for c in prefix.chars() {
+            node = match node.children.get(&c) {
+                Some(n) => n,
+                None => return vec![],
+            };
+        }
+        let mut found = vec![];
+        let mut name = prefix.to_string();
+        collect(node, &mut name, &mut found);
+        found
+    }
+}
+
+fn collect(node: &TrieNode, name: &mut String, found: &mut Vec<(String, String, String)>) {
+    for (country, state) in &node.cities {
+        found.push((name.clone(), country.clone(), state.clone()));
+    }
+    for (c, child) in &node.children {
+        name.push(*c);
+        collect(child, name, found);
+        name.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_prefix() {
+        let mut trie = CityTrie::default();
+        trie.insert("portland", "US", "OR");
+        trie.insert("portland", "US", "ME");
+        trie.insert("port hope", "CA", "ON");
+        trie.insert("porto", "PT", "13");
+        trie.insert("paris", "US", "TX");
+        let names: Vec<String> = trie
+            .find_prefix("port")
+            .into_iter()
+            .map(|(name, _, state)| format!("{}/{}", name, state))
+            .collect();
+        assert_eq!(
+            names,
+            vec!["port hope/ON", "portland/OR", "portland/ME", "porto/13"]
+        );
+        assert_eq!(trie.find_prefix("portx"), vec![]);
+        assert_eq!(trie.find_prefix("").len(), 5);
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+
+/// Warnings about doubtful decisions made while parsing a location.
+///
+/// Every warning has a stable code that is never changed or reused between
+/// releases, so it's safe to build alerting rules on top of the codes.
+/// New warnings only get new codes and aren't a breaking change, so matches
+/// on warnings outside of this crate need a wildcard arm. `Location::warnings`
+/// are always ordered by code and never contain the same warning twice.
+///
+/// | Code | Warning           | Meaning                                                        |
+/// |------|-------------------|----------------------------------------------------------------|
+/// | W001 | `AmbiguousCity`   | more than one city matches, see `Location::alternatives`       |
+/// | W002 | `UnknownCity`     | city isn't in the dataset and was taken from the input as is   |
+/// | W003 | `InferredState`   | state isn't mentioned in the input and was inferred            |
+/// | W004 | `InferredCountry` | country isn't mentioned in the input and was inferred          |
+/// | W005 | `Duplicates`      | repeated parts of the input were collapsed, see `collapsed`    |
+/// | W006 | `RestoredZipcode` | ZIP code lost its leading zeros and they were restored         |
+/// | W007 | `PhoneticCity`    | city is spelled differently and was matched by how it sounds   |
+/// | W008 | `HistoricalName`  | former name of the city or the country was replaced            |
+/// | W009 | `Blocked`         | input is on the blocklist and wasn't parsed                    |
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// use geo_rs::nodes::Warning;
+/// let parser = geo_rs::Parser::new();
+/// let location = parser.parse_location("Sausalito");
+/// assert_eq!(location.warnings, vec![Warning::InferredState, Warning::InferredCountry]);
+/// assert_eq!(location.warnings[0].code(), "W003");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum Warning {
+    AmbiguousCity = 1,
+    UnknownCity = 2,
+    InferredState = 3,
+    InferredCountry = 4,
+    Duplicates = 5,
+    RestoredZipcode = 6,
+    PhoneticCity = 7,
+    HistoricalName = 8,
+    Blocked = 9,
+}
+
+impl Warning {
+    /// Numeric code of the warning, e.g. 3 for `InferredState`
+    pub fn id(&self) -> u16 {
+        *self as u16
+    }
+
+    /// String code of the warning, e.g. "W003" for `InferredState`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::AmbiguousCity => "W001",
+            Warning::UnknownCity => "W002",
+            Warning::InferredState => "W003",
+            Warning::InferredCountry => "W004",
+            Warning::Duplicates => "W005",
+            Warning::RestoredZipcode => "W006",
+            Warning::PhoneticCity => "W007",
+            Warning::HistoricalName => "W008",
+            Warning::Blocked => "W009",
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_codes() {
+        assert_eq!(Warning::AmbiguousCity.id(), 1);
+        assert_eq!(Warning::UnknownCity.id(), 2);
+        assert_eq!(Warning::InferredState.id(), 3);
+        assert_eq!(Warning::InferredCountry.id(), 4);
+        assert_eq!(Warning::Duplicates.id(), 5);
+        assert_eq!(Warning::RestoredZipcode.id(), 6);
+        assert_eq!(Warning::PhoneticCity.id(), 7);
+        assert_eq!(Warning::HistoricalName.id(), 8);
+        assert_eq!(Warning::Blocked.id(), 9);
+        assert_eq!(format!("{}", Warning::AmbiguousCity), "W001");
+        assert_eq!(format!("{}", Warning::UnknownCity), "W002");
+        assert_eq!(format!("{}", Warning::InferredState), "W003");
+        assert_eq!(format!("{}", Warning::InferredCountry), "W004");
+        assert_eq!(format!("{}", Warning::Duplicates), "W005");
+        assert_eq!(format!("{}", Warning::RestoredZipcode), "W006");
+        assert_eq!(format!("{}", Warning::PhoneticCity), "W007");
+        assert_eq!(format!("{}", Warning::HistoricalName), "W008");
+        assert_eq!(format!("{}", Warning::Blocked), "W009");
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Location, State, CANADA};
+use super::{Country, Location, State, Warning, CANADA, UNITED_STATES};
 use crate::utils;
 use crate::Parser;
 use lazy_static::lazy_static;
@@ -13,14 +13,16 @@ lazy_static! {
     .unwrap();
 }
 
-#[derive(Debug, Clone, Hash, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Zipcode {
     pub zipcode: String,
 }
 
-impl PartialEq for Zipcode {
-    fn eq(&self, other: &Zipcode) -> bool {
-        self.zipcode == other.zipcode
+impl Zipcode {
+    pub fn new(zipcode: &str) -> Self {
+        Self {
+            zipcode: zipcode.to_string(),
+        }
     }
 }
 
@@ -50,107 +52,37 @@ impl Parser {
     ///     country: None,
     ///     zipcode: None,
     ///     address: None,
+    ///     landmark: None,
+    ///     alternatives: vec![],
+    ///     warnings: vec![],
+    ///     collapsed: vec![],
+    ///     raw: String::new(),
     /// };
     /// parser.fill_zipcode(&mut location, "Saint-Lin-Laurentides, QC J5M 0G3");
     /// assert_eq!(location.zipcode.unwrap().zipcode, String::from("J5M 0G3"));
     /// assert_eq!(location.country.unwrap().code, String::from("CA"));
     /// ```
     pub fn fill_zipcode(&self, location: &mut Location, input: &str) {
+        stage!("zipcode", input);
         if input.chars().count() == 0 {
             return;
         }
-        if let Some(zipcode_match) = CA_PATTERN.find(&input) {
+        if let Some(zipcode_match) = CA_PATTERN.find(input) {
             let zipcode = input[zipcode_match.start()..zipcode_match.end()].to_string();
             location.zipcode = Some(Zipcode {
                 zipcode: zipcode.clone(),
             });
             location.country = Some(CANADA.clone());
-            match zipcode.chars().nth(0).unwrap().to_string().as_str() {
-                "A" => {
-                    location.state = Some(State {
-                        name: String::from("Newfoundland"),
-                        code: String::from("NL"),
-                    })
-                }
-                "B" => {
-                    location.state = Some(State {
-                        name: String::from("Nova Scotia"),
-                        code: String::from("NS"),
-                    })
-                }
-                "C" => {
-                    location.state = Some(State {
-                        name: String::from("Prince Edward Is."),
-                        code: String::from("PE"),
-                    })
-                }
-                "E" => {
-                    location.state = Some(State {
-                        name: String::from("New Brunswick"),
-                        code: String::from("NB"),
-                    })
-                }
-                "G" | "H" | "J" => {
-                    location.state = Some(State {
-                        name: String::from("Quebec"),
-                        code: String::from("QC"),
-                    })
-                }
-                "K" | "L" | "M" | "N" | "P" => {
-                    location.state = Some(State {
-                        name: String::from("Ontario"),
-                        code: String::from("ON"),
-                    })
-                }
-                "R" => {
-                    location.state = Some(State {
-                        name: String::from("Manitoba"),
-                        code: String::from("MB"),
-                    })
-                }
-                "S" => {
-                    location.state = Some(State {
-                        name: String::from("Saskatchewen"),
-                        code: String::from("SK"),
-                    })
-                }
-                "T" => {
-                    location.state = Some(State {
-                        name: String::from("Alberta"),
-                        code: String::from("AB"),
-                    })
-                }
-                "V" => {
-                    location.state = Some(State {
-                        name: String::from("British Columbia"),
-                        code: String::from("BC"),
-                    })
-                }
-                "X" => {
-                    location.state = Some(State {
-                        name: String::from("Nunavut"),
-                        code: String::from("NU"),
-                    })
-                }
-                "Y" => {
-                    location.state = Some(State {
-                        name: String::from("Yukon"),
-                        code: String::from("YT"),
-                    })
-                }
-                _ => (),
-            };
+            location.state = self.state_from_zipcode(&CANADA, &zipcode);
             return;
         }
-        for part in utils::split(&input) {
-            let has_correct_len = vec![5, 9, 10].contains(&part.chars().count());
-            let has_correct_chars = &part.chars().all(|c| {
-                c.is_numeric()
-                    || c.to_string() == "-".to_string()
-                    || c.to_string() == " ".to_string()
-            });
+        for part in utils::split(input) {
+            let has_correct_len = [5, 9, 10].contains(&part.chars().count());
+            let has_correct_chars = &part
+                .chars()
+                .all(|c| c.is_numeric() || c.to_string() == "-" || c.to_string() == " ");
             if has_correct_len & has_correct_chars {
-                if let Some(zipcode) = US_PATTERN.find(&input) {
+                if let Some(zipcode) = US_PATTERN.find(input) {
                     location.zipcode = Some(Zipcode {
                         zipcode: input[zipcode.start()..zipcode.end()].to_string(),
                     });
@@ -160,6 +92,227 @@ impl Parser {
         }
     }
 
+    /// Restore ZIP codes that lost their leading zeros, e.g. in spreadsheets,
+    /// like "2139" in "Cambridge, MA 2139". A 3 or 4 digit number is only taken
+    /// when it follows the state or ends the input and the restored ZIP code
+    /// belongs to the state. Adds `Warning::RestoredZipcode` on success.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct with a state found
+    /// * `input` - Location string to be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = geo_rs::nodes::Location {
+    ///     city: None,
+    ///     state: parser.state_from_code(&None, "MA"),
+    ///     country: None,
+    ///     zipcode: None,
+    ///     address: None,
+    ///     landmark: None,
+    ///     alternatives: vec![],
+    ///     warnings: vec![],
+    ///     collapsed: vec![],
+    ///     raw: String::new(),
+    /// };
+    /// parser.fill_zipcode_with_lost_zeros(&mut location, "Cambridge, MA 2139");
+    /// assert_eq!(location.zipcode.unwrap().zipcode, String::from("02139"));
+    /// ```
+    pub fn fill_zipcode_with_lost_zeros(&self, location: &mut Location, input: &str) {
+        if location.zipcode.is_some() {
+            return;
+        }
+        let state = match &location.state {
+            Some(s) => s,
+            None => return,
+        };
+        if let Some(c) = &location.country {
+            if c.code != UNITED_STATES.code {
+                return;
+            }
+        }
+        let parts = utils::split(input);
+        for (i, part) in parts.iter().enumerate() {
+            if ![3, 4].contains(&part.len()) || !part.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let follows_state = i > 0 && parts[i - 1] == state.code;
+            if !follows_state && i != parts.len() - 1 {
+                continue;
+            }
+            let zipcode = format!("{:0>5}", part);
+            if self.state_from_zipcode(&UNITED_STATES, &zipcode).as_ref() != Some(state) {
+                trace_event!("rejected ZIP code with lost zeros", zipcode = zipcode);
+                continue;
+            }
+            location.zipcode = Some(Zipcode { zipcode });
+            location.country = Some(UNITED_STATES.clone());
+            location.warnings.push(Warning::RestoredZipcode);
+            return;
+        }
+    }
+
+    /// Make sure that the zipcode belongs to the state when both are found, e.g. "11111"
+    /// is a New York ZIP code, so it can't be right in "Saint-Lin-Laurentides, QC 11111".
+    /// On conflict the zipcode is dropped unless only the state of the zipcode has
+    /// the city of the input, then the state is replaced with the zipcode's one.
+    /// Conflicts are recorded in the trace, see `Parser::parse_location_explained`.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct with a zipcode found
+    /// * `input` - Location string to be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Saint-Lin-Laurentides, QC 11111");
+    /// assert_eq!(location.to_string(), String::from("Saint-Lin-Laurentides, QC, CA"));
+    /// let location = parser.parse_location("Chicago, MI 60601");
+    /// assert_eq!(location.to_string(), String::from("Chicago, IL, US, 60601"));
+    /// ```
+    pub fn resolve_zipcode_state(&self, location: &mut Location, input: &str) {
+        let zipcode = match &location.zipcode {
+            Some(z) => z.clone(),
+            None => return,
+        };
+        let by_zipcode = match self.location_from_zipcode(&zipcode.zipcode) {
+            Some(l) => l,
+            None => return,
+        };
+        let zipcode_state = match by_zipcode.state {
+            Some(s) => s,
+            None => return,
+        };
+        let state = match &location.state {
+            Some(s) if *s != zipcode_state => s.clone(),
+            // State of a postal code is taken from the postal code, look for another one
+            Some(_) => {
+                let mut probe = Location::default();
+                self.fill_state(&mut probe, input);
+                match probe.state {
+                    Some(s) if s != zipcode_state => s,
+                    _ => return,
+                }
+            }
+            None => return,
+        };
+        let has_city = |state: &State| {
+            let mut probe = Location {
+                state: Some(state.clone()),
+                ..Location::default()
+            };
+            self.fill_country_from_state(&mut probe);
+            self.fill_city(&mut probe, input);
+            probe
+                .city
+                .is_some_and(|c| self.state_contains_city(state, &c))
+        };
+        let zipcode_wins = has_city(&zipcode_state) && !has_city(&state);
+        trace_event!(
+            "zipcode conflicts with state",
+            zipcode = zipcode.zipcode,
+            state = state.code,
+            zipcode_state = zipcode_state.code,
+            zipcode_wins = zipcode_wins,
+        );
+        if zipcode_wins {
+            location.state = Some(zipcode_state);
+            location.country = by_zipcode.country;
+            return;
+        }
+        location.zipcode = None;
+        if let Some(c) = &location.country {
+            if !self.is_state_of(&state.code, &c.code) {
+                location.country = None;
+            }
+        }
+        location.state = Some(state);
+        if location.country.is_none() {
+            self.fill_country_from_state(location);
+        }
+    }
+
+    /// Return the state of a US ZIP code or a Canadian postal code by its first characters,
+    /// see `src/data/{country}/zipcodes.txt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `country` - Country of the zipcode
+    /// * `zipcode` - ZIP code or postal code
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let state = parser.state_from_zipcode(&geo_rs::nodes::UNITED_STATES, "48911");
+    /// assert_eq!(state.unwrap().code, String::from("MI"));
+    /// let state = parser.state_from_zipcode(&geo_rs::nodes::CANADA, "X0E 0P0");
+    /// assert_eq!(state.unwrap().code, String::from("NT"));
+    /// ```
+    pub fn state_from_zipcode(&self, country: &Country, zipcode: &str) -> Option<State> {
+        let states = self.states.get(&country.code)?;
+        let zipcode = zipcode.trim().to_uppercase();
+        let (_, _, code) = states.zipcode_prefixes.iter().find(|(from, to, _)| {
+            match zipcode.get(..from.len()) {
+                Some(prefix) => from.as_ref() <= prefix && prefix <= to.as_ref(),
+                None => false,
+            }
+        })?;
+        let name = states.code_to_name.get(code.as_ref())?;
+        Some(State {
+            code: code.to_string(),
+            name: name.clone(),
+        })
+    }
+
+    /// Return a location of a bare US ZIP code or a Canadian postal code.
+    /// Only state and country are resolved, there is no dataset of cities by zipcode.
+    ///
+    /// # Arguments
+    ///
+    /// * `zipcode` - ZIP code or postal code, e.g. "48911" or "M4E 3J1"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.location_from_zipcode("48911").unwrap();
+    /// assert_eq!(location.to_string(), String::from("MI, US, 48911"));
+    /// let location = parser.location_from_zipcode("m4e 3j1").unwrap();
+    /// assert_eq!(location.to_string(), String::from("ON, CA, M4E3J1"));
+    /// assert_eq!(parser.location_from_zipcode("Lansing, MI 48911"), None);
+    /// ```
+    pub fn location_from_zipcode(&self, zipcode: &str) -> Option<Location> {
+        let zipcode = zipcode.trim().to_uppercase();
+        let is_full_match = |pattern: &Regex| {
+            pattern
+                .find(&zipcode)
+                .is_some_and(|m| m.start() == 0 && m.end() == zipcode.len())
+        };
+        let country = if is_full_match(&CA_PATTERN) {
+            CANADA.clone()
+        } else if is_full_match(&US_PATTERN) {
+            UNITED_STATES.clone()
+        } else {
+            return None;
+        };
+        Some(Location {
+            state: self.state_from_zipcode(&country, &zipcode),
+            country: Some(country),
+            zipcode: Some(Zipcode { zipcode }),
+            ..Location::default()
+        })
+    }
+
     /// Remove zipcode from location string.
     ///
     /// # Arguments
@@ -179,8 +332,8 @@ impl Parser {
     /// ```
     pub fn remove_zipcode(&self, zipcode: &Zipcode, input: &mut String) {
         *input = input.replace(&zipcode.zipcode, "");
-        utils::clean(input);
-        debug!("after removing zipcode: {}", input);
+        self.clean(input);
+        trace_event!("removed zipcode", remainder = input);
     }
 }
 
@@ -189,6 +342,105 @@ mod tests {
     use super::*;
     use crate::mocks;
 
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_location_from_zipcode() {
+        let parser = Parser::new();
+        let inputs = [
+            ("48911", Some("MI, US, 48911")),
+            ("02139", Some("MA, US, 02139")),
+            (" 90210-1234 ", Some("CA, US, 90210-1234")),
+            ("J5M 0G3", Some("QC, CA, J5M0G3")),
+            ("X0A 0H0", Some("NU, CA, X0A0H0")),
+            ("2139", None),
+            ("ZIP 48911", None),
+            ("", None),
+        ];
+        for (input, output) in inputs.iter() {
+            assert_eq!(
+                parser.location_from_zipcode(input).map(|l| l.to_string()),
+                output.map(String::from),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_resolve_zipcode_state() {
+        let parser = Parser::new();
+        let inputs = [
+            (
+                "Saint-Lin-Laurentides, QC 11111",
+                "Saint-Lin-Laurentides, QC, CA",
+                true,
+            ),
+            ("Seattle, WA M4E 3J1", "Seattle, WA, US", true),
+            ("Lansing, MI 60601", "Lansing, MI, US", true),
+            ("Chicago, MI 60601", "Chicago, IL, US, 60601", true),
+            ("Toronto, QC M4E 3J1", "Toronto, ON, CA, M4E3J1", true),
+            ("Lansing, MI 48911", "Lansing, MI, US, 48911", false),
+            (
+                "Saint-Lin-Laurentides, QC J5M 0G3",
+                "Saint-Lin-Laurentides, QC, CA, J5M0G3",
+                false,
+            ),
+        ];
+        for (input, output, conflict) in inputs.iter() {
+            let (location, steps) = parser.parse_location_explained(input);
+            assert_eq!(
+                location.to_string(),
+                String::from(*output),
+                "input: {}",
+                input
+            );
+            assert_eq!(
+                steps
+                    .iter()
+                    .any(|s| s.message == "zipcode conflicts with state"),
+                *conflict,
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_fill_zipcode_with_lost_zeros() {
+        let parser = Parser::new();
+        let inputs = [
+            ("MA", "Cambridge, MA 2139", Some("02139")),
+            ("NJ", "Newark, NJ, 7102", Some("07102")),
+            ("PR", "San Juan, PR 901", Some("00901")),
+            ("MA", "1600 Main St, Boston, MA", None),
+            ("MA", "Boston, MA 9999", None),
+            ("NY", "Wilton, NY 2139", None),
+        ];
+        for (state, input, output) in inputs.iter() {
+            let mut location = Location {
+                city: None,
+                state: parser.state_from_code(&None, state),
+                country: None,
+                zipcode: None,
+                address: None,
+                landmark: None,
+                alternatives: vec![],
+                warnings: vec![],
+                collapsed: vec![],
+                raw: String::new(),
+            };
+            parser.fill_zipcode_with_lost_zeros(&mut location, input);
+            assert_eq!(
+                location.zipcode.map(|z| z.zipcode),
+                output.map(String::from),
+                "input: {}",
+                input
+            );
+        }
+    }
+
     #[test]
     fn test_fill_zipcode() {
         let parser = Parser::new();
@@ -199,8 +451,13 @@ mod tests {
                 country: output.2,
                 zipcode: None,
                 address: None,
+                landmark: None,
+                alternatives: vec![],
+                warnings: vec![],
+                collapsed: vec![],
+                raw: String::new(),
             };
-            parser.fill_zipcode(&mut location, &input);
+            parser.fill_zipcode(&mut location, input);
             assert_eq!(location.zipcode, output.3, "input: {}", input);
         }
     }
@@ -229,30 +486,4 @@ mod tests {
         };
         assert_eq!(format!("{}", zipcode), "J5M0G3");
     }
-
-    /// cargo test benchmark_fill_zipcode -- --nocapture --ignored
-    #[test]
-    #[ignore]
-    fn benchmark_fill_zipcode() {
-        let n = 250;
-        let parser = Parser::new();
-        let before = std::time::Instant::now();
-        for _ in 0..n {
-            for zipcode in mocks::get_mocks().keys() {
-                let mut location = Location {
-                    city: None,
-                    state: None,
-                    country: None,
-                    zipcode: None,
-                    address: None,
-                };
-                parser.fill_zipcode(&mut location, &zipcode);
-            }
-        }
-        println!(
-            "Elapsed time: {:.2?}, {:.2?} each",
-            before.elapsed(),
-            before.elapsed() / (n * mocks::get_mocks().len() as u32)
-        );
-    }
 }
@@ -0,0 +1,418 @@
+use crate::nodes::State;
+use crate::utils::{expect_line, read_lines};
+use crate::{utils, Parser};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+lazy_static! {
+    static ref RE_BRACKET_PIECES: Regex = Regex::new(r"\s*(?:[,;/]|\s-\s)\s*").unwrap();
+    /// Two or three letters separated by periods, e.g. "N.Y." or "U.S.A."
+    static ref RE_DOTTED: Regex = Regex::new(r"\b[A-Za-z](?:\.[A-Za-z]){1,2}\b\.?").unwrap();
+}
+
+/// Read noise patterns from `src/data/noise_patterns.txt`, one regular expression
+/// per line. Parts of the input matching them, e.g. store numbers and internal
+/// codes of retail feeds, are removed before parsing.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let patterns = geo_rs::noise::read_noise_patterns();
+/// assert!(patterns.iter().any(|p| p.is_match("(Store# 04278)")));
+/// ```
+pub fn read_noise_patterns() -> Vec<Regex> {
+    let filename = "noise_patterns.txt";
+    compile_noise_patterns(read_lines(filename).map(|line| expect_line(filename, line)))
+}
+
+/// Compile noise patterns, skipping empty lines and invalid regular expressions.
+///
+/// # Arguments
+///
+/// * `patterns` - Regular expressions matching noise, e.g. r"^\d{6,}\s+"
+pub fn compile_noise_patterns<I, P>(patterns: I) -> Vec<Regex>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<str>,
+{
+    patterns
+        .into_iter()
+        .filter(|p| !p.as_ref().is_empty())
+        .filter_map(|p| match Regex::new(p.as_ref()) {
+            Ok(regex) => Some(regex),
+            Err(e) => {
+                warn!("Ignoring invalid noise pattern {:?}: {}", p.as_ref(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Remove everything matching any of the patterns, in the order of patterns.
+///
+/// # Arguments
+///
+/// * `s` - String to remove noise from
+/// * `patterns` - Noise patterns, see `read_noise_patterns`
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let patterns = geo_rs::noise::read_noise_patterns();
+/// let mut s = String::from("600778 Wilton, NY - Route 50");
+/// geo_rs::noise::strip_noise(&mut s, &patterns);
+/// assert_eq!(s, String::from("Wilton, NY - Route 50"));
+/// ```
+pub fn strip_noise(s: &mut String, patterns: &[Regex]) {
+    strip_noise_except(s, patterns, |_| false);
+}
+
+/// Same as `strip_noise` but leave the matches that should be kept, e.g. known codes.
+///
+/// # Arguments
+///
+/// * `s` - String to remove noise from
+/// * `patterns` - Noise patterns, see `read_noise_patterns`
+/// * `keep` - Whether a match, trimmed of whitespace, isn't noise
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let patterns = geo_rs::noise::read_noise_patterns();
+/// let mut s = String::from("Beijing, CHN - PRK");
+/// geo_rs::noise::strip_noise_except(&mut s, &patterns, |m| m == "CHN");
+/// assert_eq!(s, String::from("Beijing, CHN - "));
+/// ```
+pub fn strip_noise_except<F: Fn(&str) -> bool>(s: &mut String, patterns: &[Regex], keep: F) {
+    for pattern in patterns {
+        if pattern.is_match(s) {
+            *s = pattern
+                .replace_all(s, |caps: &Captures| match keep(caps[0].trim()) {
+                    true => caps[0].to_string(),
+                    false => String::new(),
+                })
+                .to_string();
+        }
+    }
+}
+
+/// Remove groups of brackets that are noise as a whole, e.g. store numbers,
+/// including the ones with nested brackets, see `utils::replace_brackets`.
+/// A group is noise when nothing but punctuation is left of it after noise
+/// patterns are removed from it, the others are left as they are.
+///
+/// # Arguments
+///
+/// * `s` - String to remove noise from
+/// * `patterns` - Noise patterns, see `read_noise_patterns`
+/// * `keep` - Whether a match, trimmed of whitespace, isn't noise
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let patterns = geo_rs::noise::read_noise_patterns();
+/// let mut s = String::from("Chicago (Store (123)) (Loop), IL");
+/// geo_rs::noise::strip_noise_brackets(&mut s, &patterns, |_| false);
+/// assert_eq!(s, String::from("Chicago   (Loop), IL"));
+/// ```
+pub fn strip_noise_brackets<F: Fn(&str) -> bool>(s: &mut String, patterns: &[Regex], keep: F) {
+    if !s.contains('(') {
+        return;
+    }
+    *s = utils::replace_brackets(s, |text| match is_noise_bracket(text, patterns, &keep) {
+        true => Some(String::from(" ")),
+        false => None,
+    });
+}
+
+/// Whether the text of brackets is noise, see `strip_noise_brackets`.
+fn is_noise_bracket<F: Fn(&str) -> bool>(text: &str, patterns: &[Regex], keep: F) -> bool {
+    let mut group = format!("({})", text.trim());
+    strip_noise_except(&mut group, patterns, keep);
+    !group.chars().any(|c| c.is_alphanumeric())
+}
+
+impl Parser {
+    /// Same as `utils::clean` but with the normalizer of this parser, dotted
+    /// codes are collapsed, see `Parser::collapse_dotted_codes`, and codes in
+    /// brackets are kept, see `Parser::keep_bracket_codes`. Returns the segments
+    /// collapsed by `NormalizeStep::Dedup`.
+    pub(crate) fn clean(&self, s: &mut String) -> Vec<String> {
+        *s = self.collapse_dotted_codes(s);
+        self.normalizer.normalize_with(
+            s,
+            |token| self.is_known_token(token),
+            |s| self.keep_bracket_codes(s),
+        )
+    }
+
+    /// Whether the token is a country code or an alias that looks like noise,
+    /// e.g. consonant-only "CHN" or a custom city alias "DFW", and has to be kept.
+    fn is_known_token(&self, token: &str) -> bool {
+        let key = token.to_lowercase();
+        self.countries
+            .alpha3_to_code
+            .contains_key(&key.to_uppercase())
+            || self
+                .country_alias_tokens
+                .iter()
+                .any(|(tokens, _)| tokens.len() == 1 && tokens[0] == key)
+            || self.cities.values().any(|c| c.aliases.contains_key(&key))
+    }
+
+    /// Write dotted abbreviations of states and countries as their codes,
+    /// e.g. "B.C." as "BC" or "U.K." as "GB", otherwise periods split them into
+    /// single letters. Country codes have to be upper-case, e.g. "e.g." isn't Egypt.
+    /// Abbreviations that aren't codes or country aliases are left as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let collapsed = parser.collapse_dotted_codes("Brooklyn, N.Y., U.S.");
+    /// assert_eq!(collapsed, String::from("Brooklyn, NY, US"));
+    /// let collapsed = parser.collapse_dotted_codes("London, U.K.");
+    /// assert_eq!(collapsed, String::from("London, GB"));
+    /// let collapsed = parser.collapse_dotted_codes("Springfield, e.g. Ill.");
+    /// assert_eq!(collapsed, String::from("Springfield, e.g. Ill."));
+    /// ```
+    pub fn collapse_dotted_codes(&self, input: &str) -> String {
+        RE_DOTTED
+            .replace_all(input, |caps: &Captures| {
+                let code = caps[0].replace('.', "").to_uppercase();
+                // Lowercase "e.g." isn't Egypt, only states are common enough in lowercase
+                let is_upper = !caps[0].chars().any(|c| c.is_ascii_lowercase());
+                if self
+                    .state_of_segment(&code, &self.state_countries)
+                    .is_some()
+                    || (is_upper && self.country_from_code(&code).is_some())
+                {
+                    return code;
+                }
+                let alias = self.countries.aliases.iter().find(|(alias, _)| {
+                    alias
+                        .trim_end_matches('.')
+                        .eq_ignore_ascii_case(caps[0].trim_end_matches('.'))
+                });
+                match alias {
+                    Some((_, code)) => code.clone(),
+                    None => caps[0].to_string(),
+                }
+            })
+            .to_string()
+    }
+
+    /// Take states, countries and zipcodes out of brackets with digits, e.g. store
+    /// numbers, before such brackets are removed as noise. States and countries are
+    /// kept when a part of the brackets is a code or a name, or when a word is an
+    /// upper-case code. A zipcode is kept only along with its state, so that store
+    /// numbers aren't mistaken for zipcodes. Brackets without any are left as they are,
+    /// nested brackets are replaced along with the group around them.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let kept = parser.keep_bracket_codes("Midland (MI 48640)");
+    /// assert_eq!(kept, String::from("Midland , MI, 48640, "));
+    /// let kept = parser.keep_bracket_codes("Springfield (Store 10234)");
+    /// assert_eq!(kept, String::from("Springfield (Store 10234)"));
+    /// let kept = parser.keep_bracket_codes("Portland (Oregon (OR 97201))");
+    /// assert_eq!(kept, String::from("Portland , OR, 97201, "));
+    /// ```
+    pub fn keep_bracket_codes(&self, input: &str) -> String {
+        utils::replace_brackets(input, |text| {
+            if !text.chars().any(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            let kept = self.bracket_codes(text);
+            if kept.is_empty() {
+                return None;
+            }
+            trace_event!("kept codes from brackets", brackets = text, kept = kept);
+            Some(format!(", {}, ", kept.join(", ")))
+        })
+    }
+
+    /// States, countries and the zipcode of the text in brackets.
+    fn bracket_codes(&self, text: &str) -> Vec<String> {
+        let is_code = |w: &&str| w.len() == 2 && w.chars().all(|c| c.is_ascii_uppercase());
+        let mut kept: Vec<String> = vec![];
+        let mut states: Vec<State> = vec![];
+        let mut zipcodes: Vec<&str> = vec![];
+        for piece in RE_BRACKET_PIECES.split(text.trim()) {
+            let words: Vec<&str> = piece.split_whitespace().collect();
+            let codes = words.iter().copied().filter(is_code);
+            for candidate in std::iter::once(piece).chain(codes) {
+                // A piece that is a code is also one of its words
+                if kept.iter().any(|k| k == candidate) {
+                    continue;
+                }
+                if let Some((state, _)) = self.state_of_segment(candidate, &self.state_countries) {
+                    kept.push(candidate.to_string());
+                    states.push(state);
+                } else if self.country_of_segment(candidate).is_some() {
+                    kept.push(candidate.to_string());
+                }
+            }
+            zipcodes.push(piece);
+            zipcodes.extend(words);
+        }
+        let zipcode = zipcodes.into_iter().find(|z| {
+            self.location_from_zipcode(z)
+                .and_then(|l| l.state)
+                .is_some_and(|s| states.contains(&s))
+        });
+        kept.extend(zipcode.map(String::from));
+        kept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_noise() {
+        let patterns = read_noise_patterns();
+        for (input, output) in [
+            (
+                "CA-ON-Oakville-3235 Dundas St W (Store# 04278)",
+                "CA-ON-Oakville-3235 Dundas St W ",
+            ),
+            ("Midland (MI, USA)", "Midland (MI, USA)"),
+            ("600778 Wilton, NY - Route 50", "Wilton, NY - Route 50"),
+            ("410 - Wichita  - Kansas", "Wichita  - Kansas"),
+            (
+                "B - USA - FL - JACKSONVILLE - 9985 PRITCHARD RD",
+                "USA - FL - JACKSONVILLE - 9985 PRITCHARD RD",
+            ),
+            (
+                "United States-District of Columbia-washington-20340-DCCL",
+                "United States-District of Columbia-washington-20340-",
+            ),
+            (
+                "01713-Mall At Greece Ridge Center",
+                "01713-Mall At Greece Ridge Center",
+            ),
+            ("3235 Dundas St W", "3235 Dundas St W"),
+            ("Lansing, MI, US, 48911", "Lansing, MI, US, 48911"),
+        ] {
+            let mut s = input.to_string();
+            strip_noise(&mut s, &patterns);
+            assert_eq!(s, output, "Input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_compile_noise_patterns() {
+        let patterns = compile_noise_patterns(["", r"#\d+", "(unclosed"]);
+        assert_eq!(patterns.len(), 1);
+        let mut s = String::from("Toronto #12");
+        strip_noise(&mut s, &patterns);
+        assert_eq!(s, "Toronto ");
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_parse_location_with_custom_noise_patterns() {
+        let input = "Lansing, MI, US - Register 48911";
+        let location = Parser::new().parse_location(input);
+        assert_eq!(location.to_string(), "Lansing, MI, US, 48911");
+        let parser = Parser::builder()
+            .with_noise_patterns([r"\s*-\s*Register \d+$"])
+            .build();
+        let location = parser.parse_location(input);
+        assert_eq!(location.to_string(), "Lansing, MI, US");
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_keep_bracket_codes() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("Midland (MI 48640)", "Midland, MI, US, 48640"),
+            ("Seattle (WA 98101)", "Seattle, WA, US, 98101"),
+            ("Springfield (Store 10234, IL)", "Springfield, IL, US"),
+            ("Toronto (Store 12 - Ontario)", "Toronto, ON, CA"),
+            ("Portland (Store 31, ME)", "Portland, ME, US"),
+            ("Springfield (Store 10234)", "Springfield, MO, US"),
+            ("Portland (Oregon (OR 97201))", "Portland, OR, US, 97201"),
+            ("Chicago (Store (123)), IL", "Chicago, IL, US"),
+            ("Toronto (Toronto Eaton Centre (ON)), ON", "Toronto, ON, CA"),
+        ] {
+            assert_eq!(parser.parse_location(input).to_string(), output);
+        }
+        let kept = parser.keep_bracket_codes("Dayton (Unit 7, in OH)");
+        assert_eq!(kept, "Dayton , OH, ");
+        let kept = parser.keep_bracket_codes("Dayton (Mall (Unit 7), OH) (Store 12)");
+        assert_eq!(kept, "Dayton , OH,  (Store 12)");
+    }
+
+    #[test]
+    fn test_strip_noise_brackets() {
+        let patterns = read_noise_patterns();
+        for (input, output) in [
+            ("Chicago (Store (123)), IL", "Chicago  , IL"),
+            ("Chicago (Loop (Store 123)), IL", "Chicago  , IL"),
+            ("Chicago (Loop) (Store 123), IL", "Chicago (Loop)  , IL"),
+            ("Chicago (Loop (IL)) (#3)", "Chicago (Loop (IL))  "),
+            ("Chicago (Store 123, IL", "Chicago (Store 123, IL"),
+            ("Chicago ()", "Chicago  "),
+        ] {
+            let mut s = input.to_string();
+            strip_noise_brackets(&mut s, &patterns, |_| false);
+            assert_eq!(s, output, "Input: {}", input);
+        }
+        let mut s = String::from("Beijing (CHN)");
+        strip_noise_brackets(&mut s, &patterns, |m| m == "CHN");
+        assert_eq!(s, "Beijing (CHN)");
+    }
+
+    #[test]
+    #[cfg(feature = "data-us")]
+    fn test_keep_known_tokens() {
+        let parser = Parser::new();
+        assert_eq!(parser.normalize("Beijing, CHN"), "Beijing, CHN");
+        assert_eq!(parser.normalize("Dallas, TX - DFW"), "Dallas, TX");
+        assert_eq!(
+            parser.normalize("Portland, OR, U.S.A."),
+            "Portland, OR, USA"
+        );
+        let parser = Parser::builder()
+            .with_city_alias("DFW", "US", "TX", "Dallas")
+            .build();
+        assert_eq!(parser.parse_location("DFW").to_string(), "Dallas, TX, US");
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_collapse_dotted_codes() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("Vancouver, B.C.", "Vancouver, BC, CA"),
+            ("Brooklyn, N.Y., U.S.", "Brooklyn, NY, US"),
+            ("Halifax, n.s.", "Halifax, NS, CA"),
+            ("Seattle, WA, U.S.A.", "Seattle, WA, US"),
+            ("London, U.K.", "London, GB"),
+            ("Washington, D.C.", "Washington, DC, US"),
+        ] {
+            assert_eq!(parser.parse_location(input).to_string(), output);
+        }
+        assert_eq!(parser.collapse_dotted_codes("J.Q. Smith"), "J.Q. Smith");
+    }
+}
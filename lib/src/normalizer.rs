@@ -0,0 +1,272 @@
+//! Cleaning of location strings before they're parsed, made of steps that
+//! run in order and can be turned off or reordered, see `Normalizer`.
+//! `utils::clean` is the default normalizer, parsers get theirs from
+//! `ParserBuilder::with_normalize_steps`.
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs;
+//! use geo_rs::{NormalizeStep, Normalizer};
+//! let mut s = String::from("Mt. Vernon, NY, NY (Store 12)");
+//! Normalizer::new().normalize(&mut s);
+//! assert_eq!(s, String::from("Mount Vernon, NY"));
+//! let mut s = String::from("Mt. Vernon, NY, NY (Store 12)");
+//! Normalizer::new()
+//!     .without_step(NormalizeStep::Abbreviations)
+//!     .without_step(NormalizeStep::Dedup)
+//!     .normalize(&mut s);
+//! assert_eq!(s, String::from("Mt, Vernon, NY, NY"));
+//! ```
+use crate::noise;
+use crate::utils::{self, RE_POSSESSIVE, RE_QUOTES, RE_SPACES};
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref RE_LEADING: Regex = Regex::new(r"^[\s\-,;:_\.\?!/]*").unwrap();
+    static ref RE_TRAILING: Regex = Regex::new(r"[\s\-,;:_\.\?!/]*$").unwrap();
+    static ref RE_SPLITTER1: Regex = Regex::new(r"[^a-z\p{L}A-Z0-9\s'-]").unwrap();
+}
+
+/// Step of `Normalizer`, `Normalizer::new` runs all of them in the order they're listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NormalizeStep {
+    /// Expand abbreviations followed by a word, e.g. "Mt. Vernon" to "Mount Vernon"
+    Abbreviations,
+    /// Remove groups of brackets that are noise, see `noise::strip_noise_brackets`,
+    /// a parser takes state and zipcode codes out of them first
+    Brackets,
+    /// Remove whatever noise patterns match, see `noise::strip_noise`
+    Noise,
+    /// Split the input into segments separated by ", " at punctuation,
+    /// e.g. "Toronto/ON" to "Toronto, ON", and trim punctuation around it
+    Punctuation,
+    /// Collapse runs of whitespace and spaces around dashes
+    Whitespace,
+    /// Collapse repeated segments, e.g. "Canton, MA, Canton, MA", a parser also
+    /// collapses repeated names, see `Parser::collapse_repeats`
+    Dedup,
+}
+
+/// Every step in the default order
+const DEFAULT_STEPS: [NormalizeStep; 6] = [
+    NormalizeStep::Abbreviations,
+    NormalizeStep::Brackets,
+    NormalizeStep::Noise,
+    NormalizeStep::Punctuation,
+    NormalizeStep::Whitespace,
+    NormalizeStep::Dedup,
+];
+
+/// Ordered steps of cleaning along with the dictionaries they use,
+/// see the module docs.
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    pub(crate) steps: Vec<NormalizeStep>,
+    pub(crate) abbreviations: Vec<(String, String)>,
+    /// Patterns of store numbers, internal codes, etc.
+    pub(crate) noise_patterns: Vec<Regex>,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Normalizer {
+    /// Normalizer of all steps with abbreviations from `src/data/abbreviations.txt`
+    /// and noise patterns from `src/data/noise_patterns.txt`.
+    pub fn new() -> Self {
+        Self {
+            steps: DEFAULT_STEPS.to_vec(),
+            abbreviations: utils::read_abbreviations(),
+            noise_patterns: noise::read_noise_patterns(),
+        }
+    }
+
+    /// Run these steps in this order instead, e.g. to run `Dedup` before `Noise`.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - Steps to run, the ones that aren't listed are turned off
+    pub fn with_steps<I: IntoIterator<Item = NormalizeStep>>(mut self, steps: I) -> Self {
+        self.steps = steps.into_iter().unique().collect();
+        self
+    }
+
+    /// Turn the step off.
+    pub fn without_step(mut self, step: NormalizeStep) -> Self {
+        self.steps.retain(|s| *s != step);
+        self
+    }
+
+    /// Add abbreviations in addition to the ones it has.
+    ///
+    /// # Arguments
+    ///
+    /// * `abbreviations` - Pairs of abbreviation and its expansion, e.g. ("Hwy", "Highway")
+    pub fn with_abbreviations<I, A, E>(mut self, abbreviations: I) -> Self
+    where
+        I: IntoIterator<Item = (A, E)>,
+        A: Into<String>,
+        E: Into<String>,
+    {
+        self.abbreviations
+            .extend(abbreviations.into_iter().map(|(a, e)| (a.into(), e.into())));
+        self
+    }
+
+    /// Use these noise patterns instead of the ones it has.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - Compiled noise patterns, see `noise::compile_noise_patterns`
+    pub fn with_noise_patterns(mut self, patterns: Vec<Regex>) -> Self {
+        self.noise_patterns = patterns;
+        self
+    }
+
+    /// Steps it runs, in order.
+    pub fn steps(&self) -> &[NormalizeStep] {
+        &self.steps
+    }
+
+    /// Whether the step is on.
+    pub fn has_step(&self, step: NormalizeStep) -> bool {
+        self.steps.contains(&step)
+    }
+
+    /// Run the steps over the string and return the segments `Dedup` collapsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - String to be cleaned
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let mut s = String::from("!(#3)Toronto, Toronto,");
+    /// let collapsed = geo_rs::Normalizer::new().normalize(&mut s);
+    /// assert_eq!(s, String::from("Toronto"));
+    /// assert_eq!(collapsed, vec![String::from("Toronto")]);
+    /// ```
+    pub fn normalize(&self, s: &mut String) -> Vec<String> {
+        self.normalize_with(s, |_| false, |s| s.to_string())
+    }
+
+    /// Same as `normalize` but keep noise for which `keep` returns true and
+    /// replace brackets with `bracket_codes` before the noise ones are removed.
+    pub(crate) fn normalize_with<K, B>(
+        &self,
+        s: &mut String,
+        keep: K,
+        bracket_codes: B,
+    ) -> Vec<String>
+    where
+        K: Fn(&str) -> bool,
+        B: Fn(&str) -> String,
+    {
+        // Possessives are removed before any step, e.g. "Lee's Summit" is "Lees Summit"
+        *s = RE_POSSESSIVE.replace_all(s, "$1").to_string();
+        let mut collapsed = vec![];
+        for step in &self.steps {
+            match step {
+                NormalizeStep::Abbreviations => utils::expand_abbreviations(s, &self.abbreviations),
+                NormalizeStep::Brackets => {
+                    *s = bracket_codes(s);
+                    noise::strip_noise_brackets(s, &self.noise_patterns, &keep);
+                }
+                NormalizeStep::Noise => noise::strip_noise_except(s, &self.noise_patterns, &keep),
+                NormalizeStep::Punctuation => split_punctuation(s),
+                NormalizeStep::Whitespace => collapse_whitespace(s),
+                NormalizeStep::Dedup => collapsed.extend(dedup_segments(s)),
+            }
+        }
+        collapsed
+    }
+}
+
+/// See `NormalizeStep::Punctuation`.
+fn split_punctuation(s: &mut String) {
+    *s = RE_LEADING.replace_all(s, "").to_string();
+    *s = RE_TRAILING.replace_all(s, "").to_string();
+    // Cities are matched without apostrophes, see `city_key`
+    *s = RE_QUOTES.replace_all(s, " ").to_string();
+    *s = RE_SPLITTER1
+        .split(s)
+        .filter(|&x| !x.is_empty())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    *s = s.replace("St,", "St.").replace("Ft,", "Ft.");
+}
+
+/// See `NormalizeStep::Whitespace`.
+fn collapse_whitespace(s: &mut String) {
+    *s = RE_SPACES.replace_all(s, " ").to_string();
+    *s = s
+        .replace(" - ", "|-|")
+        .replace("- ", "-")
+        .replace("|-|", " - ")
+        .replace(", , ", ", ")
+        .replace("--", "-");
+}
+
+/// Remove segments between ", " that repeat earlier ones and return them.
+pub(crate) fn dedup_segments(s: &mut String) -> Vec<String> {
+    let mut collapsed: Vec<String> = vec![];
+    let segments: Vec<&str> = s.split(", ").collect();
+    for (i, segment) in segments.iter().enumerate() {
+        if segments[..i].contains(segment) && !collapsed.iter().any(|d| d == segment) {
+            collapsed.push(segment.to_string());
+        }
+    }
+    *s = s.split(", ").unique().join(", ");
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_steps() {
+        let input = "Ft. Meade (Store 12) -- MD/MD";
+        for (steps, output) in [
+            (DEFAULT_STEPS.to_vec(), "Fort Meade -MD, MD"),
+            (
+                vec![NormalizeStep::Abbreviations],
+                "Fort Meade (Store 12) -- MD/MD",
+            ),
+            (vec![NormalizeStep::Brackets], "Ft. Meade   -- MD/MD"),
+            (
+                vec![NormalizeStep::Punctuation, NormalizeStep::Dedup],
+                "Ft.  Meade , Store 12,  -- MD, MD",
+            ),
+            (
+                vec![NormalizeStep::Punctuation, NormalizeStep::Whitespace],
+                "Ft. Meade , Store 12, -MD, MD",
+            ),
+            (vec![], "Ft. Meade (Store 12) -- MD/MD"),
+        ] {
+            let mut s = input.to_string();
+            Normalizer::new()
+                .with_steps(steps.clone())
+                .normalize(&mut s);
+            assert_eq!(s, output, "{:?}", steps);
+        }
+        let normalizer = Normalizer::new().without_step(NormalizeStep::Noise);
+        assert!(!normalizer.has_step(NormalizeStep::Noise));
+        assert_eq!(normalizer.steps().len(), DEFAULT_STEPS.len() - 1);
+    }
+
+    #[test]
+    fn test_dedup_segments() {
+        let mut s = String::from("Canton, MA, Canton, MA, US");
+        assert_eq!(dedup_segments(&mut s), vec!["Canton", "MA"]);
+        assert_eq!(s, "Canton, MA, US");
+    }
+}
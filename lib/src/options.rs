@@ -0,0 +1,183 @@
+/// Letter case of city names in parsed locations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum OutputCase {
+    /// Title case, e.g. "Eielson Afb"
+    Title,
+    /// Spelling of the dataset, e.g. "Eielson AFB", unknown cities are title-cased
+    AsData,
+    /// Upper case, e.g. "EIELSON AFB"
+    Upper,
+}
+
+/// Options that tweak the behaviour of the `Parser`.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let options = geo_rs::ParserOptions::new().infer_unique_city(true);
+/// let parser = geo_rs::Parser::with_options(options);
+/// let location = parser.parse_location("Sausalito");
+/// assert_eq!(location.to_string(), String::from("Sausalito, CA, US"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParserOptions {
+    pub(crate) infer_unique_city: bool,
+    pub(crate) default_countries: Vec<String>,
+    pub(crate) country_bias: Vec<String>,
+    pub(crate) strict: bool,
+    pub(crate) output_case: OutputCase,
+    pub(crate) phonetic_cities: bool,
+    pub(crate) addresses: bool,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            infer_unique_city: false,
+            default_countries: vec![String::from("US"), String::from("CA")],
+            country_bias: vec![String::from("CA"), String::from("US")],
+            strict: false,
+            output_case: OutputCase::Title,
+            phonetic_cities: false,
+            addresses: false,
+        }
+    }
+}
+
+impl ParserOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ordered list of country codes to search in when input has no country,
+    /// earlier countries take priority over equally populous cities of later ones.
+    /// Defaults to `["US", "CA"]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let options = geo_rs::ParserOptions::new().default_countries(vec!["CA", "US"]);
+    /// let parser = geo_rs::Parser::with_options(options);
+    /// let location = parser.parse_location("Hamilton");
+    /// assert_eq!(location.country.unwrap().code, String::from("CA"));
+    /// ```
+    pub fn default_countries<I, S>(mut self, codes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.default_countries = codes.into_iter().map(|c| c.into()).collect();
+        self
+    }
+
+    /// Ordered list of country codes that decides what a code standing for
+    /// both a country and a state means when input has no other clue, e.g. "CA"
+    /// is Canada when "CA" comes first and California when "US" does.
+    /// Countries that aren't listed lose to the ones that are. Defaults to `["CA", "US"]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_location("Springfield, CA");
+    /// assert_eq!(location.country.unwrap().code, String::from("CA"));
+    /// let options = geo_rs::ParserOptions::new().country_bias(&["US", "CA"]);
+    /// let parser = geo_rs::Parser::with_options(options);
+    /// let location = parser.parse_location("Springfield, CA");
+    /// assert_eq!(location.to_string(), String::from("Springfield, CA, US"));
+    /// ```
+    pub fn country_bias<I, S>(mut self, codes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.country_bias = codes
+            .into_iter()
+            .map(|c| c.as_ref().to_uppercase())
+            .collect();
+        self
+    }
+
+    /// Infer state and country of a city when its name is unique
+    /// across all loaded states, even if input has no other context.
+    pub fn infer_unique_city(mut self, value: bool) -> Self {
+        self.infer_unique_city = value;
+        self
+    }
+
+    /// Letter case of city names, defaults to `OutputCase::Title`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let options = geo_rs::ParserOptions::new().output_case(geo_rs::OutputCase::Upper);
+    /// let parser = geo_rs::Parser::with_options(options);
+    /// let location = parser.parse_location("Bullhead City, AZ");
+    /// assert_eq!(location.to_string(), String::from("BULLHEAD CITY, AZ, US"));
+    /// ```
+    pub fn output_case(mut self, value: OutputCase) -> Self {
+        self.output_case = value;
+        self
+    }
+
+    /// Match misspelled cities by how they sound when nothing matches their spelling,
+    /// e.g. "Cincinatti" as Cincinnati. Such cities get `Warning::PhoneticCity`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let options = geo_rs::ParserOptions::new().phonetic_cities(true);
+    /// let parser = geo_rs::Parser::with_options(options);
+    /// let location = parser.parse_location("Pittsburg, PA");
+    /// assert_eq!(location.to_string(), String::from("Pittsburgh, PA, US"));
+    /// ```
+    pub fn phonetic_cities(mut self, value: bool) -> Self {
+        self.phonetic_cities = value;
+        self
+    }
+
+    /// Keep the street address, post office box or box of military mail of the input
+    /// in `Location::address`, so that it's displayed after the zipcode. Addresses are
+    /// fenced off from city matching either way, they are dropped by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let options = geo_rs::ParserOptions::new().addresses(true);
+    /// let parser = geo_rs::Parser::with_options(options);
+    /// let location = parser.parse_location("15 McKenna Rd  Arden, North Carolina");
+    /// assert_eq!(location.to_string(), String::from("Arden, NC, US, 15 McKenna Rd"));
+    /// ```
+    pub fn addresses(mut self, value: bool) -> Self {
+        self.addresses = value;
+        self
+    }
+
+    /// Make `Parser::try_parse_location` reject ambiguous locations and
+    /// locations with nothing recognized instead of returning them half-filled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let options = geo_rs::ParserOptions::new().strict(true);
+    /// let parser = geo_rs::Parser::with_options(options);
+    /// let result = parser.try_parse_location("Portland");
+    /// assert_eq!(result, Err(geo_rs::ParseError::Ambiguous));
+    /// ```
+    pub fn strict(mut self, value: bool) -> Self {
+        self.strict = value;
+        self
+    }
+}
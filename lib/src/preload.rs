@@ -0,0 +1,158 @@
+use crate::{Parser, ParserBuilder, ParserOptions};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+/// Parser that is being built on a background thread, see `Parser::preload`.
+/// It's a future that resolves to the parser once its datasets are loaded,
+/// so it can be awaited with any executor, or waited for with `Preload::wait`.
+#[derive(Debug)]
+pub struct Preload {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    ready: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    parser: Option<Parser>,
+    loaded: bool,
+    waker: Option<Waker>,
+}
+
+impl Preload {
+    fn spawn<F: FnOnce() -> Parser + Send + 'static>(build: F) -> Self {
+        let shared = Arc::new(Shared::default());
+        let background = shared.clone();
+        thread::Builder::new()
+            .name(String::from("geo-rs-preload"))
+            .spawn(move || {
+                let parser = build();
+                let mut state = background.state.lock().unwrap();
+                state.parser = Some(parser);
+                state.loaded = true;
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+                background.ready.notify_all();
+            })
+            .expect("failed to spawn a thread to load the parser");
+        Self { shared }
+    }
+
+    /// Whether the datasets are loaded and the parser can be taken without waiting.
+    pub fn is_ready(&self) -> bool {
+        self.shared.state.lock().unwrap().loaded
+    }
+
+    /// Block the current thread until the parser is loaded.
+    pub fn wait(self) -> Parser {
+        let mut state = self.shared.state.lock().unwrap();
+        while !state.loaded {
+            state = self.shared.ready.wait(state).unwrap();
+        }
+        state.parser.take().expect("parser was already taken")
+    }
+}
+
+impl Future for Preload {
+    type Output = Parser;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Parser> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.loaded {
+            return Poll::Ready(state.parser.take().expect("parser was already taken"));
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Parser {
+    /// Start loading datasets on a background thread, so that a service can do
+    /// other things while it starts, instead of blocking on reading the files.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Parser options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let preload = geo_rs::Parser::preload(geo_rs::ParserOptions::default());
+    /// // ... start the rest of the service, or `preload.await` in async code
+    /// let parser = preload.wait();
+    /// let location = parser.parse_location("Toronto, ON, CA");
+    /// assert_eq!(location.to_string(), String::from("Toronto, ON, CA"));
+    /// ```
+    pub fn preload(options: ParserOptions) -> Preload {
+        Preload::spawn(move || Parser::with_options(options))
+    }
+}
+
+impl ParserBuilder {
+    /// Build the parser on a background thread like `Parser::preload` does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let preload = geo_rs::Parser::builder()
+    ///     .with_city_alias("The Big Apple", "US", "NY", "New York")
+    ///     .preload();
+    /// let parser = preload.wait();
+    /// let location = parser.parse_location("The Big Apple");
+    /// assert_eq!(location.to_string(), String::from("New York, NY, US"));
+    /// ```
+    pub fn preload(self) -> Preload {
+        Preload::spawn(move || self.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::Wake;
+
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Minimal executor, polls the future whenever it's woken up
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_preload() {
+        let options = ParserOptions::new().infer_unique_city(true);
+        let parser = block_on(Parser::preload(options));
+        let location = parser.parse_location("Sausalito");
+        assert_eq!(location.to_string(), String::from("Sausalito, CA, US"));
+
+        let preload = Parser::builder().preload();
+        let parser = preload.wait();
+        let location = parser.parse_location("Toronto, ON");
+        assert_eq!(location.to_string(), String::from("Toronto, ON, CA"));
+    }
+}
@@ -0,0 +1,21 @@
+//! Re-exports of the most commonly used types.
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs::prelude::*;
+//! let parser = Parser::new();
+//! let location: Location = parser.parse_location("Toronto, ON, CA");
+//! assert_eq!(location.state, Some(State {
+//!     code: String::from("ON"),
+//!     name: String::from("Ontario"),
+//! }));
+//! ```
+pub use crate::nodes::{
+    Address, AddressKind, City, Country, Granularity, Landmark, LandmarkKind, Location,
+    LocationBuilder, Parts, ScoredLocation, State, StreetAddress, Warning, Zipcode,
+};
+pub use crate::{
+    ChainedLocation, LocationInput, OutputCase, ParseError, Parser, ParserBuilder, ParserChain,
+    ParserOptions, Preload, TraceStep, ValidationIssue, ValidationReport,
+};
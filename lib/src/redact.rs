@@ -0,0 +1,158 @@
+use crate::nodes::{Location, Warning};
+use crate::{utils, Parser};
+use lazy_static::lazy_static;
+use regex::Regex;
+use unidecode::unidecode;
+
+lazy_static! {
+    static ref RE_EMPTY_SEPARATORS: Regex = Regex::new(r"\s*,(?:\s*,)+").unwrap();
+    static ref RE_SPACE_BEFORE_COMMA: Regex = Regex::new(r"\s+,").unwrap();
+    static ref RE_SPACES: Regex = Regex::new(r"[ \t]{2,}").unwrap();
+}
+
+impl Parser {
+    /// Remove detected location components (city, state, country and zipcode)
+    /// from a free text, e.g. to scrub personal data before storing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Text to be redacted
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let text = parser.redact("Shipped from Toronto, ON M5V 2T6 yesterday");
+    /// assert_eq!(text, String::from("Shipped from yesterday"));
+    /// ```
+    pub fn redact(&self, input: &str) -> String {
+        let mut output = input.to_string();
+        for (start, end) in self.redacted_ranges(input).into_iter().rev() {
+            // Swallow the separator that follows a removed component
+            let rest = &input[end..];
+            let separator = rest.len() - rest.trim_start_matches([',', ' ']).len();
+            output.replace_range(start..end + separator, "");
+        }
+        output = RE_EMPTY_SEPARATORS.replace_all(&output, ",").to_string();
+        output = RE_SPACE_BEFORE_COMMA.replace_all(&output, ",").to_string();
+        output = RE_SPACES.replace_all(&output, " ").to_string();
+        output
+            .trim_matches(|c: char| c.is_whitespace() || c == ',')
+            .to_string()
+    }
+
+    /// Replace detected location components (city, state, country and zipcode)
+    /// in a free text with the given mask, keeping the rest of the text intact.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Text to be redacted
+    /// * `mask` - Replacement of every detected component
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let text = parser.redact_with("Shipped from Toronto, ON M5V 2T6 yesterday", "***");
+    /// assert_eq!(text, String::from("Shipped from ***, *** *** yesterday"));
+    /// ```
+    pub fn redact_with(&self, input: &str, mask: &str) -> String {
+        let mut output = input.to_string();
+        for (start, end) in self.redacted_ranges(input).into_iter().rev() {
+            output.replace_range(start..end, mask);
+        }
+        output
+    }
+
+    /// Return sorted non-overlapping byte ranges of detected location components.
+    fn redacted_ranges(&self, input: &str) -> Vec<(usize, usize)> {
+        let location = self.parse_location(input);
+        let mut ranges: Vec<(usize, usize)> = vec![];
+        for (part, case_sensitive) in self.redacted_parts(&location) {
+            ranges.extend(find_part(input, &part, case_sensitive));
+        }
+        ranges.sort();
+        // Merge overlapping ranges, e.g. "New York" and "York"
+        let mut merged: Vec<(usize, usize)> = vec![];
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// Return parts of the location to search for in the text and whether
+    /// the search has to be case sensitive, e.g. codes like "US" or "ON".
+    fn redacted_parts(&self, location: &Location) -> Vec<(String, bool)> {
+        let mut parts: Vec<(String, bool)> = vec![];
+        if let Some(z) = &location.zipcode {
+            parts.push((z.zipcode.clone(), false));
+        }
+        // Unknown cities are just leftovers of the input, not a detected component
+        if let (Some(c), false) = (
+            &location.city,
+            location.warnings.contains(&Warning::UnknownCity),
+        ) {
+            parts.push((c.name.clone(), false));
+        }
+        if let Some(s) = &location.state {
+            parts.push((s.name.clone(), false));
+            parts.push((s.code.clone(), true));
+        }
+        if let Some(c) = &location.country {
+            parts.push((c.name.clone(), false));
+            parts.push((c.code.clone(), true));
+            for (alias, code) in &self.countries.aliases {
+                if code == &c.code {
+                    parts.push((alias.clone(), alias == &alias.to_uppercase()));
+                }
+            }
+        }
+        parts
+    }
+}
+
+/// Find token-bounded occurrences of a part in the text, diacritics are ignored
+/// when the text is plain ASCII after folding.
+fn find_part(input: &str, part: &str, case_sensitive: bool) -> Vec<(usize, usize)> {
+    let mut ranges = utils::find_token(input, part);
+    if !case_sensitive {
+        let as_lowercase = input.to_lowercase();
+        // Byte offsets are only valid when lowercasing kept the length
+        if as_lowercase.len() == input.len() {
+            ranges.extend(utils::find_token(&as_lowercase, &part.to_lowercase()));
+        }
+        let folded = unidecode(input).to_lowercase();
+        if folded.len() == input.len() {
+            ranges.extend(utils::find_token(&folded, &unidecode(part).to_lowercase()));
+        }
+    }
+    ranges
+}
+
+#[cfg(all(test, feature = "data-ca"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let parser = Parser::new();
+        let text = "Contact John at 123 Main St, Toronto, ON M5V 2T6, Canada";
+        assert_eq!(
+            parser.redact_with(text, "[REDACTED]"),
+            String::from(
+                "Contact John at 123 Main St, [REDACTED], [REDACTED] [REDACTED], [REDACTED]"
+            )
+        );
+        assert_eq!(
+            parser.redact(text),
+            String::from("Contact John at 123 Main St")
+        );
+        let text = "Nothing to see here";
+        assert_eq!(parser.redact(text), String::from(text));
+    }
+}
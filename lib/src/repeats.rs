@@ -0,0 +1,318 @@
+use crate::nodes::{CountriesMap, CountryCities, CountryStates, Location, Warning};
+use crate::utils::{RE_POSSESSIVE, RE_QUOTES};
+use crate::{normalizer, Parser};
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use unidecode::unidecode;
+
+/// Longest name, in words, that is looked for among repeated parts of the input
+const MAX_NAME_WORDS: usize = 6;
+
+lazy_static! {
+    static ref RE_WORDS: Regex = Regex::new(r"[\p{L}\p{N}]+").unwrap();
+}
+
+/// Drop apostrophes the way cleaning does, see `utils::city_key`,
+/// e.g. "Lee's Summit" becomes "Lees Summit" and "Coeur d'Alene" "Coeur d Alene".
+fn without_apostrophes(s: &str) -> String {
+    let s = RE_POSSESSIVE.replace_all(s, "$1");
+    RE_QUOTES.replace_all(&s, " ").to_string()
+}
+
+/// Lowercase words of the string joined with a single space,
+/// e.g. "Winston-Salem" and "WINSTON SALEM" both become "winston salem".
+fn words_key(s: &str) -> String {
+    RE_WORDS
+        .find_iter(&without_apostrophes(s))
+        .map(|m| m.as_str().to_lowercase())
+        .join(" ")
+}
+
+/// Lowercase ASCII words of the string, e.g. "Montréal-Nord" becomes ["montreal", "nord"].
+fn words(s: &str) -> Vec<String> {
+    RE_WORDS
+        .find_iter(&without_apostrophes(&unidecode(s)))
+        .map(|m| m.as_str().to_lowercase())
+        .collect()
+}
+
+/// Collect names of cities, states and countries as `words_key`s,
+/// so that their repeated mentions in the input can be recognized.
+///
+/// # Arguments
+///
+/// * `cities` - Cities of all countries
+/// * `states` - States of all countries
+/// * `countries` - Countries map
+pub(crate) fn known_names(
+    cities: &CountryCities,
+    states: &CountryStates,
+    countries: &CountriesMap,
+) -> HashSet<String> {
+    let mut names: HashSet<String> = HashSet::new();
+    for country_cities in cities.values() {
+        for state_cities in country_cities.cities_by_state.values() {
+            names.extend(state_cities.iter().map(|c| words_key(c)));
+        }
+    }
+    for country_states in states.values() {
+        names.extend(country_states.name_to_code.keys().map(|n| words_key(n)));
+    }
+    names.extend(countries.name_to_code.keys().map(|n| words_key(n)));
+    names.extend(countries.aliases.iter().map(|(alias, _)| words_key(alias)));
+    names.retain(|n| !n.is_empty());
+    names
+}
+
+impl Parser {
+    /// Whether the string is a name of a city, a state or a country, letter case,
+    /// dashes and spacing don't matter, e.g. "college station" or "Winston Salem".
+    pub(crate) fn is_known_name(&self, s: &str) -> bool {
+        self.known_names.contains(&words_key(s))
+    }
+
+    /// Collapse repeated mentions of the same part of a location into the first one
+    /// and return collapsed parts. Repeated segments between commas are collapsed
+    /// whatever they are, e.g. "Canton, MA, Canton, MA", repeated names of cities,
+    /// states and countries and repeated state codes anywhere in the input,
+    /// e.g. "San Diego-US CA San Diego". Names that are part of a longer name,
+    /// like "Kansas" in "Kansas City, Kansas", are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Cleaned location string, see `utils::clean`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut input = String::from("United States-California-San Diego-US CA San Diego");
+    /// let collapsed = parser.collapse_repeats(&mut input);
+    /// assert_eq!(input, String::from("United States-California-San Diego-US CA"));
+    /// assert_eq!(collapsed, vec![String::from("San Diego")]);
+    /// ```
+    pub fn collapse_repeats(&self, input: &mut String) -> Vec<String> {
+        let mut collapsed = normalizer::dedup_segments(input);
+        while let Some((name, (start, end))) = self.find_repeat(input) {
+            input.replace_range(start..end, "");
+            if !collapsed.contains(&name) {
+                collapsed.push(name);
+            }
+        }
+        collapsed
+    }
+
+    /// Collapse what's left of the input once the location is parsed when it only
+    /// repeats the location, e.g. "CANTON, Massachusetts" of "Canton, MA, CANTON,
+    /// Massachusetts". Names are compared by their words, so that codes, names,
+    /// letter case and dashes don't matter. The remainder has to mention the city
+    /// or the state, collapsed segments are added to `Location::collapsed`.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Parsed location
+    /// * `remainder` - Part of the input that parsing hasn't consumed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = parser.parse_location("Canton, MA");
+    /// let mut remainder = String::from("CANTON, Massachusetts");
+    /// parser.collapse_duplicate_location(&mut location, &mut remainder);
+    /// assert_eq!(remainder, String::new());
+    /// assert_eq!(location.collapsed, vec![String::from("CANTON"), String::from("Massachusetts")]);
+    /// ```
+    pub fn collapse_duplicate_location(&self, location: &mut Location, remainder: &mut String) {
+        // Unknown city is taken from the remainder, it can't repeat itself
+        if location.warnings.contains(&Warning::UnknownCity) {
+            return;
+        }
+        let mut names: Vec<(Vec<String>, bool)> = vec![];
+        if let Some(c) = &location.city {
+            names.push((words(&c.name), true));
+        }
+        if let Some(s) = &location.state {
+            names.push((words(&s.code), true));
+            names.push((words(&s.name), true));
+        }
+        if let Some(c) = &location.country {
+            names.push((words(&c.code), false));
+            names.push((words(&c.name), false));
+        }
+        names.sort_by_key(|(name, _)| Reverse(name.len()));
+        let mut left = words(remainder);
+        if left.is_empty() {
+            return;
+        }
+        let mut mentions_location = false;
+        for (name, is_city_or_state) in names.iter().filter(|(name, _)| !name.is_empty()) {
+            while let Some(i) = left.windows(name.len()).position(|w| w == name.as_slice()) {
+                left.drain(i..i + name.len());
+                mentions_location |= is_city_or_state;
+            }
+        }
+        if !left.is_empty() || !mentions_location {
+            return;
+        }
+        trace_event!("collapsed duplicate location", remainder = remainder);
+        for segment in remainder
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            if !location.collapsed.iter().any(|c| c == segment) {
+                location.collapsed.push(segment.to_string());
+            }
+        }
+        remainder.clear();
+    }
+
+    /// Find the first name mentioned twice and return it along with the byte range
+    /// of its second mention, including the separator in front of it.
+    fn find_repeat(&self, input: &str) -> Option<(String, (usize, usize))> {
+        let words: Vec<(usize, usize)> = RE_WORDS
+            .find_iter(input)
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        let text = |from: usize, to: usize| &input[words[from].0..words[to - 1].1];
+        // Names starting at every word by their length in words, single words
+        // are compared as is, so that "ON" and "on" aren't the same
+        let names: Vec<Vec<Option<String>>> = (0..words.len())
+            .map(|from| {
+                (from + 1..=(from + MAX_NAME_WORDS).min(words.len()))
+                    .map(|to| {
+                        let text = text(from, to);
+                        let known = match to - from {
+                            1 if text.len() == 2 && text == text.to_uppercase() => {
+                                !self.code_kind(text).is_empty()
+                            }
+                            _ => self.known_names.contains(&words_key(text)),
+                        };
+                        match (known, to - from) {
+                            (false, _) => None,
+                            (true, 1) => Some(text.to_string()),
+                            (true, _) => Some(words_key(text)),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let name = |from: usize, to: usize| names[from].get(to - from - 1).and_then(|n| n.as_ref());
+        // A mention is standalone when no longer name covers it
+        let is_standalone = |from: usize, to: usize| {
+            (from.saturating_sub(MAX_NAME_WORDS)..=from).all(|a| {
+                (to..=(a + MAX_NAME_WORDS).min(words.len()))
+                    .all(|b| b - a <= to - from || name(a, b).is_none())
+            })
+        };
+        for n in (1..=MAX_NAME_WORDS).rev() {
+            for i in 0..(words.len() + 1).saturating_sub(n) {
+                let first = match name(i, i + n) {
+                    Some(first) if is_standalone(i, i + n) => first,
+                    _ => continue,
+                };
+                for j in (i + n)..(words.len() + 1).saturating_sub(n) {
+                    if name(j, j + n) == Some(first) && is_standalone(j, j + n) {
+                        let mention = text(i, i + n).to_string();
+                        return Some((mention, (words[j - 1].1, words[j + n - 1].1)));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(all(test, feature = "data-us", feature = "data-ca"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse_repeats() {
+        let parser = Parser::new();
+        for (input, output, collapsed) in [
+            (
+                "Canton, MA, Canton, MA, US",
+                "Canton, MA, US",
+                vec!["Canton", "MA"],
+            ),
+            (
+                "FORT BELVOIR, VA, US, 22060, FORT BELVOIR",
+                "FORT BELVOIR, VA, US, 22060",
+                vec!["FORT BELVOIR"],
+            ),
+            (
+                "United States-California-San Diego-US CA San Diego - West Brdway",
+                "United States-California-San Diego-US CA - West Brdway",
+                vec!["San Diego"],
+            ),
+            (
+                "New York, NY, New York, NY 10001",
+                "New York, NY 10001",
+                vec!["New York", "NY"],
+            ),
+            ("Portland Portland OR", "Portland OR", vec!["Portland"]),
+            (
+                "CA-ON-Toronto-Toronto ON",
+                "CA-ON-Toronto",
+                vec!["ON", "Toronto"],
+            ),
+            ("Kansas City, Kansas", "Kansas City, Kansas", vec![]),
+            ("Kansas, Kansas City", "Kansas, Kansas City", vec![]),
+            ("Walla Walla, WA", "Walla Walla, WA", vec![]),
+            (
+                "Saint Paul, MN, on Main St, on 5th",
+                "Saint Paul, MN, on Main St, on 5th",
+                vec![],
+            ),
+        ] {
+            let mut s = input.to_string();
+            let actual = parser.collapse_repeats(&mut s);
+            assert_eq!(s, output, "Input: {}", input);
+            assert_eq!(actual, collapsed, "Input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_collapse_duplicate_location() {
+        let parser = Parser::new();
+        for (input, output, collapsed) in [
+            (
+                "CANTON, MA, Canton, Massachusetts",
+                "Canton, MA, US",
+                vec!["CANTON", "Massachusetts"],
+            ),
+            (
+                "Toronto, ON, Toronto, Ontario, Canada",
+                "Toronto, ON, CA",
+                vec!["Toronto", "Ontario"],
+            ),
+            (
+                "Winston-Salem, NC, Winston Salem, NC",
+                "Winston Salem, NC, US",
+                vec!["NC", "Winston-Salem"],
+            ),
+            ("Foo Bar", "Foo Bar", vec![]),
+            // Cities spelled without apostrophes or in another case aren't repeats
+            ("Coeur d'Alene, ID", "Coeur d'Alene, ID, US", vec![]),
+            ("Lee's Summit, MO", "Lee's Summit, MO, US", vec![]),
+            ("TORONTO, ON", "Toronto, ON, CA", vec![]),
+        ] {
+            let location = parser.parse_location(input);
+            assert_eq!(location.to_string(), output, "Input: {}", input);
+            assert_eq!(location.collapsed, collapsed, "Input: {}", input);
+            assert_eq!(
+                location.warnings.contains(&Warning::Duplicates),
+                !collapsed.is_empty(),
+                "Input: {}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Data files may start with a header that names the version of their schema
+//! and their columns, e.g. "# geo-rs schema 1: code;name;fips" for `US/states.txt`.
+//! The header is optional, files without it are read as files of the current
+//! schema. Files with a header of another schema version or with unexpected
+//! columns are rejected by `validate`, see `ParserBuilder::try_build`.
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs;
+//! assert!(geo_rs::schema::validate(geo_rs::utils::data_source()).is_ok());
+//! ```
+use crate::utils::DataSource;
+use std::error::Error;
+use std::fmt;
+
+/// Version of the schema of data files this version of the crate reads
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Start of the header line of a data file
+pub const HEADER_PREFIX: &str = "# geo-rs schema ";
+
+/// Columns of data files of every country, the first `required` of them must be present
+const COUNTRY_FILES: [(&str, &[&str], usize); 6] = [
+    ("states.txt", &["code", "name", "fips"], 2),
+    ("state_names.txt", &["code", "name"], 2),
+    ("zipcodes.txt", &["from", "to", "state"], 3),
+    (
+        "cities.txt",
+        &["state", "name", "population", "latitude", "longitude"],
+        2,
+    ),
+    ("aliases.txt", &["state", "city", "alias"], 3),
+    ("metros.txt", &["state", "city", "metro"], 3),
+];
+
+/// Columns of data files shared by all countries
+const SHARED_FILES: [(&str, &[&str], usize); 4] = [
+    (
+        "countries.txt",
+        &[
+            "name",
+            "code",
+            "alpha3",
+            "numeric",
+            "continent",
+            "region",
+            "dialing_code",
+            "currency",
+        ],
+        2,
+    ),
+    ("country_aliases.txt", &["alias", "code"], 2),
+    ("country_unions.txt", &["union", "code"], 2),
+    ("aliases_historical.txt", &["name", "country", "city"], 2),
+];
+
+/// Header of a data file, see `Header::parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    pub columns: Vec<String>,
+}
+
+impl Header {
+    /// Parse the header line, `None` when the line isn't a header at all and
+    /// an error message when it's a header that can't be parsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - First line of a data file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::schema::Header;
+    /// let header = Header::parse("# geo-rs schema 1: code;name;fips").unwrap().unwrap();
+    /// assert_eq!(header.version, 1);
+    /// assert_eq!(header.columns, vec!["code", "name", "fips"]);
+    /// assert!(Header::parse("# geo-rs schema one: code").unwrap().is_err());
+    /// assert!(Header::parse("ON;Ontario").is_none());
+    /// ```
+    pub fn parse(line: &str) -> Option<Result<Self, String>> {
+        let rest = line.strip_prefix(HEADER_PREFIX)?;
+        let (version, columns) = rest.split_once(':').unwrap_or((rest, ""));
+        let version = match version.trim().parse::<u32>() {
+            Ok(version) => version,
+            Err(_) => return Some(Err(format!("invalid schema version {:?}", version))),
+        };
+        let columns = columns
+            .split([';', ',', '\t'])
+            .map(|c| c.trim().to_lowercase())
+            .filter(|c| !c.is_empty())
+            .collect();
+        Some(Ok(Self { version, columns }))
+    }
+}
+
+/// Reasons data files are rejected by `validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    /// The header can't be parsed
+    Malformed { file: String, reason: String },
+    /// The file is written for another version of the schema
+    Version { file: String, version: u32 },
+    /// Columns of the file aren't the ones this schema has
+    Columns {
+        file: String,
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Malformed { file, reason } => {
+                write!(f, "malformed schema header of {}: {}", file, reason)
+            }
+            SchemaError::Version { file, version } => {
+                let crate_version = match *version > SCHEMA_VERSION {
+                    true => "a newer",
+                    false => "an older",
+                };
+                write!(
+                    f,
+                    "{} is written for schema {} of {} geo-rs version, this version reads schema {}",
+                    file, version, crate_version, SCHEMA_VERSION
+                )
+            }
+            SchemaError::Columns {
+                file,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} has columns {} but schema {} expects {}",
+                file,
+                found.join(";"),
+                SCHEMA_VERSION,
+                expected.join(";")
+            ),
+        }
+    }
+}
+
+impl Error for SchemaError {}
+
+/// Check the header of the data file, files without a header are valid.
+///
+/// # Arguments
+///
+/// * `source` - Where to read the file from
+/// * `file` - Path of the file relative to the data folder, e.g. "US/states.txt"
+/// * `columns` - Columns of the current schema
+/// * `required` - Number of leading columns the file must have
+fn validate_file(
+    source: &DataSource,
+    file: &str,
+    columns: &[&str],
+    required: usize,
+) -> Result<(), SchemaError> {
+    let first_line = match source.read_optional_lines(file).next() {
+        Some(line) => line,
+        None => return Ok(()),
+    };
+    let header = match Header::parse(&first_line) {
+        None => return Ok(()),
+        Some(Ok(header)) => header,
+        Some(Err(reason)) => {
+            let file = file.to_string();
+            return Err(SchemaError::Malformed { file, reason });
+        }
+    };
+    if header.version != SCHEMA_VERSION {
+        let file = file.to_string();
+        let version = header.version;
+        return Err(SchemaError::Version { file, version });
+    }
+    let found = header.columns;
+    if found.len() < required || found.len() > columns.len() || columns[..found.len()] != found {
+        return Err(SchemaError::Columns {
+            file: file.to_string(),
+            expected: columns.iter().map(|c| c.to_string()).collect(),
+            found,
+        });
+    }
+    Ok(())
+}
+
+/// Check headers of the data files of the source, see the module docs.
+///
+/// # Arguments
+///
+/// * `source` - Where to read data files from, e.g. `utils::data_source()`
+pub fn validate(source: &DataSource) -> Result<(), SchemaError> {
+    for (file, columns, required) in SHARED_FILES {
+        validate_file(source, file, columns, required)?;
+    }
+    for country in source.countries() {
+        for (file, columns, required) in COUNTRY_FILES {
+            let file = format!("{}/{}", country, file);
+            validate_file(source, &file, columns, required)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::city::read_cities_from;
+    use crate::nodes::country::read_countries_from;
+    use std::path::Path;
+
+    #[test]
+    fn test_validate() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/schema");
+        let source = DataSource::Dir(fixtures.join("current"));
+        assert_eq!(validate(&source), Ok(()));
+        let (countries, skipped) = read_countries_from(&source);
+        assert_eq!((countries.code_to_name.len(), skipped), (2, 0));
+        let (cities, skipped) = read_cities_from(&source);
+        assert_eq!(
+            (cities.len(), skipped),
+            (cfg!(feature = "data-us") as usize, 0)
+        );
+        let source = DataSource::Dir(fixtures.join("newer"));
+        let error = validate(&source).unwrap_err();
+        assert_eq!(
+            error,
+            SchemaError::Version {
+                file: String::from("countries.txt"),
+                version: 2
+            }
+        );
+        assert!(error.to_string().contains("a newer geo-rs version"));
+        if cfg!(feature = "data-us") {
+            let source = DataSource::Dir(fixtures.join("columns"));
+            let error = validate(&source).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "US/states.txt has columns name;code but schema 1 expects code;name;fips"
+            );
+        }
+    }
+}
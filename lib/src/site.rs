@@ -0,0 +1,87 @@
+use crate::nodes::Location;
+use crate::utils::RE_SPACES;
+use crate::Parser;
+
+/// Uppercase code with single spaces, so that "dccl" and " DCCL" are the same code.
+pub(crate) fn site_key(code: &str) -> String {
+    RE_SPACES.replace_all(code.trim(), " ").to_uppercase()
+}
+
+impl Parser {
+    /// Resolve an internal site code of a company to its location, see
+    /// `ParserBuilder::with_site_codes`. The input has to be the code, or start
+    /// with it followed by a non-alphanumeric character, e.g. "410 - Lot 2" for
+    /// "410 -" or "DCCL (Dock 3)" for "DCCL". Letter case and spacing don't
+    /// matter, longer codes win. Returns whether a code matched.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that's gonna be filled with the site's location
+    /// * `input` - Raw location string
+    pub fn fill_site_code(&self, location: &mut Location, input: &str) -> bool {
+        let key = site_key(input);
+        let site = self.site_codes.iter().find(|(code, _)| {
+            key.strip_prefix(code.as_str()).is_some_and(|rest| {
+                rest.is_empty()
+                    || !code.ends_with(|c: char| c.is_alphanumeric())
+                    || !rest.starts_with(|c: char| c.is_alphanumeric())
+            })
+        });
+        let (code, site) = match site {
+            Some(site) => site,
+            None => return false,
+        };
+        trace_event!("matched site code", code = code);
+        location.city = site.city.clone();
+        location.state = site.state.clone();
+        location.country = site.country.clone();
+        location.zipcode = site.zipcode.clone();
+        location.address = site.address.clone();
+        location.landmark = site.landmark.clone();
+        true
+    }
+}
+
+#[cfg(all(test, feature = "data-ca"))]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse_site_codes() {
+        let warehouse = Location::builder()
+            .with_city("Mississauga")
+            .with_state("ON", "Ontario")
+            .with_country("CA", "Canada")
+            .with_zipcode("L5T 2N7")
+            .build();
+        let store = Location::builder()
+            .with_city("Lansing")
+            .with_state("MI", "Michigan")
+            .with_country("US", "United States")
+            .build();
+        let mut codes = HashMap::new();
+        codes.insert(String::from("DCCL"), warehouse);
+        codes.insert(String::from("410 -"), store.clone());
+        codes.insert(String::from("B -"), store);
+        let parser = Parser::builder().with_site_codes(codes).build();
+        for (input, output) in [
+            ("DCCL", "Mississauga, ON, CA, L5T2N7"),
+            (" dccl (Dock 3)", "Mississauga, ON, CA, L5T2N7"),
+            ("410 - Frandor", "Lansing, MI, US"),
+            ("410  -", "Lansing, MI, US"),
+            ("B - Toronto", "Lansing, MI, US"),
+            ("DCCLX, Toronto, ON", "Toronto, ON, CA"),
+            ("4100 - Toronto, ON", "Toronto, ON, CA"),
+            ("Toronto, ON", "Toronto, ON, CA"),
+        ] {
+            let location = parser.parse_location(input);
+            assert_eq!(location.to_string(), output, "input: {}", input);
+        }
+        let parser = Parser::builder()
+            .with_site_codes(HashMap::from([(String::from("DCCL"), Location::default())]))
+            .without_stage("site_code")
+            .build();
+        assert_eq!(parser.parse_location("DCCL").to_string(), "");
+    }
+}
@@ -0,0 +1,166 @@
+use crate::nodes::{Location, Warning};
+use crate::Parser;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// Scheme, credentials and host of a URL, e.g. "https://careers.example.com"
+    static ref RE_URL_HOST: Regex = Regex::new(r"(?i)^(?:[a-z][a-z0-9+.-]*://[^/?#]*|www\.[^/?#]*)").unwrap();
+    static ref RE_URL: Regex = Regex::new(r"(?i)\b(?:[a-z][a-z0-9+.-]*://|www\.)\S+").unwrap();
+    static ref RE_EMAIL: Regex = Regex::new(r"\S+@\S+\.\w+").unwrap();
+    /// Phone number of at least 9 digits and separators, e.g. "+1 (512) 555-0100"
+    static ref RE_PHONE: Regex = Regex::new(r"\+?\(?\d[\d\s().-]{7,}\d").unwrap();
+    /// Separators of lines of a signature, e.g. "Acme Corp | Austin, TX"
+    static ref RE_LINES: Regex = Regex::new(r"[\r\n|•]+").unwrap();
+    /// Separators of words of a slug, e.g. "new-york-ny-10001" or "san_jose+ca"
+    static ref RE_SLUG_SEPARATORS: Regex = Regex::new(r"[-_+]+|%20").unwrap();
+    /// File extension of a path segment, e.g. ".html"
+    static ref RE_EXTENSION: Regex = Regex::new(r"(?i)\.(?:s?html?|php|aspx?|jsp)$").unwrap();
+}
+
+/// Segments of a URL's path and values of its query that may name a location,
+/// e.g. "toronto-on" of "https://example.com/jobs/toronto-on/?ref=email".
+fn url_segments(url: &str) -> Vec<String> {
+    let path = RE_URL_HOST.replace(url, "");
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (path.to_string(), String::new()),
+    };
+    let path = path.split('#').next().unwrap_or_default().to_string();
+    let query = query.split('#').next().unwrap_or_default().to_string();
+    path.split('/')
+        .map(|segment| RE_EXTENSION.replace(segment, "").to_string())
+        .chain(query.split('&').filter_map(|pair| {
+            let (_, value) = pair.split_once('=')?;
+            Some(value.to_string())
+        }))
+        .filter(|segment| segment.chars().any(char::is_alphabetic))
+        .collect()
+}
+
+/// Texts that may name a location in a URL, a slug or a signature, in the order
+/// they appear in. Slugs come both with their separators replaced by spaces and
+/// as they are, so that dash separated inputs like "US-DE-Wilmington" still work.
+fn candidates(input: &str) -> Vec<String> {
+    let mut texts: Vec<String> = vec![];
+    let mut push = |text: &str| {
+        let text = text.trim_matches(|c: char| c.is_whitespace() || c == ',' || c == '/');
+        if !text.is_empty() && !texts.iter().any(|t| t == text) {
+            texts.push(text.to_string());
+        }
+    };
+    for line in RE_LINES.split(input) {
+        let mut segments: Vec<String> = RE_URL
+            .find_iter(line)
+            .flat_map(|m| url_segments(m.as_str()))
+            .collect();
+        let rest = RE_URL.replace_all(line, " ");
+        let rest = RE_EMAIL.replace_all(&rest, " ");
+        let rest = RE_PHONE.replace_all(&rest, " ");
+        match rest.contains('/') && !rest.contains(' ') {
+            true => segments.extend(url_segments(&rest)),
+            false => segments.push(rest.to_string()),
+        }
+        for segment in segments {
+            let spaced = RE_SLUG_SEPARATORS.replace_all(&segment, " ");
+            push(&spaced);
+            push(&segment);
+        }
+    }
+    texts
+}
+
+impl Parser {
+    /// Parse a location embedded in a URL, a slug or an email signature, e.g.
+    /// "https://example.com/jobs/toronto-on/" or "new-york-ny-10001". Segments of
+    /// URLs, words of slugs and lines of signatures are parsed one by one, emails
+    /// and phone numbers are dropped. The one naming a known city along with most
+    /// of the state, the country and the ZIP code wins, the first one on a tie,
+    /// see `slug_score`. `Location::raw` is the text the location was parsed from,
+    /// e.g. "toronto on".
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - URL, slug or text that mentions a location somewhere
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let location = parser.parse_slug("https://example.com/jobs/toronto-on/12345?ref=email");
+    /// assert_eq!(location.to_string(), String::from("Toronto, ON, CA"));
+    /// let location = parser.parse_slug("new-york-ny-10001");
+    /// assert_eq!(location.to_string(), String::from("New York, NY, US, 10001"));
+    /// ```
+    pub fn parse_slug(&self, input: &str) -> Location {
+        stage!("parse_slug", input);
+        let mut best: Option<(usize, Location)> = None;
+        for text in candidates(input) {
+            let location = self.parse_location(&text);
+            let score = slug_score(&location);
+            trace_event!("parsed slug candidate", text = text, score = score);
+            if score > best.as_ref().map(|(s, _)| *s).unwrap_or(0) {
+                best = Some((score, location));
+            }
+        }
+        best.map(|(_, location)| location).unwrap_or_default()
+    }
+}
+
+/// How much of a location a candidate of `Parser::parse_slug` names, 0 when it
+/// names neither a known city, nor a state, nor a country. A known city weighs
+/// the most, states and countries only count when they are mentioned.
+fn slug_score(location: &Location) -> usize {
+    let has = |warning: Warning| location.warnings.contains(&warning);
+    let known_city = location.city.is_some() && !has(Warning::UnknownCity);
+    if !known_city && location.state.is_none() && location.country.is_none() {
+        return 0;
+    }
+    1 + 3 * known_city as usize
+        + (location.state.is_some() && !has(Warning::InferredState)) as usize
+        + (location.country.is_some() && !has(Warning::InferredCountry)) as usize
+        + location.zipcode.is_some() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(
+            candidates("https://careers.example.com/en/jobs/san-jose-ca/4521.html?utm=x#top"),
+            vec!["en", "jobs", "san jose ca", "san-jose-ca", "x"]
+        );
+        assert_eq!(
+            candidates("Jane Doe | jane@acme.com | +1 (512) 555-0100\nAustin, TX"),
+            vec!["Jane Doe", "Austin, TX"]
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_parse_slug() {
+        let parser = Parser::new();
+        for (input, output) in [
+            ("/jobs/toronto-on/", "Toronto, ON, CA"),
+            ("www.example.com/careers/austin_tx", "Austin, TX, US"),
+            ("https://example.com/search?q=engineer&location=Portland%20OR", "Portland, OR, US"),
+            ("US-DE-Wilmington", "Wilmington, DE, US"),
+            (
+                "Jane Doe\nSenior Engineer, Acme Corp\njane@acme.com | 512-555-0100\nAustin, TX 78701",
+                "Austin, TX, US, 78701",
+            ),
+        ] {
+            let location = parser.parse_slug(input);
+            assert_eq!(location.to_string(), output, "input: {}", input);
+        }
+        let location = parser.parse_slug("https://example.com/jobs/canada/");
+        assert_eq!(location.country.unwrap().code, "CA");
+        assert_eq!(
+            parser.parse_slug("https://example.com/jobs/"),
+            Location::default()
+        );
+    }
+}
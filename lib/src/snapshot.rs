@@ -0,0 +1,227 @@
+//! Datasets the parser is made of. They are parsed from the text files of `src/data`,
+//! or, with `binary-data` feature, read from `src/data/datasets.bin` that is written
+//! once by `cargo run -p geo-rs-data -- snapshot` and is much faster to load.
+//! The snapshot is ignored when it's missing or when the text files have changed
+//! since it was written, so the text files always remain the source of truth.
+//!
+//! Datasets are loaded once per process and shared by every `Parser`, so creating
+//! more parsers doesn't take more memory. A parser built with custom aliases gets
+//! its own copy of the datasets it changes.
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs;
+//! use std::sync::Arc;
+//! let datasets = geo_rs::snapshot::Datasets::shared();
+//! assert!(datasets.cities.contains_key("US"));
+//! assert!(datasets.states.contains_key("CA"));
+//! let again = geo_rs::snapshot::Datasets::shared();
+//! assert!(Arc::ptr_eq(&datasets.cities, &again.cities));
+//! ```
+use crate::nodes::{
+    read_cities, read_countries, read_major_world_cities, read_states, read_world_cities,
+    CountriesMap, CountryCities, CountryStates, WorldCities, WorldCity,
+};
+use crate::{schema, utils};
+use lazy_static::lazy_static;
+#[cfg(feature = "binary-data")]
+use std::fs::{self, File};
+#[cfg(feature = "binary-data")]
+use std::io::{self, BufReader, BufWriter};
+#[cfg(feature = "binary-data")]
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+lazy_static! {
+    /// Datasets shared by parsers, loaded by the first one
+    static ref SHARED: Mutex<Option<Datasets>> = Mutex::new(None);
+}
+
+/// Name of the snapshot file in `src/data`
+#[cfg(feature = "binary-data")]
+pub const SNAPSHOT_FILE: &str = "datasets.bin";
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "binary-data", derive(serde::Serialize, serde::Deserialize))]
+pub struct Datasets {
+    pub cities: Arc<CountryCities>,
+    pub world_cities: Arc<WorldCities>,
+    pub major_world_cities: Arc<Vec<WorldCity>>,
+    pub states: Arc<CountryStates>,
+    pub countries: Arc<CountriesMap>,
+}
+
+impl Datasets {
+    /// Parse datasets from the text files of `src/data`.
+    pub fn read() -> Self {
+        if let Err(e) = schema::validate(utils::data_source()) {
+            warn!("{}", e);
+        }
+        Self {
+            cities: Arc::new(read_cities()),
+            world_cities: Arc::new(read_world_cities()),
+            major_world_cities: Arc::new(read_major_world_cities()),
+            states: Arc::new(read_states()),
+            countries: Arc::new(read_countries()),
+        }
+    }
+
+    /// Datasets shared by every parser of the process, see `Datasets::load`.
+    /// They are loaded by the first call and kept for the lifetime of the process.
+    pub fn shared() -> Self {
+        SHARED
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Self::load)
+            .clone()
+    }
+
+    /// Read datasets from the snapshot when `binary-data` feature is enabled
+    /// and the snapshot is up to date, parse the text files otherwise.
+    pub fn load() -> Self {
+        #[cfg(feature = "binary-data")]
+        {
+            let path = utils::data_dir().join(SNAPSHOT_FILE);
+            if path.exists() {
+                match read_snapshot(&path) {
+                    Ok(datasets) => return datasets,
+                    Err(e) => warn!("Ignoring snapshot {:?}: {}", path, e),
+                }
+            }
+        }
+        Self::read()
+    }
+}
+
+/// What the snapshot was made of: crate version, features of enabled datasets
+/// and sizes of the text files, a snapshot is stale when any of them changes.
+#[cfg(feature = "binary-data")]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Fingerprint {
+    version: String,
+    datasets: Vec<String>,
+    files: Vec<(String, u64)>,
+}
+
+#[cfg(feature = "binary-data")]
+impl Fingerprint {
+    fn current() -> io::Result<Self> {
+        let root = utils::data_dir();
+        let mut paths: Vec<PathBuf> = vec![];
+        let mut dirs = vec![root.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|e| e == "txt") {
+                    paths.push(path);
+                }
+            }
+        }
+        paths.sort();
+        let mut files: Vec<(String, u64)> = vec![];
+        for path in paths {
+            let name = path.strip_prefix(&root).unwrap_or(&path);
+            files.push((
+                name.to_string_lossy().into_owned(),
+                fs::metadata(&path)?.len(),
+            ));
+        }
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            datasets: [
+                ("data-us", cfg!(feature = "data-us")),
+                ("data-ca", cfg!(feature = "data-ca")),
+                ("data-world", cfg!(feature = "data-world")),
+                ("data-world-major", cfg!(feature = "data-world-major")),
+            ]
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name.to_string())
+            .collect(),
+            files,
+        })
+    }
+}
+
+#[cfg(feature = "binary-data")]
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Parse the text files and write them to the snapshot.
+///
+/// # Arguments
+///
+/// * `path` - Path of the snapshot, `Datasets::load` reads `src/data/datasets.bin`
+#[cfg(feature = "binary-data")]
+pub fn write_snapshot(path: &Path) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    bincode::serialize_into(writer, &(Fingerprint::current()?, Datasets::read()))
+        .map_err(invalid_data)
+}
+
+/// Read datasets from the snapshot, fail when it's stale.
+///
+/// # Arguments
+///
+/// * `path` - Path of the snapshot
+#[cfg(feature = "binary-data")]
+pub fn read_snapshot(path: &Path) -> io::Result<Datasets> {
+    let reader = BufReader::new(File::open(path)?);
+    let (fingerprint, datasets): (Fingerprint, Datasets) =
+        bincode::deserialize_from(reader).map_err(invalid_data)?;
+    if fingerprint != Fingerprint::current()? {
+        return Err(invalid_data("text files have changed since it was written"));
+    }
+    Ok(datasets)
+}
+
+#[cfg(all(test, feature = "data-us"))]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn test_shared_datasets() {
+        let parser = Parser::new();
+        let other = Parser::new();
+        assert!(Arc::ptr_eq(&parser.cities, &other.cities));
+        assert!(Arc::ptr_eq(&parser.countries, &other.countries));
+        let custom = Parser::builder()
+            .with_city_alias("The Big Apple", "US", "NY", "New York")
+            .build();
+        assert!(!Arc::ptr_eq(&parser.cities, &custom.cities));
+        assert!(Arc::ptr_eq(&parser.states, &custom.states));
+        let location = custom.parse_location("The Big Apple");
+        assert_eq!(location.to_string(), String::from("New York, NY, US"));
+        let location = parser.parse_location("The Big Apple");
+        assert_ne!(location.to_string(), String::from("New York, NY, US"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "binary-data", feature = "data-ca"))]
+    fn test_snapshot() {
+        let path = std::env::temp_dir().join(format!("geo-rs-{}.bin", std::process::id()));
+        write_snapshot(&path).unwrap();
+        let datasets = read_snapshot(&path).unwrap();
+        let expected = Datasets::read();
+        assert_eq!(datasets.cities["US"].names, expected.cities["US"].names);
+        assert_eq!(
+            datasets.states["CA"].code_to_name,
+            expected.states["CA"].code_to_name
+        );
+        assert_eq!(datasets.countries.aliases, expected.countries.aliases);
+        assert_eq!(datasets.world_cities, expected.world_cities);
+
+        let (mut fingerprint, datasets): (Fingerprint, Datasets) =
+            bincode::deserialize(&fs::read(&path).unwrap()).unwrap();
+        fingerprint.files[0].1 += 1;
+        fs::write(&path, bincode::serialize(&(fingerprint, datasets)).unwrap()).unwrap();
+        let error = read_snapshot(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use crate::nodes::{City, Location};
+use crate::utils::{expect_line, read_lines};
+use crate::Parser;
+use regex::Regex;
+use unidecode::unidecode;
+
+/// Input that names city, state and country at once in a way regular parsing
+/// doesn't understand, e.g. "District of Columbia" is Washington, DC, US.
+#[derive(Debug, Clone)]
+pub struct SpecialCase {
+    /// Regular expression matched against the ASCII input
+    pub pattern: Regex,
+    /// Name of the city, e.g. "Washington"
+    pub city: String,
+    /// State code of the city, e.g. "DC"
+    pub state: String,
+    /// Country code of the city, e.g. "US"
+    pub country: String,
+}
+
+impl SpecialCase {
+    /// Create a special case, fail when the pattern isn't a valid regular expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Regular expression, e.g. r"(?i)district of columbia"
+    /// * `city` - Name of the city, e.g. "Washington"
+    /// * `state` - State code of the city, e.g. "DC"
+    /// * `country` - Country code of the city, e.g. "US"
+    pub fn new(
+        pattern: &str,
+        city: &str,
+        state: &str,
+        country: &str,
+    ) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            city: city.to_string(),
+            state: state.to_string(),
+            country: country.to_string(),
+        })
+    }
+}
+
+/// Read special cases from `src/data/special_cases.txt`, one per line as
+/// "pattern;city;state;country". The pattern may contain semicolons, the last
+/// three fields are split off. Invalid lines are ignored with a warning.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let cases = geo_rs::special::read_special_cases();
+/// assert!(cases.iter().any(|c| c.pattern.is_match("District of Columbia")));
+/// ```
+pub fn read_special_cases() -> Vec<SpecialCase> {
+    let filename = "special_cases.txt";
+    read_lines(filename)
+        .map(|line| expect_line(filename, line))
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.rsplitn(4, ';').collect();
+            if parts.len() != 4 {
+                warn!("Ignoring special case {:?}, expected 4 fields", line);
+                return None;
+            }
+            match SpecialCase::new(parts[3], parts[2], parts[1], parts[0]) {
+                Ok(case) => Some(case),
+                Err(e) => {
+                    warn!("Ignoring invalid special case {:?}: {}", line, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+impl Parser {
+    /// Fill city, state and country of the first special case that matches the input,
+    /// see `ParserBuilder::with_special_case`. Cases of countries or states that aren't
+    /// loaded are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Location struct that stores final values
+    /// * `input` - Location string
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// let parser = geo_rs::Parser::new();
+    /// let mut location = geo_rs::nodes::Location::default();
+    /// parser.fill_special_case_city(&mut location, "United States-District of Columbia");
+    /// assert_eq!(location.to_string(), String::from("Washington, DC, US"));
+    /// ```
+    pub fn fill_special_case_city(&self, location: &mut Location, input: &str) {
+        let input = &unidecode(input);
+        for case in &self.special_cases {
+            if !case.pattern.is_match(input) {
+                continue;
+            }
+            let country = match self.country_from_code(&case.country) {
+                Some(c) => c,
+                None => continue,
+            };
+            let state = match self.state_from_code(&Some(country.clone()), &case.state) {
+                Some(s) => s,
+                None => continue,
+            };
+            trace_event!("matched special case", pattern = case.pattern.as_str());
+            location.country = Some(country);
+            location.state = Some(state);
+            location.city = Some(City::new(&case.city));
+            return;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "data-us"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_special_cases() {
+        let parser = Parser::builder()
+            .with_special_case(r"(?i)\bbig apple\b", "New York", "NY", "US")
+            .with_special_case(r"(?i)\bgotham\b", "New York", "XX", "US")
+            .with_special_case(r"(unclosed", "New York", "NY", "US")
+            .build();
+        let location = parser.parse_location("Big Apple Store #12");
+        assert_eq!(location.to_string(), String::from("New York, NY, US"));
+        let location = parser.parse_location("Gotham, NJ");
+        assert_ne!(location.to_string(), String::from("New York, NY, US"));
+        let location = parser.parse_location("Washington, D.C. 20001");
+        assert_eq!(
+            location.to_string(),
+            String::from("Washington, DC, US, 20001")
+        );
+    }
+}
@@ -0,0 +1,470 @@
+//! Stages of the parsing pipeline. `Parser` runs its stages in order, every stage
+//! fills some parts of the location and removes what it has found from the remainder
+//! of the input, so that later stages don't stumble upon it.
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs;
+//! use geo_rs::nodes::{City, Location};
+//! use geo_rs::stages::{Flow, Stage};
+//!
+//! /// Resolve an internal office name before anything else
+//! #[derive(Debug)]
+//! struct Headquarters;
+//!
+//! impl Stage for Headquarters {
+//!     fn name(&self) -> &'static str {
+//!         "headquarters"
+//!     }
+//!
+//!     fn run(&self, parser: &geo_rs::Parser, location: &mut Location, remainder: &mut String) -> Flow {
+//!         if remainder.trim() != "HQ" {
+//!             return Flow::Continue;
+//!         }
+//!         location.city = Some(City { name: String::from("Toronto") });
+//!         location.state = parser.state_from_code(&None, "ON");
+//!         location.country = Some(geo_rs::nodes::CANADA.clone());
+//!         Flow::Stop
+//!     }
+//! }
+//!
+//! let mut stages = geo_rs::stages::default_stages();
+//! stages.insert(0, Box::new(Headquarters));
+//! let parser = geo_rs::Parser::builder().with_stages(stages).build();
+//! assert_eq!(parser.parse_location("HQ").to_string(), String::from("Toronto, ON, CA"));
+//! ```
+use crate::nodes::{City, Location, Warning};
+use crate::utils;
+use crate::{NormalizeStep, Parser};
+use std::fmt;
+use titlecase::titlecase;
+
+/// What the parser should do after a stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// Run the next stage
+    Continue,
+    /// Location is complete, skip the remaining stages
+    Stop,
+}
+
+/// A step of the parsing pipeline.
+pub trait Stage: fmt::Debug + Send + Sync {
+    /// Unique name of the stage, e.g. "country"
+    fn name(&self) -> &'static str;
+
+    /// Fill the location from the remainder of the input and remove the found parts from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `parser` - Parser that runs the stage
+    /// * `location` - Location filled by the previous stages
+    /// * `remainder` - Part of the input that previous stages haven't consumed
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow;
+}
+
+/// Return stages of the default pipeline in the order they run.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let names: Vec<&str> = geo_rs::stages::default_stages().iter().map(|s| s.name()).collect();
+/// assert_eq!(
+///     names,
+///     vec![
+///         "blocklist",
+///         "site_code",
+///         "address",
+///         "landmark",
+///         "normalize",
+///         "historical",
+///         "delimited",
+///         "world_city",
+///         "country",
+///         "zipcode",
+///         "state",
+///         "city",
+///         "duplicates"
+///     ]
+/// );
+/// ```
+pub fn default_stages() -> Vec<Box<dyn Stage>> {
+    vec![
+        Box::new(BlocklistStage),
+        Box::new(SiteCodeStage),
+        Box::new(AddressStage),
+        Box::new(LandmarkStage),
+        Box::new(NormalizeStage),
+        Box::new(HistoricalStage),
+        Box::new(DelimitedStage),
+        Box::new(WorldCityStage),
+        Box::new(CountryStage),
+        Box::new(ZipcodeStage),
+        Box::new(StateStage),
+        Box::new(CityStage),
+        Box::new(DuplicatesStage),
+    ]
+}
+
+/// Stop parsing inputs on the blocklist, e.g. "Remote", so that they don't match
+/// a city by accident, see `ParserBuilder::with_blocklist`.
+#[derive(Debug)]
+pub struct BlocklistStage;
+
+impl Stage for BlocklistStage {
+    fn name(&self) -> &'static str {
+        "blocklist"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        if !parser.is_blocked(remainder) {
+            return Flow::Continue;
+        }
+        trace_event!("blocked input", input = remainder);
+        location.warnings.push(Warning::Blocked);
+        remainder.clear();
+        Flow::Stop
+    }
+}
+
+/// Resolve internal site codes of companies, input that has one is complete,
+/// see `ParserBuilder::with_site_codes`.
+#[derive(Debug)]
+pub struct SiteCodeStage;
+
+impl Stage for SiteCodeStage {
+    fn name(&self) -> &'static str {
+        "site_code"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        if !parser.fill_site_code(location, remainder) {
+            return Flow::Continue;
+        }
+        remainder.clear();
+        Flow::Stop
+    }
+}
+
+/// Fence off a post office box, a box of military mail or a street address before
+/// the input is cleaned, cleaning drops words like "BLVD".
+#[derive(Debug)]
+pub struct AddressStage;
+
+impl Stage for AddressStage {
+    fn name(&self) -> &'static str {
+        "address"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        let preset = location.address.is_some();
+        parser.fill_mailbox_address(location, remainder);
+        parser.fill_street_address(location, remainder);
+        if let Some(a) = &location.address {
+            *remainder = remainder.replacen(&a.address, "", 1);
+        }
+        // Addresses set by earlier stages are kept, see `ParserOptions::addresses`
+        if !preset && !parser.options.addresses {
+            location.address = None;
+        }
+        Flow::Continue
+    }
+}
+
+/// Fence off a campus, a building or another landmark, so that it isn't mistaken
+/// for a city, nor dropped with the brackets it's in, e.g. "Toronto (Eaton Center)".
+#[derive(Debug)]
+pub struct LandmarkStage;
+
+impl Stage for LandmarkStage {
+    fn name(&self) -> &'static str {
+        "landmark"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        parser.fill_landmark(location, remainder);
+        if let Some(l) = &location.landmark {
+            *remainder = remainder.replacen(&l.name, "", 1);
+        }
+        Flow::Continue
+    }
+}
+
+/// Expand abbreviations and country aliases and clean the input, collapsed duplicates
+/// are stored in `Location::collapsed`.
+#[derive(Debug)]
+pub struct NormalizeStage;
+
+impl Stage for NormalizeStage {
+    fn name(&self) -> &'static str {
+        "normalize"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        let normalizer = &parser.normalizer;
+        if normalizer.has_step(NormalizeStep::Abbreviations) {
+            utils::expand_abbreviations(remainder, &normalizer.abbreviations);
+        }
+        parser.collapse_country_aliases(remainder);
+        parser.translate_state_names(remainder);
+        location.collapsed = parser.clean(remainder);
+        if normalizer.has_step(NormalizeStep::Dedup) {
+            for name in parser.collapse_repeats(remainder) {
+                if !location.collapsed.contains(&name) {
+                    location.collapsed.push(name);
+                }
+            }
+        }
+        trace_event!("cleaned input", input = remainder);
+        Flow::Continue
+    }
+}
+
+/// Replace former names of cities and countries with the current ones,
+/// e.g. "Bombay" with "Mumbai", see `Parser::replace_historical_name`.
+#[derive(Debug)]
+pub struct HistoricalStage;
+
+impl Stage for HistoricalStage {
+    fn name(&self) -> &'static str {
+        "historical"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        parser.replace_historical_name(location, remainder);
+        Flow::Continue
+    }
+}
+
+/// Map segments of dash separated inputs like "US-DE-Wilmington" by their position,
+/// input that fits such a format is complete, see `Parser::parse_delimited`.
+#[derive(Debug)]
+pub struct DelimitedStage;
+
+impl Stage for DelimitedStage {
+    fn name(&self) -> &'static str {
+        "delimited"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        let parsed = match parser.parse_delimited(remainder) {
+            Some(l) => l,
+            None => return Flow::Continue,
+        };
+        location.city = parsed.city;
+        location.state = parsed.state;
+        location.country = parsed.country;
+        location.zipcode = parsed.zipcode;
+        remainder.clear();
+        Flow::Stop
+    }
+}
+
+/// Find cities outside of US and CA, input that has one is complete.
+#[derive(Debug)]
+pub struct WorldCityStage;
+
+impl Stage for WorldCityStage {
+    fn name(&self) -> &'static str {
+        "world_city"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        parser.fill_world_city(location, remainder);
+        if location.city.is_some() {
+            return Flow::Stop;
+        }
+        parser.fill_major_world_city(location, remainder);
+        parser.fill_city_alias(location, remainder);
+        Flow::Continue
+    }
+}
+
+#[derive(Debug)]
+pub struct CountryStage;
+
+impl Stage for CountryStage {
+    fn name(&self) -> &'static str {
+        "country"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        parser.fill_country(location, remainder);
+        trace_event!("matched country", country = location.country);
+        if let Some(c) = &location.country {
+            parser.remove_country(c, remainder);
+        }
+        Flow::Continue
+    }
+}
+
+#[derive(Debug)]
+pub struct ZipcodeStage;
+
+impl Stage for ZipcodeStage {
+    fn name(&self) -> &'static str {
+        "zipcode"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        parser.fill_zipcode(location, remainder);
+        trace_event!("matched zipcode", zipcode = location.zipcode);
+        if let Some(z) = &location.zipcode {
+            parser.remove_zipcode(z, remainder);
+            if let Some(c) = &location.country {
+                parser.remove_country(c, remainder);
+            }
+        }
+        Flow::Continue
+    }
+}
+
+/// Find a state, special cases that name city, state and country at once go first.
+#[derive(Debug)]
+pub struct StateStage;
+
+impl Stage for StateStage {
+    fn name(&self) -> &'static str {
+        "state"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        parser.fill_special_case_city(location, remainder);
+        if let (Some(_), Some(_), Some(_)) = (&location.city, &location.state, &location.country) {
+            return Flow::Stop;
+        }
+        // The mention of the state that was matched is removed right away,
+        // so that the same name inside a city, e.g. "New York Mills", stays
+        let mention = parser.detect_state(location, remainder);
+        let detected = location.state.clone();
+        trace_event!("matched state", state = location.state);
+        if let Some((start, end)) = mention {
+            remainder.replace_range(start..end, "");
+            parser.clean(remainder);
+        }
+        if location.zipcode.is_none() {
+            parser.fill_zipcode_with_lost_zeros(location, remainder);
+            if let Some(z) = &location.zipcode {
+                utils::remove_token(remainder, z.zipcode.trim_start_matches('0'));
+                parser.clean(remainder);
+            }
+        }
+        parser.resolve_zipcode_state(location, remainder);
+        if let (Some(s), Some(c)) = (&location.state, &location.country) {
+            // States found by earlier stages or replaced by the zipcode's one weren't detected here
+            if mention.is_none() || location.state != detected {
+                parser.remove_state(s, c, remainder);
+            }
+            parser.remove_country(c, remainder);
+        }
+        Flow::Continue
+    }
+}
+
+/// Find a city, whatever is left of the input becomes the city when no known city is found.
+#[derive(Debug)]
+pub struct CityStage;
+
+impl Stage for CityStage {
+    fn name(&self) -> &'static str {
+        "city"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        if parser.options.infer_unique_city {
+            parser.fill_unique_city(location, remainder);
+        }
+        if location.city.is_none() {
+            parser.fill_city(location, remainder);
+            trace_event!("matched city", city = location.city);
+        }
+        if location.city.is_none() && parser.options.phonetic_cities {
+            parser.fill_phonetic_city(location, remainder);
+        }
+        if let Some(c) = &location.city {
+            parser.remove_city(remainder, c);
+        }
+        if location.city.is_none() && remainder.chars().count() > 0 {
+            trace_event!(
+                "city not found, using the input as is",
+                remainder = remainder
+            );
+            location.warnings.push(Warning::UnknownCity);
+            location.city = Some(City {
+                name: titlecase(
+                    remainder
+                        .split(",")
+                        .next()
+                        .unwrap_or("")
+                        .to_string()
+                        .chars()
+                        .filter(|c| !c.is_ascii_digit())
+                        .collect::<String>()
+                        .as_str(),
+                ),
+            })
+        }
+        utils::decode(location);
+        Flow::Continue
+    }
+}
+
+/// Collapse the rest of the input when it repeats the parsed location,
+/// e.g. "Canton, MA, Canton, Massachusetts".
+#[derive(Debug)]
+pub struct DuplicatesStage;
+
+impl Stage for DuplicatesStage {
+    fn name(&self) -> &'static str {
+        "duplicates"
+    }
+
+    fn run(&self, parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+        parser.collapse_duplicate_location(location, remainder);
+        Flow::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::Address;
+
+    /// Take everything that follows "Attn:" as an address
+    #[derive(Debug)]
+    struct Attention;
+
+    impl Stage for Attention {
+        fn name(&self) -> &'static str {
+            "attention"
+        }
+
+        fn run(&self, _parser: &Parser, location: &mut Location, remainder: &mut String) -> Flow {
+            if let Some(p) = remainder.find("Attn:") {
+                location.address = Some(Address::new(remainder[p + 5..].trim()));
+                remainder.truncate(p);
+            }
+            Flow::Continue
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "data-ca")]
+    fn test_custom_stages() {
+        let input = "Toronto, ON, Attn: Front Desk";
+        let parser = Parser::new();
+        assert_eq!(parser.parse_location(input).address, None);
+        let mut stages = default_stages();
+        stages.insert(0, Box::new(Attention));
+        let parser = Parser::builder().with_stages(stages).build();
+        let location = parser.parse_location(input);
+        assert_eq!(location.to_string(), "Toronto, ON, CA, Front Desk");
+        let parser = Parser::builder().with_stage(Attention).build();
+        assert_eq!(parser.stages.last().unwrap().name(), "attention");
+        let parser = Parser::builder().without_stage("city").build();
+        let location = parser.parse_location("Toronto, ON");
+        assert_eq!(location.to_string(), "ON, CA");
+    }
+}
@@ -0,0 +1,244 @@
+//! Messy location strings synthesized from the datasets, for fuzzing the
+//! parser and for benchmark corpora larger than the mocks. Every sample is
+//! a known city written the way feeds write locations: parts in another
+//! order, random casing and delimiters, repeated parts and noise codes.
+//! The same `n` and `seed` always produce the same samples.
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs;
+//! let samples = geo_rs::testing::generate(3, 42);
+//! assert_eq!(samples.len(), 3);
+//! assert_eq!(samples, geo_rs::testing::generate(3, 42));
+//! for sample in &samples {
+//!     println!("{} -> {}, {}, {}", sample.input, sample.city, sample.state, sample.country);
+//! }
+//! ```
+use crate::nodes::{read_cities, read_countries, read_states};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref KNOWN_CITIES: Vec<Known> = known_cities();
+}
+
+/// Delimiters between parts of a sample, the first ones are the most common
+const DELIMITERS: [&str; 8] = [", ", ", ", ", ", ",", " ", " - ", "; ", " / "];
+
+/// Noise of retail feeds the parser is expected to remove, whether it leads
+/// the input, zeros are replaced by random digits
+const NOISE: [(&str, bool); 5] = [
+    ("(Store# 00000)", false),
+    ("000000", true),
+    ("000 -", true),
+    ("- Route 00", false),
+    ("(Unit 00)", false),
+];
+
+/// Location string along with the city it was generated from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub input: String,
+    pub city: String,
+    /// State code, e.g. "ON"
+    pub state: String,
+    /// Country code, e.g. "CA"
+    pub country: String,
+}
+
+/// City with its state and country as they're written in samples
+#[derive(Debug, Clone)]
+struct Known {
+    city: String,
+    state_code: String,
+    state_name: String,
+    country_code: String,
+    country_name: String,
+}
+
+/// Cities with population data, except for the ones named after their states,
+/// e.g. "New York, New York" is ambiguous. Sorted so that samples don't depend
+/// on the order of hash maps.
+fn known_cities() -> Vec<Known> {
+    let countries = read_countries();
+    let states = read_states();
+    let mut known = vec![];
+    for (country_code, cities) in read_cities() {
+        let country_name = match countries.code_to_name.get(&country_code) {
+            Some(name) => name.clone(),
+            None => continue,
+        };
+        for key in cities.population.keys() {
+            let state_name = states
+                .get(&country_code)
+                .and_then(|s| s.code_to_name.get(&key.0));
+            let state_name = match state_name {
+                Some(name) if name.to_lowercase() != key.1 => name.clone(),
+                _ => continue,
+            };
+            known.push(Known {
+                city: cities.names[key].to_string(),
+                state_code: key.0.clone(),
+                state_name,
+                country_code: country_code.clone(),
+                country_name: country_name.clone(),
+            });
+        }
+    }
+    known.sort_by(|a, b| {
+        (&a.country_code, &a.state_code, &a.city).cmp(&(&b.country_code, &b.state_code, &b.city))
+    });
+    known
+}
+
+/// SplitMix64, small and stable across versions, so a seed is a corpus
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Number in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// True with the probability of `percent`
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// Write the part in upper, lower or random case, or leave it as it is.
+fn mangle_case(rng: &mut Rng, part: &str) -> String {
+    match rng.below(10) {
+        0 | 1 => part.to_uppercase(),
+        2 => part.to_lowercase(),
+        3 => part
+            .chars()
+            .map(|c| match rng.chance(50) {
+                true => c.to_ascii_uppercase(),
+                false => c.to_ascii_lowercase(),
+            })
+            .collect(),
+        _ => part.to_string(),
+    }
+}
+
+/// Noise with its zeros replaced by random digits.
+fn noise(rng: &mut Rng) -> (String, bool) {
+    let (pattern, leading) = *rng.pick(&NOISE);
+    let noise = pattern
+        .chars()
+        .map(|c| match c {
+            '0' => char::from(b'0' + rng.below(10) as u8),
+            c => c,
+        })
+        .collect();
+    (noise, leading)
+}
+
+/// Messy string of the known city.
+fn sample(rng: &mut Rng, known: &Known) -> String {
+    let state = match rng.chance(70) {
+        true => &known.state_code,
+        false => &known.state_name,
+    };
+    let mut parts = vec![known.city.as_str(), state.as_str()];
+    match rng.below(10) {
+        0..=3 => (),
+        4..=6 => parts.push(&known.country_code),
+        _ => parts.push(&known.country_name),
+    }
+    // State first, e.g. "ON, Toronto"
+    if rng.chance(10) {
+        parts.swap(0, 1);
+    }
+    // Repeated part, e.g. "Toronto, Toronto, ON"
+    if rng.chance(10) {
+        let i = rng.below(parts.len());
+        parts.insert(i, parts[i]);
+    }
+    let delimiter = *rng.pick(&DELIMITERS);
+    let mut input = parts
+        .iter()
+        .map(|part| mangle_case(rng, part))
+        .collect::<Vec<String>>()
+        .join(delimiter);
+    if rng.chance(20) {
+        input = match noise(rng) {
+            (noise, true) => format!("{} {}", noise, input),
+            (noise, false) => format!("{} {}", input, noise),
+        };
+    }
+    if rng.chance(10) {
+        input = format!("  {} ", input.replace(' ', "  "));
+    }
+    input
+}
+
+/// Generate `n` samples from cities of the datasets, see the module docs.
+///
+/// # Arguments
+///
+/// * `n` - Number of samples
+/// * `seed` - Seed of the random generator, the same seed gives the same samples
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let samples = geo_rs::testing::generate(100, 1);
+/// assert_eq!(samples.len(), 100);
+/// assert_ne!(samples, geo_rs::testing::generate(100, 2));
+/// ```
+pub fn generate(n: usize, seed: u64) -> Vec<Sample> {
+    let mut rng = Rng(seed);
+    if KNOWN_CITIES.is_empty() {
+        return vec![];
+    }
+    (0..n)
+        .map(|_| {
+            let known = rng.pick(&KNOWN_CITIES).clone();
+            Sample {
+                input: sample(&mut rng, &known),
+                city: known.city,
+                state: known.state_code,
+                country: known.country_code,
+            }
+        })
+        .collect()
+}
+
+#[cfg(all(test, any(feature = "data-us", feature = "data-ca")))]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn test_generate() {
+        let samples = generate(500, 7);
+        assert_eq!(samples, generate(500, 7));
+        assert!(samples.iter().any(|s| s.input.contains('#')));
+        assert!(samples.iter().any(|s| s.input == s.input.to_uppercase()));
+        // Most samples are still parsed as the city they were generated from
+        let parser = Parser::new();
+        let parsed = samples
+            .iter()
+            .filter(|s| {
+                let location = parser.parse_location(&s.input);
+                location.to_string() == format!("{}, {}, {}", s.city, s.state, s.country)
+            })
+            .count();
+        assert!(parsed * 10 > samples.len() * 7, "{} parsed", parsed);
+    }
+}
@@ -0,0 +1,106 @@
+//! Instrumentation of the parsing pipeline. With `tracing` feature every stage
+//! gets its own span and events carry structured fields, otherwise events
+//! are logged with `log::debug!`. Both are also recorded as `TraceStep`s
+//! while `Parser::parse_location_explained` runs.
+
+use std::cell::RefCell;
+
+/// A single decision made by the parser, see `Parser::parse_location_explained`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// Parsing stage the step belongs to, e.g. "country"
+    pub stage: &'static str,
+    /// What happened, e.g. "matched country"
+    pub message: &'static str,
+    /// Values involved in the decision, formatted with `Debug`
+    pub fields: Vec<(&'static str, String)>,
+}
+
+#[derive(Default)]
+struct Recorder {
+    stages: Vec<&'static str>,
+    steps: Vec<TraceStep>,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+/// Run `f` collecting every stage and event it goes through.
+pub(crate) fn record<R>(f: impl FnOnce() -> R) -> (R, Vec<TraceStep>) {
+    let previous = RECORDER.with(|r| r.replace(Some(Recorder::default())));
+    let result = f();
+    let recorder = RECORDER.with(|r| r.replace(previous));
+    (result, recorder.map(|r| r.steps).unwrap_or_default())
+}
+
+pub(crate) fn is_recording() -> bool {
+    RECORDER.with(|r| r.borrow().is_some())
+}
+
+pub(crate) fn record_event(message: &'static str, fields: Vec<(&'static str, String)>) {
+    RECORDER.with(|r| {
+        if let Some(recorder) = r.borrow_mut().as_mut() {
+            let stage = recorder.stages.last().copied().unwrap_or("parse");
+            recorder.steps.push(TraceStep {
+                stage,
+                message,
+                fields,
+            });
+        }
+    });
+}
+
+/// Marks the stage as current for recorded events until dropped.
+pub(crate) struct StageGuard;
+
+impl StageGuard {
+    pub(crate) fn enter(name: &'static str, input: String) -> Self {
+        RECORDER.with(|r| {
+            if let Some(recorder) = r.borrow_mut().as_mut() {
+                recorder.stages.push(name);
+            }
+        });
+        record_event("entered stage", vec![("input", input)]);
+        StageGuard
+    }
+}
+
+impl Drop for StageGuard {
+    fn drop(&mut self) {
+        RECORDER.with(|r| {
+            if let Some(recorder) = r.borrow_mut().as_mut() {
+                recorder.stages.pop();
+            }
+        });
+    }
+}
+
+/// Enter a span of the parsing stage until the end of the current block.
+macro_rules! stage {
+    ($name:literal, $input:expr) => {
+        #[cfg(feature = "tracing")]
+        let _stage = tracing::debug_span!("stage", name = $name, input = %$input).entered();
+        let _recorded_stage = if crate::trace::is_recording() {
+            Some(crate::trace::StageGuard::enter($name, $input.to_string()))
+        } else {
+            None
+        };
+    };
+}
+
+/// Record a parsing event, fields are formatted with `Debug`.
+macro_rules! trace_event {
+    ($message:literal $(, $field:ident = $value:expr)* $(,)?) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($field = ?$value,)* $message);
+        #[cfg(not(feature = "tracing"))]
+        debug!(concat!($message $(, ", ", stringify!($field), ": {:?}")*) $(, $value)*);
+        if crate::trace::is_recording() {
+            crate::trace::record_event(
+                $message,
+                vec![$((stringify!($field), format!("{:?}", $value))),*],
+            );
+        }
+    };
+}
@@ -1,25 +1,184 @@
-use crate::{Country, Location};
+use crate::{schema, Country, Location, Normalizer};
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::borrow::Cow;
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufRead, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use unidecode::unidecode;
 
+/// Mean radius of the Earth in kilometers
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
 lazy_static! {
-    static ref RE_BRACKETS: Regex = Regex::new(r"\(.*?\)").unwrap();
-    static ref RE_LEADING: Regex = Regex::new(r"^[\s\-,;:_\.\?!/]*").unwrap();
-    static ref RE_TRAILING: Regex = Regex::new(r"[\s\-,;:_\.\?!/]*$").unwrap();
-    static ref RE_SPLITTER1: Regex = Regex::new(r"[^a-z\p{L}A-Z0-9\s-]").unwrap();
+    pub(crate) static ref RE_POSSESSIVE: Regex = Regex::new(r"\b'([sS])\b").unwrap();
+    pub(crate) static ref RE_QUOTES: Regex = Regex::new(r"['’]+").unwrap();
     static ref RE_SPLITTER2: Regex = Regex::new(r"[^a-z\p{L}A-Z0-9]").unwrap();
-    static ref RE_SPACES: Regex = Regex::new(r"\s+").unwrap();
-    static ref RE_ABBREVIATIONS: Regex =
-        Regex::new(r"\b(?:[QWRTPSDFGHKLZXCVBNM]{3,5}\b|(?:[A-Za-z]\.){3,})\s*").unwrap();
+    pub(crate) static ref RE_SPACES: Regex = Regex::new(r"\s+").unwrap();
+    static ref DEFAULT_NORMALIZER: Normalizer = Normalizer::new();
+}
+
+/// Name of the environment variable with the path of the data folder, see `data_source`
+pub const DATA_DIR_VAR: &str = "GEO_RS_DATA_DIR";
+
+/// Data files embedded with `embedded-data` feature, by their path relative to `src/data`
+const EMBEDDED_DATA: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/embedded_data.rs"));
+
+/// Where the datasets are read from, see `data_source`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataSource {
+    /// Folder with the files of `src/data`
+    Dir(PathBuf),
+    /// Files of `src/data` embedded into the binary with `embedded-data` feature
+    Embedded,
+}
+
+lazy_static! {
+    static ref DATA_SOURCE: DataSource = resolve_data_source(
+        env::var_os(DATA_DIR_VAR).map(PathBuf::from),
+        env::current_exe().ok(),
+    );
+}
+
+/// Where the datasets are read from, resolved once per process in this order:
+/// the folder from `GEO_RS_DATA_DIR` environment variable, `data` folder next
+/// to the executable, files embedded with `embedded-data` feature, and finally
+/// `src/data` of the crate on the machine it was built on. So a standalone
+/// binary works when it's shipped with its `data` folder or built with
+/// `embedded-data` feature.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// use geo_rs::utils::DataSource;
+/// match geo_rs::utils::data_source() {
+///     DataSource::Dir(dir) => assert!(dir.join("countries.txt").is_file()),
+///     DataSource::Embedded => assert!(cfg!(feature = "embedded-data")),
+/// }
+/// ```
+pub fn data_source() -> &'static DataSource {
+    &DATA_SOURCE
+}
+
+fn resolve_data_source(env_dir: Option<PathBuf>, exe: Option<PathBuf>) -> DataSource {
+    let is_data_dir = |dir: &Path| dir.join("countries.txt").is_file();
+    if let Some(dir) = env_dir {
+        if is_data_dir(&dir) {
+            return DataSource::Dir(dir);
+        }
+        warn!(
+            "Ignoring {}={:?}, it has no countries.txt",
+            DATA_DIR_VAR, dir
+        );
+    }
+    let next_to_exe = exe.and_then(|exe| Some(exe.parent()?.join("data")));
+    if let Some(dir) = next_to_exe.filter(|dir| is_data_dir(dir)) {
+        return DataSource::Dir(dir);
+    }
+    if !EMBEDDED_DATA.is_empty() {
+        return DataSource::Embedded;
+    }
+    DataSource::Dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("src/data"))
+}
+
+impl DataSource {
+    /// Open a data file by its path relative to `src/data`, e.g. "US/cities.txt".
+    fn open(&self, filename: &str) -> Option<Box<dyn BufRead>> {
+        match self {
+            DataSource::Dir(dir) => File::open(dir.join(filename))
+                .ok()
+                .map(|file| Box::new(BufReader::new(file)) as Box<dyn BufRead>),
+            DataSource::Embedded => EMBEDDED_DATA
+                .iter()
+                .find(|(name, _)| *name == filename)
+                .map(|(_, bytes)| Box::new(*bytes) as Box<dyn BufRead>),
+        }
+    }
+
+    /// Same as `utils::read_lines` but read from this source.
+    pub fn read_lines(&self, filename: &str) -> io::Lines<Box<dyn BufRead>> {
+        match self.open(filename) {
+            Some(reader) => reader.lines(),
+            None => panic!("data file {} not found in {:?}", filename, self),
+        }
+    }
+
+    /// Same as `utils::read_optional_lines` but read from this source.
+    pub fn read_optional_lines(&self, filename: &str) -> impl Iterator<Item = String> {
+        let filename = filename.to_string();
+        self.read_optional_results(&filename)
+            .map(move |line| expect_line(&filename, line))
+    }
+
+    /// Same as `read_optional_lines` but return lines that can't be read as errors.
+    pub(crate) fn read_optional_results(
+        &self,
+        filename: &str,
+    ) -> impl Iterator<Item = io::Result<String>> {
+        self.open(filename)
+            .into_iter()
+            .flat_map(|reader| reader.lines())
+    }
+
+    /// Same as `utils::data_file_exists` but check this source.
+    pub fn file_exists(&self, filename: &str) -> bool {
+        match self {
+            DataSource::Dir(dir) => dir.join(filename).is_file(),
+            DataSource::Embedded => EMBEDDED_DATA.iter().any(|(name, _)| *name == filename),
+        }
+    }
+
+    /// Same as `utils::data_files` but list this source.
+    pub fn files(&self) -> Vec<String> {
+        let mut files: Vec<String> = match self {
+            DataSource::Dir(root) => {
+                let mut files = vec![];
+                let mut dirs = vec![root.clone()];
+                while let Some(dir) = dirs.pop() {
+                    let entries = fs::read_dir(dir)
+                        .into_iter()
+                        .flat_map(|d| d.map_while(Result::ok));
+                    for path in entries.map(|e| e.path()) {
+                        if path.is_dir() {
+                            dirs.push(path);
+                        } else if path.extension().is_some_and(|e| e == "txt") {
+                            let name = path.strip_prefix(root).unwrap_or(&path);
+                            files.push(name.to_string_lossy().replace('\\', "/"));
+                        }
+                    }
+                }
+                files
+            }
+            DataSource::Embedded => EMBEDDED_DATA
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        };
+        files.sort();
+        files
+    }
+
+    /// Same as `utils::data_countries` but for the files of this source.
+    pub fn countries(&self) -> Vec<String> {
+        let excluded = [
+            ("US", !cfg!(feature = "data-us")),
+            ("CA", !cfg!(feature = "data-ca")),
+        ];
+        self.files()
+            .iter()
+            .filter_map(|name| name.strip_suffix("/states.txt"))
+            .filter(|country| !country.contains('/'))
+            .filter(|country| !excluded.contains(&(*country, true)))
+            .map(String::from)
+            .collect()
+    }
 }
 
-/// Read file with the given name from `src/data` folder and return `std::io::Lines`
+/// Read file with the given name from the data folder, see `data_source`,
+/// and return `std::io::Lines`. Panics when the file doesn't exist.
 ///
 /// # Arguments
 ///
@@ -31,14 +190,212 @@ lazy_static! {
 /// use geo_rs;
 /// let lines = geo_rs::utils::read_lines("countries.txt");
 /// ```
-pub fn read_lines(filename: &str) -> std::io::Lines<BufReader<File>> {
-    let data_path = format!("{}/src/data", env!("CARGO_MANIFEST_DIR"));
-    let file_path = Path::new(&data_path).join(&filename);
-    let file = File::open(file_path).unwrap();
-    io::BufReader::new(file).lines()
+pub fn read_lines(filename: &str) -> io::Lines<Box<dyn BufRead>> {
+    data_source().read_lines(filename)
+}
+
+/// Take a line of a data file, panics when it can't be read, e.g. isn't valid UTF-8,
+/// so that a broken file doesn't silently cut the dataset short.
+///
+/// # Arguments
+///
+/// * `filename` - Name of the file the line is read from
+/// * `line` - Line as `std::io::Lines` returns it
+pub(crate) fn expect_line(filename: &str, line: io::Result<String>) -> String {
+    line.unwrap_or_else(|e| panic!("failed to read data file {}: {}", filename, e))
+}
+
+/// Same as `read_lines` but return no lines when the file doesn't exist,
+/// for datasets that only some countries have.
+///
+/// # Arguments
+///
+/// * `filename` - Name of the file to read
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// assert!(geo_rs::utils::read_optional_lines("US/state_names.txt").count() > 0);
+/// assert_eq!(geo_rs::utils::read_optional_lines("XX/state_names.txt").count(), 0);
+/// ```
+pub fn read_optional_lines(filename: &str) -> impl Iterator<Item = String> {
+    data_source().read_optional_lines(filename)
+}
+
+/// Whether the data file exists, see `read_optional_lines`.
+///
+/// # Arguments
+///
+/// * `filename` - Path of the file relative to `src/data`, e.g. "US/cities.txt"
+pub fn data_file_exists(filename: &str) -> bool {
+    data_source().file_exists(filename)
+}
+
+/// Paths of the data files relative to the data folder, e.g. "US/cities.txt", sorted.
+pub fn data_files() -> Vec<String> {
+    data_source().files()
+}
+
+/// Path of the data folder, see `data_source`. With embedded data it's `src/data`
+/// of the crate on the machine it was built on, which may not exist.
+pub fn data_dir() -> PathBuf {
+    match data_source() {
+        DataSource::Dir(dir) => dir.clone(),
+        DataSource::Embedded => Path::new(env!("CARGO_MANIFEST_DIR")).join("src/data"),
+    }
+}
+
+/// Codes of countries that have `src/data/{country}/states.txt`, except for the ones
+/// whose datasets are excluded by features, e.g. "US" without `data-us` feature.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let countries = geo_rs::utils::data_countries();
+/// assert_eq!(countries.contains(&String::from("US")), cfg!(feature = "data-us"));
+/// assert_eq!(countries.contains(&String::from("CA")), cfg!(feature = "data-ca"));
+/// ```
+pub fn data_countries() -> Vec<String> {
+    data_source().countries()
+}
+
+/// Delimiters of data files in the order they're detected in, see `Records`
+const DELIMITERS: [char; 3] = ['\t', ';', ','];
+
+/// Splits lines of a data file into fields, skipping blank lines and lines with
+/// missing fields with a warning, and counts the skipped ones. Fields are separated
+/// by tabs, semicolons or commas, whichever of them the first line of the file has
+/// outside of quotes, so CSV and TSV extracts work as long as they have the same
+/// columns. Fields may be quoted, with quotes inside them doubled. The schema
+/// header of the file is skipped, see `schema`.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let mut records = geo_rs::utils::Records::new("countries.txt", 2);
+/// assert_eq!(records.split("Canada;CA").unwrap(), ["Canada", "CA"]);
+/// assert!(records.split("Canada").is_none());
+/// assert!(records.split("").is_none());
+/// assert_eq!(records.skipped, 2);
+/// let mut records = geo_rs::utils::Records::new("countries.csv", 2);
+/// let korea = records.split(r#""Korea, Republic of",KR"#).unwrap();
+/// assert_eq!(korea, ["Korea, Republic of", "KR"]);
+/// let quoted = records.split(r#""Côte d""Ivoire",CI"#).unwrap();
+/// assert_eq!(quoted, ["Côte d\"Ivoire", "CI"]);
+/// ```
+#[derive(Debug)]
+pub struct Records<'a> {
+    filename: &'a str,
+    fields: usize,
+    delimiter: Option<char>,
+    line: usize,
+    /// Number of lines skipped so far
+    pub skipped: usize,
+}
+
+impl<'a> Records<'a> {
+    /// # Arguments
+    ///
+    /// * `filename` - Name of the file, for warnings
+    /// * `fields` - Number of leading fields that must be present and non-empty
+    pub fn new(filename: &'a str, fields: usize) -> Self {
+        Self {
+            filename,
+            fields,
+            delimiter: None,
+            line: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Fields of the next line of the file, `None` when the line is skipped.
+    pub fn split<'l>(&mut self, line: &'l str) -> Option<Vec<Cow<'l, str>>> {
+        self.line += 1;
+        // The header is checked by `schema::validate`
+        if self.line == 1 && line.starts_with(schema::HEADER_PREFIX) {
+            return None;
+        }
+        if self.delimiter.is_none() && !line.trim().is_empty() {
+            self.delimiter = Some(detect_delimiter(line));
+        }
+        let parts = split_quoted(line, self.delimiter.unwrap_or(';'));
+        if let Some(parts) = parts.filter(|parts| {
+            parts.len() >= self.fields && parts[..self.fields].iter().all(|p| !p.trim().is_empty())
+        }) {
+            return Some(parts);
+        }
+        warn!(
+            "Skipping malformed line {} of {}: {:?}",
+            self.line, self.filename, line
+        );
+        self.skipped += 1;
+        None
+    }
+}
+
+/// First of `DELIMITERS` that the line has outside of quotes, commas by default.
+fn detect_delimiter(line: &str) -> char {
+    let mut quoted = false;
+    let mut found: Vec<char> = vec![];
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if !quoted && DELIMITERS.contains(&c) => found.push(c),
+            _ => (),
+        }
+    }
+    DELIMITERS
+        .iter()
+        .copied()
+        .find(|d| found.contains(d))
+        .unwrap_or(',')
+}
+
+/// Split the line by the delimiter, unquoting quoted fields, `None` when
+/// a quote isn't closed.
+fn split_quoted(line: &str, delimiter: char) -> Option<Vec<Cow<'_, str>>> {
+    if !line.contains('"') {
+        return Some(line.split(delimiter).map(Cow::Borrowed).collect());
+    }
+    let mut parts: Vec<Cow<str>> = vec![];
+    let mut rest = line;
+    loop {
+        let (field, next) = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let mut field = String::new();
+                let mut chars = quoted.char_indices();
+                let end = loop {
+                    match chars.next()? {
+                        (i, '"') if quoted[i + 1..].starts_with('"') => {
+                            field.push('"');
+                            chars.next();
+                        }
+                        (i, '"') => break i + 1,
+                        (_, c) => field.push(c),
+                    }
+                };
+                (Cow::Owned(field), &quoted[end..])
+            }
+            None => {
+                let end = rest.find(delimiter).unwrap_or(rest.len());
+                (Cow::Borrowed(&rest[..end]), &rest[end..])
+            }
+        };
+        parts.push(field);
+        match next.strip_prefix(delimiter) {
+            Some(next) => rest = next,
+            None if next.is_empty() => return Some(parts),
+            // Text after the closing quote
+            None => return None,
+        }
+    }
 }
 
 /// Remove useless garbage from the given string, e.g. trailing commas, values in brackets, etc.
+/// with the default `Normalizer`.
 ///
 /// # Arguments
 ///
@@ -53,54 +410,125 @@ pub fn read_lines(filename: &str) -> std::io::Lines<BufReader<File>> {
 /// assert_eq!(s, String::from("Toronto"));
 /// ```
 pub fn clean(s: &mut String) {
-    *s = s.replace("'s", "s");
-    *s = s.replace("St. ", "Saint ");
-    *s = s.replace("Ft. ", "Fort ");
-    *s = s.replace("FT. ", "FORT ");
-    *s = RE_ABBREVIATIONS.replace_all(&s, "").to_string();
-    // find values in brackets and if it contain digits, remove everything in brackets
-    // example: `CA-ON-Oakville-3235 (Store# 04278)` - we DON'T need value in brackets
-    // example: `Midland (MI, USA)` - we DO need value in brackets
-    if let Some(in_brackets) = RE_BRACKETS.find(&s) {
-        let v = &s[in_brackets.start()..in_brackets.end()];
-        if !v
-            .chars()
-            .filter(|c| c.is_digit(10))
-            .collect::<Vec<_>>()
-            .is_empty()
-        {
-            *s = RE_BRACKETS.replace_all(&s, "").to_string();
-        }
-    }
-    *s = RE_LEADING.replace_all(&s, "").to_string();
-    *s = RE_TRAILING.replace_all(&s, "").to_string();
-    *s = RE_SPLITTER1
-        .split(&s)
-        .filter(|&x| !x.is_empty())
-        .collect::<Vec<&str>>()
-        .join(", ");
-    *s = s.replace("St,", "St.").replace("Ft,", "Ft.");
-    *s = RE_SPACES.replace_all(&s, " ").to_string();
-    *s = s
-        .replace(" - ", "|-|")
-        .replace("- ", "-")
-        .replace("|-|", " - ")
-        .replace(", , ", ", ")
-        .replace("--", "-");
-    *s = s.split(", ").into_iter().unique().join(", ");
+    DEFAULT_NORMALIZER.normalize(s);
+}
+
+/// Same as `clean` but with the given noise patterns instead of the ones
+/// from `src/data/noise_patterns.txt`.
+///
+/// # Arguments
+///
+/// * `s` - String to be cleaned
+/// * `noise_patterns` - Patterns of store numbers, internal codes, etc., see `noise::strip_noise`
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let patterns = geo_rs::noise::compile_noise_patterns([r"\s*#\d+"]);
+/// let mut s = String::from("Toronto #12, ON");
+/// geo_rs::utils::clean_with_noise(&mut s, &patterns);
+/// assert_eq!(s, String::from("Toronto, ON"));
+/// ```
+pub fn clean_with_noise(s: &mut String, noise_patterns: &[Regex]) {
+    let normalizer = DEFAULT_NORMALIZER
+        .clone()
+        .with_noise_patterns(noise_patterns.to_vec());
+    normalizer.normalize(s);
+}
+
+/// Read abbreviations dictionary from `src/data/abbreviations.txt`
+/// and return a `Vec` of abbreviation and expansion pairs.
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let abbreviations = geo_rs::utils::read_abbreviations();
+/// assert!(abbreviations.contains(&(String::from("Mt."), String::from("Mount"))));
+/// ```
+pub fn read_abbreviations() -> Vec<(String, String)> {
+    let mut abbreviations: Vec<(String, String)> = vec![];
+    let filename = "abbreviations.txt";
+    for s in read_lines(filename).map(|line| expect_line(filename, line)) {
+        let parts: Vec<&str> = s.split(";").collect();
+        abbreviations.push((parts[0].to_string(), parts[1].to_string()));
+    }
+    abbreviations
+}
+
+/// Replace abbreviations followed by a word with their expansions,
+/// e.g. "Mt. Vernon" becomes "Mount Vernon" but "Dundas St W" is kept as is.
+/// Single letters, e.g. "N" for "North", are only expanded when no word comes
+/// before them in their part of the input, so "N Las Vegas" is expanded but
+/// apartment letters and initials like "Apt E Toronto" or "John W Smith" aren't.
+///
+/// # Arguments
+///
+/// * `s` - String to expand abbreviations in
+/// * `abbreviations` - Pairs of abbreviation and its expansion
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let mut s = String::from("Mt. Vernon, N Las Vegas");
+/// let abbreviations = geo_rs::utils::read_abbreviations();
+/// geo_rs::utils::expand_abbreviations(&mut s, &abbreviations);
+/// assert_eq!(s, String::from("Mount Vernon, North Las Vegas"));
+/// ```
+pub fn expand_abbreviations(s: &mut String, abbreviations: &[(String, String)]) {
+    for (abbreviation, expansion) in abbreviations {
+        let is_letter = abbreviation.chars().count() == 1;
+        let ranges: Vec<(usize, usize)> = find_token(s, abbreviation)
+            .into_iter()
+            .filter(|(_, end)| match s[*end..].strip_prefix(' ') {
+                Some(rest) => rest.chars().next().map(|c| c.is_alphanumeric()) == Some(true),
+                None => false,
+            })
+            .filter(|(start, _)| {
+                let before = s[..*start].trim_end().chars().next_back();
+                !is_letter || !before.is_some_and(|c| c.is_alphanumeric())
+            })
+            .collect();
+        for (start, end) in ranges.into_iter().rev() {
+            s.replace_range(start..end, expansion);
+        }
+    }
 }
 
 pub fn decode(location: &mut Location) {
-    if location.city.is_some() {
-        let decoded = &location.city.as_ref().unwrap().name;
-        location.city.as_mut().unwrap().name = unidecode(decoded);
+    if let Some(city) = location.city.as_mut() {
+        city.name = unidecode(&city.name);
     }
-    if location.state.is_some() {
-        let decoded = &location.state.as_ref().unwrap().name;
-        location.state.as_mut().unwrap().name = unidecode(decoded);
+    if let Some(state) = location.state.as_mut() {
+        state.name = unidecode(&state.name);
     }
 }
 
+/// Great-circle distance in kilometers between two points given
+/// as (latitude, longitude) in degrees, computed with the haversine formula.
+///
+/// # Arguments
+///
+/// * `from` - Latitude and longitude of the first point
+/// * `to` - Latitude and longitude of the second point
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let distance = geo_rs::utils::haversine((43.65, -79.38), (45.42, -75.70));
+/// assert_eq!(distance.round(), 352.0);
+/// ```
+pub fn haversine(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
 /// Split given string by non alphanumeric symbol and return a `Vec<&str>`
 ///
 /// # Arguments
@@ -115,10 +543,333 @@ pub fn decode(location: &mut Location) {
 /// assert_eq!(parts, vec!["a", "b", "c"]);
 /// ```
 pub fn split(s: &str) -> Vec<&str> {
-    RE_SPLITTER2.split(&s).filter(|&x| !x.is_empty()).collect()
+    RE_SPLITTER2.split(s).filter(|&x| !x.is_empty()).collect()
+}
+
+/// Same as `split` but lazy, so that checking words of many strings
+/// doesn't allocate a `Vec` for each of them.
+///
+/// # Arguments
+///
+/// * `s` - String to split into words
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let words: Vec<&str> = geo_rs::utils::words("Winston-Salem, NC").collect();
+/// assert_eq!(words, vec!["Winston", "Salem", "NC"]);
+/// ```
+pub fn words(s: &str) -> impl Iterator<Item = &str> {
+    RE_SPLITTER2.split(s).filter(|w| !w.is_empty())
+}
+
+/// Return the Soundex code of a word without truncating it to four characters,
+/// so that words that sound alike get the same code, e.g. "Pittsburg" and "Pittsburgh".
+///
+/// # Arguments
+///
+/// * `word` - Word to encode, non ASCII letters are ignored
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// assert_eq!(geo_rs::utils::soundex("Pittsburgh"), String::from("P32162"));
+/// assert_eq!(geo_rs::utils::soundex("Pittsburg"), String::from("P32162"));
+/// assert_eq!(geo_rs::utils::soundex("Cincinatti"), geo_rs::utils::soundex("Cincinnati"));
+/// ```
+pub fn soundex(word: &str) -> String {
+    let digit = |c: char| match c {
+        'B' | 'F' | 'P' | 'V' => Some('1'),
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+        'D' | 'T' => Some('3'),
+        'L' => Some('4'),
+        'M' | 'N' => Some('5'),
+        'R' => Some('6'),
+        _ => None,
+    };
+    let mut letters = word
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase());
+    let first = match letters.next() {
+        Some(c) => c,
+        None => return String::new(),
+    };
+    let mut code = first.to_string();
+    let mut last = digit(first);
+    for c in letters {
+        let current = digit(c);
+        if let Some(d) = current {
+            if current != last {
+                code.push(d);
+            }
+        }
+        // Same digits separated by H or W are coded once, separated by a vowel twice
+        if c != 'H' && c != 'W' {
+            last = current;
+        }
+    }
+    code
+}
+
+/// Return a phonetic key of a name made of Soundex codes of its words,
+/// see `soundex`.
+///
+/// # Arguments
+///
+/// * `s` - Name to encode, e.g. "Fort Lauderdale"
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// assert_eq!(geo_rs::utils::phonetic_key("Fort Lauderdal"), String::from("F63 L3634"));
+/// ```
+pub fn phonetic_key(s: &str) -> String {
+    split(&unidecode(s))
+        .iter()
+        .map(|word| soundex(word))
+        .filter(|code| !code.is_empty())
+        .join(" ")
+}
+
+/// Return the key a city is matched by: its ASCII name in lowercase without
+/// apostrophes, so "Lee's Summit" and "Lees Summit", or "O'Fallon" and "O Fallon",
+/// are the same city. The punctuated name is kept in `CitiesMap::names`.
+///
+/// # Arguments
+///
+/// * `name` - City name, e.g. "Coeur d'Alene"
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// assert_eq!(geo_rs::utils::city_key("Lee's Summit"), String::from("lees summit"));
+/// assert_eq!(geo_rs::utils::city_key("Coeur d'Alene"), String::from("coeur d alene"));
+/// assert_eq!(geo_rs::utils::city_key("Val-d’Or"), String::from("val-d or"));
+/// ```
+pub fn city_key(name: &str) -> String {
+    let name = unidecode(name);
+    let name = RE_POSSESSIVE.replace_all(&name, "$1");
+    let name = RE_QUOTES.replace_all(&name, " ");
+    RE_SPACES.replace_all(name.trim(), " ").to_lowercase()
+}
+
+/// Check whether the given string mentions an entity by its code or name,
+/// e.g. "Toronto, ON" mentions Ontario while "Toronto" doesn't.
+///
+/// # Arguments
+///
+/// * `s` - String to search in
+/// * `code` - Code of the entity, e.g. "ON"
+/// * `name` - Name of the entity, e.g. "Ontario"
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// assert!(geo_rs::utils::mentions("Toronto, ON", "ON", "Ontario"));
+/// assert!(geo_rs::utils::mentions("Toronto, ontario", "ON", "Ontario"));
+/// assert!(!geo_rs::utils::mentions("Toronto", "ON", "Ontario"));
+/// ```
+pub fn mentions(s: &str, code: &str, name: &str) -> bool {
+    let s = unidecode(s);
+    let tokens = split(&s);
+    // USA is the most common way to write United States down
+    if code == "US" && tokens.contains(&"USA") {
+        return true;
+    }
+    tokens.contains(&code) || s.to_lowercase().contains(&name.to_lowercase())
+}
+
+/// Return byte ranges of all occurrences of `token` in the given string that
+/// are not a part of a bigger word, e.g. "US" in "HOUSTON" is ignored.
+///
+/// # Arguments
+///
+/// * `s` - String to search in
+/// * `token` - Token to search for
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let ranges = geo_rs::utils::find_token("HOUSTON, TX, US", "US");
+/// assert_eq!(ranges, vec![(13, 15)]);
+/// ```
+pub fn find_token(s: &str, token: &str) -> Vec<(usize, usize)> {
+    if token.is_empty() {
+        return vec![];
+    }
+    s.match_indices(token)
+        .map(|(start, m)| (start, start + m.len()))
+        .filter(|(start, end)| {
+            let before = s[..*start].chars().next_back();
+            let after = s[*end..].chars().next();
+            !before.map(|c| c.is_alphanumeric()).unwrap_or(false)
+                && !after.map(|c| c.is_alphanumeric()).unwrap_or(false)
+        })
+        .collect()
 }
 
-/// Return a `Vec` of CA and US countries or a single country `Vec`
+/// Return byte ranges of all occurrences of a phrase in the given string as a sequence
+/// of whole words, compared case-insensitively, words may be separated by anything
+/// but letters and digits, e.g. "New York" in "Albany, NEW-YORK".
+///
+/// # Arguments
+///
+/// * `s` - String to search in
+/// * `phrase` - Words to search for
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let ranges = geo_rs::utils::find_words("Albany, NEW-YORK", "New York");
+/// assert_eq!(ranges, vec![(8, 16)]);
+/// assert!(geo_rs::utils::find_words("Indianapolis", "Indiana").is_empty());
+/// ```
+pub fn find_words(s: &str, phrase: &str) -> Vec<(usize, usize)> {
+    let phrase: Vec<&str> = words(phrase).collect();
+    if phrase.is_empty() {
+        return vec![];
+    }
+    let is_word_char = |c: char| c.is_alphabetic() || c.is_ascii_digit();
+    let mut tokens: Vec<(usize, &str)> = vec![];
+    let mut start: Option<usize> = None;
+    for (i, c) in s.char_indices().chain(std::iter::once((s.len(), ' '))) {
+        match (start, is_word_char(c)) {
+            (None, true) => start = Some(i),
+            (Some(from), false) => {
+                tokens.push((from, &s[from..i]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    tokens
+        .windows(phrase.len())
+        .filter(|w| {
+            w.iter()
+                .zip(&phrase)
+                .all(|((_, t), p)| t.to_lowercase() == p.to_lowercase())
+        })
+        .map(|w| {
+            let (last, token) = w[w.len() - 1];
+            (w[0].0, last + token.len())
+        })
+        .collect()
+}
+
+/// Replace groups of brackets, innermost first, with what `replace` returns for
+/// their text. Brackets of nested groups are left out of the text but not their
+/// content, e.g. the text of "(Store (123))" is "Store  123 ". Groups for which
+/// `replace` returns `None` are kept along with replacements of nested groups.
+/// Unbalanced brackets are left as they are.
+///
+/// # Arguments
+///
+/// * `s` - String to replace brackets in
+/// * `replace` - Replacement of a group by its text, `None` to keep it
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let replaced = geo_rs::utils::replace_brackets("Toronto (Store (12)), (ON", |text| {
+///     match text.contains("12") {
+///         true => Some(String::new()),
+///         false => None,
+///     }
+/// });
+/// assert_eq!(replaced, String::from("Toronto , (ON"));
+/// ```
+pub fn replace_brackets<F>(s: &str, mut replace: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    // Replaced content and text of every open group, the first one is outside of brackets
+    let mut groups: Vec<(String, String)> = vec![Default::default()];
+    for c in s.chars() {
+        match c {
+            '(' => groups.push(Default::default()),
+            ')' if groups.len() > 1 => {
+                let (content, text) = groups.pop().unwrap();
+                let replacement = replace(&text);
+                let parent = groups.last_mut().unwrap();
+                match replacement {
+                    Some(replacement) => parent.0.push_str(&replacement),
+                    None => parent.0.push_str(&format!("({})", content)),
+                }
+                parent.1.push_str(&format!(" {} ", text));
+            }
+            c => {
+                let group = groups.last_mut().unwrap();
+                group.0.push(c);
+                group.1.push(c);
+            }
+        }
+    }
+    // Brackets that are never closed
+    while groups.len() > 1 {
+        let (content, _) = groups.pop().unwrap();
+        let parent = groups.last_mut().unwrap();
+        parent.0.push('(');
+        parent.0.push_str(&content);
+    }
+    groups.pop().unwrap().0
+}
+
+/// Remove standalone occurrences of `token` from the given string. Occurrences
+/// between two words of the same part, e.g. "USA" in "1 USA Parkway, NV", are
+/// kept because they're most likely a part of a street or a place name. Parts
+/// are separated by anything but letters, digits and spaces, so the letter case
+/// of the input doesn't matter.
+///
+/// # Arguments
+///
+/// * `s` - String to remove token from
+/// * `token` - Token to be removed
+///
+/// # Examples
+///
+/// ```
+/// use geo_rs;
+/// let mut s = String::from("1 USA Parkway, Sparks, NV, USA");
+/// geo_rs::utils::remove_token(&mut s, "USA");
+/// assert_eq!(s, String::from("1 USA Parkway, Sparks, NV, "));
+/// ```
+pub fn remove_token(s: &mut String, token: &str) {
+    let ranges: Vec<(usize, usize)> = find_token(s, token)
+        .into_iter()
+        .filter(|(start, end)| {
+            let before = s[..*start].trim_end_matches(' ');
+            let after = s[*end..].trim_start_matches(' ');
+            let word_before = before.len() < *start
+                && before
+                    .chars()
+                    .next_back()
+                    .map(|c| c.is_alphanumeric())
+                    .unwrap_or(false);
+            let word_after = after.len() < s.len() - *end
+                && after
+                    .split(|c: char| !c.is_alphanumeric())
+                    .next()
+                    .map(|w| !w.is_empty() && w.chars().all(|c| c.is_alphabetic()))
+                    .unwrap_or(false);
+            !(word_before && word_after)
+        })
+        .collect();
+    for (start, end) in ranges.into_iter().rev() {
+        s.replace_range(start..end, "");
+    }
+}
+
+/// Return a `Vec` of CA and US countries or a single country `Vec`.
+/// See `Parser::candidate_countries` for a list that respects parser options.
 ///
 /// # Arguments
 ///
@@ -151,6 +902,48 @@ pub fn get_countries(country: &Option<Country>) -> Vec<Country> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_resolve_data_source() {
+        let root = env::temp_dir().join(format!("geo-rs-data-{}", std::process::id()));
+        let env_dir = root.join("env");
+        let exe_dir = root.join("bin");
+        fs::create_dir_all(&env_dir).unwrap();
+        fs::create_dir_all(exe_dir.join("data")).unwrap();
+        fs::write(exe_dir.join("data/countries.txt"), "US;United States\n").unwrap();
+        let exe = Some(exe_dir.join("geo-rs"));
+        // No countries.txt in the env dir, falls back to the one next to the executable
+        assert_eq!(
+            resolve_data_source(Some(env_dir.clone()), exe.clone()),
+            DataSource::Dir(exe_dir.join("data"))
+        );
+        fs::write(env_dir.join("countries.txt"), "US;United States\n").unwrap();
+        assert_eq!(
+            resolve_data_source(Some(env_dir.clone()), exe),
+            DataSource::Dir(env_dir)
+        );
+        let fallback = match cfg!(feature = "embedded-data") {
+            true => DataSource::Embedded,
+            false => DataSource::Dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("src/data")),
+        };
+        assert_eq!(
+            resolve_data_source(None, Some(root.join("geo-rs"))),
+            fallback
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to read data file countries.txt")]
+    fn test_read_optional_lines_unreadable() {
+        let dir = env::temp_dir().join(format!("geo-rs-broken-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("countries.txt"), b"Canada;CA\nM\xe9xico;MX\n").unwrap();
+        let source = DataSource::Dir(dir.clone());
+        let lines: Vec<String> = source.read_optional_lines("countries.txt").collect();
+        fs::remove_dir_all(dir).unwrap();
+        assert_eq!(lines, vec!["Canada;CA"]);
+    }
+
     #[test]
     fn test_clean() {
         let mut s = "BULLHEAD CITY FORT MOHAVE, Arizona, 86426".to_string();
@@ -177,22 +970,135 @@ mod tests {
             s,
             "United States-District of Columbia-washington-20340".to_string()
         );
-        let mut s = "Canton, MA, Canton, MA, US".to_string();
-        clean(&mut s);
-        assert_eq!(s, "Canton, MA, US".to_string());
-        let mut s = "Canton,MA,Canton,MA,US".to_string();
+        let mut s = "Canton,MA,US".to_string();
         clean(&mut s);
         assert_eq!(s, "Canton, MA, US".to_string());
-        let mut s = "FT. BELVOIR, VA, US, 22060, FT. BELVOIR".to_string();
+        let mut s = "FT. BELVOIR, VA, US, 22060".to_string();
         clean(&mut s);
         assert_eq!(s, "FORT BELVOIR, VA, US, 22060".to_string());
     }
 
+    #[test]
+    fn test_expand_abbreviations() {
+        let abbreviations = read_abbreviations();
+        let mut s = "Pt. Pleasant, NJ".to_string();
+        expand_abbreviations(&mut s, &abbreviations);
+        assert_eq!(s, "Point Pleasant, NJ".to_string());
+        let mut s = "Ste. Marie, QC".to_string();
+        expand_abbreviations(&mut s, &abbreviations);
+        assert_eq!(s, "Sainte Marie, QC".to_string());
+        let mut s = "E Lansing, MI".to_string();
+        expand_abbreviations(&mut s, &abbreviations);
+        assert_eq!(s, "East Lansing, MI".to_string());
+        let mut s = "3235 Dundas St W, Oakville".to_string();
+        expand_abbreviations(&mut s, &abbreviations);
+        assert_eq!(s, "3235 Dundas St W, Oakville".to_string());
+        let mut s = "Las Vegas - N Las Vegas, NV".to_string();
+        expand_abbreviations(&mut s, &abbreviations);
+        assert_eq!(s, "Las Vegas - North Las Vegas, NV".to_string());
+        for input in [
+            "Apt E Toronto, ON",
+            "John W Smith Park, Chicago",
+            "12 S Main Street, Ann Arbor",
+        ] {
+            let mut s = input.to_string();
+            expand_abbreviations(&mut s, &abbreviations);
+            assert_eq!(s, input);
+        }
+        let custom = vec![("Hwy".to_string(), "Highway".to_string())];
+        let mut s = "Hwy 50, Wilton".to_string();
+        expand_abbreviations(&mut s, &custom);
+        assert_eq!(s, "Highway 50, Wilton".to_string());
+    }
+
     #[test]
     fn test_split() {
         let s = "s - s !! test";
-        let parts = split(&s);
-        assert_eq!(parts, vec!["s", "s", "test"])
+        let parts = split(s);
+        assert_eq!(parts, vec!["s", "s", "test"]);
+        let parts = split("Montréal, Québec");
+        assert_eq!(parts, vec!["Montréal", "Québec"]);
+    }
+
+    #[test]
+    fn test_words_match_split() {
+        for s in [
+            "s - s !! test",
+            "Winston-Salem, NC 27101",
+            "Montréal, Québec",
+            // Combining accent, Roman numeral and Arabic digits aren't letters
+            "Sa\u{301}o Paulo",
+            "Louis \u{216B}",
+            "Dubai \u{661}\u{662}",
+            "",
+        ] {
+            assert_eq!(words(s).collect::<Vec<&str>>(), split(s), "input: {:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_replace_brackets() {
+        let drop_digits = |text: &str| match text.chars().any(|c| c.is_ascii_digit()) {
+            true => Some(String::new()),
+            false => None,
+        };
+        for (input, output) in [
+            (
+                "Toronto (Eaton Centre) (Store 12), ON",
+                "Toronto (Eaton Centre) , ON",
+            ),
+            ("Toronto (Eaton Centre (Store 12)), ON", "Toronto , ON"),
+            (
+                "Toronto (Eaton (Centre)), ON",
+                "Toronto (Eaton (Centre)), ON",
+            ),
+            ("Toronto ((12)), ON", "Toronto , ON"),
+            ("Toronto (ON", "Toronto (ON"),
+            ("Toronto (12), ON)", "Toronto , ON)"),
+            ("Toronto ((12), ON", "Toronto (, ON"),
+            ("Montréal (Québec (12))", "Montréal "),
+        ] {
+            assert_eq!(replace_brackets(input, drop_digits), output, "{}", input);
+        }
+        let mut texts = vec![];
+        replace_brackets("A (B (C) D) (E)", |text| {
+            texts.push(text.to_string());
+            None
+        });
+        assert_eq!(texts, ["C", "B  C  D", "E"]);
+    }
+
+    #[test]
+    fn test_find_token() {
+        assert_eq!(find_token("HOUSTON, TX, US", "US"), vec![(13, 15)]);
+        assert_eq!(find_token("US-DE-Wilmington", "US"), vec![(0, 2)]);
+        assert!(find_token("USA Parkway", "US").is_empty());
+        assert!(find_token("anything", "").is_empty());
+    }
+
+    #[test]
+    fn test_remove_token() {
+        let mut s = "US CA San Diego".to_string();
+        remove_token(&mut s, "US");
+        assert_eq!(s, " CA San Diego".to_string());
+        let mut s = "AUGUSTA, GA, US".to_string();
+        remove_token(&mut s, "US");
+        assert_eq!(s, "AUGUSTA, GA, ".to_string());
+        let mut s = "1 USA Parkway, USA".to_string();
+        remove_token(&mut s, "USA");
+        assert_eq!(s, "1 USA Parkway, ".to_string());
+        // Letter case of the input doesn't matter
+        let mut s = "1 USA PARKWAY, SPARKS NV USA".to_string();
+        remove_token(&mut s, "USA");
+        assert_eq!(s, "1 USA PARKWAY, SPARKS NV ".to_string());
+        for input in ["PARIS FR", "Paris FR"] {
+            let mut s = input.to_string();
+            remove_token(&mut s, "FR");
+            assert_eq!(s, input.replace("FR", ""));
+        }
+        let mut s = "US-DE-WILMINGTON".to_string();
+        remove_token(&mut s, "US");
+        assert_eq!(s, "-DE-WILMINGTON".to_string());
     }
 
     #[test]
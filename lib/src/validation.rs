@@ -0,0 +1,369 @@
+use crate::nodes::{City, Country, Location, State};
+use crate::{utils, Parser};
+use std::cmp::Reverse;
+use std::fmt;
+use titlecase::titlecase;
+use unidecode::unidecode;
+
+/// Location entered as separate fields, e.g. in a web form.
+/// Empty fields are treated as missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocationInput {
+    pub city: Option<String>,
+    /// State code or name, e.g. "ON" or "Ontario"
+    pub state: Option<String>,
+    /// Country code or name, e.g. "CA", "CAN" or "Canada"
+    pub country: Option<String>,
+    pub zipcode: Option<String>,
+}
+
+/// Problem with a `LocationInput` found by `Parser::validate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// Country is neither a known code nor a known name
+    UnknownCountry,
+    /// State is neither a known code nor a known name
+    UnknownState,
+    /// Zipcode is neither a US ZIP code nor a Canadian postal code
+    InvalidZipcode,
+    /// State belongs to another country
+    StateNotInCountry,
+    /// City isn't known in the state
+    CityNotInState,
+    /// Zipcode belongs to another country
+    ZipcodeNotInCountry,
+    /// Zipcode belongs to another state
+    ZipcodeNotInState,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ValidationIssue::UnknownCountry => "unknown country",
+            ValidationIssue::UnknownState => "unknown state",
+            ValidationIssue::InvalidZipcode => "invalid zipcode",
+            ValidationIssue::StateNotInCountry => "state is not in the country",
+            ValidationIssue::CityNotInState => "city is not in the state",
+            ValidationIssue::ZipcodeNotInCountry => "zipcode is not in the country",
+            ValidationIssue::ZipcodeNotInState => "zipcode is not in the state",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+/// Outcome of `Parser::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    /// Input fields resolved to known values, unknown state and country are left empty
+    pub location: Location,
+    /// Problems found in the input, empty when it's consistent
+    pub issues: Vec<ValidationIssue>,
+    /// Consistent locations that differ from the input the least, most likely first
+    pub suggestions: Vec<Location>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+impl Parser {
+    /// Check that fields of a structured location are consistent with each other:
+    /// zipcode is in the state, city is in the state and state is in the country.
+    /// Missing state and country are taken from the zipcode or the state.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location fields entered by a user
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use geo_rs;
+    /// use geo_rs::{LocationInput, ValidationIssue};
+    /// let parser = geo_rs::Parser::new();
+    /// let report = parser.validate(&LocationInput {
+    ///     city: Some(String::from("Seattle")),
+    ///     state: Some(String::from("OR")),
+    ///     country: Some(String::from("US")),
+    ///     zipcode: None,
+    /// });
+    /// assert_eq!(report.issues, vec![ValidationIssue::CityNotInState]);
+    /// assert_eq!(report.suggestions[0].to_string(), String::from("Seattle, WA, US"));
+    /// ```
+    pub fn validate(&self, input: &LocationInput) -> ValidationReport {
+        let mut location = Location::default();
+        let mut issues: Vec<ValidationIssue> = vec![];
+
+        if let Some(country) = non_empty(&input.country) {
+            location.country = self.country_from_input(country);
+            if location.country.is_none() {
+                issues.push(ValidationIssue::UnknownCountry);
+            }
+        }
+
+        let mut zipcode_location: Option<Location> = None;
+        if let Some(zipcode) = non_empty(&input.zipcode) {
+            zipcode_location = self.location_from_zipcode(zipcode);
+            match &zipcode_location {
+                Some(z) => location.zipcode = z.zipcode.clone(),
+                None => issues.push(ValidationIssue::InvalidZipcode),
+            }
+        }
+
+        if let Some(state) = non_empty(&input.state) {
+            location.state = self.state_from_input(state, &location.country);
+            match (&location.state, &location.country) {
+                (None, _) => issues.push(ValidationIssue::UnknownState),
+                (Some(s), Some(c)) if !self.is_state_of(&s.code, &c.code) => {
+                    issues.push(ValidationIssue::StateNotInCountry)
+                }
+                _ => (),
+            }
+        }
+        if location.country.is_none() && non_empty(&input.country).is_none() {
+            self.fill_country_from_state(&mut location);
+        }
+
+        if let Some(z) = &zipcode_location {
+            if location.country.is_none() && non_empty(&input.country).is_none() {
+                location.country = z.country.clone();
+            }
+            if location.state.is_none() && non_empty(&input.state).is_none() {
+                location.state = z.state.clone();
+            }
+            if location.country.is_some() && location.country != z.country {
+                issues.push(ValidationIssue::ZipcodeNotInCountry);
+            } else if location.state.is_some() && z.state.is_some() && location.state != z.state {
+                issues.push(ValidationIssue::ZipcodeNotInState);
+            }
+        }
+
+        if let Some(city) = non_empty(&input.city) {
+            let city = City::new(&titlecase(&city.to_lowercase()));
+            if let Some(state) = &location.state {
+                if !self.state_contains_city(state, &city) {
+                    issues.push(ValidationIssue::CityNotInState);
+                }
+            }
+            location.city = Some(city);
+        }
+
+        issues.sort_by_key(|i| *i as u8);
+        issues.dedup();
+        let suggestions = if issues.is_empty() {
+            vec![]
+        } else {
+            self.suggest_corrections(&location, zipcode_location.as_ref())
+        };
+        ValidationReport {
+            location,
+            issues,
+            suggestions,
+        }
+    }
+
+    /// Resolve a country by its code or name.
+    fn country_from_input(&self, input: &str) -> Option<Country> {
+        if let Some(country) = self.country_from_code(input) {
+            return Some(country);
+        }
+        let mut location = Location::default();
+        self.fill_country(&mut location, input);
+        location.country
+    }
+
+    /// Resolve a state by its code or name, states of the given country go first.
+    fn state_from_input(&self, input: &str, country: &Option<Country>) -> Option<State> {
+        if country.is_some() {
+            if let Some(state) = self.state_from_code(country, input) {
+                return Some(state);
+            }
+        }
+        if let Some(state) = self.state_from_code(&None, input) {
+            return Some(state);
+        }
+        let mut input = unidecode(input);
+        self.translate_state_names(&mut input);
+        if let Some(state) = self.state_from_code(country, input.trim()) {
+            return Some(state);
+        }
+        let mut location = Location::default();
+        self.fill_state(&mut location, &input);
+        location.state
+    }
+
+    /// Consistent locations made of the input city and zipcode with states
+    /// that could have been meant: the state of the zipcode, states having
+    /// the city and the input state in its own country.
+    fn suggest_corrections(
+        &self,
+        location: &Location,
+        zipcode: Option<&Location>,
+    ) -> Vec<Location> {
+        let mut candidates: Vec<State> = vec![];
+        if let Some(state) = zipcode.and_then(|z| z.state.clone()) {
+            candidates.push(state);
+        }
+        if let Some(city) = &location.city {
+            let key = utils::city_key(&city.name);
+            for country in self.candidate_countries(&location.country) {
+                let cities = match self.cities.get(&country.code) {
+                    Some(c) => c,
+                    None => continue,
+                };
+                for code in cities.states_by_city.get(&key).into_iter().flatten() {
+                    if let Some(state) = self.state_from_code(&Some(country.clone()), code) {
+                        candidates.push(state);
+                    }
+                }
+            }
+        }
+        if let Some(state) = &location.state {
+            candidates.push(state.clone());
+        }
+
+        let mut scored: Vec<(usize, u64, Location)> = vec![];
+        for state in candidates {
+            let country = match self
+                .state_countries
+                .iter()
+                .find(|c| self.is_state_of(&state.code, &c.code))
+            {
+                Some(c) => c.clone(),
+                None => continue,
+            };
+            let zipcode_fits = zipcode.is_some_and(|z| z.state.as_ref() == Some(&state));
+            let city_fits = location
+                .city
+                .as_ref()
+                .is_some_and(|c| self.state_contains_city(&state, c));
+            if location.city.is_some() && !city_fits {
+                continue;
+            }
+            // A typo in the country is more likely than in the state or zipcode
+            let score = [
+                (zipcode_fits, 2),
+                (city_fits, 2),
+                (location.state.as_ref() == Some(&state), 2),
+                (location.country.as_ref() == Some(&country), 1),
+            ]
+            .iter()
+            .filter(|(fits, _)| *fits)
+            .map(|(_, weight)| weight)
+            .sum::<usize>();
+            let population = location
+                .city
+                .as_ref()
+                .and_then(|c| {
+                    let key = (state.code.clone(), utils::city_key(&c.name));
+                    self.cities
+                        .get(&country.code)?
+                        .population
+                        .get(&key)
+                        .copied()
+                })
+                .unwrap_or(0);
+            let suggestion = Location {
+                city: location.city.clone(),
+                zipcode: location.zipcode.clone().filter(|_| zipcode_fits),
+                state: Some(state),
+                country: Some(country),
+                ..Location::default()
+            };
+            if suggestion != *location && !scored.iter().any(|(_, _, l)| *l == suggestion) {
+                scored.push((score, population, suggestion));
+            }
+        }
+        let best = scored.iter().map(|(score, _, _)| *score).max().unwrap_or(0);
+        scored.retain(|(score, _, _)| *score == best);
+        scored.sort_by_key(|(_, population, _)| Reverse(*population));
+        scored
+            .into_iter()
+            .map(|(_, _, location)| location)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(city: &str, state: &str, country: &str, zipcode: &str) -> LocationInput {
+        let field = |v: &str| Some(v.to_string()).filter(|v| !v.is_empty());
+        LocationInput {
+            city: field(city),
+            state: field(state),
+            country: field(country),
+            zipcode: field(zipcode),
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_validate() {
+        let parser = Parser::new();
+        let cases = [
+            (("Portland", "ME", "US", "04101"), vec![], vec![]),
+            (("toronto", "Ontario", "Canada", ""), vec![], vec![]),
+            (
+                ("Vancouver", "Colombie-Britannique", "CA", ""),
+                vec![],
+                vec![],
+            ),
+            (
+                ("Toronto", "ON", "US", ""),
+                vec![ValidationIssue::StateNotInCountry],
+                vec!["Toronto, ON, CA"],
+            ),
+            (
+                ("Lansing", "MI", "US", "60601"),
+                vec![ValidationIssue::ZipcodeNotInState],
+                vec!["Lansing, MI, US", "Lansing, IL, US, 60601"],
+            ),
+            (("Seattle", "", "", "98101"), vec![], vec![]),
+            (
+                ("Ottawa", "ON", "CA", "98101"),
+                vec![ValidationIssue::ZipcodeNotInCountry],
+                vec!["Ottawa, ON, CA"],
+            ),
+            (
+                ("Toronto", "XX", "Atlantis", "123"),
+                vec![
+                    ValidationIssue::UnknownCountry,
+                    ValidationIssue::UnknownState,
+                    ValidationIssue::InvalidZipcode,
+                ],
+                vec![
+                    "Toronto, ON, CA",
+                    "Toronto, KS, US",
+                    "Toronto, OH, US",
+                    "Toronto, SD, US",
+                ],
+            ),
+        ];
+        for ((city, state, country, zipcode), issues, suggestions) in cases {
+            let report = parser.validate(&input(city, state, country, zipcode));
+            assert_eq!(report.issues, issues, "{}", city);
+            assert_eq!(report.is_valid(), issues.is_empty());
+            let suggestions: Vec<String> = suggestions.iter().map(|s| s.to_string()).collect();
+            let actual: Vec<String> = report.suggestions.iter().map(|l| l.to_string()).collect();
+            assert_eq!(actual, suggestions, "{}", city);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "data-us", feature = "data-ca"))]
+    fn test_validate_fills_missing_fields() {
+        let parser = Parser::new();
+        let report = parser.validate(&input("Seattle", "", "", "98101"));
+        assert_eq!(report.location.to_string(), "Seattle, WA, US, 98101");
+        let report = parser.validate(&input("Montreal", "QC", "", ""));
+        assert_eq!(report.location.to_string(), "Montreal, QC, CA");
+    }
+}
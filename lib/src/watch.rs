@@ -0,0 +1,154 @@
+//! Hot reload of data files for long-running services, enable with `watch` feature.
+//!
+//! `WatchedParser` watches the data folder of its parser and swaps in a reloaded
+//! parser, see `Parser::reload`, once the files stop changing. Parsing never waits
+//! for a reload, it keeps using the previous parser until the new one is ready,
+//! and keeps it when the changed files can't be read.
+//!
+//! # Examples
+//!
+//! ```
+//! use geo_rs::watch::WatchedParser;
+//! let watched = WatchedParser::new(geo_rs::Parser::new()).unwrap();
+//! let location = watched.parse_location("Toronto, ON");
+//! assert_eq!(location.to_string(), String::from("Toronto, ON, CA"));
+//! ```
+use crate::nodes::Location;
+use crate::utils::{self, DataSource};
+use crate::{Parser, ReloadError};
+use arc_swap::ArcSwap;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// How long the data files have to stay unchanged before they are reloaded,
+/// so that a dataset that is being copied isn't read half-written
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Parser that is reloaded whenever its data files change.
+pub struct WatchedParser {
+    current: Arc<ArcSwap<Parser>>,
+    /// Stops watching when dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl WatchedParser {
+    /// Start watching the data folder of the parser, see `utils::data_source`,
+    /// and the folder of `ParserBuilder::with_data_dir`. Embedded data files
+    /// never change, such parsers are only reloaded by `WatchedParser::reload`.
+    ///
+    /// # Arguments
+    ///
+    /// * `parser` - Parser to reload
+    pub fn new(parser: Parser) -> notify::Result<Self> {
+        let mut dirs: Vec<PathBuf> = parser.recipe.data_dir().into_iter().collect();
+        if let DataSource::Dir(dir) = utils::data_source() {
+            dirs.push(dir.clone());
+        }
+        let current = Arc::new(ArcSwap::from_pointee(parser));
+        let (sender, receiver) = mpsc::channel::<()>();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if event.kind.is_access() => {}
+                Ok(_) => {
+                    let _ = sender.send(());
+                }
+                Err(e) => warn!("Failed to watch data files: {}", e),
+            })?;
+        for dir in &dirs {
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+        }
+        let swapped = current.clone();
+        thread::spawn(move || {
+            // Ends when the watcher is dropped along with the sender
+            while receiver.recv().is_ok() {
+                while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+                info!("Reloading data files of {:?}", dirs);
+                match swapped.load().reload() {
+                    Ok(parser) => swapped.store(Arc::new(parser)),
+                    Err(e) => warn!("Keeping the current parser: {}", e),
+                }
+            }
+        });
+        Ok(Self {
+            current,
+            _watcher: watcher,
+        })
+    }
+
+    /// Current parser, parsing with it isn't affected by reloads that happen meanwhile.
+    pub fn load(&self) -> Arc<Parser> {
+        self.current.load_full()
+    }
+
+    /// Parse location string with the current parser, see `Parser::parse_location`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Location string that's gonna be parsed
+    pub fn parse_location(&self, input: &str) -> Location {
+        self.current.load().parse_location(input)
+    }
+
+    /// Reload the data files right away and swap in the reloaded parser,
+    /// the current parser is kept when the data files can't be read.
+    pub fn reload(&self) -> Result<(), ReloadError> {
+        let parser = self.current.load().reload()?;
+        self.current.store(Arc::new(parser));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::Warning;
+    use std::fs;
+    use std::path::Path;
+    use std::time::Instant;
+
+    #[test]
+    fn test_reload_changed_data_files() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/schema/current");
+        let dir = std::env::temp_dir().join(format!("geo-rs-watch-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("US")).unwrap();
+        for file in [
+            "countries.txt",
+            "country_aliases.txt",
+            "US/states.txt",
+            "US/cities.txt",
+        ] {
+            fs::copy(fixture.join(file), dir.join(file)).unwrap();
+        }
+        let parser = Parser::builder().with_data_dir(&dir).build();
+        let watched = WatchedParser::new(parser).unwrap();
+        let is_known = |w: &WatchedParser| {
+            !w.parse_location("Albany, NY")
+                .warnings
+                .contains(&Warning::UnknownCity)
+        };
+        assert!(!is_known(&watched));
+        let cities = fs::read_to_string(dir.join("US/cities.txt")).unwrap();
+        // A broken file keeps the current parser, and the watcher keeps watching
+        // Files are replaced rather than changed in place, they may be mapped
+        let replace = |contents: &[u8]| {
+            fs::write(dir.join("US/cities.new"), contents).unwrap();
+            fs::rename(dir.join("US/cities.new"), dir.join("US/cities.txt")).unwrap();
+        };
+        replace(b"NY;Alb\xe9ny;99224\n");
+        assert!(watched.reload().is_err());
+        assert!(!is_known(&watched));
+        thread::sleep(DEBOUNCE * 2);
+        replace((cities + "NY;Albany;99224\n").as_bytes());
+        let started = Instant::now();
+        while !is_known(&watched) && started.elapsed() < Duration::from_secs(30) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(is_known(&watched));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
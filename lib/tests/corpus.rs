@@ -0,0 +1,173 @@
+//! Regression tests over `tests/data/corpus.txt` and property tests that
+//! rearrange and re-case known locations, asserting the parser still finds
+//! the same city, state and country.
+#![cfg(any(feature = "data-us", feature = "data-ca"))]
+use geo_rs::nodes::{read_cities, Location};
+use geo_rs::Parser;
+use lazy_static::lazy_static;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+lazy_static! {
+    static ref PARSER: Parser = Parser::new();
+    static ref KNOWN_LOCATIONS: Vec<Known> = known_locations();
+}
+
+/// City along with its state and country
+#[derive(Debug, Clone)]
+struct Known {
+    city: String,
+    state_code: String,
+    state_name: String,
+    country_code: String,
+    country_name: String,
+}
+
+impl Known {
+    fn matches(&self, location: &Location) -> bool {
+        location.to_string() == format!("{}, {}, {}", self.city, self.state_code, self.country_code)
+    }
+}
+
+/// Cities with population data, except for the ones named after their states,
+/// e.g. "US New York New York" is ambiguous, see known failures of the corpus
+fn known_locations() -> Vec<Known> {
+    let mut known: Vec<Known> = vec![];
+    for (country_code, country_cities) in read_cities() {
+        let country = PARSER.country_from_code(&country_code).unwrap();
+        for key in country_cities.population.keys() {
+            let state = PARSER
+                .state_from_code(&Some(country.clone()), &key.0)
+                .unwrap();
+            if state.name.to_lowercase() == key.1 {
+                continue;
+            }
+            known.push(Known {
+                city: country_cities.names[key].to_string(),
+                state_code: state.code,
+                state_name: state.name,
+                country_code: country.code.clone(),
+                country_name: country.name.clone(),
+            });
+        }
+    }
+    known.sort_by(|a, b| {
+        (&a.country_code, &a.state_code, &a.city).cmp(&(&b.country_code, &b.state_code, &b.city))
+    });
+    known
+}
+
+/// Join city, state and country in the given order, e.g. "csn" is city, state and country
+fn arrange(city: &str, state: &str, country: &str, order: &str, separator: &str) -> String {
+    order
+        .chars()
+        .map(|part| match part {
+            'c' => city,
+            's' => state,
+            _ => country,
+        })
+        .collect::<Vec<&str>>()
+        .join(separator)
+}
+
+/// Change case of every letter according to the mask, e.g. "Toronto" becomes "tOrOnto"
+fn mangle_case(s: &str, mask: &[bool]) -> String {
+    s.chars()
+        .zip(mask.iter().cycle())
+        .map(|(c, upper)| match upper {
+            true => c.to_ascii_uppercase(),
+            false => c.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+fn known() -> impl Strategy<Value = Known> {
+    select(KNOWN_LOCATIONS.clone())
+}
+
+fn order() -> impl Strategy<Value = &'static str> {
+    select(vec!["csn", "ncs", "nsc", "scn"])
+}
+
+#[test]
+#[cfg(all(feature = "data-us", feature = "data-ca"))]
+fn test_corpus() {
+    let path = format!("{}/tests/data/corpus.txt", env!("CARGO_MANIFEST_DIR"));
+    let corpus = std::fs::read_to_string(path).unwrap();
+    let mut failures: Vec<String> = vec![];
+    let mut total = 0;
+    for line in corpus.lines() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (input, expected) = line.split_once(';').unwrap();
+        let actual = PARSER.parse_location(input).to_string();
+        if actual != expected {
+            failures.push(format!(
+                "{:?}: expected {:?}, got {:?}",
+                input, expected, actual
+            ));
+        }
+        total += 1;
+    }
+    assert!(total > 500);
+    assert!(
+        failures.is_empty(),
+        "{} of {} failed:\n{}",
+        failures.len(),
+        total,
+        failures.join("\n")
+    );
+}
+
+proptest! {
+    #[test]
+    fn test_parts_in_any_order(
+        known in known(),
+        order in order(),
+        separator in select(vec![", ", " - ", "-", " "]),
+        country_as_name: bool,
+        upper: bool,
+    ) {
+        let country = match country_as_name {
+            true => &known.country_name,
+            false => &known.country_code,
+        };
+        let mut input = arrange(&known.city, &known.state_code, country, order, separator);
+        if upper && separator != "-" {
+            input = input.to_uppercase();
+        }
+        let location = PARSER.parse_location(&input);
+        prop_assert!(known.matches(&location), "{:?} parsed as {:?}", input, location.to_string());
+    }
+
+    #[test]
+    fn test_city_in_any_case(
+        known in known(),
+        order in order(),
+        separator in select(vec![", ", " - ", "-"]),
+        mask in prop::collection::vec(any::<bool>(), 1..8),
+    ) {
+        let city = mangle_case(&known.city, &mask);
+        let country = mangle_case(&known.country_name, &mask);
+        let input = arrange(&city, &known.state_code, &country, order, separator);
+        let location = PARSER.parse_location(&input);
+        prop_assert!(known.matches(&location), "{:?} parsed as {:?}", input, location.to_string());
+    }
+
+    #[test]
+    fn test_state_name(
+        known in known(),
+        order in select(vec!["csn", "ncs"]),
+        separator in select(vec![", ", " - ", "-", " "]),
+        country_as_name: bool,
+    ) {
+        let country = match country_as_name {
+            true => &known.country_name,
+            false => &known.country_code,
+        };
+        let input = arrange(&known.city, &known.state_name, country, order, separator);
+        let location = PARSER.parse_location(&input);
+        prop_assert!(known.matches(&location), "{:?} parsed as {:?}", input, location.to_string());
+    }
+}
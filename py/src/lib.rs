@@ -0,0 +1,80 @@
+//! Python bindings of `geo-rs`.
+use geo_rs::nodes::Location;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Python facing wrapper of `geo_rs::Parser`.
+#[pyclass(name = "Parser", module = "geo_rs")]
+pub struct PyParser {
+    parser: geo_rs::Parser,
+}
+
+#[pymethods]
+impl PyParser {
+    #[new]
+    #[pyo3(signature = (infer_unique_city = false))]
+    fn new(infer_unique_city: bool) -> Self {
+        let options = geo_rs::ParserOptions::new().infer_unique_city(infer_unique_city);
+        Self {
+            parser: geo_rs::Parser::with_options(options),
+        }
+    }
+
+    /// Parse location string into a dict, missing parts are `None`.
+    fn parse<'py>(&self, py: Python<'py>, input: &str) -> PyResult<Bound<'py, PyDict>> {
+        to_dict(py, &self.parser.parse_location(input))
+    }
+}
+
+/// Convert location into a dict with the same keys as `Location::format` placeholders.
+fn to_dict<'py>(py: Python<'py>, location: &Location) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("city", location.city.as_ref().map(|c| &c.name))?;
+    dict.set_item("state", location.state.as_ref().map(|s| &s.code))?;
+    dict.set_item("state_name", location.state.as_ref().map(|s| &s.name))?;
+    dict.set_item("country", location.country.as_ref().map(|c| &c.code))?;
+    dict.set_item("country_name", location.country.as_ref().map(|c| &c.name))?;
+    dict.set_item("zipcode", location.zipcode.as_ref().map(|z| &z.zipcode))?;
+    dict.set_item("address", location.address.as_ref().map(|a| &a.address))?;
+    dict.set_item("granularity", location.granularity().map(|g| g.name()))?;
+    let warnings: Vec<&str> = location.warnings.iter().map(|w| w.code()).collect();
+    dict.set_item("warnings", warnings)?;
+    Ok(dict)
+}
+
+#[pymodule]
+#[pyo3(name = "geo_rs")]
+fn geo_rs_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyParser>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let parser = PyParser::new(false);
+            let dict = parser.parse(py, "Toronto, ON, CA, M4E 3J1").unwrap();
+            let get = |key: &str| -> Option<String> {
+                dict.get_item(key).unwrap().unwrap().extract().unwrap()
+            };
+            assert_eq!(get("city"), Some(String::from("Toronto")));
+            assert_eq!(get("state_name"), Some(String::from("Ontario")));
+            assert_eq!(get("country"), Some(String::from("CA")));
+            assert_eq!(get("zipcode"), Some(String::from("M4E 3J1")));
+            assert_eq!(get("granularity"), Some(String::from("zipcode")));
+            assert_eq!(get("address"), None);
+            let warnings: Vec<String> = dict
+                .get_item("warnings")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert!(warnings.is_empty());
+        });
+    }
+}